@@ -14,16 +14,80 @@ import axios from "axios";
 import { useEffect, useState } from "react";
 import { Alert, AlertDescription, AlertTitle } from "@/components/ui/alert";
 
+const defaultApiAddress = "http://localhost:8080/oauth";
+
+// Splits an absolute `return_to` like `https://sso.example.com/t/{tenant}/oauth/authorize?...`
+// into the issuer's API address and the request to resume there. Only authorization, SAML and
+// device requests of this server are resumed, never arbitrary URLs.
+function parseReturnTo(
+  returnTo: string | null,
+): { apiAddress: string; path: string } | null {
+  let url: URL;
+  try {
+    url = new URL(returnTo ?? "");
+  } catch {
+    return null;
+  }
+  // The login page is served by the issuer's host, e.g. a tenant's custom domain, or next to it
+  const trustedOrigins = [
+    window.location.origin,
+    new URL(defaultApiAddress).origin,
+  ];
+  if (!trustedOrigins.includes(url.origin)) {
+    return null;
+  }
+
+  const match = url.pathname.match(/^((?:\/t\/[0-9a-fA-F-]{36})?\/oauth)(\/.*)$/);
+  if (!match) {
+    return null;
+  }
+  const path = match[2] + url.search;
+  const resumable =
+    path.startsWith("/authorize?") ||
+    path.startsWith("/saml/") ||
+    path === "/device" ||
+    path.startsWith("/device?");
+
+  return resumable ? { apiAddress: url.origin + match[1], path } : null;
+}
+
+// Branding of the tenant the user logs in to, empty for the default look
+type TenantBranding = {
+  logo_url?: string;
+  primary_color?: string;
+  background_color?: string;
+  custom_css?: string;
+  support_url?: string;
+  support_email?: string;
+  privacy_policy_url?: string;
+  terms_of_service_url?: string;
+  texts?: Record<string, Record<string, string>>;
+};
+
+// Language tags the browser accepts by preference, each followed by its primary language
+function acceptedLocales(): string[] {
+  return navigator.languages.flatMap((locale) => {
+    const primary = locale.split("-")[0];
+    return primary === locale ? [locale] : [locale, primary];
+  });
+}
+
 export function LoginForm({
   className,
   ...props
 }: React.ComponentProps<"div">) {
-  const apiAddress = "http://localhost:8080/oauth";
   const [searchParams] = useSearchParams();
+  const [apiAddress, setApiAddress] = useState(defaultApiAddress);
 
   const [email, setEmail] = useState("");
   const [password, setPassword] = useState("");
+  const [rememberMe, setRememberMe] = useState(false);
   const [error, setError] = useState<string | null>(null);
+  const [returnTo, setReturnTo] = useState<string | null>(null);
+  const [providers, setProviders] = useState<{ id: string; name: string }[]>(
+    [],
+  );
+  const [branding, setBranding] = useState<TenantBranding>({});
 
   const [oauthParams, setOauthParams] = useState({
     client_id: "sap_concur_client_001", // default fallback
@@ -48,15 +112,95 @@ export function LoginForm({
       state: urlState || "",
       scope: urlScope || "",
     });
+
+    // The login continues at the issuer that sent the user here, e.g. a tenant's
+    const resume = parseReturnTo(searchParams.get("return_to"));
+    setApiAddress(resume?.apiAddress ?? defaultApiAddress);
+    setReturnTo(resume?.path ?? null);
+
+    const loginHint = searchParams.get("login_hint");
+    if (loginHint) {
+      setEmail(loginHint);
+    }
   }, [searchParams]);
 
+  // Application the user logs in to, only users of its tenant can log in
+  const clientId = returnTo?.startsWith("/authorize?")
+    ? new URLSearchParams(returnTo.split("?")[1]).get("client_id")
+    : oauthParams.client_id;
+
+  useEffect(() => {
+    // Identity providers of the tenant the requesting application belongs to
+    if (!clientId) {
+      return;
+    }
+
+    axios
+      .get(apiAddress + "/federation/providers", {
+        params: { client_id: clientId },
+      })
+      .then((response) => setProviders(response.data))
+      .catch(() => setProviders([]));
+  }, [apiAddress, clientId]);
+
+  useEffect(() => {
+    // Branding of the application's tenant, or of the issuer's without an application
+    axios
+      .get<TenantBranding>(apiAddress + "/branding", {
+        params: clientId ? { client_id: clientId } : {},
+      })
+      .then((response) => setBranding(response.data))
+      .catch(() => setBranding({}));
+  }, [apiAddress, clientId]);
+
+  useEffect(() => {
+    const root = document.documentElement.style;
+    if (branding.primary_color) {
+      root.setProperty("--primary", branding.primary_color);
+    }
+    if (branding.background_color) {
+      root.setProperty("--background", branding.background_color);
+    }
+    const style = document.createElement("style");
+    style.textContent = branding.custom_css ?? "";
+    document.head.appendChild(style);
+
+    return () => {
+      root.removeProperty("--primary");
+      root.removeProperty("--background");
+      style.remove();
+    };
+  }, [branding]);
+
+  // The tenant's text for `key` in the first accepted language it has one in
+  const text = (key: string, fallback: string) => {
+    for (const locale of acceptedLocales()) {
+      const value = branding.texts?.[locale]?.[key];
+      if (value) {
+        return value;
+      }
+    }
+    return fallback;
+  };
+
+  const federationLoginUrl = (providerId: string) => {
+    const url = `${apiAddress}/federation/${encodeURIComponent(providerId)}/login`;
+    return returnTo ? `${url}?return_to=${encodeURIComponent(returnTo)}` : url;
+  };
+
   return (
     <div className={cn("flex flex-col gap-6", className)} {...props}>
       <Card>
         <CardHeader>
-          <CardTitle>Login to your account</CardTitle>
+          {branding.logo_url && (
+            <img src={branding.logo_url} alt="" className="mb-2 h-10 w-fit" />
+          )}
+          <CardTitle>{text("login_title", "Login to your account")}</CardTitle>
           <CardDescription>
-            Enter your email below to login to your account
+            {text(
+              "login_description",
+              "Enter your email below to login to your account",
+            )}
           </CardDescription>
         </CardHeader>
         <CardContent>
@@ -70,6 +214,8 @@ export function LoginForm({
                   {
                     email,
                     password,
+                    remember_me: rememberMe,
+                    client_id: clientId || undefined,
                   },
                   {
                     withCredentials: true,
@@ -77,7 +223,10 @@ export function LoginForm({
                 );
                 // console.log("Login success: ", response.data);
 
-                if (response.status === 200) {
+                if (response.status === 200 && returnTo) {
+                  // Resume the original authorization request
+                  window.location.href = `${apiAddress}${returnTo}`;
+                } else if (response.status === 200) {
                   // Build OAuth authorization URL with the same parameters from the original request
                   const authParams = new URLSearchParams({
                     response_type: oauthParams.response_type,
@@ -133,6 +282,15 @@ export function LoginForm({
                   onChange={(e) => setPassword(e.target.value)}
                 />
               </div>
+              <div className="flex items-center gap-2">
+                <input
+                  id="remember-me"
+                  type="checkbox"
+                  checked={rememberMe}
+                  onChange={(e) => setRememberMe(e.target.checked)}
+                />
+                <Label htmlFor="remember-me">Remember me</Label>
+              </div>
               {error && (
                 <Alert variant="destructive">
                   <AlertTitle>Error</AlertTitle>
@@ -143,6 +301,18 @@ export function LoginForm({
                 <Button type="submit" className="w-full">
                   Login
                 </Button>
+                {providers.map((provider) => (
+                  <Button
+                    key={provider.id}
+                    variant="outline"
+                    className="w-full"
+                    asChild
+                  >
+                    <a href={federationLoginUrl(provider.id)}>
+                      Login with {provider.name}
+                    </a>
+                  </Button>
+                ))}
               </div>
             </div>
             <div className="mt-4 text-center text-sm">
@@ -151,6 +321,26 @@ export function LoginForm({
                 Sign up
               </Link>
             </div>
+            <div className="mt-4 flex flex-wrap justify-center gap-4 text-xs text-muted-foreground">
+              {branding.support_url && (
+                <a href={branding.support_url}>{text("support", "Support")}</a>
+              )}
+              {branding.support_email && (
+                <a href={`mailto:${branding.support_email}`}>
+                  {text("support_email", "Contact")}
+                </a>
+              )}
+              {branding.privacy_policy_url && (
+                <a href={branding.privacy_policy_url}>
+                  {text("privacy_policy", "Privacy policy")}
+                </a>
+              )}
+              {branding.terms_of_service_url && (
+                <a href={branding.terms_of_service_url}>
+                  {text("terms_of_service", "Terms of service")}
+                </a>
+              )}
+            </div>
           </form>
         </CardContent>
       </Card>
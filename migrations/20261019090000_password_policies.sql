@@ -0,0 +1,29 @@
+-- Per-tenant password policies and password history
+
+CREATE TABLE PasswordPolicies
+(
+    tenant_id         UUID PRIMARY KEY REFERENCES Tenants (id) ON DELETE CASCADE,
+    min_length        INTEGER NOT NULL DEFAULT 8,
+    require_uppercase BOOLEAN NOT NULL DEFAULT FALSE,
+    require_lowercase BOOLEAN NOT NULL DEFAULT FALSE,
+    require_digit     BOOLEAN NOT NULL DEFAULT FALSE,
+    require_symbol    BOOLEAN NOT NULL DEFAULT FALSE,
+    history_size      INTEGER NOT NULL DEFAULT 0,
+    max_age_days      INTEGER,
+    check_breached    BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at        TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at        TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE PasswordHistory
+(
+    id            UUID PRIMARY KEY,
+    user_id       UUID NOT NULL REFERENCES Users (id) ON DELETE CASCADE,
+    password_hash TEXT NOT NULL,
+    created_at    TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX passwordhistory_user_id_created_at_idx ON PasswordHistory (user_id, created_at DESC);
+
+ALTER TABLE Users
+    ADD COLUMN password_changed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP;
@@ -0,0 +1,12 @@
+-- Per-tenant session lifetime policies
+
+CREATE TABLE SessionPolicies
+(
+    tenant_id                    UUID PRIMARY KEY REFERENCES Tenants (id) ON DELETE CASCADE,
+    idle_timeout_seconds         INTEGER NOT NULL DEFAULT 900,
+    absolute_lifetime_seconds    INTEGER NOT NULL DEFAULT 43200,
+    remember_me_enabled          BOOLEAN NOT NULL DEFAULT TRUE,
+    remember_me_lifetime_seconds INTEGER NOT NULL DEFAULT 2592000,
+    created_at                   TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at                   TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,22 @@
+-- OIDC standard profile, email, phone and address claims for users
+
+ALTER TABLE Users
+    ADD COLUMN name                  VARCHAR(255),
+    ADD COLUMN given_name            VARCHAR(255),
+    ADD COLUMN family_name           VARCHAR(255),
+    ADD COLUMN middle_name           VARCHAR(255),
+    ADD COLUMN nickname              VARCHAR(255),
+    ADD COLUMN picture               TEXT,
+    ADD COLUMN website               TEXT,
+    ADD COLUMN gender                VARCHAR(50),
+    ADD COLUMN birthdate             VARCHAR(10),
+    ADD COLUMN zoneinfo              VARCHAR(100),
+    ADD COLUMN locale                VARCHAR(35),
+    ADD COLUMN email_verified        BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN phone_number          VARCHAR(50),
+    ADD COLUMN phone_number_verified BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN street_address        TEXT,
+    ADD COLUMN locality              VARCHAR(255),
+    ADD COLUMN region                VARCHAR(255),
+    ADD COLUMN postal_code           VARCHAR(20),
+    ADD COLUMN country               VARCHAR(255);
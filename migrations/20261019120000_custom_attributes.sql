@@ -0,0 +1,29 @@
+-- Custom user attributes and per-application claim mapping
+
+ALTER TABLE Users
+    ADD COLUMN attributes JSONB NOT NULL DEFAULT '{}'::jsonb;
+
+CREATE TABLE AttributeDefinitions
+(
+    tenant_id   UUID         NOT NULL REFERENCES Tenants (id) ON DELETE CASCADE,
+    name        VARCHAR(255) NOT NULL,
+    data_type   VARCHAR(20)  NOT NULL,
+    required    BOOLEAN      NOT NULL DEFAULT FALSE,
+    description TEXT,
+    created_at  TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at  TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (tenant_id, name)
+);
+
+CREATE TABLE ClaimMappings
+(
+    application_id      UUID         NOT NULL REFERENCES Applications (id) ON DELETE CASCADE,
+    claim_name          VARCHAR(255) NOT NULL,
+    attribute           VARCHAR(255) NOT NULL,
+    in_id_token         BOOLEAN      NOT NULL DEFAULT TRUE,
+    in_access_token     BOOLEAN      NOT NULL DEFAULT FALSE,
+    in_userinfo         BOOLEAN      NOT NULL DEFAULT TRUE,
+    created_at          TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at          TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (application_id, claim_name)
+);
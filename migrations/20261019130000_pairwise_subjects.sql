@@ -0,0 +1,17 @@
+-- Pairwise subject identifiers per application sector
+
+ALTER TABLE Applications
+    ADD COLUMN subject_type          VARCHAR(20) NOT NULL DEFAULT 'public',
+    ADD COLUMN sector_identifier_uri TEXT,
+    ADD COLUMN sector_identifier     VARCHAR(255);
+
+-- Issued pairwise subjects, so they can be resolved back to the user
+CREATE TABLE PairwiseSubjects
+(
+    sector_identifier VARCHAR(255) NOT NULL,
+    subject           VARCHAR(255) NOT NULL,
+    user_id           UUID         NOT NULL REFERENCES Users (id) ON DELETE CASCADE,
+    created_at        TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (sector_identifier, subject),
+    UNIQUE (sector_identifier, user_id)
+);
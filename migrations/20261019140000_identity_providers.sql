@@ -0,0 +1,28 @@
+-- Upstream OpenID Connect identity providers per tenant
+
+CREATE TABLE IdentityProviders
+(
+    id             VARCHAR(100) PRIMARY KEY,
+    tenant_id      UUID         NOT NULL REFERENCES Tenants (id) ON DELETE CASCADE,
+    name           VARCHAR(255) NOT NULL,
+    discovery_url  TEXT         NOT NULL,
+    client_id      VARCHAR(255) NOT NULL,
+    client_secret  VARCHAR(255) NOT NULL,
+    scopes         TEXT[]       NOT NULL,
+    claim_mapping  JSONB        NOT NULL DEFAULT '{}'::jsonb,
+    auto_provision BOOLEAN      NOT NULL DEFAULT TRUE,
+    link_by_email  BOOLEAN      NOT NULL DEFAULT FALSE,
+    created_at     TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at     TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Users known by their subject at an upstream provider. Users provisioned just in time
+-- have an empty password hash and can only log in through their provider.
+CREATE TABLE FederatedIdentities
+(
+    provider_id VARCHAR(100) NOT NULL REFERENCES IdentityProviders (id) ON DELETE CASCADE,
+    subject     VARCHAR(255) NOT NULL,
+    user_id     UUID         NOT NULL REFERENCES Users (id) ON DELETE CASCADE,
+    created_at  TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (provider_id, subject)
+);
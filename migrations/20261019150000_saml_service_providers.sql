@@ -0,0 +1,13 @@
+-- Applications that log users in through SAML 2.0 in addition to (or instead of) OpenID Connect
+
+CREATE TABLE SamlServiceProviders
+(
+    application_id UUID PRIMARY KEY REFERENCES Applications (id) ON DELETE CASCADE,
+    entity_id      VARCHAR(255) NOT NULL UNIQUE,
+    acs_urls       TEXT[]       NOT NULL,
+    name_id_format VARCHAR(20)  NOT NULL DEFAULT 'persistent',
+    -- [{"name": ..., "source": ..., "friendly_name": ...}], sources are standard claims or custom attributes
+    attributes     JSONB        NOT NULL DEFAULT '[]'::jsonb,
+    created_at     TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at     TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,15 @@
+-- LDAP / Active Directory authentication per tenant
+
+CREATE TABLE TenantDirectories
+(
+    tenant_id     UUID PRIMARY KEY REFERENCES Tenants (id) ON DELETE CASCADE,
+    config        JSONB  NOT NULL,
+    -- Unknown users with an email in these domains are looked up in the directory
+    email_domains TEXT[] NOT NULL DEFAULT '{}',
+    created_at    TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at    TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Users provisioned from a directory authenticate against it, their password hash stays empty
+ALTER TABLE Users
+    ADD COLUMN directory_dn TEXT;
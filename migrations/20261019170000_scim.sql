@@ -0,0 +1,20 @@
+-- SCIM 2.0 provisioning, bearer tokens scope HR systems to a tenant
+
+CREATE TABLE ScimTokens
+(
+    id         UUID PRIMARY KEY,
+    tenant_id  UUID         NOT NULL REFERENCES Tenants (id) ON DELETE CASCADE,
+    name       VARCHAR(255) NOT NULL,
+    -- SHA-256 of the token, base64url encoded
+    token_hash TEXT         NOT NULL UNIQUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (tenant_id, name)
+);
+
+-- Identifiers the provisioning client knows users and groups by
+ALTER TABLE Users
+    ADD COLUMN external_id TEXT;
+
+ALTER TABLE Roles
+    ADD COLUMN external_id TEXT;
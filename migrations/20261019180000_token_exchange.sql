@@ -0,0 +1,4 @@
+-- Audiences an application may exchange user tokens into (RFC 8693)
+
+ALTER TABLE Applications
+    ADD COLUMN token_exchange_audiences TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,13 @@
+-- APIs access tokens can be issued for with the `resource` parameter (RFC 8707)
+
+CREATE TABLE ApiResources
+(
+    id         UUID PRIMARY KEY,
+    tenant_id  UUID         NOT NULL REFERENCES Tenants (id) ON DELETE CASCADE,
+    -- Absolute URI, becomes the `aud` of access tokens for the API
+    identifier TEXT         NOT NULL UNIQUE,
+    name       VARCHAR(255) NOT NULL,
+    scopes     TEXT[]       NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
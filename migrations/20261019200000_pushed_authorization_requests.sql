@@ -0,0 +1,4 @@
+-- Applications that only accept authorization requests pushed to /oauth/par (RFC 9126)
+
+ALTER TABLE Applications
+    ADD COLUMN require_pushed_authorization_requests BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,5 @@
+-- Public keys of applications, used to verify their signed request objects (RFC 9101)
+
+ALTER TABLE Applications
+    ADD COLUMN jwks     JSONB,
+    ADD COLUMN jwks_uri TEXT;
@@ -0,0 +1,6 @@
+-- Client authentication with TLS client certificates and certificate-bound tokens (RFC 8705)
+
+ALTER TABLE Applications
+    ADD COLUMN token_endpoint_auth_method TEXT NOT NULL DEFAULT 'client_secret_post',
+    ADD COLUMN tls_client_auth_subject_dn TEXT,
+    ADD COLUMN tls_client_certificate_bound_access_tokens BOOLEAN NOT NULL DEFAULT FALSE;
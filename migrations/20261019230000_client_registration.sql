@@ -0,0 +1,18 @@
+-- Dynamic client registration (RFC 7591) and client configuration endpoint (RFC 7592)
+
+-- Initial access tokens that allow registering clients into a tenant
+CREATE TABLE RegistrationTokens
+(
+    id         UUID PRIMARY KEY,
+    tenant_id  UUID         NOT NULL REFERENCES Tenants (id) ON DELETE CASCADE,
+    name       VARCHAR(255) NOT NULL,
+    -- SHA-256 of the token, base64url encoded
+    token_hash TEXT         NOT NULL UNIQUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (tenant_id, name)
+);
+
+-- Registration access token of dynamically registered applications, hashed like the tokens above
+ALTER TABLE Applications
+    ADD COLUMN registration_access_token_hash TEXT UNIQUE;
@@ -0,0 +1,16 @@
+-- Per-application grant types, response types, scopes and token lifetimes
+
+ALTER TABLE Applications
+    ADD COLUMN grant_types                 TEXT[]  NOT NULL DEFAULT '{authorization_code,refresh_token}',
+    ADD COLUMN response_types              TEXT[]  NOT NULL DEFAULT '{code}',
+    -- NULL allows every scope
+    ADD COLUMN allowed_scopes              TEXT[],
+    -- Seconds
+    ADD COLUMN access_token_lifetime       INTEGER NOT NULL DEFAULT 3600,
+    ADD COLUMN id_token_lifetime           INTEGER NOT NULL DEFAULT 3600,
+    ADD COLUMN refresh_token_lifetime      INTEGER NOT NULL DEFAULT 86400,
+    ADD COLUMN authorization_code_lifetime INTEGER NOT NULL DEFAULT 600;
+
+-- Existing applications keep every grant they could use before
+UPDATE Applications
+SET grant_types = '{authorization_code,refresh_token,urn:ietf:params:oauth:grant-type:device_code,urn:ietf:params:oauth:grant-type:token-exchange}';
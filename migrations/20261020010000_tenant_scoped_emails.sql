@@ -0,0 +1,2 @@
+-- An email address may be used once per tenant, UNIQUE (tenant_id, email) stays in place
+ALTER TABLE Users DROP CONSTRAINT IF EXISTS users_email_key;
@@ -0,0 +1,5 @@
+-- Custom domains and branding of the login, consent and error pages per tenant
+
+ALTER TABLE Tenants
+    ADD COLUMN domain   VARCHAR(255) UNIQUE,
+    ADD COLUMN branding JSONB;
@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use axum_extra::{TypedHeader, headers::Cookie};
+use serde_json::{Map, Value};
+
+use crate::{
+    handlers::session_handler::require_admin, models::services_config::ServicesConfig,
+    utils::attribute_utils::AttributeError,
+};
+
+/// Get the custom attributes of a user of the admin's tenant
+pub async fn admin_get_attributes(
+    Path(user_id): Path<String>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    if let Err(response) = require_admin(&cookies, &services, &user_id).await {
+        return response;
+    }
+
+    match services
+        .attribute_service
+        .get_user_attributes(&user_id)
+        .await
+    {
+        Ok(attributes) => Json(attributes).into_response(),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Could not load attributes",
+        )
+            .into_response(),
+    }
+}
+
+/// Replace the custom attributes of a user of the admin's tenant
+pub async fn admin_update_attributes(
+    Path(user_id): Path<String>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Json(attributes): Json<Map<String, Value>>,
+) -> Response {
+    if let Err(response) = require_admin(&cookies, &services, &user_id).await {
+        return response;
+    }
+
+    match services
+        .attribute_service
+        .set_user_attributes(&user_id, attributes)
+        .await
+    {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) if e.downcast_ref::<AttributeError>().is_some() => {
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Could not store attributes",
+        )
+            .into_response(),
+    }
+}
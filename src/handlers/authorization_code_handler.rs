@@ -1,70 +1,131 @@
-use axum::response::Redirect;
+use axum::response::{Html, Redirect, Response};
+use chrono::Utc;
 use std::sync::Arc;
 
-use axum::{Extension, extract::Query, http::StatusCode, response::IntoResponse};
+use axum::{
+    Extension, Form,
+    extract::{Query, RawQuery},
+    http::{
+        HeaderMap, StatusCode,
+        header::{LOCATION, SET_COOKIE},
+    },
+    response::IntoResponse,
+};
 use axum_extra::{TypedHeader, headers::Cookie};
 use uuid::Uuid;
 
-use crate::models::{
-    auth_code_data::AuthCodeData, authorize_request::AuthorizeRequest,
-    services_config::ServicesConfig,
+use crate::{
+    handlers::{branding_handler::page_branding, login_handler::session_cookie},
+    models::{
+        application_model::Application,
+        auth_code_data::AuthCodeData,
+        authorize_request::{AuthorizeRequest, ResponseMode, SelectAccountRequest},
+        claims::ClaimsRequest,
+        pushed_request::RequestReference,
+        services_config::ServicesConfig,
+        session::SessionData,
+    },
+    utils::{
+        account_chooser_utils::{
+            AccountChoice, SESSION_IDS_COOKIE, account_chooser_page, parse_session_ids,
+        },
+        authorization_response_utils::{form_post_page, response_url},
+        claims_utils::{has_scope, scopes_allowed, subject_matches},
+        request_object_utils::{merge_request_object, verify_request_object},
+        resource_utils::scopes_allowed_for_resource,
+        tenant_issuer::TenantIssuer,
+        token_issuer::TokenIssuer,
+    },
 };
 
+/// There is no consent step, so `consent` is rejected rather than silently skipped
+const PROMPT_VALUES: [&str; 3] = ["none", "login", "select_account"];
+/// Seconds a client has to accept a signed authorization response
+const AUTHORIZATION_RESPONSE_LIFETIME: i64 = 600;
+
+/// Why an authorization request cannot be processed
+pub(crate) enum InvalidRequest {
+    /// The client or its redirect URI cannot be trusted, so the error is shown to the user
+    Rejected(StatusCode, &'static str),
+    /// Sent back to the client via its redirect URI
+    Redirect(&'static str, &'static str),
+    /// The signed request object could not be verified, its redirect URI cannot be trusted
+    RequestObject(&'static str),
+}
+
 pub async fn authorize(
-    Query(params): Query<AuthorizeRequest>,
-    TypedHeader(cookies): TypedHeader<Cookie>,
+    Query(reference): Query<RequestReference>,
+    RawQuery(query): RawQuery,
+    cookies: Option<TypedHeader<Cookie>>,
+    headers: HeaderMap,
     Extension(services): Extension<Arc<ServicesConfig>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
 ) -> impl IntoResponse {
-    // Only "code" is supported
-    if params.response_type != "code" {
-        return (
-            StatusCode::BAD_REQUEST,
-            "Only 'code' response_type is supported".to_string(),
-        )
-            .into_response();
-    }
-
-    // Validate client_id and redirect_uri
-    let application_info = match services
-        .application_service
-        .get_client_information(&params.client_id)
-        .await
-    {
-        Ok(application_info) => application_info,
-        Err(_) => {
-            println!("Expected  but got: {:?}", &params.client_id);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "An error occurred during client id checking".to_string(),
-            )
-                .into_response();
+    let token_issuer = &issuer.token_issuer;
+    // Parameters come from a pushed request, or from the query and its signed request object
+    let params = match &reference.request_uri {
+        Some(request_uri) => match pushed_request(&services, &reference, request_uri).await {
+            Ok(params) => params,
+            Err(response) => return response,
+        },
+        None => {
+            let query = serde_urlencoded::from_str(query.as_deref().unwrap_or_default())
+                .unwrap_or_default();
+            match parse_request(&services, issuer.token_verifier.issuer(), query).await {
+                Ok(params) => params,
+                Err(invalid_request) => return rejection(invalid_request),
+            }
         }
     };
 
-    // if params.redirect_uri != "https://my-frontend.com/callback" {
+    let (application_info, claims_request) =
+        match validate_request(&services, &issuer, &params).await {
+            Ok(validated) => validated,
+            Err(InvalidRequest::Redirect(error, description)) => {
+                return error_redirect(token_issuer, &params, error, description);
+            }
+            Err(invalid_request) => return rejection(invalid_request),
+        };
 
-    if !application_info
-        .redirect_uris
-        .iter()
-        .any(|s| s == &params.redirect_uri)
-    {
-        return (
-            StatusCode::BAD_REQUEST,
-            "Invalid client_id or redirect_uri".to_string(),
-        )
-            .into_response();
+    if reference.request_uri.is_none() && application_info.require_pushed_authorization_requests {
+        return error_redirect(
+            token_issuer,
+            &params,
+            "invalid_request",
+            "The client has to push its authorization requests",
+        );
     }
+    let prompt = params.prompt_values();
+
+    // The expected subject from a previous ID token, if the client sent one
+    let hinted_subject = match &params.id_token_hint {
+        Some(id_token_hint) => {
+            match issuer
+                .token_verifier
+                .verify_id_token_hint(id_token_hint, &params.client_id)
+            {
+                Ok(token_data) => Some(token_data.claims.sub),
+                Err(_) => {
+                    return error_redirect(
+                        token_issuer,
+                        &params,
+                        "invalid_request",
+                        "Invalid id_token_hint",
+                    );
+                }
+            }
+        }
+        None => None,
+    };
 
     // Check for user session
-    let user_id = match cookies.get("session_id") {
+    let session_id = cookies
+        .as_ref()
+        .and_then(|TypedHeader(cookies)| cookies.get("session_id"));
+    let session = match session_id {
         Some(session_cookie) => {
-            match services
-                .session_service
-                .validate_session(session_cookie)
-                .await
-            {
-                Ok(Some(user_id)) => Some(user_id),
-                Ok(None) => None, // session not found or expired
+            match services.session_service.touch_session(session_cookie).await {
+                Ok(session) => session, // None if session not found or expired
                 Err(_) => {
                     return (
                         StatusCode::INTERNAL_SERVER_ERROR,
@@ -77,22 +138,84 @@ pub async fn authorize(
         None => None,
     };
 
-    // If not logged in, redirect to login UI
-    if user_id.is_none() {
-        let return_to = format!(
-            "/authorize?{}",
-            serde_urlencoded::to_string(&params).unwrap()
-        );
+    // A session is only usable if it satisfies max_age and belongs to the hinted or requested user
+    let session = match session {
+        Some(session) => match is_usable_session(
+            &services,
+            &session,
+            &params,
+            &application_info,
+            hinted_subject.as_deref(),
+            claims_request.as_ref(),
+        )
+        .await
+        {
+            Ok(true) => Some(session),
+            Ok(false) => None,
+            Err(response) => return response,
+        },
+        None => None,
+    };
 
-        let login_url = format!(
-            "http://localhost:5173/login?return_to={}",
-            urlencoding::encode(&return_to)
-        );
-        dbg!(Redirect::temporary(&login_url).into_response());
-        return Redirect::temporary(&login_url).into_response();
-    }
+    let requires_interaction = prompt.contains(&"login") || prompt.contains(&"select_account");
+
+    let session = match session {
+        Some(session) if !requires_interaction => session,
+        _ => {
+            // The browser may hold sessions of other accounts, e.g. one in the application's tenant
+            let accounts = match prompt.contains(&"login") {
+                true => Vec::new(),
+                false => match browser_accounts(
+                    &services,
+                    cookies.as_ref().map(|TypedHeader(cookies)| cookies),
+                    &params,
+                    &application_info,
+                    hinted_subject.as_deref(),
+                    claims_request.as_ref(),
+                )
+                .await
+                {
+                    Ok(accounts) => accounts,
+                    Err(response) => return response,
+                },
+            };
 
-    let user_id = user_id.unwrap();
+            // Silent authentication cannot show the login UI or the account chooser
+            if prompt.contains(&"none") {
+                let (error, description) = match accounts.is_empty() {
+                    true => ("login_required", "User is not logged in"),
+                    false => (
+                        "account_selection_required",
+                        "The user has to choose an account",
+                    ),
+                };
+                return error_redirect(token_issuer, &params, error, description);
+            }
+
+            let request_uri = reference.request_uri.as_deref();
+            let return_to = match return_to(&services, &params, request_uri).await {
+                Ok(return_to) => return_to,
+                Err(response) => return response,
+            };
+            // The login UI resumes at this issuer, which may be a tenant's
+            let login_url = login_url(&params, issuer.login_url(&return_to));
+            if accounts.is_empty() {
+                return Redirect::temporary(&login_url).into_response();
+            }
+            let branding =
+                page_branding(&services, Some(application_info.tenant_id), &headers).await;
+            return Html(account_chooser_page(
+                &branding,
+                &accounts,
+                &issuer.endpoint_url("/select_account"),
+                &return_to,
+                &login_url,
+            ))
+            .into_response();
+        }
+    };
+
+    let user_id = session.user_id;
     let code = Uuid::new_v4().to_string();
 
     let auth_data = AuthCodeData {
@@ -101,12 +224,20 @@ pub async fn authorize(
         redirect_uri: params.redirect_uri.clone(),
         scope: params.scope.clone(),
         nonce: params.nonce.clone(),
-        expires_in: 600,
+        session_id: session_id.map(str::to_owned),
+        auth_time: Some(session.auth_time),
+        claims: claims_request,
+        resource: params.resource.clone(),
+        expires_in: application_info.authorization_code_lifetime as u64,
     };
 
     if let Err(err) = services
         .auth_code_service
-        .store_code(&code, auth_data, 600)
+        .store_code(
+            &code,
+            auth_data,
+            application_info.authorization_code_lifetime as u64,
+        )
         .await
     {
         eprintln!("Failed to store auth code: {err:?}");
@@ -117,12 +248,610 @@ pub async fn authorize(
             .into_response();
     }
 
-    // Redirect back with code and optional state
-    let mut redirect_url = format!("{}?code={}", params.redirect_uri, code);
-    if let Some(state) = params.state {
-        redirect_url.push_str("&state=");
-        redirect_url.push_str(&urlencoding::encode(&state));
+    if let Some(request_uri) = &reference.request_uri
+        && let Err(err) = services
+            .pushed_request_service
+            .delete_request(request_uri)
+            .await
+    {
+        eprintln!("Failed to remove pushed authorization request: {err:?}");
+    }
+
+    if let Some(session_id) = session_id
+        && let Err(err) = services
+            .session_service
+            .add_client(session_id, &params.client_id)
+            .await
+    {
+        eprintln!("Failed to record client in session: {err:?}");
+    }
+
+    authorization_response(token_issuer, &params, vec![("code", code)])
+}
+
+/// Makes one of the browser's sessions the active one and continues the authorization request
+pub async fn select_account(
+    cookies: Option<TypedHeader<Cookie>>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+    Form(request): Form<SelectAccountRequest>,
+) -> impl IntoResponse {
+    let cookies = cookies.as_ref().map(|TypedHeader(cookies)| cookies);
+    let is_browser_session = cookies.is_some_and(|cookies| {
+        cookies.get("session_id") == Some(request.session_id.as_str())
+            || parse_session_ids(cookies.get(SESSION_IDS_COOKIE)).contains(&request.session_id)
+    });
+    if !is_browser_session || !request.return_to.starts_with("/authorize?") {
+        return (StatusCode::BAD_REQUEST, "Invalid account selection").into_response();
+    }
+
+    let location = issuer.endpoint_url(&request.return_to);
+    match services
+        .session_service
+        .get_session(&request.session_id)
+        .await
+    {
+        Ok(Some(session)) => {
+            let cookie = session_cookie(request.session_id, &session);
+            (
+                StatusCode::SEE_OTHER,
+                [(SET_COOKIE, cookie.to_string()), (LOCATION, location)],
+            )
+                .into_response()
+        }
+        // The request shows the login or the remaining accounts again
+        Ok(None) => Redirect::to(&location).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     }
+}
+
+/// Authorization parameters from a query or form, a signed `request` object takes precedence
+pub(crate) async fn parse_request(
+    services: &ServicesConfig,
+    issuer: &str,
+    params: Vec<(String, String)>,
+) -> Result<AuthorizeRequest, InvalidRequest> {
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    };
 
-    return Redirect::temporary(&redirect_url).into_response();
+    let params = match param("request") {
+        Some(request) => {
+            let Some(client_id) = param("client_id") else {
+                return Err(InvalidRequest::Rejected(
+                    StatusCode::BAD_REQUEST,
+                    "Missing client_id",
+                ));
+            };
+            let jwks = match services
+                .application_service
+                .get_client_jwks(&client_id)
+                .await
+            {
+                Ok(Some(jwks)) => jwks,
+                Ok(None) | Err(_) => {
+                    return Err(InvalidRequest::RequestObject(
+                        "No keys of the client to verify the request object",
+                    ));
+                }
+            };
+            let claims = verify_request_object(&request, &jwks, &client_id, issuer)
+                .map_err(|_| InvalidRequest::RequestObject("Invalid request object"))?;
+            merge_request_object(params, &claims)
+        }
+        None => params,
+    };
+
+    serde_urlencoded::to_string(&params)
+        .ok()
+        .and_then(|params| serde_urlencoded::from_str(&params).ok())
+        .ok_or(InvalidRequest::Rejected(
+            StatusCode::BAD_REQUEST,
+            "Invalid authorization request",
+        ))
+}
+
+/// Checks of an authorization request that do not depend on the user, also applied to pushed requests
+pub(crate) async fn validate_request(
+    services: &ServicesConfig,
+    issuer: &TenantIssuer,
+    params: &AuthorizeRequest,
+) -> Result<(Application, Option<ClaimsRequest>), InvalidRequest> {
+    // Only "code" is supported
+    if params.response_type != "code" {
+        return Err(InvalidRequest::Rejected(
+            StatusCode::BAD_REQUEST,
+            "Only 'code' response_type is supported",
+        ));
+    }
+
+    // Validate client_id and redirect_uri
+    let application_info = match services
+        .application_service
+        .get_client_information(&params.client_id)
+        .await
+    {
+        Ok(application_info) => application_info,
+        Err(_) => {
+            println!("Expected  but got: {:?}", &params.client_id);
+            return Err(InvalidRequest::Rejected(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "An error occurred during client id checking",
+            ));
+        }
+    };
+
+    // Applications of other tenants are unknown to a tenant's issuer
+    if !issuer.serves(application_info.tenant_id)
+        || !application_info
+            .redirect_uris
+            .iter()
+            .any(|s| s == &params.redirect_uri)
+    {
+        return Err(InvalidRequest::Rejected(
+            StatusCode::BAD_REQUEST,
+            "Invalid client_id or redirect_uri",
+        ));
+    }
+
+    // From here on errors are returned to the client via its redirect URI
+    if params.response_mode().is_none() {
+        return Err(InvalidRequest::Redirect(
+            "invalid_request",
+            "Unsupported response_mode",
+        ));
+    }
+
+    if !application_info
+        .response_types
+        .contains(&params.response_type)
+    {
+        return Err(InvalidRequest::Redirect(
+            "unauthorized_client",
+            "The client may not use this response_type",
+        ));
+    }
+
+    if !scopes_allowed(
+        params.scope.as_deref(),
+        application_info.allowed_scopes.as_deref(),
+    ) {
+        return Err(InvalidRequest::Redirect(
+            "invalid_scope",
+            "The client may not request this scope",
+        ));
+    }
+
+    let prompt = params.prompt_values();
+    if prompt.iter().any(|value| !PROMPT_VALUES.contains(value))
+        || (prompt.contains(&"none") && prompt.len() > 1)
+    {
+        return Err(InvalidRequest::Redirect(
+            "invalid_request",
+            "Invalid or unsupported prompt value",
+        ));
+    }
+    // A time in the future could never be satisfied by a login
+    if params
+        .login_after
+        .is_some_and(|login_after| login_after > Utc::now().timestamp())
+    {
+        return Err(InvalidRequest::Redirect(
+            "invalid_request",
+            "Invalid login_after",
+        ));
+    }
+
+    let claims_request = match params
+        .claims
+        .as_deref()
+        .map(serde_json::from_str::<ClaimsRequest>)
+    {
+        Some(Ok(claims_request)) => Some(claims_request),
+        Some(Err(_)) => {
+            return Err(InvalidRequest::Redirect(
+                "invalid_request",
+                "Invalid claims parameter",
+            ));
+        }
+        None => None,
+    };
+
+    // ID token claims can only be requested from OpenID Connect requests
+    if claims_request
+        .as_ref()
+        .is_some_and(|claims_request| !claims_request.id_token.is_empty())
+        && !has_scope(params.scope.as_deref(), "openid")
+    {
+        return Err(InvalidRequest::Redirect(
+            "invalid_request",
+            "The claims parameter requires the openid scope",
+        ));
+    }
+    // UserInfo returns the claims of the granted scopes, it does not know the requested ones
+    if claims_request
+        .as_ref()
+        .is_some_and(|claims_request| !claims_request.userinfo.is_empty())
+    {
+        return Err(InvalidRequest::Redirect(
+            "invalid_request",
+            "Requesting userinfo claims is not supported",
+        ));
+    }
+
+    // Access tokens for an API of the client's tenant may only carry scopes the API defines
+    if let Some(resource) = &params.resource {
+        let resource = match services
+            .api_resource_service
+            .get_resource_for_client(resource, &params.client_id)
+            .await
+        {
+            Ok(Some(resource)) => resource,
+            Ok(None) => {
+                return Err(InvalidRequest::Redirect(
+                    "invalid_target",
+                    "Unknown resource",
+                ));
+            }
+            Err(_) => {
+                return Err(InvalidRequest::Rejected(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Could not validate resource",
+                ));
+            }
+        };
+        if !scopes_allowed_for_resource(params.scope.as_deref(), &resource.scopes) {
+            return Err(InvalidRequest::Redirect(
+                "invalid_scope",
+                "The resource does not define the requested scope",
+            ));
+        }
+    }
+
+    Ok((application_info, claims_request))
+}
+
+/// The pushed request `request_uri` refers to, it has to be used by the client that pushed it
+async fn pushed_request(
+    services: &ServicesConfig,
+    reference: &RequestReference,
+    request_uri: &str,
+) -> Result<AuthorizeRequest, Response> {
+    match services
+        .pushed_request_service
+        .get_request(request_uri)
+        .await
+    {
+        Ok(Some(params)) if reference.client_id.as_ref() == Some(&params.client_id) => Ok(params),
+        Ok(_) => Err((
+            StatusCode::BAD_REQUEST,
+            "Invalid or expired request_uri".to_string(),
+        )
+            .into_response()),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Could not load pushed authorization request".to_string(),
+        )
+            .into_response()),
+    }
+}
+
+fn is_within_max_age(session: &SessionData, max_age: Option<i64>) -> bool {
+    match max_age {
+        Some(max_age) => Utc::now().timestamp() - session.auth_time <= max_age,
+        None => true,
+    }
+}
+
+/// Whether the user authenticated in the session after the login a `prompt=login` asked for
+fn is_fresh_login(session: &SessionData, login_after: Option<i64>) -> bool {
+    login_after.is_none_or(|login_after| session.auth_time >= login_after)
+}
+
+/// Whether the session satisfies max_age and a requested login, and belongs to the hinted or requested user of the
+/// application's tenant
+async fn is_usable_session(
+    services: &ServicesConfig,
+    session: &SessionData,
+    params: &AuthorizeRequest,
+    application_info: &Application,
+    hinted_subject: Option<&str>,
+    claims_request: Option<&ClaimsRequest>,
+) -> Result<bool, Response> {
+    if !is_within_max_age(session, params.max_age) || !is_fresh_login(session, params.login_after) {
+        return Ok(false);
+    }
+
+    // Users of other tenants have to log in with an account of the application's tenant
+    let in_tenant = match session.tenant_id {
+        Some(tenant_id) => Ok(tenant_id == application_info.tenant_id),
+        // Sessions started before they were scoped to a tenant
+        None => {
+            services
+                .user_service
+                .is_user_in_tenant(&session.user_id, application_info.tenant_id)
+                .await
+        }
+    };
+    match in_tenant {
+        Ok(true) => {}
+        Ok(false) => return Ok(false),
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Could not validate session".to_string(),
+            )
+                .into_response());
+        }
+    }
+
+    // Hints carry the subject the client knows, which may be pairwise
+    let subject = match services
+        .subject_service
+        .subject_for(&session.user_id, &params.client_id)
+        .await
+    {
+        Ok(subject) => subject,
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Could not resolve subject".to_string(),
+            )
+                .into_response());
+        }
+    };
+
+    Ok(
+        hinted_subject.is_none_or(|hinted_subject| hinted_subject == subject)
+            && subject_matches(claims_request, &subject),
+    )
+}
+
+/// Accounts of the browser's sessions that can be used for the request
+async fn browser_accounts(
+    services: &ServicesConfig,
+    cookies: Option<&Cookie>,
+    params: &AuthorizeRequest,
+    application_info: &Application,
+    hinted_subject: Option<&str>,
+    claims_request: Option<&ClaimsRequest>,
+) -> Result<Vec<AccountChoice>, Response> {
+    let mut session_ids =
+        parse_session_ids(cookies.and_then(|cookies| cookies.get(SESSION_IDS_COOKIE)));
+    // The active session may have been started before the browser's sessions were tracked
+    if let Some(session_id) = cookies.and_then(|cookies| cookies.get("session_id"))
+        && !session_ids.iter().any(|id| id == session_id)
+    {
+        session_ids.push(session_id.to_owned());
+    }
+
+    let now = Utc::now().timestamp();
+    let mut accounts = Vec::new();
+    for session_id in session_ids {
+        let session = match services.session_service.get_session(&session_id).await {
+            Ok(Some(session)) if session.remaining_ttl(now).is_some() => session,
+            Ok(_) => continue,
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Could not validate session".to_string(),
+                )
+                    .into_response());
+            }
+        };
+        if !is_usable_session(
+            services,
+            &session,
+            params,
+            application_info,
+            hinted_subject,
+            claims_request,
+        )
+        .await?
+        {
+            continue;
+        }
+
+        if let Ok(Some((email, tenant_name))) = services
+            .user_service
+            .get_account_label(&session.user_id)
+            .await
+        {
+            accounts.push(AccountChoice {
+                session_id,
+                email,
+                tenant_name,
+            });
+        }
+    }
+
+    Ok(accounts)
+}
+
+/// Path below `/oauth` that continues this request after the user logged in or chose an account
+async fn return_to(
+    services: &ServicesConfig,
+    params: &AuthorizeRequest,
+    request_uri: Option<&str>,
+) -> Result<String, Response> {
+    // The user is about to log in, so the request must not force another login when it returns.
+    // It still only accepts a session of a login that happened from now on.
+    let mut return_params = params.clone();
+    let prompt = params.prompt_values();
+    if prompt.contains(&"login") {
+        return_params.login_after = Some(Utc::now().timestamp());
+    }
+    let remaining_prompt: Vec<&str> = prompt
+        .into_iter()
+        .filter(|value| *value != "login" && *value != "select_account")
+        .collect();
+    return_params.prompt = (!remaining_prompt.is_empty()).then(|| remaining_prompt.join(" "));
+
+    // Pushed requests stay on the server, only their reference is passed through the login
+    let return_to = match request_uri {
+        Some(request_uri) => {
+            if services
+                .pushed_request_service
+                .update_request(request_uri, &return_params)
+                .await
+                .is_err()
+            {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Could not store pushed authorization request".to_string(),
+                )
+                    .into_response());
+            }
+            format!(
+                "/authorize?{}",
+                serde_urlencoded::to_string([
+                    ("client_id", params.client_id.as_str()),
+                    ("request_uri", request_uri),
+                ])
+                .unwrap()
+            )
+        }
+        None => format!(
+            "/authorize?{}",
+            serde_urlencoded::to_string(&return_params).unwrap()
+        ),
+    };
+
+    Ok(return_to)
+}
+
+/// The issuer's `login_url` with the client and the login hint of the request
+fn login_url(params: &AuthorizeRequest, mut login_url: String) -> String {
+    // The client tells the login which tenant's users can sign in
+    login_url.push_str("&client_id=");
+    login_url.push_str(&urlencoding::encode(&params.client_id));
+    if let Some(login_hint) = &params.login_hint {
+        login_url.push_str("&login_hint=");
+        login_url.push_str(&urlencoding::encode(login_hint));
+    }
+
+    login_url
+}
+
+/// Show an error that cannot be sent to the client's redirect URI
+fn rejection(invalid_request: InvalidRequest) -> Response {
+    match invalid_request {
+        InvalidRequest::Rejected(status, message) => (status, message.to_string()).into_response(),
+        InvalidRequest::Redirect(_, message) | InvalidRequest::RequestObject(message) => {
+            (StatusCode::BAD_REQUEST, message.to_string()).into_response()
+        }
+    }
+}
+
+/// Return an authorization error to the client
+fn error_redirect(
+    token_issuer: &TokenIssuer,
+    params: &AuthorizeRequest,
+    error: &str,
+    description: &str,
+) -> Response {
+    authorization_response(
+        token_issuer,
+        params,
+        vec![
+            ("error", error.to_owned()),
+            ("error_description", description.to_owned()),
+        ],
+    )
+}
+
+/// Return the response parameters to the redirect URI in the requested response mode,
+/// with `state` and the `iss` of this server (RFC 9207)
+fn authorization_response(
+    token_issuer: &TokenIssuer,
+    params: &AuthorizeRequest,
+    mut response: Vec<(&str, String)>,
+) -> Response {
+    if let Some(state) = &params.state {
+        response.push(("state", state.clone()));
+    }
+
+    // Unsupported modes were rejected with an error that is returned as a query
+    let response_mode = params.response_mode().unwrap_or_default();
+    let response = match response_mode.is_jwt() {
+        true => {
+            let claims = response
+                .into_iter()
+                .map(|(name, value)| (name.to_owned(), value.into()))
+                .collect();
+            match token_issuer.create_authorization_response(
+                &params.client_id,
+                claims,
+                AUTHORIZATION_RESPONSE_LIFETIME,
+            ) {
+                Ok(jwt) => vec![("response", jwt)],
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Could not sign authorization response".to_string(),
+                    )
+                        .into_response();
+                }
+            }
+        }
+        false => {
+            response.push(("iss", token_issuer.issuer.clone()));
+            response
+        }
+    };
+
+    match response_mode {
+        ResponseMode::Query | ResponseMode::QueryJwt => {
+            Redirect::temporary(&response_url(&params.redirect_uri, &response, false))
+                .into_response()
+        }
+        ResponseMode::Fragment | ResponseMode::FragmentJwt => {
+            Redirect::temporary(&response_url(&params.redirect_uri, &response, true))
+                .into_response()
+        }
+        ResponseMode::FormPost | ResponseMode::FormPostJwt => {
+            Html(form_post_page(&params.redirect_uri, &response)).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_login_url_names_client() {
+        let params: AuthorizeRequest = serde_urlencoded::from_str(
+            "response_type=code&client_id=acme%20app&redirect_uri=https%3A%2F%2Facme.example%2Fcb&login_hint=jane%40acme.example",
+        )
+        .unwrap();
+
+        // The login UI sends the client with the credentials, so only its tenant's users log in
+        let login_url = login_url(
+            &params,
+            "https://login.example.com/login?return_to=%2Fauthorize".to_owned(),
+        );
+        let (_, query) = login_url.split_once('?').unwrap();
+        let query: HashMap<String, String> = serde_urlencoded::from_str(query).unwrap();
+        assert_eq!(query["client_id"], "acme app");
+        assert_eq!(query["return_to"], "/authorize");
+        assert_eq!(query["login_hint"], "jane@acme.example");
+    }
+
+    #[test]
+    fn test_prompt_login_needs_a_later_login() {
+        let mut session = SessionData::new("jane".to_owned(), Uuid::new_v4());
+        let requested_at = session.auth_time;
+        assert!(is_fresh_login(&session, None));
+        assert!(is_fresh_login(&session, Some(requested_at)));
+
+        // The session from before the request cannot stand in for the requested login
+        session.auth_time = requested_at - 60;
+        assert!(!is_fresh_login(&session, Some(requested_at)));
+    }
 }
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::Query,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use uuid::Uuid;
+
+use crate::{
+    models::{branding::BrandingQuery, services_config::ServicesConfig},
+    utils::{branding_utils::PageBranding, tenant_issuer::TenantIssuer},
+};
+
+/// Branding of the application's tenant or of the issuer's tenant, applied by the login UI
+pub async fn get_branding(
+    Query(query): Query<BrandingQuery>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+) -> Response {
+    let tenant_id = match &query.client_id {
+        Some(client_id) => match services
+            .application_service
+            .get_client_information(client_id)
+            .await
+        {
+            Ok(application) if issuer.serves(application.tenant_id) => application.tenant_id,
+            Ok(_) | Err(_) => {
+                return (StatusCode::BAD_REQUEST, "Invalid client_id").into_response();
+            }
+        },
+        None => match issuer.tenant_id {
+            Some(tenant_id) => tenant_id,
+            // The server's own pages keep the default look
+            None => return Json(serde_json::json!({})).into_response(),
+        },
+    };
+
+    match services.branding_service.get_branding(tenant_id).await {
+        Ok(branding) => Json(branding.unwrap_or_default()).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// Branding for a page rendered by the server, the default look without a tenant or on errors
+pub(crate) async fn page_branding(
+    services: &ServicesConfig,
+    tenant_id: Option<Uuid>,
+    headers: &HeaderMap,
+) -> PageBranding {
+    let branding = match tenant_id {
+        Some(tenant_id) => services
+            .branding_service
+            .get_branding(tenant_id)
+            .await
+            .unwrap_or_else(|err| {
+                eprintln!("Failed to load branding of tenant {tenant_id}: {err:?}");
+                None
+            }),
+        None => None,
+    };
+
+    PageBranding::new(branding, headers)
+}
@@ -0,0 +1,209 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::{StatusCode, header::WWW_AUTHENTICATE},
+    response::{IntoResponse, Response},
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use serde_json::{Value, json};
+
+use crate::{
+    models::{
+        application_model::SubjectType,
+        client_registration::{ClientMetadata, ClientUpdateRequest, RegisteredClientSQL},
+        services_config::ServicesConfig,
+    },
+    utils::{client_registration_utils::RegistrationError, tenant_issuer::TenantIssuer},
+};
+
+type BearerHeader = Option<TypedHeader<Authorization<Bearer>>>;
+
+/// Client registration endpoint (RFC 7591), open or with an initial access token of a tenant
+pub async fn register_client_handler(
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+    Json(body): Json<Value>,
+) -> Response {
+    let initial_access_token = authorization
+        .as_ref()
+        .map(|TypedHeader(Authorization(bearer))| bearer.token());
+    let tenant_id = match services
+        .client_registration_service
+        .get_registration_tenant(initial_access_token)
+        .await
+    {
+        // A tenant's issuer only registers clients into its tenant
+        Ok(Some(tenant_id)) if issuer.serves(tenant_id) => tenant_id,
+        Ok(_) => return invalid_token(),
+        Err(e) => return registration_error(e.into()),
+    };
+    let metadata: ClientMetadata = match serde_json::from_value(body) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            return registration_error(RegistrationError::InvalidClientMetadata(e.to_string()));
+        }
+    };
+    if let Err(e) = check_subject_type(&services, &metadata) {
+        return registration_error(e);
+    }
+
+    match services
+        .client_registration_service
+        .register_client(tenant_id, metadata)
+        .await
+    {
+        Ok(client) => (StatusCode::CREATED, Json(client)).into_response(),
+        Err(e) => registration_error(e),
+    }
+}
+
+/// Client read request of the client configuration endpoint (RFC 7592 2.1)
+pub async fn get_client_handler(
+    Path(client_id): Path<String>,
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    let Some(TypedHeader(Authorization(bearer))) = authorization else {
+        return invalid_token();
+    };
+
+    match services
+        .client_registration_service
+        .get_client(&client_id, bearer.token())
+        .await
+    {
+        Ok(Some(client)) => Json(client).into_response(),
+        Ok(None) => invalid_token(),
+        Err(e) => registration_error(e.into()),
+    }
+}
+
+/// Client update request of the client configuration endpoint (RFC 7592 2.2)
+pub async fn update_client_handler(
+    Path(client_id): Path<String>,
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Json(body): Json<Value>,
+) -> Response {
+    let client = match registered_client(&services, &client_id, authorization).await {
+        Ok(client) => client,
+        Err(response) => return response,
+    };
+    let request: ClientUpdateRequest = match serde_json::from_value(body) {
+        Ok(request) => request,
+        Err(e) => {
+            return registration_error(RegistrationError::InvalidClientMetadata(e.to_string()));
+        }
+    };
+    if request.client_id != client.client_id
+        || request
+            .client_secret
+            .is_some_and(|client_secret| client_secret != client.client_secret)
+    {
+        return registration_error(RegistrationError::InvalidClientMetadata(
+            "client_id and client_secret must match the registered client".to_string(),
+        ));
+    }
+    if let Err(e) = check_subject_type(&services, &request.metadata) {
+        return registration_error(e);
+    }
+
+    match services
+        .client_registration_service
+        .update_client(client, request.metadata)
+        .await
+    {
+        Ok(client) => Json(client).into_response(),
+        Err(e) => registration_error(e),
+    }
+}
+
+/// Client delete request of the client configuration endpoint (RFC 7592 2.3)
+pub async fn delete_client_handler(
+    Path(client_id): Path<String>,
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    let client = match registered_client(&services, &client_id, authorization).await {
+        Ok(client) => client,
+        Err(response) => return response,
+    };
+
+    match services
+        .client_registration_service
+        .delete_client(client)
+        .await
+    {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => registration_error(e.into()),
+    }
+}
+
+/// The client the registration access token was issued for
+async fn registered_client(
+    services: &ServicesConfig,
+    client_id: &str,
+    authorization: BearerHeader,
+) -> Result<RegisteredClientSQL, Response> {
+    let Some(TypedHeader(Authorization(bearer))) = authorization else {
+        return Err(invalid_token());
+    };
+
+    match services
+        .client_registration_service
+        .registered_client(client_id, bearer.token())
+        .await
+    {
+        Ok(Some(client)) => Ok(client),
+        Ok(None) => Err(invalid_token()),
+        Err(e) => Err(registration_error(e.into())),
+    }
+}
+
+/// Missing or unknown initial or registration access token, also for clients that do not exist
+fn invalid_token() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(WWW_AUTHENTICATE, "Bearer error=\"invalid_token\"")],
+        Json(json!({ "error": "invalid_token" })),
+    )
+        .into_response()
+}
+
+/// Pairwise subjects can only be registered if the server has a salt to derive them with
+fn check_subject_type(
+    services: &ServicesConfig,
+    metadata: &ClientMetadata,
+) -> Result<(), RegistrationError> {
+    if metadata.subject_type == SubjectType::Pairwise
+        && !services.subject_service.supports_pairwise()
+    {
+        return Err(RegistrationError::InvalidClientMetadata(
+            "subject_type pairwise is not supported by this server".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn registration_error(error: RegistrationError) -> Response {
+    if let RegistrationError::Internal(_) = error {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error while processing client registration",
+        )
+            .into_response();
+    }
+
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({ "error": error.error_code(), "error_description": error.to_string() })),
+    )
+        .into_response()
+}
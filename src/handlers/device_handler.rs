@@ -0,0 +1,308 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Form, Json,
+    extract::Query,
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_extra::{TypedHeader, headers::Cookie};
+use uuid::Uuid;
+
+use crate::{
+    handlers::{
+        branding_handler::page_branding,
+        token_handler::{authenticate_client, oauth_error},
+    },
+    models::{
+        client_certificate::ClientCertificate,
+        device_grant::{
+            DEVICE_CODE_GRANT_TYPE, DeviceAuthorizationRequest, DeviceAuthorizationResponse,
+            DeviceGrantData, DeviceGrantStatus, DeviceVerificationForm, DeviceVerificationQuery,
+        },
+        services_config::ServicesConfig,
+        session::SessionData,
+    },
+    services::device_grant_service::DEVICE_CODE_LIFETIME,
+    utils::{
+        branding_utils::PageBranding,
+        claims_utils::scopes_allowed,
+        device_utils::{
+            approval_page, format_user_code, normalize_user_code, result_page, user_code_page,
+        },
+        tenant_issuer::TenantIssuer,
+    },
+};
+
+/// Device authorization request of a client without a browser (RFC 8628 3.1)
+pub async fn device_authorization(
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+    client_certificate: Option<ClientCertificate>,
+    Form(request): Form<DeviceAuthorizationRequest>,
+) -> Response {
+    let application = match authenticate_client(
+        &services,
+        &issuer,
+        &request.client_id,
+        &request.client_secret,
+        client_certificate.as_ref(),
+    )
+    .await
+    {
+        Ok(application) => application,
+        Err(response) => return response,
+    };
+    if !application.allows_grant_type(DEVICE_CODE_GRANT_TYPE) {
+        return oauth_error("unauthorized_client");
+    }
+    if !scopes_allowed(
+        request.scope.as_deref(),
+        application.allowed_scopes.as_deref(),
+    ) {
+        return oauth_error("invalid_scope");
+    }
+
+    let (device_code, grant) = match services
+        .device_grant_service
+        .create_grant(&request.client_id, request.scope)
+        .await
+    {
+        Ok(grant) => grant,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to start device authorization",
+            )
+                .into_response();
+        }
+    };
+
+    // Where the user enters the code shown on the device, at the issuer's host
+    let verification_uri = issuer.endpoint_url("/device");
+    let user_code = format_user_code(&grant.user_code);
+    Json(DeviceAuthorizationResponse {
+        device_code,
+        verification_uri_complete: format!("{}?user_code={}", verification_uri, user_code),
+        user_code,
+        verification_uri,
+        expires_in: DEVICE_CODE_LIFETIME,
+        interval: grant.interval,
+    })
+    .into_response()
+}
+
+/// Page where a signed in user enters the code of a device, then approves or denies it
+pub async fn device_verification(
+    Query(query): Query<DeviceVerificationQuery>,
+    cookies: Option<TypedHeader<Cookie>>,
+    headers: HeaderMap,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+) -> Response {
+    let user_code = query
+        .user_code
+        .filter(|user_code| !user_code.trim().is_empty());
+
+    let session = match current_session(&services, cookies.as_ref()).await {
+        Ok(Some(session)) => session,
+        Ok(None) => return login_redirect(&issuer, user_code.as_deref()),
+        Err(response) => return response,
+    };
+
+    // The application is only known once the code was entered
+    let issuer_branding = page_branding(&services, issuer.tenant_id, &headers).await;
+    let Some(user_code) = user_code else {
+        return Html(user_code_page(&issuer_branding, None)).into_response();
+    };
+    let (_, grant) = match pending_grant(&services, &issuer_branding, &user_code).await {
+        Ok(grant) => grant,
+        Err(response) => return response,
+    };
+
+    let application = match services
+        .application_service
+        .get_client_information(&grant.client_id)
+        .await
+    {
+        Ok(application) => application,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let branding = page_branding(&services, Some(application.tenant_id), &headers).await;
+    if let Err(response) = check_user_tenant(
+        &services,
+        &branding,
+        &session.user_id,
+        application.tenant_id,
+    )
+    .await
+    {
+        return response;
+    }
+
+    Html(approval_page(
+        &branding,
+        &grant.user_code,
+        &application.name,
+        grant.scope.as_deref(),
+    ))
+    .into_response()
+}
+
+pub async fn device_decision(
+    cookies: Option<TypedHeader<Cookie>>,
+    headers: HeaderMap,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+    Form(form): Form<DeviceVerificationForm>,
+) -> Response {
+    let session = match current_session(&services, cookies.as_ref()).await {
+        Ok(Some(session)) => session,
+        Ok(None) => return login_redirect(&issuer, Some(&form.user_code)),
+        Err(response) => return response,
+    };
+
+    let issuer_branding = page_branding(&services, issuer.tenant_id, &headers).await;
+    let (device_code, mut grant) =
+        match pending_grant(&services, &issuer_branding, &form.user_code).await {
+            Ok(grant) => grant,
+            Err(response) => return response,
+        };
+
+    let application = match services
+        .application_service
+        .get_client_information(&grant.client_id)
+        .await
+    {
+        Ok(application) => application,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let branding = page_branding(&services, Some(application.tenant_id), &headers).await;
+    if let Err(response) = check_user_tenant(
+        &services,
+        &branding,
+        &session.user_id,
+        application.tenant_id,
+    )
+    .await
+    {
+        return response;
+    }
+
+    let approved = match form.action.as_str() {
+        "approve" => true,
+        "deny" => false,
+        _ => return (StatusCode::BAD_REQUEST, "Invalid action").into_response(),
+    };
+    grant.status = match approved {
+        true => DeviceGrantStatus::Approved {
+            user_id: session.user_id,
+            auth_time: session.auth_time,
+        },
+        false => DeviceGrantStatus::Denied,
+    };
+
+    // Another decision on the code may have been made since it was looked up
+    match services
+        .device_grant_service
+        .decide_grant(&device_code, &grant)
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            return Html(user_code_page(
+                &branding,
+                Some("This code is invalid or has expired. Check the code on your device."),
+            ))
+            .into_response();
+        }
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+
+    Html(result_page(&branding, approved)).into_response()
+}
+
+/// The grant of a user code that still waits for a decision, the code entry page otherwise
+async fn pending_grant(
+    services: &ServicesConfig,
+    branding: &PageBranding,
+    user_code: &str,
+) -> Result<(String, DeviceGrantData), Response> {
+    let invalid_code = || {
+        Html(user_code_page(
+            branding,
+            Some("This code is invalid or has expired. Check the code on your device."),
+        ))
+        .into_response()
+    };
+
+    let Some(user_code) = normalize_user_code(user_code) else {
+        return Err(invalid_code());
+    };
+
+    match services
+        .device_grant_service
+        .get_grant_by_user_code(&user_code)
+        .await
+    {
+        Ok(Some((device_code, grant))) if grant.status == DeviceGrantStatus::Pending => {
+            Ok((device_code, grant))
+        }
+        Ok(_) => Err(invalid_code()),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+/// Users only approve devices of the applications of their own tenant
+async fn check_user_tenant(
+    services: &ServicesConfig,
+    branding: &PageBranding,
+    user_id: &str,
+    tenant_id: Uuid,
+) -> Result<(), Response> {
+    match services
+        .user_service
+        .is_user_in_tenant(user_id, tenant_id)
+        .await
+    {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Html(user_code_page(branding, Some(
+            "This code is for an application of another organization. Sign in with an account of that organization.",
+        )))
+        .into_response()),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+/// The session of the session cookie, `None` if there is none or it expired
+async fn current_session(
+    services: &ServicesConfig,
+    cookies: Option<&TypedHeader<Cookie>>,
+) -> Result<Option<SessionData>, Response> {
+    let Some(session_id) = cookies.and_then(|TypedHeader(cookies)| cookies.get("session_id"))
+    else {
+        return Ok(None);
+    };
+
+    services
+        .session_service
+        .touch_session(session_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Could not validate session",
+            )
+                .into_response()
+        })
+}
+
+/// Redirect to the login UI, returning to the verification page with the code entered so far
+fn login_redirect(issuer: &TenantIssuer, user_code: Option<&str>) -> Response {
+    let return_to = match user_code {
+        Some(user_code) => format!("/device?user_code={}", urlencoding::encode(user_code)),
+        None => "/device".to_owned(),
+    };
+
+    Redirect::to(&issuer.login_url(&return_to)).into_response()
+}
@@ -0,0 +1,428 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    Extension, Json,
+    extract::{ConnectInfo, Path, Query},
+    http::{HeaderMap, StatusCode, header::LOCATION, header::SET_COOKIE},
+    response::{AppendHeaders, IntoResponse, Redirect, Response},
+};
+use axum_extra::{TypedHeader, headers::Cookie as CookieHeader};
+use cookie::Cookie;
+
+use crate::{
+    handlers::login_handler::{browser_sessions_cookie, start_session},
+    models::{
+        identity_provider::{
+            FederatedUser, FederationCallbackRequest, FederationLoginRequest,
+            FederationProvidersRequest, FederationState, IdentityProviderSQL,
+        },
+        services_config::ServicesConfig,
+        session::SessionData,
+    },
+    utils::{
+        client_info_utils::client_info,
+        federation_utils::{
+            FEDERATION_STATE_COOKIE, federated_user, is_valid_return_to, pkce_challenge,
+            random_token, state_cookie, state_matches_cookie,
+        },
+        tenant_issuer::TenantIssuer,
+    },
+};
+
+/// How long a user may take to log in at the upstream provider
+const FEDERATION_STATE_TTL: u64 = 600;
+
+/// Identity providers offered on the login page of an application
+pub async fn federation_providers(
+    Query(params): Query<FederationProvidersRequest>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    match services
+        .federation_service
+        .list_providers_for_client(&params.client_id)
+        .await
+    {
+        Ok(providers) => Json(providers).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// Redirect the user to the upstream provider's authorization endpoint
+pub async fn federation_login(
+    Path(provider_id): Path<String>,
+    Query(params): Query<FederationLoginRequest>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+) -> Response {
+    if let Some(return_to) = &params.return_to
+        && !is_valid_return_to(return_to)
+    {
+        return (StatusCode::BAD_REQUEST, "Invalid return_to").into_response();
+    }
+
+    let provider = match provider(&services, &issuer, &provider_id).await {
+        Ok(provider) => provider,
+        Err(response) => return response,
+    };
+
+    let metadata = match services.federation_service.discover(&provider).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            println!(
+                "Discovery of identity provider {} failed: {}",
+                provider.id, e
+            );
+            return StatusCode::BAD_GATEWAY.into_response();
+        }
+    };
+
+    let (Ok(state), Ok(nonce), Ok(code_verifier)) =
+        (random_token(), random_token(), random_token())
+    else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let code_challenge = pkce_challenge(&code_verifier);
+
+    let federation_state = FederationState {
+        provider_id: provider.id.clone(),
+        nonce: nonce.clone(),
+        code_verifier,
+        // Resume at the issuer the login started from, the callback may arrive at another
+        return_to: params
+            .return_to
+            .map(|return_to| issuer.endpoint_url(&return_to)),
+    };
+    if services
+        .federation_service
+        .store_state(&state, &federation_state, FEDERATION_STATE_TTL)
+        .await
+        .is_err()
+    {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    let query = serde_urlencoded::to_string([
+        ("response_type", "code"),
+        ("client_id", provider.client_id.as_str()),
+        (
+            "redirect_uri",
+            services
+                .federation_service
+                .redirect_uri(&provider.id)
+                .as_str(),
+        ),
+        ("scope", provider.scopes.join(" ").as_str()),
+        ("state", state.as_str()),
+        ("nonce", nonce.as_str()),
+        ("code_challenge", code_challenge.as_str()),
+        ("code_challenge_method", "S256"),
+    ])
+    .unwrap();
+    let separator = if metadata.authorization_endpoint.contains('?') {
+        '&'
+    } else {
+        '?'
+    };
+
+    (
+        [(
+            SET_COOKIE,
+            state_cookie(&state, FEDERATION_STATE_TTL).to_string(),
+        )],
+        Redirect::to(&format!(
+            "{}{}{}",
+            metadata.authorization_endpoint, separator, query
+        )),
+    )
+        .into_response()
+}
+
+/// Finish a federated login and continue the authorization request it started from
+pub async fn federation_callback(
+    Path(provider_id): Path<String>,
+    Query(params): Query<FederationCallbackRequest>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    cookies: Option<TypedHeader<CookieHeader>>,
+    headers: HeaderMap,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+) -> Response {
+    // Only the browser that started the login may finish it
+    let state_cookie = cookies
+        .as_ref()
+        .and_then(|TypedHeader(cookies)| cookies.get(FEDERATION_STATE_COOKIE));
+    if !state_matches_cookie(&params.state, state_cookie) {
+        return (StatusCode::BAD_REQUEST, "Invalid or expired state").into_response();
+    }
+
+    let provider = match provider(&services, &issuer, &provider_id).await {
+        Ok(provider) => provider,
+        Err(response) => return response,
+    };
+
+    let federation_state = match services
+        .federation_service
+        .consume_state(&params.state)
+        .await
+    {
+        Ok(Some(state)) if state.provider_id == provider_id => state,
+        Ok(_) => return (StatusCode::BAD_REQUEST, "Invalid or expired state").into_response(),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    if let Some(error) = &params.error {
+        return (
+            StatusCode::UNAUTHORIZED,
+            format!("Upstream login failed: {}", error),
+        )
+            .into_response();
+    }
+    let Some(code) = &params.code else {
+        return (StatusCode::BAD_REQUEST, "Missing code").into_response();
+    };
+
+    let metadata = match services.federation_service.discover(&provider).await {
+        Ok(metadata) => metadata,
+        Err(_) => return StatusCode::BAD_GATEWAY.into_response(),
+    };
+
+    let redirect_uri = services.federation_service.redirect_uri(&provider.id);
+    let claims = match services
+        .federation_service
+        .exchange_code(&provider, &metadata, code, &redirect_uri, &federation_state)
+        .await
+    {
+        Ok(claims) => claims,
+        Err(e) => {
+            println!("Federated login with {} failed: {}", provider.id, e);
+            return (StatusCode::UNAUTHORIZED, "Upstream login failed").into_response();
+        }
+    };
+
+    let upstream_user = match federated_user(&claims, &provider.claim_mapping) {
+        Ok(user) => user,
+        Err(_) => return (StatusCode::UNAUTHORIZED, "Upstream login failed").into_response(),
+    };
+
+    let user_id = match resolve_federated_user(&services, &provider, &upstream_user).await {
+        Ok(user_id) => user_id,
+        Err(response) => return response,
+    };
+
+    match services.user_service.is_user_active(&user_id).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::FORBIDDEN, "User is disabled").into_response(),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+
+    let mut session = SessionData::new(user_id, provider.tenant_id);
+    (session.ip, session.user_agent) = client_info(&addr, &headers);
+    session.auth_methods = vec!["fed".to_string()];
+
+    let cookie = match start_session(&services, &mut session, false).await {
+        Ok(cookie) => cookie,
+        Err(response) => return response,
+    };
+    let sessions_cookie = browser_sessions_cookie(&services, &headers, cookie.value()).await;
+
+    let location = match federation_state.return_to {
+        Some(return_to) => return_to,
+        None => format!("{}/", issuer.login_ui_url),
+    };
+
+    (
+        StatusCode::SEE_OTHER,
+        AppendHeaders([
+            (SET_COOKIE, cookie.to_string()),
+            (SET_COOKIE, sessions_cookie.to_string()),
+            (
+                SET_COOKIE,
+                Cookie::build((FEDERATION_STATE_COOKIE, ""))
+                    .path("/")
+                    .removal()
+                    .build()
+                    .to_string(),
+            ),
+            (LOCATION, location),
+        ]),
+    )
+        .into_response()
+}
+
+/// The identity provider `provider_id`, providers of other tenants are unknown to a tenant's issuer
+async fn provider(
+    services: &ServicesConfig,
+    issuer: &TenantIssuer,
+    provider_id: &str,
+) -> Result<IdentityProviderSQL, Response> {
+    match services.federation_service.get_provider(provider_id).await {
+        Ok(Some(provider)) if issuer.serves(provider.tenant_id) => Ok(provider),
+        Ok(_) => Err(StatusCode::NOT_FOUND.into_response()),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+/// The local user behind an upstream identity, linking or provisioning one if the provider allows it
+async fn resolve_federated_user(
+    services: &ServicesConfig,
+    provider: &IdentityProviderSQL,
+    upstream_user: &FederatedUser,
+) -> Result<String, Response> {
+    let internal_error = |_| StatusCode::INTERNAL_SERVER_ERROR.into_response();
+
+    if let Some(user_id) = services
+        .federation_service
+        .find_linked_user(&provider.id, &upstream_user.subject)
+        .await
+        .map_err(internal_error)?
+    {
+        return Ok(user_id);
+    }
+
+    // Only a verified address proves the upstream identity owns the local account
+    let mut user_id = None;
+    if provider.link_by_email
+        && upstream_user.email_verified
+        && let Some(email) = &upstream_user.email
+    {
+        user_id = services
+            .user_service
+            .find_user_id_in_tenant(provider.tenant_id, email)
+            .await
+            .map_err(internal_error)?;
+    }
+
+    let user_id = match user_id {
+        Some(user_id) => user_id,
+        None if provider.auto_provision => services
+            .user_service
+            .provision_federated_user(provider.tenant_id, upstream_user)
+            .await
+            .map_err(|e| {
+                println!("Provisioning federated user failed: {}", e);
+                (StatusCode::CONFLICT, "Unable to create user").into_response()
+            })?,
+        None => {
+            return Err((StatusCode::FORBIDDEN, "No user linked to this identity").into_response());
+        }
+    };
+
+    services
+        .federation_service
+        .link_user(&provider.id, &upstream_user.subject, &user_id)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(user_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use axum::http::HeaderValue;
+    use axum_extra::headers::Header;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::utils::{
+        database::{create_test_tenant, delete_test_tenant, test_postgres_pool},
+        setup::test_services,
+        tenant_issuer::test_issuer,
+    };
+
+    /// Provider of `tenant_id` whose discovery document cannot be fetched
+    async fn create_provider(tenant_id: Uuid) -> String {
+        let provider_id = Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO IdentityProviders \
+             (id, tenant_id, name, discovery_url, client_id, client_secret, scopes) \
+             VALUES ($1, $2, 'Upstream', 'http://127.0.0.1:9', 'sso', 'secret', ARRAY['openid'])",
+        )
+        .bind(&provider_id)
+        .bind(tenant_id)
+        .execute(&test_postgres_pool().await)
+        .await
+        .unwrap();
+
+        provider_id
+    }
+
+    async fn login(
+        services: &Arc<ServicesConfig>,
+        issuer: &Arc<TenantIssuer>,
+        provider_id: &str,
+    ) -> Response {
+        federation_login(
+            Path(provider_id.to_owned()),
+            Query(FederationLoginRequest { return_to: None }),
+            Extension(services.clone()),
+            Extension(issuer.clone()),
+        )
+        .await
+    }
+
+    async fn callback(
+        services: &Arc<ServicesConfig>,
+        issuer: &Arc<TenantIssuer>,
+        provider_id: &str,
+        state: &str,
+    ) -> Response {
+        let cookie = format!("{FEDERATION_STATE_COOKIE}={}", pkce_challenge(state));
+        let cookie = HeaderValue::from_str(&cookie).unwrap();
+        federation_callback(
+            Path(provider_id.to_owned()),
+            Query(FederationCallbackRequest {
+                code: Some("code".to_owned()),
+                state: state.to_owned(),
+                error: None,
+            }),
+            ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 0))),
+            Some(TypedHeader(
+                CookieHeader::decode(&mut iter::once(&cookie)).unwrap(),
+            )),
+            HeaderMap::new(),
+            Extension(services.clone()),
+            Extension(issuer.clone()),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_providers_of_other_tenants_are_unknown() {
+        let services = test_services().await;
+        let db_pool = test_postgres_pool().await;
+        let tenant_id = create_test_tenant(&db_pool).await;
+        let other_tenant_id = create_test_tenant(&db_pool).await;
+        let provider_id = create_provider(tenant_id).await;
+        let issuer = Arc::new(test_issuer(Some(tenant_id), None));
+        let other_issuer = Arc::new(test_issuer(Some(other_tenant_id), None));
+
+        // The tenant's own issuer gets as far as the provider's discovery
+        let response = login(&services, &issuer, &provider_id).await;
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        let response = login(&services, &other_issuer, &provider_id).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let state = random_token().unwrap();
+        let federation_state = FederationState {
+            provider_id: provider_id.clone(),
+            nonce: random_token().unwrap(),
+            code_verifier: random_token().unwrap(),
+            return_to: None,
+        };
+        services
+            .federation_service
+            .store_state(&state, &federation_state, FEDERATION_STATE_TTL)
+            .await
+            .unwrap();
+        let response = callback(&services, &other_issuer, &provider_id, &state).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = callback(&services, &issuer, &provider_id, &state).await;
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+
+        delete_test_tenant(&db_pool, tenant_id).await;
+        delete_test_tenant(&db_pool, other_tenant_id).await;
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Form, Json,
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    handlers::token_handler::authenticate_client,
+    models::{
+        client_certificate::ClientCertificate,
+        dpop::DPOP_TOKEN_TYPE,
+        introspection::{IntrospectionRequest, IntrospectionResponse},
+        services_config::ServicesConfig,
+    },
+    utils::{dpop_utils::bound_key, tenant_issuer::TenantIssuer, token_verifier::TokenVerifier},
+};
+
+/// Token introspection (RFC 7662) for authenticated clients
+pub async fn introspect(
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+    client_certificate: Option<ClientCertificate>,
+    Form(params): Form<IntrospectionRequest>,
+) -> Response {
+    if let Err(response) = authenticate_client(
+        &services,
+        &issuer,
+        &params.client_id,
+        &params.client_secret,
+        client_certificate.as_ref(),
+    )
+    .await
+    {
+        return response;
+    }
+
+    let response = if params.token_type_hint.as_deref() == Some("refresh_token") {
+        match introspect_refresh_token(&services, &issuer.token_verifier, &params).await {
+            Some(response) => Some(response),
+            None => introspect_access_token(&services, &issuer.token_verifier, &params.token).await,
+        }
+    } else {
+        match introspect_access_token(&services, &issuer.token_verifier, &params.token).await {
+            Some(response) => Some(response),
+            None => introspect_refresh_token(&services, &issuer.token_verifier, &params).await,
+        }
+    };
+
+    Json(response.unwrap_or_default()).into_response()
+}
+
+async fn introspect_access_token(
+    services: &ServicesConfig,
+    token_verifier: &TokenVerifier,
+    token: &str,
+) -> Option<IntrospectionResponse> {
+    let claims = token_verifier
+        .verify_issued_access_token(token)
+        .ok()?
+        .claims;
+
+    // The token is only active as long as the user behind its subject is
+    let user_id = services
+        .subject_service
+        .resolve_subject(&claims.sub, &claims.client_id)
+        .await
+        .ok()??;
+    if !services.user_service.is_user_active(&user_id).await.ok()? {
+        return None;
+    }
+
+    let token_type = match bound_key(&claims) {
+        Some(_) => DPOP_TOKEN_TYPE,
+        None => "Bearer",
+    };
+
+    Some(IntrospectionResponse {
+        active: true,
+        scope: claims.scope,
+        client_id: Some(claims.client_id),
+        token_type: Some(token_type.to_string()),
+        sub: Some(claims.sub),
+        aud: Some(claims.aud),
+        iss: Some(claims.iss),
+        exp: Some(claims.exp),
+        iat: Some(claims.iat),
+        act: claims.user_claims.get("act").cloned(),
+        cnf: claims.user_claims.get("cnf").cloned(),
+    })
+}
+
+async fn introspect_refresh_token(
+    services: &ServicesConfig,
+    token_verifier: &TokenVerifier,
+    params: &IntrospectionRequest,
+) -> Option<IntrospectionResponse> {
+    let claims = token_verifier
+        .verify_refresh_token(&params.token)
+        .ok()?
+        .claims;
+
+    // Refresh tokens are only revealed to the client they were issued to
+    let data = services
+        .refresh_token_service
+        .get_token(&claims.jti)
+        .await
+        .ok()??;
+    if data.client_id != params.client_id || data.user_id != claims.sub {
+        return None;
+    }
+    if !services
+        .user_service
+        .is_user_active(&data.user_id)
+        .await
+        .ok()?
+    {
+        return None;
+    }
+
+    let subject = services
+        .subject_service
+        .subject_for(&data.user_id, &data.client_id)
+        .await
+        .ok()?;
+
+    Some(IntrospectionResponse {
+        active: true,
+        scope: data.scope,
+        client_id: Some(data.client_id),
+        token_type: Some("refresh_token".to_string()),
+        sub: Some(subject),
+        exp: Some(claims.exp),
+        iat: Some(claims.iat),
+        ..Default::default()
+    })
+}
@@ -1,14 +1,16 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::State,
+    Extension,
     http::{Response, StatusCode, header},
     response::IntoResponse,
 };
-use serde_json::Value;
 
-pub async fn jwk_set_handler(State(jwk_set): State<Arc<Value>>) -> impl IntoResponse {
-    match serde_json::to_string(&jwk_set) {
+use crate::utils::tenant_issuer::TenantIssuer;
+
+/// Keys of the server's issuer, or of a tenant's below `/t/{tenant_id}`
+pub async fn jwk_set_handler(Extension(issuer): Extension<Arc<TenantIssuer>>) -> impl IntoResponse {
+    match serde_json::to_string(&issuer.jwks) {
         Ok(body) => Response::builder()
             .status(StatusCode::OK)
             .header(header::CONTENT_TYPE, "application/json")
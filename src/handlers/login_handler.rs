@@ -1,52 +1,54 @@
-use crate::models::{login::LoginRequest, services_config::ServicesConfig};
+use crate::models::{login::LoginRequest, services_config::ServicesConfig, session::SessionData};
+use crate::utils::account_chooser_utils::{
+    SESSION_IDS_COOKIE, parse_session_ids, session_ids_cookie,
+};
+use crate::utils::client_info_utils::client_info;
+use crate::utils::tenant_issuer::TenantIssuer;
 use axum::{
-    Json,
-    http::{Response as HttpResponse, StatusCode, header::SET_COOKIE},
-    response::IntoResponse,
+    Extension, Json,
+    extract::ConnectInfo,
+    http::{HeaderMap, Response as HttpResponse, StatusCode, header::SET_COOKIE},
+    response::{IntoResponse, Response},
 };
+use axum_extra::headers::{self, HeaderMapExt};
 use cookie::Cookie;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use uuid::Uuid;
 
 pub async fn authenticate_user(
     services: Arc<ServicesConfig>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(login_request): Json<LoginRequest>,
 ) -> impl IntoResponse {
-    // TODO: Return user_id in the first call
-    let user_has_right_credentials = services.user_service.auth_user(&login_request);
-    if user_has_right_credentials.await.is_some_and(|x| x) {
-        let user = match services
-            .user_service
-            .get_user_id_from_email(&login_request.email)
-            .await
-        {
-            Ok(user) => user,
-            Err(_) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to retrieve mail address",
-                )
-                    .into_response();
-            }
-        };
-        let session_id = Uuid::new_v4().to_string();
-        let ttl: u64 = 900;
+    let tenant_id = match login_tenant(&services, &issuer, login_request.client_id.as_deref()).await
+    {
+        Ok(tenant_id) => tenant_id,
+        Err(response) => return response,
+    };
 
-        if services
-            .session_service
-            .set_session(&session_id, &user, ttl)
+    let authenticated_user = services.user_service.auth_user(&login_request, tenant_id);
+    if let Some(mut user) = authenticated_user.await {
+        match services
+            .user_service
+            .is_password_expired(&user.user_id)
             .await
-            .is_err()
         {
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            Ok(false) => {}
+            Ok(true) => return (StatusCode::FORBIDDEN, "Password expired").into_response(),
+            Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
         }
 
-        let cookie = Cookie::build(("session_id", &session_id))
-            .path("/")
-            .max_age(cookie::time::Duration::seconds(ttl as i64))
-            .http_only(true)
-            .secure(true)
-            .same_site(cookie::SameSite::Lax);
+        (user.ip, user.user_agent) = client_info(&addr, &headers);
+        user.auth_methods = vec!["pwd".to_string()];
+
+        let cookie = match start_session(&services, &mut user, login_request.remember_me).await {
+            Ok(cookie) => cookie,
+            Err(response) => return response,
+        };
+        let sessions_cookie = browser_sessions_cookie(&services, &headers, cookie.value()).await;
 
         let json = match serde_json::to_string(&user) {
             Ok(json) => json,
@@ -57,9 +59,195 @@ pub async fn authenticate_user(
         HttpResponse::builder()
             .status(StatusCode::OK)
             .header(SET_COOKIE, cookie.to_string())
+            .header(SET_COOKIE, sessions_cookie.to_string())
             .body(json.into())
             .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
     } else {
         StatusCode::UNAUTHORIZED.into_response()
     }
 }
+
+/// Tenant whose users can log in, the one of the application or of the tenant's issuer
+async fn login_tenant(
+    services: &ServicesConfig,
+    issuer: &TenantIssuer,
+    client_id: Option<&str>,
+) -> Result<Option<Uuid>, Response> {
+    let Some(client_id) = client_id else {
+        return Ok(issuer.tenant_id);
+    };
+
+    match services
+        .application_service
+        .get_client_information(client_id)
+        .await
+    {
+        Ok(application) if issuer.serves(application.tenant_id) => Ok(Some(application.tenant_id)),
+        Ok(_) | Err(_) => Err((StatusCode::BAD_REQUEST, "Invalid client_id").into_response()),
+    }
+}
+
+/// Store a new session for a freshly authenticated user and build its cookie
+pub(crate) async fn start_session(
+    services: &ServicesConfig,
+    user: &mut SessionData,
+    remember_me: bool,
+) -> Result<Cookie<'static>, Response> {
+    let session_policy = match services
+        .user_service
+        .get_session_policy(&user.user_id)
+        .await
+    {
+        Ok(policy) => policy,
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    };
+
+    let session_id = Uuid::new_v4().to_string();
+    let ttl = user.apply_policy(&session_policy, remember_me);
+
+    if services
+        .session_service
+        .set_session(&session_id, user, ttl)
+        .await
+        .is_err()
+    {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+    }
+
+    Ok(session_cookie(session_id, user))
+}
+
+/// Cookie that makes the session the browser's active one
+pub(crate) fn session_cookie(session_id: String, session: &SessionData) -> Cookie<'static> {
+    // Without "remember me" the cookie lives until the browser is closed,
+    // the server side idle timeout and absolute lifetime still apply
+    let mut cookie = Cookie::build(("session_id", session_id))
+        .path("/")
+        .http_only(true)
+        .secure(true)
+        .same_site(cookie::SameSite::Lax);
+    if session.remember_me {
+        cookie = cookie.max_age(cookie::time::Duration::seconds(
+            session.expires_at - session.created_at,
+        ));
+    }
+
+    cookie.build()
+}
+
+/// Adds a new session to the browser's sessions, dropping the ones that ended
+pub(crate) async fn browser_sessions_cookie(
+    services: &ServicesConfig,
+    headers: &HeaderMap,
+    session_id: &str,
+) -> Cookie<'static> {
+    let cookies = headers.typed_get::<headers::Cookie>();
+    let mut session_ids = Vec::new();
+    for known_session_id in
+        parse_session_ids(cookies.as_ref().and_then(|c| c.get(SESSION_IDS_COOKIE)))
+    {
+        if let Ok(Some(_)) = services
+            .session_service
+            .get_session(&known_session_id)
+            .await
+        {
+            session_ids.push(known_session_id);
+        }
+    }
+    session_ids.push(session_id.to_owned());
+
+    session_ids_cookie(session_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use redis::AsyncCommands;
+
+    use super::*;
+    use crate::{
+        models::session_policy::SessionPolicy,
+        services::config::tenant_service::TenantService,
+        utils::{
+            database::{
+                create_test_tenant, create_test_user, delete_test_tenant, test_postgres_pool,
+            },
+            redis_utils::test_redis_pool,
+            setup::test_services,
+        },
+    };
+
+    #[test]
+    fn test_only_remember_me_sessions_get_a_persistent_cookie() {
+        let policy = SessionPolicy::default();
+
+        let mut session = SessionData::new("user".to_owned(), Uuid::new_v4());
+        session.apply_policy(&policy, false);
+        let cookie = session_cookie("sid".to_owned(), &session);
+        assert_eq!(cookie.max_age(), None);
+        assert_eq!(cookie.http_only(), Some(true));
+
+        session.apply_policy(&policy, true);
+        let cookie = session_cookie("sid".to_owned(), &session);
+        assert_eq!(
+            cookie.max_age(),
+            Some(cookie::time::Duration::seconds(
+                policy.remember_me_lifetime_seconds as i64
+            ))
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_start_session_applies_the_tenant_policy() {
+        let services = test_services().await;
+        let db_pool = test_postgres_pool().await;
+        let tenant_id = create_test_tenant(&db_pool).await;
+        let default_tenant_id = create_test_tenant(&db_pool).await;
+        let policy = SessionPolicy {
+            idle_timeout_seconds: 120,
+            absolute_lifetime_seconds: 600,
+            remember_me_enabled: true,
+            remember_me_lifetime_seconds: 7200,
+        };
+        TenantService::new(db_pool.clone())
+            .upsert_session_policy(tenant_id, &policy)
+            .await
+            .unwrap();
+        let mut redis = test_redis_pool().await.get_owned().await.unwrap();
+
+        for (remember_me, ttl, max_age) in [(false, 120, None), (true, 7200, Some(7200))] {
+            let user_id = create_test_user(&db_pool, tenant_id, false).await;
+            let mut user = SessionData::new(user_id.to_string(), tenant_id);
+            let cookie = start_session(&services, &mut user, remember_me)
+                .await
+                .unwrap();
+
+            assert_eq!(
+                cookie.max_age(),
+                max_age.map(cookie::time::Duration::seconds)
+            );
+            let stored: i64 = redis.ttl(format!("sess:{}", cookie.value())).await.unwrap();
+            assert!(
+                ttl - 5 <= stored && stored <= ttl,
+                "session expires in {stored}s"
+            );
+        }
+
+        // Tenants without a policy get the default one
+        let user_id = create_test_user(&db_pool, default_tenant_id, false).await;
+        let mut user = SessionData::new(user_id.to_string(), default_tenant_id);
+        start_session(&services, &mut user, false).await.unwrap();
+        let default_policy = SessionPolicy::default();
+        assert_eq!(
+            user.idle_timeout,
+            default_policy.idle_timeout_seconds as i64
+        );
+        assert_eq!(
+            user.expires_at - user.created_at,
+            default_policy.absolute_lifetime_seconds as i64
+        );
+
+        delete_test_tenant(&db_pool, tenant_id).await;
+        delete_test_tenant(&db_pool, default_tenant_id).await;
+    }
+}
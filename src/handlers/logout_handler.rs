@@ -1,3 +1,4 @@
+use crate::handlers::session_handler::end_session;
 use crate::models::services_config::ServicesConfig;
 use axum::{
     Extension,
@@ -14,8 +15,8 @@ pub async fn logout(
 ) -> impl IntoResponse {
     // Get session_id from cookie if it exists
     if let Some(session_id) = cookies.get("session_id") {
-        // Delete session from Redis (ignore errors since the goal is cleanup)
-        let _ = services.session_service.delete_session(session_id).await;
+        // Delete session and its refresh tokens from Redis (ignore errors since the goal is cleanup)
+        let _ = end_session(&services, session_id).await;
     }
 
     // Create expired cookies to remove them from the client
@@ -1,7 +1,20 @@
+pub mod attribute_handler;
 pub mod authorization_code_handler;
+pub mod branding_handler;
+pub mod client_registration_handler;
+pub mod device_handler;
+pub mod federation_handler;
+pub mod introspection_handler;
 pub mod jwk_set_handler;
 pub mod login_handler;
 pub mod logout_handler;
 pub mod oidc_discovery_handler;
+pub mod password_handler;
+pub mod pushed_authorization_handler;
+pub mod saml_handler;
+pub mod scim_handler;
+pub mod session_handler;
+pub mod subject_handler;
 pub mod token_handler;
 pub mod user_handler;
+pub mod userinfo_handler;
@@ -1,42 +1,142 @@
+use std::sync::Arc;
+
 use axum::{
+    Extension,
     http::StatusCode,
     response::{IntoResponse, Json},
 };
 
-use crate::models::oidc_discovery_document::OidcDiscoveryDocument;
+use crate::{
+    models::{
+        application_model::{GRANT_TYPES, RESPONSE_TYPES, TokenEndpointAuthMethod},
+        authorize_request::ResponseMode,
+        oidc_discovery_document::OidcDiscoveryDocument,
+        services_config::ServicesConfig,
+    },
+    utils::{
+        dpop_utils::DPOP_ALGORITHMS, request_object_utils::REQUEST_OBJECT_ALGORITHMS,
+        tenant_issuer::TenantIssuer,
+    },
+};
 
-pub async fn discovery_handler() -> impl IntoResponse {
-    // let issuer = config.issuer_url.clone(); // e.g., "https://sso.example.com"
-    let issuer = "https://sso.example.com";
+/// Discovery document of the server's issuer, or of a tenant's below `/t/{tenant_id}`
+pub async fn discovery_handler(
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+) -> impl IntoResponse {
+    // Pairwise subjects are only offered when they can be derived
+    let mut subject_types_supported = vec!["public".to_string()];
+    if services.subject_service.supports_pairwise() {
+        subject_types_supported.push("pairwise".to_string());
+    }
 
-    println!("Returned");
-    return (
+    (
         StatusCode::OK,
         Json(OidcDiscoveryDocument {
-            issuer: issuer.to_string(),
-            authorization_endpoint: format!("{}/oauth/authorize", issuer),
-            token_endpoint: format!("{}/oauth/token", issuer),
-            userinfo_endpoint: None, // Optional: Implement if supporting it
-            jwks_uri: format!("{}/.well-known/jwks.json", issuer),
-            response_types_supported: vec!["code".to_string()],
-            subject_types_supported: vec!["public".to_string()],
+            issuer: issuer.token_issuer.issuer.clone(),
+            authorization_endpoint: issuer.endpoint_url("/authorize"),
+            token_endpoint: issuer.endpoint_url("/token"),
+            userinfo_endpoint: Some(issuer.endpoint_url("/userinfo")),
+            introspection_endpoint: issuer.endpoint_url("/introspect"),
+            device_authorization_endpoint: issuer.endpoint_url("/device_authorization"),
+            pushed_authorization_request_endpoint: issuer.endpoint_url("/par"),
+            require_pushed_authorization_requests: false,
+            registration_endpoint: issuer.endpoint_url("/register/client"),
+            jwks_uri: format!("{}/.well-known/jwks.json", issuer.base_url),
+            response_types_supported: RESPONSE_TYPES.map(str::to_string).to_vec(),
+            response_modes_supported: ResponseMode::ALL.map(str::to_string).to_vec(),
+            authorization_response_iss_parameter_supported: true,
+            authorization_signing_alg_values_supported: vec!["RS256".to_string()],
+            grant_types_supported: GRANT_TYPES.map(str::to_string).to_vec(),
+            subject_types_supported,
             id_token_signing_alg_values_supported: vec!["RS256".to_string()],
             scopes_supported: vec![
                 "openid".to_string(),
                 "profile".to_string(),
                 "email".to_string(),
+                "address".to_string(),
+                "phone".to_string(),
             ],
-            token_endpoint_auth_methods_supported: vec!["client_secret_post".to_string()],
+            token_endpoint_auth_methods_supported: TokenEndpointAuthMethod::ALL
+                .iter()
+                .map(|method| method.as_str().to_string())
+                .collect(),
+            tls_client_certificate_bound_access_tokens: true,
             claims_supported: vec![
                 "sub".to_string(),
                 "iss".to_string(),
                 "aud".to_string(),
                 "exp".to_string(),
                 "iat".to_string(),
-                "email".to_string(),
+                "auth_time".to_string(),
+                "nonce".to_string(),
                 "name".to_string(),
+                "given_name".to_string(),
+                "family_name".to_string(),
+                "middle_name".to_string(),
+                "nickname".to_string(),
+                "preferred_username".to_string(),
+                "picture".to_string(),
+                "website".to_string(),
+                "gender".to_string(),
+                "birthdate".to_string(),
+                "zoneinfo".to_string(),
+                "locale".to_string(),
+                "updated_at".to_string(),
+                "email".to_string(),
+                "email_verified".to_string(),
+                "address".to_string(),
+                "phone_number".to_string(),
+                "phone_number_verified".to_string(),
             ],
+            claims_parameter_supported: true,
+            dpop_signing_alg_values_supported: DPOP_ALGORITHMS
+                .iter()
+                .map(|algorithm| format!("{algorithm:?}"))
+                .collect(),
+            request_parameter_supported: true,
+            request_uri_parameter_supported: false,
+            request_object_signing_alg_values_supported: REQUEST_OBJECT_ALGORITHMS
+                .iter()
+                .map(|algorithm| format!("{algorithm:?}"))
+                .collect(),
         }),
     )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::to_bytes, response::Response};
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::{
+        services::subject_service::SubjectService,
+        utils::{database::test_postgres_pool, setup::test_services, tenant_issuer::test_issuer},
+    };
+
+    async fn subject_types(pairwise_salt: Option<String>) -> Value {
+        let mut services = Arc::into_inner(test_services().await).unwrap();
+        services.subject_service = SubjectService::new(test_postgres_pool().await, pairwise_salt);
+
+        let response: Response = discovery_handler(
+            Extension(Arc::new(services)),
+            Extension(Arc::new(test_issuer(None, None))),
+        )
+        .await
         .into_response();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice::<Value>(&body).unwrap()["subject_types_supported"].clone()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_pairwise_subjects_need_a_salt() {
+        assert_eq!(subject_types(None).await, json!(["public"]));
+        assert_eq!(
+            subject_types(Some("salt".to_owned())).await,
+            json!(["public", "pairwise"])
+        );
+    }
 }
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, http::StatusCode, response::IntoResponse, response::Response};
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        services_config::ServicesConfig,
+        user_models::{ChangePasswordRequest, ForgotPasswordRequest, ResetPasswordRequest},
+    },
+    utils::password_policy_utils::PasswordPolicyError,
+};
+
+const RESET_TOKEN_LIFETIME_SECONDS: u64 = 900;
+
+pub async fn change_password(
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Json(request): Json<ChangePasswordRequest>,
+) -> impl IntoResponse {
+    match services
+        .user_service
+        .change_password(
+            &request.email,
+            request.tenant_id,
+            &request.current_password,
+            &request.new_password,
+        )
+        .await
+    {
+        Ok(true) => (StatusCode::OK, "Password changed").into_response(),
+        Ok(false) => StatusCode::UNAUTHORIZED.into_response(),
+        Err(e) => password_error_response(e),
+    }
+}
+
+pub async fn forgot_password(
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Json(request): Json<ForgotPasswordRequest>,
+) -> impl IntoResponse {
+    // Always answer the same way so the endpoint cannot be used to probe for accounts.
+    // An email used in several tenants gets a reset link for each of its accounts.
+    let user_ids = services
+        .user_service
+        .get_user_ids_from_email(&request.email)
+        .await
+        .unwrap_or_default();
+
+    for user_id in user_ids {
+        let token = Uuid::new_v4().to_string();
+
+        if let Err(err) = services
+            .password_reset_service
+            .store_token(&token, &user_id, RESET_TOKEN_LIFETIME_SECONDS)
+            .await
+        {
+            eprintln!("Failed to store password reset token: {err:?}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+
+        // The token only ever leaves by mail, a failed delivery must not reveal the account
+        if let Err(err) = services
+            .mail_service
+            .send_password_reset(&request.email, &token, RESET_TOKEN_LIFETIME_SECONDS / 60)
+            .await
+        {
+            eprintln!("Failed to send password reset mail: {err:?}");
+        }
+    }
+
+    StatusCode::ACCEPTED.into_response()
+}
+
+pub async fn reset_password(
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> impl IntoResponse {
+    let user_id = match services
+        .password_reset_service
+        .consume_token(&request.token)
+        .await
+    {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => {
+            return (StatusCode::BAD_REQUEST, "Reset token invalid or expired").into_response();
+        }
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    match services
+        .user_service
+        .reset_password(&user_id, &request.new_password)
+        .await
+    {
+        Ok(()) => (StatusCode::OK, "Password reset").into_response(),
+        Err(e) => password_error_response(e),
+    }
+}
+
+fn password_error_response(e: anyhow::Error) -> Response {
+    match e.downcast_ref::<PasswordPolicyError>() {
+        Some(policy_error) => (StatusCode::BAD_REQUEST, policy_error.to_string()).into_response(),
+        None => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to update password".to_string(),
+        )
+            .into_response(),
+    }
+}
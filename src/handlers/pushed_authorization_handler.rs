@@ -0,0 +1,350 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+
+use crate::{
+    handlers::{
+        authorization_code_handler::{InvalidRequest, parse_request, validate_request},
+        token_handler::authenticate_client,
+    },
+    models::{
+        client_certificate::ClientCertificate,
+        pushed_request::{PushedAuthorizationResponse, PushedRequestCredentials},
+        services_config::ServicesConfig,
+    },
+    services::pushed_request_service::PUSHED_REQUEST_LIFETIME,
+    utils::tenant_issuer::TenantIssuer,
+};
+
+/// Pushed authorization request (RFC 9126), the parameters are validated and kept on the server
+pub async fn pushed_authorization_request(
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+    client_certificate: Option<ClientCertificate>,
+    body: String,
+) -> Response {
+    // Client credentials and authorization parameters share the form body
+    let (Ok(credentials), Ok(params)) = (
+        serde_urlencoded::from_str::<PushedRequestCredentials>(&body),
+        serde_urlencoded::from_str::<Vec<(String, String)>>(&body),
+    ) else {
+        return par_error("invalid_request", "Malformed authorization request");
+    };
+
+    if let Err(response) = authenticate_client(
+        &services,
+        &issuer,
+        &credentials.client_id,
+        &credentials.client_secret,
+        client_certificate.as_ref(),
+    )
+    .await
+    {
+        return response;
+    }
+
+    if credentials.request_uri.is_some() {
+        return par_error("invalid_request", "request_uri is not allowed");
+    }
+
+    // A signed request object is verified now and stored merged with the other parameters
+    let params = match parse_request(&services, issuer.token_verifier.issuer(), params).await {
+        Ok(params) => params,
+        Err(invalid_request) => return invalid_request_error(invalid_request),
+    };
+    if params.client_id != credentials.client_id {
+        return par_error("invalid_request", "client_id mismatch");
+    }
+
+    if let Err(invalid_request) = validate_request(&services, &issuer, &params).await {
+        return invalid_request_error(invalid_request);
+    }
+
+    let request_uri = match services.pushed_request_service.store_request(&params).await {
+        Ok(request_uri) => request_uri,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to store authorization request",
+            )
+                .into_response();
+        }
+    };
+
+    (
+        StatusCode::CREATED,
+        Json(PushedAuthorizationResponse {
+            request_uri,
+            expires_in: PUSHED_REQUEST_LIFETIME,
+        }),
+    )
+        .into_response()
+}
+
+fn invalid_request_error(invalid_request: InvalidRequest) -> Response {
+    match invalid_request {
+        InvalidRequest::Rejected(StatusCode::INTERNAL_SERVER_ERROR, message) => {
+            (StatusCode::INTERNAL_SERVER_ERROR, message).into_response()
+        }
+        InvalidRequest::Rejected(_, message) => par_error("invalid_request", message),
+        InvalidRequest::Redirect(error, description) => par_error(error, description),
+        InvalidRequest::RequestObject(description) => {
+            par_error("invalid_request_object", description)
+        }
+    }
+}
+
+fn par_error(error: &str, description: &str) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({ "error": error, "error_description": description })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{iter, time::Duration};
+
+    use axum::{
+        body::to_bytes,
+        extract::{Query, RawQuery},
+        http::{HeaderMap, HeaderValue, header::LOCATION},
+    };
+    use axum_extra::{
+        TypedHeader,
+        headers::{Cookie, Header},
+    };
+    use redis::AsyncCommands;
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        handlers::authorization_code_handler::authorize,
+        models::{
+            pushed_request::{REQUEST_URI_PREFIX, RequestReference},
+            session::SessionData,
+        },
+        utils::{
+            database::{
+                create_test_application, create_test_tenant, create_test_user, delete_test_tenant,
+                test_postgres_pool,
+            },
+            redis_utils::test_redis_pool,
+            setup::test_services,
+            tenant_issuer::test_issuer,
+        },
+    };
+
+    const AUTHORIZATION_PARAMS: &str = "response_type=code&scope=openid&state=xyz\
+        &redirect_uri=https%3A%2F%2Fapp.example%2Fcallback";
+
+    struct Fixture {
+        services: Arc<ServicesConfig>,
+        issuer: Arc<TenantIssuer>,
+        tenant_id: Uuid,
+        client_id: String,
+        /// Cookie of a logged in user of the tenant
+        cookie: TypedHeader<Cookie>,
+    }
+
+    async fn fixture() -> Fixture {
+        let services = test_services().await;
+        let db_pool = test_postgres_pool().await;
+        let tenant_id = create_test_tenant(&db_pool).await;
+        let client_id = create_test_application(&db_pool, tenant_id).await;
+        let user_id = create_test_user(&db_pool, tenant_id, false).await;
+
+        let session_id = Uuid::new_v4().to_string();
+        let session = SessionData::new(user_id.to_string(), tenant_id);
+        services
+            .session_service
+            .set_session(&session_id, &session, 3600)
+            .await
+            .unwrap();
+        let value = HeaderValue::from_str(&format!("session_id={session_id}")).unwrap();
+        let cookie = TypedHeader(Cookie::decode(&mut iter::once(&value)).unwrap());
+
+        Fixture {
+            services,
+            issuer: Arc::new(test_issuer(Some(tenant_id), None)),
+            tenant_id,
+            client_id,
+            cookie,
+        }
+    }
+
+    async fn push(fixture: &Fixture, client_id: &str) -> Response {
+        let body = format!("client_id={client_id}&client_secret=secret&{AUTHORIZATION_PARAMS}");
+        pushed_authorization_request(
+            Extension(fixture.services.clone()),
+            Extension(fixture.issuer.clone()),
+            None,
+            body,
+        )
+        .await
+    }
+
+    async fn pushed_request_uri(fixture: &Fixture) -> String {
+        let response = push(fixture, &fixture.client_id).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["expires_in"], PUSHED_REQUEST_LIFETIME);
+        body["request_uri"].as_str().unwrap().to_owned()
+    }
+
+    async fn authorize_pushed(fixture: &Fixture, client_id: &str, request_uri: &str) -> Response {
+        let query =
+            serde_urlencoded::to_string([("client_id", client_id), ("request_uri", request_uri)])
+                .unwrap();
+        authorize_query(fixture, query).await
+    }
+
+    async fn authorize_query(fixture: &Fixture, query: String) -> Response {
+        authorize(
+            Query(serde_urlencoded::from_str::<RequestReference>(&query).unwrap()),
+            RawQuery(Some(query)),
+            Some(fixture.cookie.clone()),
+            HeaderMap::new(),
+            Extension(fixture.services.clone()),
+            Extension(fixture.issuer.clone()),
+        )
+        .await
+        .into_response()
+    }
+
+    fn redirect_location(response: &Response) -> &str {
+        response.headers()[LOCATION].to_str().unwrap()
+    }
+
+    async fn cleanup(fixture: Fixture) {
+        delete_test_tenant(&test_postgres_pool().await, fixture.tenant_id).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_request_uri_is_single_use() {
+        let fixture = fixture().await;
+        let request_uri = pushed_request_uri(&fixture).await;
+
+        let response = authorize_pushed(&fixture, &fixture.client_id, &request_uri).await;
+        assert!(
+            redirect_location(&response).starts_with("https://app.example/callback?code="),
+            "{}",
+            redirect_location(&response)
+        );
+
+        let response = authorize_pushed(&fixture, &fixture.client_id, &request_uri).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        cleanup(fixture).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_request_uri_expires() {
+        let fixture = fixture().await;
+        let request_uri = pushed_request_uri(&fixture).await;
+
+        let key = format!(
+            "par:{}",
+            request_uri.strip_prefix(REQUEST_URI_PREFIX).unwrap()
+        );
+        let mut conn = test_redis_pool().await.get_owned().await.unwrap();
+        let ttl: i64 = conn.ttl(&key).await.unwrap();
+        assert!(0 < ttl && ttl <= PUSHED_REQUEST_LIFETIME as i64);
+
+        let _: () = conn.expire(&key, 1).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let response = authorize_pushed(&fixture, &fixture.client_id, &request_uri).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        cleanup(fixture).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_request_uri_is_bound_to_client() {
+        let fixture = fixture().await;
+        let other_client_id =
+            create_test_application(&test_postgres_pool().await, fixture.tenant_id).await;
+        let request_uri = pushed_request_uri(&fixture).await;
+
+        let response = authorize_pushed(&fixture, &other_client_id, &request_uri).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let query = serde_urlencoded::to_string([("request_uri", &request_uri)]).unwrap();
+        let response = authorize_query(&fixture, query).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // The request was not used up by the other client
+        let response = authorize_pushed(&fixture, &fixture.client_id, &request_uri).await;
+        assert!(redirect_location(&response).contains("code="));
+
+        cleanup(fixture).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_required_par_rejects_inline_parameters() {
+        let fixture = fixture().await;
+        sqlx::query(
+            "UPDATE Applications SET require_pushed_authorization_requests = TRUE \
+             WHERE client_id = $1",
+        )
+        .bind(&fixture.client_id)
+        .execute(&test_postgres_pool().await)
+        .await
+        .unwrap();
+
+        let query = format!("client_id={}&{AUTHORIZATION_PARAMS}", fixture.client_id);
+        let response = authorize_query(&fixture, query).await;
+        let location = redirect_location(&response);
+        assert!(
+            location.starts_with("https://app.example/callback?error=invalid_request"),
+            "{location}"
+        );
+        assert!(!location.contains("code="));
+
+        let request_uri = pushed_request_uri(&fixture).await;
+        let response = authorize_pushed(&fixture, &fixture.client_id, &request_uri).await;
+        assert!(redirect_location(&response).contains("code="));
+
+        cleanup(fixture).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_push_needs_client_authentication() {
+        let fixture = fixture().await;
+
+        let body = format!(
+            "client_id={}&client_secret=wrong&{AUTHORIZATION_PARAMS}",
+            fixture.client_id
+        );
+        let response = pushed_authorization_request(
+            Extension(fixture.services.clone()),
+            Extension(fixture.issuer.clone()),
+            None,
+            body,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Applications of other tenants are unknown to the tenant's issuer
+        let other_tenant_id = create_test_tenant(&test_postgres_pool().await).await;
+        let other_client_id =
+            create_test_application(&test_postgres_pool().await, other_tenant_id).await;
+        let response = push(&fixture, &other_client_id).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        delete_test_tenant(&test_postgres_pool().await, other_tenant_id).await;
+        cleanup(fixture).await;
+    }
+}
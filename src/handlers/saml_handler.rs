@@ -0,0 +1,368 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Form,
+    extract::{Path, Query},
+    http::{StatusCode, header::CONTENT_TYPE},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_extra::{TypedHeader, headers::Cookie};
+use chrono::Utc;
+
+use crate::{
+    models::{
+        saml::{NameIdFormat, SamlIdpInitiatedRequest, SamlServiceProviderSQL, SamlSsoRequest},
+        services_config::ServicesConfig,
+        session::SessionData,
+    },
+    utils::{
+        claims_utils::standard_claim_values,
+        saml_issuer::SamlIssuer,
+        saml_utils::{
+            AssertionContext, HTTP_POST_BINDING, SamlStatus, build_assertion, build_response,
+            decode_post_request, decode_redirect_request, encode_redirect_request, new_id,
+            parse_authn_request, post_form, released_attributes,
+        },
+        tenant_issuer::TenantIssuer,
+    },
+};
+
+/// How long a service provider may take to consume an assertion
+const ASSERTION_LIFETIME: i64 = 300;
+
+/// Where a SAML response is delivered to
+struct ResponseTarget<'a> {
+    acs_url: &'a str,
+    in_response_to: Option<&'a str>,
+    relay_state: Option<&'a str>,
+}
+
+pub async fn saml_metadata(Extension(saml_issuer): Extension<Arc<SamlIssuer>>) -> Response {
+    match saml_issuer.metadata() {
+        Ok(metadata) => {
+            ([(CONTENT_TYPE, "application/samlmetadata+xml")], metadata).into_response()
+        }
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// SP-initiated login with the HTTP-Redirect binding
+pub async fn saml_sso_redirect(
+    Query(request): Query<SamlSsoRequest>,
+    cookies: Option<TypedHeader<Cookie>>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Extension(saml_issuer): Extension<Arc<SamlIssuer>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+) -> Response {
+    let xml = match decode_redirect_request(&request.saml_request) {
+        Ok(xml) => xml,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    let authn_request = match parse_authn_request(&xml) {
+        Ok(authn_request) => authn_request,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    let service_provider = match services
+        .saml_service
+        .get_by_entity_id(&authn_request.issuer)
+        .await
+    {
+        Ok(Some(service_provider)) => service_provider,
+        Ok(None) => {
+            return (StatusCode::BAD_REQUEST, "Unknown service provider").into_response();
+        }
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    // Responses carry assertions, which are only delivered with the HTTP-POST binding
+    if authn_request
+        .protocol_binding
+        .as_deref()
+        .is_some_and(|binding| binding != HTTP_POST_BINDING)
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Only the HTTP-POST binding is supported for responses",
+        )
+            .into_response();
+    }
+
+    let acs_url = match &authn_request.acs_url {
+        Some(acs_url) if service_provider.acs_urls.contains(acs_url) => acs_url.clone(),
+        Some(_) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Unregistered AssertionConsumerServiceURL",
+            )
+                .into_response();
+        }
+        None => match service_provider.acs_urls.first() {
+            Some(acs_url) => acs_url.clone(),
+            None => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        },
+    };
+    let target = ResponseTarget {
+        acs_url: &acs_url,
+        in_response_to: Some(&authn_request.id),
+        relay_state: request.relay_state.as_deref(),
+    };
+
+    let (session_id, session) = match current_session(&services, cookies.as_ref()).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    // ForceAuthn is satisfied by a login that happened after the request was issued
+    let session = match session {
+        Some(session)
+            if !authn_request.force_authn || session.auth_time >= authn_request.issue_instant =>
+        {
+            session
+        }
+        _ if authn_request.is_passive => {
+            return status_response(&saml_issuer, &target, SamlStatus::NoPassive);
+        }
+        _ => {
+            let Ok(query) = serde_urlencoded::to_string([
+                ("SAMLRequest", Some(request.saml_request.as_str())),
+                ("RelayState", request.relay_state.as_deref()),
+            ]) else {
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            };
+            return login_redirect(&issuer, &format!("/saml/sso?{}", query));
+        }
+    };
+
+    send_assertion(
+        &services,
+        &saml_issuer,
+        &service_provider,
+        (session_id.as_deref(), &session),
+        &target,
+    )
+    .await
+}
+
+/// SP-initiated login with the HTTP-POST binding
+///
+/// Session cookies are `SameSite=Lax` and not sent with cross-site posts, so the request
+/// continues as a top-level GET with the HTTP-Redirect binding.
+pub async fn saml_sso_post(
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+    Form(request): Form<SamlSsoRequest>,
+) -> Response {
+    let xml = match decode_post_request(&request.saml_request) {
+        Ok(xml) => xml,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    let Ok(saml_request) = encode_redirect_request(&xml) else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+
+    let Ok(query) = serde_urlencoded::to_string([
+        ("SAMLRequest", Some(saml_request.as_str())),
+        ("RelayState", request.relay_state.as_deref()),
+    ]) else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+
+    Redirect::to(&issuer.endpoint_url(&format!("/saml/sso?{}", query))).into_response()
+}
+
+/// IdP-initiated login into the application `client_id`
+pub async fn saml_idp_initiated(
+    Path(client_id): Path<String>,
+    Query(request): Query<SamlIdpInitiatedRequest>,
+    cookies: Option<TypedHeader<Cookie>>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Extension(saml_issuer): Extension<Arc<SamlIssuer>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+) -> Response {
+    let service_provider = match services.saml_service.get_by_client_id(&client_id).await {
+        Ok(Some(service_provider)) => service_provider,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let Some(acs_url) = service_provider.acs_urls.first() else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+
+    let (session_id, session) = match current_session(&services, cookies.as_ref()).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let Some(session) = session else {
+        let Ok(query) = serde_urlencoded::to_string([("RelayState", &request.relay_state)]) else {
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        };
+        return login_redirect(
+            &issuer,
+            &format!("/saml/init/{}?{}", urlencoding::encode(&client_id), query),
+        );
+    };
+
+    let target = ResponseTarget {
+        acs_url,
+        in_response_to: None,
+        relay_state: request.relay_state.as_deref(),
+    };
+    send_assertion(
+        &services,
+        &saml_issuer,
+        &service_provider,
+        (session_id.as_deref(), &session),
+        &target,
+    )
+    .await
+}
+
+/// The session of the session cookie, `None` if there is none or it expired
+async fn current_session(
+    services: &ServicesConfig,
+    cookies: Option<&TypedHeader<Cookie>>,
+) -> Result<(Option<String>, Option<SessionData>), Response> {
+    let Some(session_id) = cookies.and_then(|TypedHeader(cookies)| cookies.get("session_id"))
+    else {
+        return Ok((None, None));
+    };
+
+    match services.session_service.touch_session(session_id).await {
+        Ok(session) => Ok((Some(session_id.to_owned()), session)),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Could not validate session",
+        )
+            .into_response()),
+    }
+}
+
+/// Redirect to the login UI, continuing at `return_to` of the issuer afterwards
+fn login_redirect(issuer: &TenantIssuer, return_to: &str) -> Response {
+    Redirect::to(&issuer.login_url(return_to)).into_response()
+}
+
+/// Post a signed assertion about the session's user to the service provider
+async fn send_assertion(
+    services: &ServicesConfig,
+    saml_issuer: &SamlIssuer,
+    service_provider: &SamlServiceProviderSQL,
+    (session_id, session): (Option<&str>, &SessionData),
+    target: &ResponseTarget<'_>,
+) -> Response {
+    let internal_error = || StatusCode::INTERNAL_SERVER_ERROR.into_response();
+
+    // Users only log in to the service providers of their own tenant
+    match services
+        .user_service
+        .is_user_in_tenant(&session.user_id, service_provider.tenant_id)
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => return status_response(saml_issuer, target, SamlStatus::RequestDenied),
+        Err(_) => return internal_error(),
+    }
+
+    let Ok(user_claims) = services
+        .user_service
+        .get_user_claims(&session.user_id)
+        .await
+    else {
+        return internal_error();
+    };
+    let Ok(name_id_format) = service_provider.name_id_format.parse::<NameIdFormat>() else {
+        return internal_error();
+    };
+    let name_id = match name_id_format {
+        NameIdFormat::EmailAddress => user_claims.email.clone(),
+        NameIdFormat::Persistent | NameIdFormat::Unspecified => match services
+            .subject_service
+            .subject_for(&session.user_id, &service_provider.client_id)
+            .await
+        {
+            Ok(subject) => subject,
+            Err(_) => return internal_error(),
+        },
+    };
+
+    // Custom attributes can be released as well, standard claims take precedence
+    let mut values = standard_claim_values(&user_claims);
+    if !service_provider.attributes.is_empty() {
+        let Ok(attributes) = services
+            .attribute_service
+            .get_user_attributes(&session.user_id)
+            .await
+        else {
+            return internal_error();
+        };
+        for (name, value) in attributes {
+            values.entry(name).or_insert(value);
+        }
+    }
+    let attributes = released_attributes(&values, &service_provider.attributes);
+
+    let (Ok(assertion_id), Ok(response_id)) = (new_id(), new_id()) else {
+        return internal_error();
+    };
+    let now = Utc::now().timestamp();
+    let assertion = build_assertion(
+        &assertion_id,
+        now,
+        ASSERTION_LIFETIME,
+        &AssertionContext {
+            issuer: &saml_issuer.entity_id,
+            audience: &service_provider.entity_id,
+            recipient: target.acs_url,
+            in_response_to: target.in_response_to,
+            name_id: &name_id,
+            name_id_format,
+            auth_instant: session.auth_time,
+            attributes: &attributes,
+        },
+    );
+    let Ok(signed_assertion) = saml_issuer.sign_assertion(&assertion, &assertion_id) else {
+        return internal_error();
+    };
+    let response = build_response(
+        &response_id,
+        now,
+        &saml_issuer.entity_id,
+        target.acs_url,
+        target.in_response_to,
+        SamlStatus::Success,
+        Some(&signed_assertion),
+    );
+
+    if let Some(session_id) = session_id
+        && let Err(err) = services
+            .session_service
+            .add_client(session_id, &service_provider.client_id)
+            .await
+    {
+        eprintln!("Failed to record client in session: {err:?}");
+    }
+
+    Html(post_form(target.acs_url, &response, target.relay_state)).into_response()
+}
+
+/// Post a response without assertion, e.g. when a passive login is not possible
+fn status_response(
+    saml_issuer: &SamlIssuer,
+    target: &ResponseTarget<'_>,
+    status: SamlStatus,
+) -> Response {
+    let Ok(response_id) = new_id() else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let response = build_response(
+        &response_id,
+        Utc::now().timestamp(),
+        &saml_issuer.entity_id,
+        target.acs_url,
+        target.in_response_to,
+        status,
+        None,
+    );
+
+    Html(post_form(target.acs_url, &response, target.relay_state)).into_response()
+}
@@ -0,0 +1,684 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, Query},
+    http::{
+        StatusCode,
+        header::{CONTENT_TYPE, LOCATION, WWW_AUTHENTICATE},
+    },
+    response::{IntoResponse, Response},
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use serde::Serialize;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::{
+    handlers::session_handler::end_user_sessions,
+    models::{
+        scim::{
+            ERROR_SCHEMA, GROUP_ATTRIBUTES, GROUP_SCHEMA, PATCH_OP_SCHEMA, SchemaAttribute,
+            ScimGroup, ScimListQuery, ScimListResponse, ScimLookup, ScimPatchRequest, ScimUser,
+            USER_ATTRIBUTES, USER_SCHEMA,
+        },
+        services_config::ServicesConfig,
+    },
+    utils::{
+        password_policy_utils::PasswordPolicyError,
+        scim_utils::{
+            Filter, MAX_RESULTS, ScimError, apply_patch, group_lookup, list_page, page_bounds,
+            paginate, parse_filter, project, resource_type, schema_resource, user_lookup,
+        },
+    },
+};
+
+const SCIM_CONTENT_TYPE: &str = "application/scim+json";
+
+type BearerHeader = Option<TypedHeader<Authorization<Bearer>>>;
+
+pub async fn scim_service_provider_config(
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    scim_response(
+        StatusCode::OK,
+        json!({
+            "schemas": ["urn:ietf:params:scim:schemas:core:2.0:ServiceProviderConfig"],
+            "patch": { "supported": true },
+            "bulk": { "supported": false, "maxOperations": 0, "maxPayloadSize": 0 },
+            "filter": { "supported": true, "maxResults": MAX_RESULTS },
+            "changePassword": { "supported": true },
+            "sort": { "supported": false },
+            "etag": { "supported": false },
+            "authenticationSchemes": [{
+                "type": "oauthbearertoken",
+                "name": "Bearer token",
+                "description": "Provisioning token issued for a tenant",
+                "primary": true
+            }],
+            "meta": {
+                "resourceType": "ServiceProviderConfig",
+                "location": services.scim_service.location("ServiceProviderConfig")
+            }
+        }),
+    )
+}
+
+pub async fn scim_schemas(Extension(services): Extension<Arc<ServicesConfig>>) -> Response {
+    scim_response(StatusCode::OK, paginate(schemas(&services), None, None))
+}
+
+pub async fn scim_schema(
+    Path(id): Path<String>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    match schemas(&services)
+        .into_iter()
+        .find(|schema| schema["id"] == id)
+    {
+        Some(schema) => scim_response(StatusCode::OK, schema),
+        None => scim_error(StatusCode::NOT_FOUND, None, "Schema not found"),
+    }
+}
+
+fn schemas(services: &ServicesConfig) -> Vec<Value> {
+    [
+        (USER_SCHEMA, "User", USER_ATTRIBUTES),
+        (GROUP_SCHEMA, "Group", GROUP_ATTRIBUTES),
+    ]
+    .into_iter()
+    .map(|(id, name, attributes)| {
+        let location = services.scim_service.location(&format!("Schemas/{id}"));
+        schema_resource(id, name, attributes, &location)
+    })
+    .collect()
+}
+
+pub async fn scim_resource_types(Extension(services): Extension<Arc<ServicesConfig>>) -> Response {
+    scim_response(
+        StatusCode::OK,
+        paginate(resource_types(&services), None, None),
+    )
+}
+
+pub async fn scim_resource_type(
+    Path(name): Path<String>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    match resource_types(&services)
+        .into_iter()
+        .find(|resource_type| resource_type["id"] == name)
+    {
+        Some(resource_type) => scim_response(StatusCode::OK, resource_type),
+        None => scim_error(StatusCode::NOT_FOUND, None, "Resource type not found"),
+    }
+}
+
+fn resource_types(services: &ServicesConfig) -> Vec<Value> {
+    [
+        ("User", "/Users", USER_SCHEMA),
+        ("Group", "/Groups", GROUP_SCHEMA),
+    ]
+    .into_iter()
+    .map(|(name, endpoint, schema)| {
+        let location = services
+            .scim_service
+            .location(&format!("ResourceTypes/{name}"));
+        resource_type(name, endpoint, schema, &location)
+    })
+    .collect()
+}
+
+pub async fn scim_list_users(
+    Query(query): Query<ScimListQuery>,
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    let tenant_id = match scim_tenant(authorization, &services).await {
+        Ok(tenant_id) => tenant_id,
+        Err(response) => return response,
+    };
+
+    let filter = match query.filter.as_deref().map(parse_filter).transpose() {
+        Ok(filter) => filter,
+        Err(e) => return error_response(e.into()),
+    };
+    let (start_index, count) = page_bounds(query.start_index, query.count);
+
+    // Equality filters and pages are left to the database, other filters need all users
+    let result = match user_lookup(filter.as_ref()) {
+        Some(lookup) => services
+            .scim_service
+            .list_users(tenant_id, &lookup, start_index - 1, Some(count))
+            .await
+            .map(|(users, total_results)| page_response(users, total_results, start_index, &query)),
+        None => services
+            .scim_service
+            .list_users(tenant_id, &ScimLookup::default(), 0, None)
+            .await
+            .map(|(users, _)| list_response(users, filter.as_ref(), &query)),
+    };
+    result.unwrap_or_else(error_response)
+}
+
+pub async fn scim_get_user(
+    Path(user_id): Path<String>,
+    Query(query): Query<ScimListQuery>,
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    let tenant_id = match scim_tenant(authorization, &services).await {
+        Ok(tenant_id) => tenant_id,
+        Err(response) => return response,
+    };
+    let Ok(user_id) = Uuid::parse_str(&user_id) else {
+        return user_not_found();
+    };
+
+    match services.scim_service.get_user(tenant_id, user_id).await {
+        Ok(Some(user)) => resource_response(StatusCode::OK, user, &query),
+        Ok(None) => user_not_found(),
+        Err(e) => error_response(e),
+    }
+}
+
+pub async fn scim_create_user(
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Json(user): Json<ScimUser>,
+) -> Response {
+    let tenant_id = match scim_tenant(authorization, &services).await {
+        Ok(tenant_id) => tenant_id,
+        Err(response) => return response,
+    };
+
+    let password_hash = match password_hash(&services, tenant_id, None, &user).await {
+        Ok(password_hash) => password_hash,
+        Err(e) => return error_response(e),
+    };
+
+    let user_id = match services
+        .scim_service
+        .create_user(tenant_id, &user, password_hash.as_deref())
+        .await
+    {
+        Ok(user_id) => user_id,
+        Err(e) => return error_response(e),
+    };
+
+    match services.scim_service.get_user(tenant_id, user_id).await {
+        Ok(Some(user)) => created_response(user),
+        Ok(None) => user_not_found(),
+        Err(e) => error_response(e),
+    }
+}
+
+pub async fn scim_replace_user(
+    Path(user_id): Path<String>,
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Json(user): Json<ScimUser>,
+) -> Response {
+    let tenant_id = match scim_tenant(authorization, &services).await {
+        Ok(tenant_id) => tenant_id,
+        Err(response) => return response,
+    };
+    let Ok(user_id) = Uuid::parse_str(&user_id) else {
+        return user_not_found();
+    };
+
+    save_user(&services, tenant_id, user_id, &user).await
+}
+
+pub async fn scim_patch_user(
+    Path(user_id): Path<String>,
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Json(patch): Json<ScimPatchRequest>,
+) -> Response {
+    let tenant_id = match scim_tenant(authorization, &services).await {
+        Ok(tenant_id) => tenant_id,
+        Err(response) => return response,
+    };
+    let Ok(user_id) = Uuid::parse_str(&user_id) else {
+        return user_not_found();
+    };
+
+    let user = match services.scim_service.get_user(tenant_id, user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return user_not_found(),
+        Err(e) => return error_response(e),
+    };
+    let user = match patched(&user, &patch, USER_ATTRIBUTES) {
+        Ok(user) => user,
+        Err(e) => return error_response(e),
+    };
+
+    save_user(&services, tenant_id, user_id, &user).await
+}
+
+pub async fn scim_delete_user(
+    Path(user_id): Path<String>,
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    let tenant_id = match scim_tenant(authorization, &services).await {
+        Ok(tenant_id) => tenant_id,
+        Err(response) => return response,
+    };
+    let Ok(user_id) = Uuid::parse_str(&user_id) else {
+        return user_not_found();
+    };
+
+    match services.scim_service.delete_user(tenant_id, user_id).await {
+        Ok(true) => {}
+        Ok(false) => return user_not_found(),
+        Err(e) => return error_response(e),
+    }
+    // Deprovisioned users are logged out everywhere
+    if let Err(e) = end_user_sessions(services.as_ref(), &user_id.to_string()).await {
+        return error_response(e);
+    }
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// Hash of the user's new password checked against the tenant's password policy, if it has one
+async fn password_hash(
+    services: &ServicesConfig,
+    tenant_id: Uuid,
+    user_id: Option<Uuid>,
+    user: &ScimUser,
+) -> Result<Option<String>, anyhow::Error> {
+    match &user.password {
+        Some(password) => Ok(Some(
+            services
+                .user_service
+                .hash_new_password(tenant_id, user_id, password)
+                .await?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Replace a user and, if given, its password
+async fn save_user(
+    services: &ServicesConfig,
+    tenant_id: Uuid,
+    user_id: Uuid,
+    user: &ScimUser,
+) -> Response {
+    let password_hash = match password_hash(services, tenant_id, Some(user_id), user).await {
+        Ok(password_hash) => password_hash,
+        Err(e) => return error_response(e),
+    };
+
+    match services
+        .scim_service
+        .replace_user(tenant_id, user_id, user, password_hash.as_deref())
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => return user_not_found(),
+        Err(e) => return error_response(e),
+    }
+    // Deactivated users are logged out everywhere
+    if user.active == Some(false)
+        && let Err(e) = end_user_sessions(services, &user_id.to_string()).await
+    {
+        return error_response(e);
+    }
+
+    match services.scim_service.get_user(tenant_id, user_id).await {
+        Ok(Some(user)) => scim_response(StatusCode::OK, user),
+        Ok(None) => user_not_found(),
+        Err(e) => error_response(e),
+    }
+}
+
+pub async fn scim_list_groups(
+    Query(query): Query<ScimListQuery>,
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    let tenant_id = match scim_tenant(authorization, &services).await {
+        Ok(tenant_id) => tenant_id,
+        Err(response) => return response,
+    };
+
+    let filter = match query.filter.as_deref().map(parse_filter).transpose() {
+        Ok(filter) => filter,
+        Err(e) => return error_response(e.into()),
+    };
+    let (start_index, count) = page_bounds(query.start_index, query.count);
+
+    // Equality filters and pages are left to the database, other filters need all groups
+    let result = match group_lookup(filter.as_ref()) {
+        Some(lookup) => services
+            .scim_service
+            .list_groups(tenant_id, &lookup, start_index - 1, Some(count))
+            .await
+            .map(|(groups, total_results)| {
+                page_response(groups, total_results, start_index, &query)
+            }),
+        None => services
+            .scim_service
+            .list_groups(tenant_id, &ScimLookup::default(), 0, None)
+            .await
+            .map(|(groups, _)| list_response(groups, filter.as_ref(), &query)),
+    };
+    result.unwrap_or_else(error_response)
+}
+
+pub async fn scim_get_group(
+    Path(group_id): Path<String>,
+    Query(query): Query<ScimListQuery>,
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    let tenant_id = match scim_tenant(authorization, &services).await {
+        Ok(tenant_id) => tenant_id,
+        Err(response) => return response,
+    };
+    let Ok(group_id) = Uuid::parse_str(&group_id) else {
+        return group_not_found();
+    };
+
+    match services.scim_service.get_group(tenant_id, group_id).await {
+        Ok(Some(group)) => resource_response(StatusCode::OK, group, &query),
+        Ok(None) => group_not_found(),
+        Err(e) => error_response(e),
+    }
+}
+
+pub async fn scim_create_group(
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Json(group): Json<ScimGroup>,
+) -> Response {
+    let tenant_id = match scim_tenant(authorization, &services).await {
+        Ok(tenant_id) => tenant_id,
+        Err(response) => return response,
+    };
+
+    let group_id = match services.scim_service.create_group(tenant_id, &group).await {
+        Ok(group_id) => group_id,
+        Err(e) => return error_response(e),
+    };
+
+    match services.scim_service.get_group(tenant_id, group_id).await {
+        Ok(Some(group)) => created_response(group),
+        Ok(None) => group_not_found(),
+        Err(e) => error_response(e),
+    }
+}
+
+pub async fn scim_replace_group(
+    Path(group_id): Path<String>,
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Json(group): Json<ScimGroup>,
+) -> Response {
+    let tenant_id = match scim_tenant(authorization, &services).await {
+        Ok(tenant_id) => tenant_id,
+        Err(response) => return response,
+    };
+    let Ok(group_id) = Uuid::parse_str(&group_id) else {
+        return group_not_found();
+    };
+
+    save_group(&services, tenant_id, group_id, &group).await
+}
+
+pub async fn scim_patch_group(
+    Path(group_id): Path<String>,
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Json(patch): Json<ScimPatchRequest>,
+) -> Response {
+    let tenant_id = match scim_tenant(authorization, &services).await {
+        Ok(tenant_id) => tenant_id,
+        Err(response) => return response,
+    };
+    let Ok(group_id) = Uuid::parse_str(&group_id) else {
+        return group_not_found();
+    };
+
+    let group = match services.scim_service.get_group(tenant_id, group_id).await {
+        Ok(Some(group)) => group,
+        Ok(None) => return group_not_found(),
+        Err(e) => return error_response(e),
+    };
+    let group = match patched(&group, &patch, GROUP_ATTRIBUTES) {
+        Ok(group) => group,
+        Err(e) => return error_response(e),
+    };
+
+    save_group(&services, tenant_id, group_id, &group).await
+}
+
+pub async fn scim_delete_group(
+    Path(group_id): Path<String>,
+    authorization: BearerHeader,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    let tenant_id = match scim_tenant(authorization, &services).await {
+        Ok(tenant_id) => tenant_id,
+        Err(response) => return response,
+    };
+    let Ok(group_id) = Uuid::parse_str(&group_id) else {
+        return group_not_found();
+    };
+
+    match services
+        .scim_service
+        .delete_group(tenant_id, group_id)
+        .await
+    {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => group_not_found(),
+        Err(e) => error_response(e),
+    }
+}
+
+async fn save_group(
+    services: &ServicesConfig,
+    tenant_id: Uuid,
+    group_id: Uuid,
+    group: &ScimGroup,
+) -> Response {
+    match services
+        .scim_service
+        .replace_group(tenant_id, group_id, group)
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => return group_not_found(),
+        Err(e) => return error_response(e),
+    }
+
+    match services.scim_service.get_group(tenant_id, group_id).await {
+        Ok(Some(group)) => scim_response(StatusCode::OK, group),
+        Ok(None) => group_not_found(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Tenant the provisioning client's bearer token was issued for
+async fn scim_tenant(
+    authorization: BearerHeader,
+    services: &ServicesConfig,
+) -> Result<Uuid, Response> {
+    let unauthorized = |detail| {
+        let mut response = scim_error(StatusCode::UNAUTHORIZED, None, detail);
+        response
+            .headers_mut()
+            .insert(WWW_AUTHENTICATE, "Bearer".parse().unwrap());
+        response
+    };
+    let Some(TypedHeader(Authorization(bearer))) = authorization else {
+        return Err(unauthorized("Bearer token required"));
+    };
+
+    match services
+        .scim_service
+        .get_tenant_for_token(bearer.token())
+        .await
+    {
+        Ok(Some(tenant_id)) => Ok(tenant_id),
+        Ok(None) => Err(unauthorized("Invalid bearer token")),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+/// Apply the operations of a PATCH request to a copy of a resource
+fn patched<T>(
+    resource: &T,
+    patch: &ScimPatchRequest,
+    attributes: &[SchemaAttribute],
+) -> Result<T, anyhow::Error>
+where
+    T: Serialize + serde::de::DeserializeOwned,
+{
+    if !patch.schemas.is_empty() && !patch.schemas.iter().any(|schema| schema == PATCH_OP_SCHEMA) {
+        return Err(ScimError::InvalidSyntax(format!("Expected schema {PATCH_OP_SCHEMA}")).into());
+    }
+
+    let mut value = serde_json::to_value(resource)?;
+    for operation in &patch.operations {
+        apply_patch(&mut value, operation, attributes)?;
+    }
+
+    serde_json::from_value(value).map_err(|e| ScimError::InvalidValue(e.to_string()).into())
+}
+
+/// Filtered and paginated list response
+/// Filters and paginates all resources
+fn list_response<T: Serialize>(
+    resources: Vec<T>,
+    filter: Option<&Filter>,
+    query: &ScimListQuery,
+) -> Response {
+    let resources: Vec<Value> = resources
+        .iter()
+        .filter_map(|resource| serde_json::to_value(resource).ok())
+        .filter(|resource| filter.is_none_or(|filter| filter.matches(resource)))
+        .collect();
+    let page = paginate(resources, query.start_index, query.count);
+
+    projected_response(page, query)
+}
+
+/// A page the database already filtered
+fn page_response<T: Serialize>(
+    resources: Vec<T>,
+    total_results: usize,
+    start_index: usize,
+    query: &ScimListQuery,
+) -> Response {
+    let resources: Vec<Value> = resources
+        .iter()
+        .filter_map(|resource| serde_json::to_value(resource).ok())
+        .collect();
+    let page = list_page(resources, total_results, start_index);
+
+    projected_response(page, query)
+}
+
+fn projected_response(mut page: ScimListResponse, query: &ScimListQuery) -> Response {
+    for resource in &mut page.resources {
+        project(
+            resource,
+            query.attributes.as_deref(),
+            query.excluded_attributes.as_deref(),
+        );
+    }
+
+    scim_response(StatusCode::OK, page)
+}
+
+fn resource_response<T: Serialize>(
+    status: StatusCode,
+    resource: T,
+    query: &ScimListQuery,
+) -> Response {
+    let Ok(mut resource) = serde_json::to_value(resource) else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    project(
+        &mut resource,
+        query.attributes.as_deref(),
+        query.excluded_attributes.as_deref(),
+    );
+
+    scim_response(status, resource)
+}
+
+/// 201 with the new resource's `meta.location` as `Location`
+fn created_response<T: Serialize>(resource: T) -> Response {
+    let Ok(resource) = serde_json::to_value(resource) else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let location = resource["meta"]["location"]
+        .as_str()
+        .unwrap_or_default()
+        .to_owned();
+
+    let mut response = scim_response(StatusCode::CREATED, resource);
+    if let Ok(location) = location.parse() {
+        response.headers_mut().insert(LOCATION, location);
+    }
+    response
+}
+
+fn scim_response<T: Serialize>(status: StatusCode, body: T) -> Response {
+    (status, [(CONTENT_TYPE, SCIM_CONTENT_TYPE)], Json(body)).into_response()
+}
+
+fn scim_error(status: StatusCode, scim_type: Option<&str>, detail: &str) -> Response {
+    let mut error = json!({
+        "schemas": [ERROR_SCHEMA],
+        "status": status.as_u16().to_string(),
+        "detail": detail,
+    });
+    if let Some(scim_type) = scim_type {
+        error["scimType"] = json!(scim_type);
+    }
+
+    scim_response(status, error)
+}
+
+fn error_response(e: anyhow::Error) -> Response {
+    if let Some(error) = e.downcast_ref::<ScimError>() {
+        let status = match error {
+            ScimError::Uniqueness(_) => StatusCode::CONFLICT,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        return scim_error(status, Some(error.scim_type()), &error.to_string());
+    }
+    if let Some(error) = e.downcast_ref::<PasswordPolicyError>() {
+        return scim_error(
+            StatusCode::BAD_REQUEST,
+            Some("invalidValue"),
+            &error.to_string(),
+        );
+    }
+
+    eprintln!("SCIM request failed: {e:?}");
+    scim_error(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        None,
+        "Internal server error",
+    )
+}
+
+fn user_not_found() -> Response {
+    scim_error(StatusCode::NOT_FOUND, None, "User not found")
+}
+
+fn group_not_found() -> Response {
+    scim_error(StatusCode::NOT_FOUND, None, "Group not found")
+}
@@ -0,0 +1,507 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use axum_extra::{TypedHeader, headers::Cookie};
+use uuid::Uuid;
+
+use crate::models::{
+    services_config::ServicesConfig,
+    session::{SessionData, SessionInfo},
+};
+
+/// List the sessions of the logged in user
+pub async fn list_sessions(
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    let (current_id, current) = match current_session(&cookies, &services).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    session_list_response(&services, &current.user_id, Some(&current_id)).await
+}
+
+/// Revoke one of the logged in user's sessions
+pub async fn revoke_session(
+    Path(session_id): Path<String>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    let (_, current) = match current_session(&cookies, &services).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    revoke_user_session(&services, &current.user_id, &session_id).await
+}
+
+/// Revoke all sessions of the logged in user except the current one
+pub async fn revoke_other_sessions(
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    let (current_id, current) = match current_session(&cookies, &services).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    revoke_all_user_sessions(&services, &current.user_id, Some(&current_id)).await
+}
+
+/// List the sessions of a user of the admin's tenant
+pub async fn admin_list_sessions(
+    Path(user_id): Path<String>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    let current_id = match require_admin(&cookies, &services, &user_id).await {
+        Ok(current_id) => current_id,
+        Err(response) => return response,
+    };
+
+    session_list_response(&services, &user_id, Some(&current_id)).await
+}
+
+/// Revoke one session of a user of the admin's tenant
+pub async fn admin_revoke_session(
+    Path((user_id, session_id)): Path<(String, String)>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    if let Err(response) = require_admin(&cookies, &services, &user_id).await {
+        return response;
+    }
+
+    revoke_user_session(&services, &user_id, &session_id).await
+}
+
+/// Revoke all sessions of a user of the admin's tenant
+pub async fn admin_revoke_all_sessions(
+    Path(user_id): Path<String>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    let current_id = match require_admin(&cookies, &services, &user_id).await {
+        Ok(current_id) => current_id,
+        Err(response) => return response,
+    };
+
+    revoke_all_user_sessions(&services, &user_id, Some(&current_id)).await
+}
+
+/// Deletes a session together with all refresh tokens issued from it
+pub async fn end_session(services: &ServicesConfig, session_id: &str) -> Result<(), anyhow::Error> {
+    services.session_service.delete_session(session_id).await?;
+    services
+        .refresh_token_service
+        .revoke_session_tokens(session_id)
+        .await
+}
+
+/// Ends every session of a user and revokes all of their refresh tokens, e.g. once deprovisioned
+pub async fn end_user_sessions(
+    services: &ServicesConfig,
+    user_id: &str,
+) -> Result<(), anyhow::Error> {
+    for (session_id, _) in services.session_service.list_user_sessions(user_id).await? {
+        end_session(services, &session_id).await?;
+    }
+    services
+        .refresh_token_service
+        .revoke_user_tokens(user_id)
+        .await
+}
+
+async fn current_session(
+    cookies: &Cookie,
+    services: &ServicesConfig,
+) -> Result<(String, SessionData), Response> {
+    let Some(session_id) = cookies.get("session_id") else {
+        return Err(StatusCode::UNAUTHORIZED.into_response());
+    };
+
+    match services.session_service.touch_session(session_id).await {
+        Ok(Some(session)) => Ok((session_id.to_owned(), session)),
+        Ok(None) => Err(StatusCode::UNAUTHORIZED.into_response()),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Could not validate session",
+        )
+            .into_response()),
+    }
+}
+
+/// Checks that the logged in user is an admin of the tenant the target user belongs to
+pub(crate) async fn require_admin(
+    cookies: &Cookie,
+    services: &ServicesConfig,
+    target_user_id: &str,
+) -> Result<String, Response> {
+    let (current_id, current) = current_session(cookies, services).await?;
+
+    match services
+        .user_service
+        .is_tenant_admin_of(&current.user_id, target_user_id)
+        .await
+    {
+        Ok(true) => Ok(current_id),
+        Ok(false) => Err(StatusCode::FORBIDDEN.into_response()),
+        Err(_) => Err(StatusCode::FORBIDDEN.into_response()),
+    }
+}
+
+/// Checks that the logged in user is an admin of the tenant `tenant_id`, returns the admin's ID
+pub(crate) async fn require_tenant_admin(
+    cookies: &Cookie,
+    services: &ServicesConfig,
+    tenant_id: Uuid,
+) -> Result<String, Response> {
+    let (_, current) = current_session(cookies, services).await?;
+
+    match services
+        .user_service
+        .is_tenant_admin(&current.user_id, tenant_id)
+        .await
+    {
+        Ok(true) => Ok(current.user_id),
+        Ok(false) | Err(_) => Err(StatusCode::FORBIDDEN.into_response()),
+    }
+}
+
+async fn session_list_response(
+    services: &ServicesConfig,
+    user_id: &str,
+    current_id: Option<&str>,
+) -> Response {
+    match services.session_service.list_user_sessions(user_id).await {
+        Ok(sessions) => {
+            let sessions: Vec<SessionInfo> = sessions
+                .into_iter()
+                .map(|(id, session)| SessionInfo {
+                    current: current_id == Some(id.as_str()),
+                    id,
+                    session,
+                })
+                .collect();
+
+            Json(sessions).into_response()
+        }
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Could not list sessions").into_response(),
+    }
+}
+
+async fn revoke_user_session(
+    services: &ServicesConfig,
+    user_id: &str,
+    session_id: &str,
+) -> Response {
+    // Only sessions of the given user may be revoked
+    match services.session_service.get_session(session_id).await {
+        Ok(Some(session)) if session.user_id == user_id => {}
+        Ok(_) => return StatusCode::NOT_FOUND.into_response(),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+
+    match end_session(services, session_id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Could not revoke session",
+        )
+            .into_response(),
+    }
+}
+
+async fn revoke_all_user_sessions(
+    services: &ServicesConfig,
+    user_id: &str,
+    keep_session_id: Option<&str>,
+) -> Response {
+    let sessions = match services.session_service.list_user_sessions(user_id).await {
+        Ok(sessions) => sessions,
+        Err(_) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Could not list sessions").into_response();
+        }
+    };
+
+    for (session_id, _) in sessions {
+        if keep_session_id == Some(session_id.as_str()) {
+            continue;
+        }
+
+        if end_session(services, &session_id).await.is_err() {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Could not revoke session",
+            )
+                .into_response();
+        }
+    }
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use axum::{body::to_bytes, http::HeaderValue};
+    use axum_extra::headers::Header;
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        models::session::RefreshTokenData,
+        utils::{
+            database::{
+                create_test_tenant, create_test_user, delete_test_tenant, test_postgres_pool,
+            },
+            setup::test_services,
+        },
+    };
+
+    fn session_cookie(session_id: &str) -> TypedHeader<Cookie> {
+        let value = HeaderValue::from_str(&format!("session_id={session_id}")).unwrap();
+        TypedHeader(Cookie::decode(&mut iter::once(&value)).unwrap())
+    }
+
+    /// Logs the user in and issues a refresh token from the new session
+    async fn login(services: &ServicesConfig, user_id: Uuid, tenant_id: Uuid) -> (String, String) {
+        let session_id = Uuid::new_v4().to_string();
+        let session = SessionData::new(user_id.to_string(), tenant_id);
+        services
+            .session_service
+            .set_session(&session_id, &session, 3600)
+            .await
+            .unwrap();
+
+        let jti = Uuid::new_v4().to_string();
+        let token = RefreshTokenData {
+            user_id: user_id.to_string(),
+            client_id: "app".to_owned(),
+            session_id: Some(session_id.clone()),
+            scope: Some("openid".to_owned()),
+            resource: None,
+            jkt: None,
+            x5t_s256: None,
+        };
+        services
+            .refresh_token_service
+            .store_token(&jti, &token, 3600)
+            .await
+            .unwrap();
+
+        (session_id, jti)
+    }
+
+    async fn is_active(services: &ServicesConfig, (session_id, jti): &(String, String)) -> bool {
+        let session = services.session_service.get_session(session_id).await;
+        let token = services.refresh_token_service.get_token(jti).await;
+        session.unwrap().is_some() && token.unwrap().is_some()
+    }
+
+    async fn json_body(response: Response) -> Value {
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_owner_lists_and_revokes_sessions() {
+        let services = test_services().await;
+        let db_pool = test_postgres_pool().await;
+        let tenant_id = create_test_tenant(&db_pool).await;
+        let user_id = create_test_user(&db_pool, tenant_id, false).await;
+        let current = login(&services, user_id, tenant_id).await;
+        let other = login(&services, user_id, tenant_id).await;
+
+        let response = list_sessions(session_cookie(&current.0), Extension(services.clone())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let sessions = json_body(response).await;
+        let sessions = sessions.as_array().unwrap();
+        assert_eq!(sessions.len(), 2);
+        for session in sessions {
+            assert_eq!(session["user_id"], user_id.to_string());
+            assert_eq!(session["current"], session["id"] == current.0.as_str());
+        }
+
+        let response = revoke_session(
+            Path(other.0.clone()),
+            session_cookie(&current.0),
+            Extension(services.clone()),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(!is_active(&services, &other).await);
+        assert!(is_active(&services, &current).await);
+
+        let sessions = services
+            .session_service
+            .list_user_sessions(&user_id.to_string())
+            .await
+            .unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].0, current.0);
+
+        delete_test_tenant(&db_pool, tenant_id).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_non_owner_cannot_revoke_session() {
+        let services = test_services().await;
+        let db_pool = test_postgres_pool().await;
+        let tenant_id = create_test_tenant(&db_pool).await;
+        let owner_id = create_test_user(&db_pool, tenant_id, false).await;
+        let other_id = create_test_user(&db_pool, tenant_id, false).await;
+        let owner = login(&services, owner_id, tenant_id).await;
+        let other = login(&services, other_id, tenant_id).await;
+
+        let response = revoke_session(
+            Path(owner.0.clone()),
+            session_cookie(&other.0),
+            Extension(services.clone()),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(is_active(&services, &owner).await);
+
+        // Without a valid session nothing is revealed at all
+        let response = revoke_session(
+            Path(owner.0.clone()),
+            session_cookie("unknown"),
+            Extension(services.clone()),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(is_active(&services, &owner).await);
+
+        delete_test_tenant(&db_pool, tenant_id).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_revoke_other_sessions_revokes_refresh_tokens() {
+        let services = test_services().await;
+        let db_pool = test_postgres_pool().await;
+        let tenant_id = create_test_tenant(&db_pool).await;
+        let user_id = create_test_user(&db_pool, tenant_id, false).await;
+        let current = login(&services, user_id, tenant_id).await;
+        let others = [
+            login(&services, user_id, tenant_id).await,
+            login(&services, user_id, tenant_id).await,
+        ];
+
+        let response =
+            revoke_other_sessions(session_cookie(&current.0), Extension(services.clone())).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        for other in &others {
+            assert!(!is_active(&services, other).await);
+        }
+        assert!(is_active(&services, &current).await);
+
+        delete_test_tenant(&db_pool, tenant_id).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_admin_manages_sessions_of_tenant_users() {
+        let services = test_services().await;
+        let db_pool = test_postgres_pool().await;
+        let tenant_id = create_test_tenant(&db_pool).await;
+        let admin_id = create_test_user(&db_pool, tenant_id, true).await;
+        let user_id = create_test_user(&db_pool, tenant_id, false).await;
+        let admin = login(&services, admin_id, tenant_id).await;
+        let user_sessions = [
+            login(&services, user_id, tenant_id).await,
+            login(&services, user_id, tenant_id).await,
+        ];
+
+        let response = admin_list_sessions(
+            Path(user_id.to_string()),
+            session_cookie(&admin.0),
+            Extension(services.clone()),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let sessions = json_body(response).await;
+        let sessions = sessions.as_array().unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert!(sessions.iter().all(|session| session["current"] == false));
+
+        // Sessions of other users are not revoked through the user's path
+        let response = admin_revoke_session(
+            Path((user_id.to_string(), admin.0.clone())),
+            session_cookie(&admin.0),
+            Extension(services.clone()),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = admin_revoke_all_sessions(
+            Path(user_id.to_string()),
+            session_cookie(&admin.0),
+            Extension(services.clone()),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        for session in &user_sessions {
+            assert!(!is_active(&services, session).await);
+        }
+        assert!(is_active(&services, &admin).await);
+
+        delete_test_tenant(&db_pool, tenant_id).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_admin_endpoints_need_an_admin_of_the_tenant() {
+        let services = test_services().await;
+        let db_pool = test_postgres_pool().await;
+        let tenant_id = create_test_tenant(&db_pool).await;
+        let other_tenant_id = create_test_tenant(&db_pool).await;
+        let user_id = create_test_user(&db_pool, tenant_id, false).await;
+        let colleague_id = create_test_user(&db_pool, tenant_id, false).await;
+        let foreign_admin_id = create_test_user(&db_pool, other_tenant_id, true).await;
+        let user = login(&services, user_id, tenant_id).await;
+        let colleague = login(&services, colleague_id, tenant_id).await;
+        let foreign_admin = login(&services, foreign_admin_id, other_tenant_id).await;
+
+        for caller in [&colleague, &foreign_admin] {
+            let response = admin_list_sessions(
+                Path(user_id.to_string()),
+                session_cookie(&caller.0),
+                Extension(services.clone()),
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+            let response = admin_revoke_session(
+                Path((user_id.to_string(), user.0.clone())),
+                session_cookie(&caller.0),
+                Extension(services.clone()),
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+            let response = admin_revoke_all_sessions(
+                Path(user_id.to_string()),
+                session_cookie(&caller.0),
+                Extension(services.clone()),
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+        assert!(is_active(&services, &user).await);
+
+        delete_test_tenant(&db_pool, tenant_id).await;
+        delete_test_tenant(&db_pool, other_tenant_id).await;
+    }
+}
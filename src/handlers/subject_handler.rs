@@ -0,0 +1,163 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use axum_extra::{TypedHeader, headers::Cookie};
+use serde_json::json;
+
+use crate::{
+    handlers::session_handler::require_tenant_admin, models::services_config::ServicesConfig,
+};
+
+/// Resolve the `sub` an application knows a user by to the internal user ID
+pub async fn admin_resolve_subject(
+    Path((client_id, subject)): Path<(String, String)>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+) -> Response {
+    // Only admins of the application's tenant learn whether a subject exists. Unknown
+    // applications belong to no tenant, so nobody is their admin.
+    let tenant_id = services
+        .application_service
+        .get_client_information(&client_id)
+        .await
+        .map(|application| application.tenant_id)
+        .unwrap_or_default();
+    let admin_id = match require_tenant_admin(&cookies, &services, tenant_id).await {
+        Ok(admin_id) => admin_id,
+        Err(response) => return response,
+    };
+
+    let user_id = match services
+        .subject_service
+        .resolve_subject(&subject, &client_id)
+        .await
+    {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) | Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    // Public subjects are user IDs, which may belong to another tenant
+    match services
+        .user_service
+        .is_tenant_admin_of(&admin_id, &user_id)
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) | Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    }
+
+    Json(json!({ "user_id": user_id })).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use axum::{body::to_bytes, http::HeaderValue};
+    use axum_extra::headers::Header;
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        models::session::SessionData,
+        services::subject_service::SubjectService,
+        utils::{
+            database::{
+                create_test_application, create_test_tenant, create_test_user, delete_test_tenant,
+                test_postgres_pool,
+            },
+            setup::test_services,
+        },
+    };
+
+    async fn login(services: &ServicesConfig, user_id: Uuid, tenant_id: Uuid) -> Cookie {
+        let session_id = Uuid::new_v4().to_string();
+        let session = SessionData::new(user_id.to_string(), tenant_id);
+        services
+            .session_service
+            .set_session(&session_id, &session, 3600)
+            .await
+            .unwrap();
+        let value = HeaderValue::from_str(&format!("session_id={session_id}")).unwrap();
+        Cookie::decode(&mut iter::once(&value)).unwrap()
+    }
+
+    async fn resolve(
+        services: &Arc<ServicesConfig>,
+        cookie: &Cookie,
+        client_id: &str,
+        subject: &str,
+    ) -> Response {
+        admin_resolve_subject(
+            Path((client_id.to_owned(), subject.to_owned())),
+            TypedHeader(cookie.clone()),
+            Extension(services.clone()),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_only_tenant_admins_resolve_subjects() {
+        let db_pool = test_postgres_pool().await;
+        let mut services = Arc::into_inner(test_services().await).unwrap();
+        services.subject_service = SubjectService::new(db_pool.clone(), Some("salt".to_owned()));
+        let services = Arc::new(services);
+
+        let tenant_id = create_test_tenant(&db_pool).await;
+        let other_tenant_id = create_test_tenant(&db_pool).await;
+        let client_id = create_test_application(&db_pool, tenant_id).await;
+        sqlx::query(
+            "UPDATE Applications SET subject_type = 'pairwise', \
+             sector_identifier = 'app.example' WHERE client_id = $1",
+        )
+        .bind(&client_id)
+        .execute(&db_pool)
+        .await
+        .unwrap();
+        let user_id = create_test_user(&db_pool, tenant_id, false).await;
+        let subject = services
+            .subject_service
+            .subject_for(&user_id.to_string(), &client_id)
+            .await
+            .unwrap();
+
+        let admin = create_test_user(&db_pool, tenant_id, true).await;
+        let admin = login(&services, admin, tenant_id).await;
+        let response = resolve(&services, &admin, &client_id, &subject).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["user_id"], user_id.to_string());
+        let response = resolve(&services, &admin, &client_id, "unknown").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // Whether a subject exists is not revealed to anyone else
+        let colleague = create_test_user(&db_pool, tenant_id, false).await;
+        let colleague = login(&services, colleague, tenant_id).await;
+        let foreign_admin = create_test_user(&db_pool, other_tenant_id, true).await;
+        let foreign_admin = login(&services, foreign_admin, other_tenant_id).await;
+        for cookie in [&colleague, &foreign_admin] {
+            for subject in [subject.as_str(), "unknown"] {
+                let response = resolve(&services, cookie, &client_id, subject).await;
+                assert_eq!(response.status(), StatusCode::FORBIDDEN);
+            }
+            let response = resolve(&services, cookie, "unknown", &subject).await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+        let logged_out = Cookie::decode(&mut iter::once(&HeaderValue::from_static("a=b"))).unwrap();
+        for subject in [subject.as_str(), "unknown"] {
+            let response = resolve(&services, &logged_out, &client_id, subject).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        delete_test_tenant(&db_pool, tenant_id).await;
+        delete_test_tenant(&db_pool, other_tenant_id).await;
+    }
+}
@@ -1,38 +1,122 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use axum::{
-    Extension, Form,
-    http::{Response as HttpResponse, StatusCode, header::SET_COOKIE},
-    response::IntoResponse,
+    Extension, Form, Json,
+    http::{HeaderMap, Response as HttpResponse, StatusCode, header::SET_COOKIE},
+    response::{IntoResponse, Response},
 };
+use axum_extra::{TypedHeader, headers::Cookie as CookieHeader};
 use axum_macros::debug_handler;
+use chrono::Utc;
 use cookie::Cookie;
+use serde_json::{Map, Value, json};
+use uuid::Uuid;
 
 use crate::{
     models::{
-        services_config::ServicesConfig, token_request::TokenRequest, token_response::TokenResponse,
+        application_model::{
+            AUTHORIZATION_CODE_GRANT_TYPE, Application, REFRESH_TOKEN_GRANT_TYPE,
+            TokenEndpointAuthMethod,
+        },
+        claims::{AccessTokenClaims, ClaimRequest},
+        client_certificate::ClientCertificate,
+        device_grant::{DEVICE_CODE_GRANT_TYPE, DeviceGrantStatus},
+        dpop::DPOP_TOKEN_TYPE,
+        services_config::ServicesConfig,
+        session::RefreshTokenData,
+        token_exchange::{ACCESS_TOKEN_TYPE, TOKEN_EXCHANGE_GRANT_TYPE, is_access_token_type},
+        token_request::TokenRequest,
+        token_response::TokenResponse,
+        user_attributes::ClaimTarget,
+    },
+    services::user_service::UserService,
+    utils::{
+        claims_utils::{downscope, has_scope, select_claims, standard_claim_values},
+        dpop_utils::{bound_key, verify_dpop_proof},
+        mtls_utils::{
+            certificate_thumbprint, jwks_contains_certificate, subject_dn, subject_dn_matches,
+            verify_certificate_binding,
+        },
+        resource_utils::{resource_matches, resource_token_scope},
+        tenant_issuer::TenantIssuer,
+        token_issuer::TokenIssuer,
     },
-    utils::token_issuer::TokenIssuer,
 };
 
 #[debug_handler]
 pub async fn token(
     Extension(services): Extension<Arc<ServicesConfig>>,
-    Extension(token_issuer): Extension<Arc<TokenIssuer>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+    cookies: Option<TypedHeader<CookieHeader>>,
+    headers: HeaderMap,
+    client_certificate: Option<ClientCertificate>,
     Form(params): Form<TokenRequest>,
-) -> impl IntoResponse {
-    if params.grant_type != "authorization_code" {
-        return (StatusCode::BAD_REQUEST, "unsupported grant").into_response();
+) -> Response {
+    let client_certificate = client_certificate.as_ref();
+
+    // Tokens requested with a DPoP proof are bound to its key
+    let jkt = match dpop_key(&services, &issuer, &headers).await {
+        Ok(jkt) => jkt,
+        Err(response) => return response,
+    };
+
+    match params.grant_type.as_str() {
+        AUTHORIZATION_CODE_GRANT_TYPE => {
+            authorization_code_grant(&services, &issuer, params, jkt, client_certificate).await
+        }
+        DEVICE_CODE_GRANT_TYPE => {
+            device_code_grant(&services, &issuer, params, jkt, client_certificate).await
+        }
+        TOKEN_EXCHANGE_GRANT_TYPE => {
+            token_exchange_grant(&services, &issuer, params, jkt, client_certificate).await
+        }
+        REFRESH_TOKEN_GRANT_TYPE => {
+            // Fall back to the HTTP-only cookie set by a previous token response
+            let refresh_token = params.refresh_token.clone().or_else(|| {
+                cookies
+                    .as_ref()
+                    .and_then(|TypedHeader(cookies)| cookies.get("refresh_token"))
+                    .map(str::to_owned)
+            });
+
+            match refresh_token {
+                Some(refresh_token) => {
+                    refresh_token_grant(
+                        &services,
+                        &issuer,
+                        params,
+                        &refresh_token,
+                        jkt,
+                        client_certificate,
+                    )
+                    .await
+                }
+                None => (StatusCode::BAD_REQUEST, "Missing refresh token").into_response(),
+            }
+        }
+        _ => (StatusCode::BAD_REQUEST, "unsupported grant").into_response(),
     }
+}
 
-    let auth_code = match services.auth_code_service.consume_code(&params.code).await {
+async fn authorization_code_grant(
+    services: &ServicesConfig,
+    issuer: &TenantIssuer,
+    params: TokenRequest,
+    jkt: Option<String>,
+    client_certificate: Option<&ClientCertificate>,
+) -> Response {
+    let (Some(code), Some(redirect_uri)) = (&params.code, &params.redirect_uri) else {
+        return (StatusCode::BAD_REQUEST, "Missing code or redirect_uri").into_response();
+    };
+
+    let auth_code = match services.auth_code_service.consume_code(code).await {
         Ok(Some(data)) => data,
         Ok(None) | Err(_) => {
             return (StatusCode::BAD_REQUEST, "Code invalid or expired").into_response();
         }
     };
 
-    if auth_code.redirect_uri != params.redirect_uri {
+    if &auth_code.redirect_uri != redirect_uri {
         return (StatusCode::BAD_REQUEST, "Redirect URI mismatch").into_response();
     }
 
@@ -40,25 +124,258 @@ pub async fn token(
         return (StatusCode::BAD_REQUEST, "Client ID mismatch").into_response();
     }
 
-    let application_informantion = match services
-        .application_service
-        .get_client_information(&params.client_id)
+    if !resource_matches(params.resource.as_deref(), auth_code.resource.as_deref()) {
+        return oauth_error("invalid_target");
+    }
+
+    let (application, x5t_s256) =
+        match authenticate_bound_client(services, issuer, &params, client_certificate).await {
+            Ok(authenticated) => authenticated,
+            Err(response) => return response,
+        };
+    if !application.allows_grant_type(AUTHORIZATION_CODE_GRANT_TYPE) {
+        return oauth_error("unauthorized_client");
+    }
+
+    if let Err(response) = check_user_active(&services.user_service, &auth_code.user_id).await {
+        return response;
+    }
+
+    let grant = RefreshTokenData {
+        user_id: auth_code.user_id,
+        client_id: params.client_id,
+        session_id: auth_code.session_id,
+        scope: auth_code.scope,
+        resource: auth_code.resource,
+        jkt,
+        x5t_s256,
+    };
+    let requested_claims = auth_code
+        .claims
+        .as_ref()
+        .map(|claims_request| &claims_request.id_token);
+    let id_token = match id_token(
+        services,
+        &issuer.token_issuer,
+        &application,
+        &grant,
+        auth_code.nonce,
+        auth_code.auth_time,
+        requested_claims,
+    )
+    .await
+    {
+        Ok(id_token) => id_token,
+        Err(response) => return response,
+    };
+
+    issue_tokens(
+        services,
+        &issuer.token_issuer,
+        &application,
+        grant,
+        id_token,
+        false,
+    )
+    .await
+}
+
+/// Device access token request, polled until the user decided (RFC 8628 3.4)
+async fn device_code_grant(
+    services: &ServicesConfig,
+    issuer: &TenantIssuer,
+    params: TokenRequest,
+    jkt: Option<String>,
+    client_certificate: Option<&ClientCertificate>,
+) -> Response {
+    let Some(device_code) = &params.device_code else {
+        return oauth_error("invalid_request");
+    };
+
+    let (application, x5t_s256) =
+        match authenticate_bound_client(services, issuer, &params, client_certificate).await {
+            Ok(authenticated) => authenticated,
+            Err(response) => return response,
+        };
+    if !application.allows_grant_type(DEVICE_CODE_GRANT_TYPE) {
+        return oauth_error("unauthorized_client");
+    }
+
+    let grant = match services.device_grant_service.get_grant(device_code).await {
+        Ok(Some(grant)) => grant,
+        Ok(None) => return oauth_error("expired_token"),
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error while retriving device authorization",
+            )
+                .into_response();
+        }
+    };
+
+    if grant.client_id != params.client_id {
+        return oauth_error("invalid_grant");
+    }
+
+    let (user_id, auth_time) = match grant.status {
+        DeviceGrantStatus::Pending => {
+            // Devices polling faster than allowed have to wait longer from now on
+            let too_fast = match services
+                .device_grant_service
+                .record_poll(device_code, &grant)
+                .await
+            {
+                Ok(too_fast) => too_fast,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to store device authorization",
+                    )
+                        .into_response();
+                }
+            };
+
+            return match too_fast {
+                true => oauth_error("slow_down"),
+                false => oauth_error("authorization_pending"),
+            };
+        }
+        DeviceGrantStatus::Denied => {
+            let _ = services
+                .device_grant_service
+                .consume_grant(device_code)
+                .await;
+            return oauth_error("access_denied");
+        }
+        DeviceGrantStatus::Approved { user_id, auth_time } => (user_id, auth_time),
+    };
+
+    // Only the first request after the approval receives tokens
+    match services
+        .device_grant_service
+        .consume_grant(device_code)
         .await
     {
-        Ok(application_informantion) => application_informantion,
-        Err(_) => return (StatusCode::BAD_REQUEST, "Invalid Client").into_response(),
+        Ok(Some(_)) => {}
+        Ok(None) => return oauth_error("expired_token"),
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error while retriving device authorization",
+            )
+                .into_response();
+        }
+    }
+
+    if let Err(response) = check_user_active(&services.user_service, &user_id).await {
+        return response;
+    }
+
+    let grant = RefreshTokenData {
+        user_id,
+        client_id: params.client_id,
+        session_id: None,
+        scope: grant.scope,
+        resource: None,
+        jkt,
+        x5t_s256,
+    };
+    let id_token = match id_token(
+        services,
+        &issuer.token_issuer,
+        &application,
+        &grant,
+        None,
+        Some(auth_time),
+        None,
+    )
+    .await
+    {
+        Ok(id_token) => id_token,
+        Err(response) => return response,
     };
 
-    if application_informantion.client_secret != params.client_secret {
-        return (StatusCode::UNAUTHORIZED, "Invalid client id").into_response();
+    // Devices have no cookie jar to keep the refresh token in
+    issue_tokens(
+        services,
+        &issuer.token_issuer,
+        &application,
+        grant,
+        id_token,
+        true,
+    )
+    .await
+}
+
+/// Exchange a user's access token for a token targeted at another audience (RFC 8693)
+async fn token_exchange_grant(
+    services: &ServicesConfig,
+    issuer: &TenantIssuer,
+    params: TokenRequest,
+    jkt: Option<String>,
+    client_certificate: Option<&ClientCertificate>,
+) -> Response {
+    let (application, x5t_s256) =
+        match authenticate_bound_client(services, issuer, &params, client_certificate).await {
+            Ok(authenticated) => authenticated,
+            Err(response) => return response,
+        };
+    if !application.allows_grant_type(TOKEN_EXCHANGE_GRANT_TYPE)
+        || application.token_exchange_audiences.is_empty()
+    {
+        return oauth_error("unauthorized_client");
+    }
+
+    let (Some(subject_token), Some(subject_token_type)) =
+        (&params.subject_token, &params.subject_token_type)
+    else {
+        return oauth_error("invalid_request");
+    };
+    if !is_access_token_type(subject_token_type)
+        || params
+            .requested_token_type
+            .as_deref()
+            .is_some_and(|token_type| !is_access_token_type(token_type))
+    {
+        return oauth_error("invalid_request");
     }
 
-    let user_information = match services
+    let Ok(subject_token) = issuer
+        .token_verifier
+        .verify_issued_access_token(subject_token)
+    else {
+        return oauth_error("invalid_request");
+    };
+    let subject_claims = subject_token.claims;
+    if !is_proven(&subject_claims, jkt.as_deref(), client_certificate) {
+        return oauth_error("invalid_request");
+    }
+    let user_id = match services
+        .subject_service
+        .resolve_subject(&subject_claims.sub, &subject_claims.client_id)
+        .await
+    {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => return oauth_error("invalid_request"),
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to resolve subject",
+            )
+                .into_response();
+        }
+    };
+    if let Err(response) = check_user_active(&services.user_service, &user_id).await {
+        return response;
+    }
+    // Clients only act on behalf of users of their own tenant
+    match services
         .user_service
-        .get_user_information(&auth_code.user_id)
+        .is_user_in_tenant(&user_id, application.tenant_id)
         .await
     {
-        Ok(user_information) => user_information,
+        Ok(true) => {}
+        Ok(false) => return oauth_error("invalid_request"),
         Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -66,32 +383,96 @@ pub async fn token(
             )
                 .into_response();
         }
+    }
+
+    // Only audiences of the client's policy, the audience may be left out if there is one
+    let audience = match &params.audience {
+        Some(audience) => audience,
+        None if application.token_exchange_audiences.len() == 1 => {
+            &application.token_exchange_audiences[0]
+        }
+        None => return oauth_error("invalid_target"),
     };
+    if !application.token_exchange_audiences.contains(audience) {
+        return oauth_error("invalid_target");
+    }
 
-    let id_token = match token_issuer.create_id_token(
-        "user identifier",
-        &params.client_id,
-        auth_code.nonce,
-        Some(user_information.email),
-        Some(user_information.username),
-        3600,
-    ) {
-        Ok(id_token) => id_token,
+    let Some(scope) = downscope(subject_claims.scope.as_deref(), params.scope.as_deref()) else {
+        return oauth_error("invalid_scope");
+    };
+
+    // Delegation names the actor, earlier actors of the subject token stay nested (RFC 8693 4.1)
+    let prior_actor = subject_claims.user_claims.get("act").cloned();
+    let actor = match (&params.actor_token, &params.actor_token_type) {
+        (None, None) => prior_actor,
+        (Some(actor_token), Some(actor_token_type)) if is_access_token_type(actor_token_type) => {
+            let Ok(actor_token) = issuer
+                .token_verifier
+                .verify_issued_access_token(actor_token)
+            else {
+                return oauth_error("invalid_request");
+            };
+            if !is_proven(&actor_token.claims, jkt.as_deref(), client_certificate) {
+                return oauth_error("invalid_request");
+            }
+            let mut actor = json!({
+                "sub": actor_token.claims.sub,
+                "client_id": actor_token.claims.client_id,
+            });
+            if let Some(prior_actor) = prior_actor {
+                actor["act"] = prior_actor;
+            }
+            Some(actor)
+        }
+        _ => return oauth_error("invalid_request"),
+    };
+
+    let mut claims = match services
+        .attribute_service
+        .get_mapped_claims(&user_id, audience, ClaimTarget::AccessToken)
+        .await
+    {
+        Ok(claims) => claims,
         Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to issue ID token",
+                "Error while retriving user attributes",
             )
                 .into_response();
         }
     };
+    if let Some(actor) = actor {
+        claims.insert("act".to_owned(), actor);
+    }
+    if let Some(cnf) = confirmation(jkt.as_deref(), x5t_s256.as_deref()) {
+        claims.insert("cnf".to_owned(), cnf);
+    }
 
-    // TODO: Get roles, permissions from database for user
-    let access_token = match token_issuer.create_access_token(
-        &auth_code.user_id.to_string(),
+    let subject = match services
+        .subject_service
+        .subject_for(&user_id, &params.client_id)
+        .await
+    {
+        Ok(subject) => subject,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to resolve subject",
+            )
+                .into_response();
+        }
+    };
+
+    // The exchanged token does not outlive the token it was exchanged for
+    let expires_in = (subject_claims.exp as i64 - Utc::now().timestamp())
+        .clamp(0, application.access_token_lifetime as i64);
+    let access_token = match issuer.token_issuer.create_access_token(
+        &subject,
+        audience,
         &params.client_id,
-        Some("scope".to_string()),
-        3600,
+        scope.clone(),
+        claims,
+        expires_in,
     ) {
         Ok(access_token) => access_token,
         Err(_) => {
@@ -103,36 +484,475 @@ pub async fn token(
         }
     };
 
-    // Generate refresh token
-    let refresh_token = match token_issuer.create_refresh_token(
-        &auth_code.user_id.to_string(),
-        86400, // 24 hours expiry for refresh token
+    Json(TokenResponse {
+        access_token,
+        token_type: token_type(jkt.as_deref()),
+        expires_in: expires_in as i32,
+        id_token: None,
+        refresh_token: None,
+        issued_token_type: Some(ACCESS_TOKEN_TYPE.to_owned()),
+        scope,
+    })
+    .into_response()
+}
+
+/// Whether a token bound to a `DPoP` key or client certificate is presented with a proof of it,
+/// unbound tokens need none
+fn is_proven(
+    claims: &AccessTokenClaims,
+    jkt: Option<&str>,
+    client_certificate: Option<&ClientCertificate>,
+) -> bool {
+    let certificate = client_certificate.map(|ClientCertificate(certificate)| certificate);
+    bound_key(claims).is_none_or(|bound| jkt == Some(bound))
+        && verify_certificate_binding(claims, certificate)
+}
+
+/// Verify the `DPoP` proof of a token request, returns the thumbprint of its key (RFC 9449 5)
+async fn dpop_key(
+    services: &ServicesConfig,
+    issuer: &TenantIssuer,
+    headers: &HeaderMap,
+) -> Result<Option<String>, Response> {
+    let mut proofs = headers.get_all("DPoP").iter();
+    let Some(proof) = proofs.next() else {
+        return Ok(None);
+    };
+    if proofs.next().is_some() {
+        return Err(oauth_error("invalid_dpop_proof"));
+    }
+
+    let token_endpoint = issuer.endpoint_url("/token");
+    let Ok(proof) = proof
+        .to_str()
+        .map_err(anyhow::Error::from)
+        .and_then(|proof| verify_dpop_proof(proof, "POST", &token_endpoint, None))
+    else {
+        return Err(oauth_error("invalid_dpop_proof"));
+    };
+
+    if services.dpop_service.requires_nonce() {
+        let valid_nonce = match &proof.nonce {
+            Some(nonce) => services.dpop_service.is_valid_nonce(nonce).await,
+            None => Ok(false),
+        };
+        match valid_nonce {
+            Ok(true) => {}
+            Ok(false) => return Err(use_dpop_nonce(services).await),
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to check DPoP nonce",
+                )
+                    .into_response());
+            }
+        }
+    }
+
+    match services.dpop_service.record_proof(&proof).await {
+        Ok(true) => Ok(Some(proof.jkt)),
+        Ok(false) => Err(oauth_error("invalid_dpop_proof")),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to record DPoP proof",
+        )
+            .into_response()),
+    }
+}
+
+/// Ask the client to retry with a nonce provided by the server (RFC 9449 8)
+async fn use_dpop_nonce(services: &ServicesConfig) -> Response {
+    match services.dpop_service.issue_nonce().await {
+        Ok(nonce) => (
+            StatusCode::BAD_REQUEST,
+            [("DPoP-Nonce", nonce)],
+            Json(json!({ "error": "use_dpop_nonce" })),
+        )
+            .into_response(),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to issue DPoP nonce",
+        )
+            .into_response(),
+    }
+}
+
+/// `DPoP` for tokens bound to a key, `Bearer` otherwise
+fn token_type(jkt: Option<&str>) -> String {
+    match jkt {
+        Some(_) => DPOP_TOKEN_TYPE.to_owned(),
+        None => "Bearer".to_owned(),
+    }
+}
+
+/// OAuth error response, e.g. the device decides by `error` whether to keep polling
+pub(crate) fn oauth_error(error: &str) -> Response {
+    (StatusCode::BAD_REQUEST, Json(json!({ "error": error }))).into_response()
+}
+
+async fn refresh_token_grant(
+    services: &ServicesConfig,
+    issuer: &TenantIssuer,
+    params: TokenRequest,
+    refresh_token: &str,
+    jkt: Option<String>,
+    client_certificate: Option<&ClientCertificate>,
+) -> Response {
+    let refresh_token_in_body = params.refresh_token.is_some();
+    let (application, x5t_s256) =
+        match authenticate_bound_client(services, issuer, &params, client_certificate).await {
+            Ok(authenticated) => authenticated,
+            Err(response) => return response,
+        };
+    if !application.allows_grant_type(REFRESH_TOKEN_GRANT_TYPE) {
+        return oauth_error("unauthorized_client");
+    }
+
+    let claims = match issuer.token_verifier.verify_refresh_token(refresh_token) {
+        Ok(token_data) => token_data.claims,
+        Err(_) => return (StatusCode::BAD_REQUEST, "Invalid refresh token").into_response(),
+    };
+
+    // Refresh tokens are single use; revoked sessions have already removed theirs
+    let mut refresh_data = match services
+        .refresh_token_service
+        .consume_token(&claims.jti)
+        .await
+    {
+        Ok(Some(data)) => data,
+        Ok(None) | Err(_) => {
+            return (StatusCode::BAD_REQUEST, "Refresh token invalid or revoked").into_response();
+        }
+    };
+
+    if refresh_data.client_id != params.client_id || refresh_data.user_id != claims.sub {
+        return (StatusCode::BAD_REQUEST, "Client ID mismatch").into_response();
+    }
+    if let Err(response) = check_user_active(&services.user_service, &refresh_data.user_id).await {
+        return response;
+    }
+
+    if !resource_matches(params.resource.as_deref(), refresh_data.resource.as_deref()) {
+        return oauth_error("invalid_target");
+    }
+
+    // Bound refresh tokens are only usable with a proof of the same key
+    match (&refresh_data.jkt, jkt) {
+        (Some(bound), jkt) if jkt.as_ref() != Some(bound) => return oauth_error("invalid_grant"),
+        (Some(_), _) => {}
+        (None, jkt) => refresh_data.jkt = jkt,
+    }
+    // Access tokens are bound to the certificate of the connection they are refreshed over
+    refresh_data.x5t_s256 = x5t_s256;
+
+    // Clients that sent the refresh token themselves keep the new one themselves as well
+    issue_tokens(
+        services,
+        &issuer.token_issuer,
+        &application,
+        refresh_data,
+        None,
+        refresh_token_in_body,
+    )
+    .await
+}
+
+/// Authenticate the client with its secret or TLS client certificate, depending on its method
+pub(crate) async fn authenticate_client(
+    services: &ServicesConfig,
+    issuer: &TenantIssuer,
+    client_id: &str,
+    client_secret: &str,
+    client_certificate: Option<&ClientCertificate>,
+) -> Result<Application, Response> {
+    let application_informantion = match services
+        .application_service
+        .get_client_information(client_id)
+        .await
+    {
+        Ok(application_informantion) => application_informantion,
+        Err(_) => return Err((StatusCode::BAD_REQUEST, "Invalid Client").into_response()),
+    };
+    // Applications of other tenants are unknown to a tenant's issuer
+    if !issuer.serves(application_informantion.tenant_id) {
+        return Err((StatusCode::BAD_REQUEST, "Invalid Client").into_response());
+    }
+
+    let method =
+        TokenEndpointAuthMethod::parse(&application_informantion.token_endpoint_auth_method)
+            .unwrap_or_default();
+    let certificate = client_certificate.map(|ClientCertificate(certificate)| certificate);
+    let authenticated = match (method, certificate) {
+        (TokenEndpointAuthMethod::ClientSecretPost, _) => {
+            application_informantion.client_secret == client_secret
+        }
+        (TokenEndpointAuthMethod::TlsClientAuth, Some(certificate)) => {
+            services.mtls_service.is_trusted(certificate)
+                && application_informantion
+                    .tls_client_auth_subject_dn
+                    .as_deref()
+                    .zip(subject_dn(certificate).ok())
+                    .is_some_and(|(registered_dn, certificate_dn)| {
+                        subject_dn_matches(&certificate_dn, registered_dn)
+                    })
+        }
+        (TokenEndpointAuthMethod::SelfSignedTlsClientAuth, Some(certificate)) => match services
+            .application_service
+            .get_client_jwks(client_id)
+            .await
+        {
+            Ok(Some(jwks)) => jwks_contains_certificate(&jwks, certificate),
+            Ok(None) | Err(_) => false,
+        },
+        (_, None) => false,
+    };
+
+    if !authenticated {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid client id").into_response());
+    }
+
+    Ok(application_informantion)
+}
+
+/// Authenticate the client of a token request, with the thumbprint of its certificate if the
+/// application's access tokens are bound to it (RFC 8705 3)
+async fn authenticate_bound_client(
+    services: &ServicesConfig,
+    issuer: &TenantIssuer,
+    params: &TokenRequest,
+    client_certificate: Option<&ClientCertificate>,
+) -> Result<(Application, Option<String>), Response> {
+    let application = authenticate_client(
+        services,
+        issuer,
+        &params.client_id,
+        &params.client_secret,
+        client_certificate,
+    )
+    .await?;
+
+    if !application.tls_client_certificate_bound_access_tokens {
+        return Ok((application, None));
+    }
+    match client_certificate
+        .map(|ClientCertificate(certificate)| certificate_thumbprint(certificate))
+    {
+        Some(Ok(x5t_s256)) => Ok((application, Some(x5t_s256))),
+        Some(Err(_)) | None => Err(oauth_error("invalid_request")),
+    }
+}
+
+/// `cnf` claim of access tokens bound to a DPoP key or a client certificate
+fn confirmation(jkt: Option<&str>, x5t_s256: Option<&str>) -> Option<Value> {
+    let mut cnf = Map::new();
+    if let Some(jkt) = jkt {
+        cnf.insert("jkt".to_owned(), json!(jkt));
+    }
+    if let Some(x5t_s256) = x5t_s256 {
+        cnf.insert("x5t#S256".to_owned(), json!(x5t_s256));
+    }
+
+    (!cnf.is_empty()).then_some(Value::Object(cnf))
+}
+
+/// Tokens are only issued to active users, deactivated users lose their grants
+async fn check_user_active(user_service: &UserService, user_id: &str) -> Result<(), Response> {
+    let is_active = match user_service.is_user_active(user_id).await {
+        Ok(is_active) => is_active,
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error while retriving user information",
+            )
+                .into_response());
+        }
+    };
+
+    if !is_active {
+        return Err((StatusCode::FORBIDDEN, "User is inactive").into_response());
+    }
+
+    Ok(())
+}
+
+/// ID token for `grant`, only OpenID Connect requests receive one
+async fn id_token(
+    services: &ServicesConfig,
+    token_issuer: &TokenIssuer,
+    application: &Application,
+    grant: &RefreshTokenData,
+    nonce: Option<String>,
+    auth_time: Option<i64>,
+    requested_claims: Option<&HashMap<String, Option<ClaimRequest>>>,
+) -> Result<Option<String>, Response> {
+    if !has_scope(grant.scope.as_deref(), "openid") {
+        return Ok(None);
+    }
+
+    let user_claims = match services.user_service.get_user_claims(&grant.user_id).await {
+        Ok(user_claims) => standard_claim_values(&user_claims),
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error while retriving user information",
+            )
+                .into_response());
+        }
+    };
+    let mut id_token_claims = select_claims(&user_claims, grant.scope.as_deref(), requested_claims);
+
+    // Claims mapped from custom attributes by the application's rules
+    match services
+        .attribute_service
+        .get_mapped_claims(&grant.user_id, &grant.client_id, ClaimTarget::IdToken)
+        .await
+    {
+        Ok(mapped_claims) => id_token_claims.extend(mapped_claims),
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error while retriving user attributes",
+            )
+                .into_response());
+        }
+    }
+
+    let subject = match services
+        .subject_service
+        .subject_for(&grant.user_id, &grant.client_id)
+        .await
+    {
+        Ok(subject) => subject,
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to resolve subject",
+            )
+                .into_response());
+        }
+    };
+
+    match token_issuer.create_id_token(
+        &subject,
+        &grant.client_id,
+        nonce,
+        auth_time,
+        id_token_claims,
+        application.id_token_lifetime as i64,
     ) {
-        Ok(refresh_token) => refresh_token,
+        Ok(id_token) => Ok(Some(id_token)),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to issue ID token",
+        )
+            .into_response()),
+    }
+}
+
+/// Issues an access token and, if the application may refresh, a new refresh token bound to the
+/// session in `grant`
+/// Issue the access token and, if the application may refresh, a refresh token in an HTTP-only
+/// cookie. Clients without a cookie jar, e.g. devices, receive it in the JSON body as well.
+async fn issue_tokens(
+    services: &ServicesConfig,
+    token_issuer: &TokenIssuer,
+    application: &Application,
+    grant: RefreshTokenData,
+    id_token: Option<String>,
+    refresh_token_in_body: bool,
+) -> Response {
+    let mut user_claims = match services
+        .attribute_service
+        .get_mapped_claims(&grant.user_id, &grant.client_id, ClaimTarget::AccessToken)
+        .await
+    {
+        Ok(user_claims) => user_claims,
         Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to issue refresh token",
+                "Error while retriving user attributes",
             )
                 .into_response();
         }
     };
 
-    // Create HTTP-only cookie for refresh token
-    let refresh_cookie = Cookie::build(("refresh_token", &refresh_token))
-        .path("")
-        .max_age(cookie::time::Duration::seconds(86400)) // 24 hours
-        .http_only(true)
-        .secure(true)
-        .same_site(cookie::SameSite::Lax);
+    // Tokens for an API are audience restricted to it and carry only its scopes
+    let (audience, scope) = match &grant.resource {
+        Some(resource) => match services
+            .api_resource_service
+            .get_resource_for_client(resource, &grant.client_id)
+            .await
+        {
+            Ok(Some(resource)) => {
+                let scope = resource_token_scope(grant.scope.as_deref(), &resource.scopes);
+                (resource.identifier, scope)
+            }
+            Ok(None) => return oauth_error("invalid_target"),
+            Err(_) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Error while retriving resource",
+                )
+                    .into_response();
+            }
+        },
+        None => (grant.client_id.clone(), grant.scope.clone()),
+    };
+
+    let subject = match services
+        .subject_service
+        .subject_for(&grant.user_id, &grant.client_id)
+        .await
+    {
+        Ok(subject) => subject,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to resolve subject",
+            )
+                .into_response();
+        }
+    };
+
+    if let Some(cnf) = confirmation(grant.jkt.as_deref(), grant.x5t_s256.as_deref()) {
+        user_claims.insert("cnf".to_owned(), cnf);
+    }
+
+    // TODO: Get roles, permissions from database for user
+    let access_token = match token_issuer.create_access_token(
+        &subject,
+        &audience,
+        &grant.client_id,
+        scope,
+        user_claims,
+        application.access_token_lifetime as i64,
+    ) {
+        Ok(access_token) => access_token,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to issue access token",
+            )
+                .into_response();
+        }
+    };
+
+    let refresh_token = match application.allows_grant_type(REFRESH_TOKEN_GRANT_TYPE) {
+        true => match issue_refresh_token(services, token_issuer, application, &grant).await {
+            Ok(refresh_token) => Some(refresh_token),
+            Err(response) => return response,
+        },
+        false => None,
+    };
 
-    // Create token response (without refresh_token in JSON)
     let token_response = TokenResponse {
         access_token,
-        token_type: "Bearer".into(),
-        expires_in: 3600,
+        token_type: token_type(grant.jkt.as_deref()),
+        expires_in: application.access_token_lifetime,
         id_token,
-        refresh_token: None, // Don't include refresh token in JSON response
+        refresh_token: refresh_token.clone().filter(|_| refresh_token_in_body),
+        issued_token_type: None,
+        scope: None,
     };
 
     // Serialize the JSON response
@@ -147,13 +967,199 @@ pub async fn token(
         }
     };
 
-    println!("Issuing new refresh token");
-
-    // Return response with both JSON body and refresh token cookie
-    HttpResponse::builder()
+    let mut response = HttpResponse::builder()
         .status(StatusCode::OK)
-        .header("Content-Type", "application/json")
-        .header(SET_COOKIE, refresh_cookie.to_string())
+        .header("Content-Type", "application/json");
+
+    if let Some(refresh_token) = refresh_token {
+        // Create HTTP-only cookie for refresh token
+        let refresh_cookie = Cookie::build(("refresh_token", &refresh_token))
+            .path("")
+            .max_age(cookie::time::Duration::seconds(
+                application.refresh_token_lifetime as i64,
+            ))
+            .http_only(true)
+            .secure(true)
+            .same_site(cookie::SameSite::Lax);
+
+        println!("Issuing new refresh token");
+        response = response.header(SET_COOKIE, refresh_cookie.to_string());
+    }
+
+    // Return response with the JSON body and the refresh token cookie
+    response
         .body(json_body.into())
         .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
 }
+
+/// Create a refresh token for `grant` and store it, so it can be used once
+async fn issue_refresh_token(
+    services: &ServicesConfig,
+    token_issuer: &TokenIssuer,
+    application: &Application,
+    grant: &RefreshTokenData,
+) -> Result<String, Response> {
+    let refresh_lifetime = application.refresh_token_lifetime;
+
+    // Generate refresh token
+    let refresh_jti = Uuid::new_v4().to_string();
+    let refresh_token = match token_issuer.create_refresh_token(
+        &grant.user_id,
+        &refresh_jti,
+        refresh_lifetime as i64,
+    ) {
+        Ok(refresh_token) => refresh_token,
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to issue refresh token",
+            )
+                .into_response());
+        }
+    };
+
+    if services
+        .refresh_token_service
+        .store_token(&refresh_jti, grant, refresh_lifetime as u64)
+        .await
+        .is_err()
+    {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to store refresh token",
+        )
+            .into_response());
+    }
+
+    Ok(refresh_token)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::to_bytes;
+
+    use super::*;
+    use crate::utils::{
+        database::{
+            create_test_application, create_test_tenant, create_test_user, delete_test_tenant,
+            test_postgres_pool,
+        },
+        setup::test_services,
+        tenant_issuer::test_issuer,
+    };
+
+    async fn token_request(
+        services: &Arc<ServicesConfig>,
+        issuer: &Arc<TenantIssuer>,
+        form: &[(&str, &str)],
+    ) -> (StatusCode, Value) {
+        let params = serde_urlencoded::from_str(&serde_urlencoded::to_string(form).unwrap());
+        let response = token(
+            Extension(services.clone()),
+            Extension(issuer.clone()),
+            None,
+            HeaderMap::new(),
+            None,
+            Form(params.unwrap()),
+        )
+        .await;
+
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_devices_receive_refresh_token_in_body() {
+        let services = test_services().await;
+        let db_pool = test_postgres_pool().await;
+        let tenant_id = create_test_tenant(&db_pool).await;
+        let user_id = create_test_user(&db_pool, tenant_id, false).await;
+        let client_id = create_test_application(&db_pool, tenant_id).await;
+        sqlx::query("UPDATE Applications SET grant_types = $1 WHERE client_id = $2")
+            .bind(vec![DEVICE_CODE_GRANT_TYPE, REFRESH_TOKEN_GRANT_TYPE])
+            .bind(&client_id)
+            .execute(&db_pool)
+            .await
+            .unwrap();
+        let issuer = Arc::new(test_issuer(Some(tenant_id), None));
+
+        let (device_code, mut grant) = services
+            .device_grant_service
+            .create_grant(&client_id, None)
+            .await
+            .unwrap();
+        grant.status = DeviceGrantStatus::Approved {
+            user_id: user_id.to_string(),
+            auth_time: Utc::now().timestamp(),
+        };
+        assert!(
+            services
+                .device_grant_service
+                .decide_grant(&device_code, &grant)
+                .await
+                .unwrap()
+        );
+
+        let (status, body) = token_request(
+            &services,
+            &issuer,
+            &[
+                ("grant_type", DEVICE_CODE_GRANT_TYPE),
+                ("client_id", &client_id),
+                ("client_secret", "secret"),
+                ("device_code", &device_code),
+            ],
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["access_token"].is_string());
+        let refresh_token = body["refresh_token"].as_str().unwrap().to_owned();
+
+        // The device keeps refreshing with the rotated token from the body
+        let (status, body) = token_request(
+            &services,
+            &issuer,
+            &[
+                ("grant_type", REFRESH_TOKEN_GRANT_TYPE),
+                ("client_id", &client_id),
+                ("client_secret", "secret"),
+                ("refresh_token", &refresh_token),
+            ],
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let rotated = body["refresh_token"].as_str().unwrap();
+        assert_ne!(rotated, refresh_token);
+
+        delete_test_tenant(&db_pool, tenant_id).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database at DATABASE_URL and a Redis server at REDIS_URL"]
+    async fn test_inactive_user_gets_no_tokens() {
+        let services = test_services().await;
+        let db_pool = test_postgres_pool().await;
+        let tenant_id = create_test_tenant(&db_pool).await;
+        let user_id = create_test_user(&db_pool, tenant_id, false).await;
+
+        assert!(
+            check_user_active(&services.user_service, &user_id.to_string())
+                .await
+                .is_ok()
+        );
+
+        sqlx::query("UPDATE Users SET is_active = FALSE WHERE id = $1")
+            .bind(user_id)
+            .execute(&db_pool)
+            .await
+            .unwrap();
+        let response = check_user_active(&services.user_service, &user_id.to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        delete_test_tenant(&db_pool, tenant_id).await;
+    }
+}
@@ -1,7 +1,9 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use axum::{
-    http::{header::SET_COOKIE, Response as HttpResponse, StatusCode},
+    extract::ConnectInfo,
+    http::{header::SET_COOKIE, HeaderMap, Response as HttpResponse, StatusCode},
     response::IntoResponse,
     Extension, Json,
 };
@@ -10,19 +12,22 @@ use cookie::Cookie;
 use uuid::Uuid;
 
 use crate::models::{services_config::ServicesConfig, user_models::CreateUserRequest};
+use crate::utils::client_info_utils::client_info;
+use crate::utils::password_policy_utils::PasswordPolicyError;
 
 pub async fn register_user_handler(
     Extension(services): Extension<Arc<ServicesConfig>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(new_user): Json<CreateUserRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     match services.user_service.create_user(&new_user).await {
         Ok(_) => {
             let session_id = Uuid::new_v4().to_string();
-            let ttl: u64 = 900;
 
-            let user = match services
+            let mut user = match services
                 .user_service
-                .get_user_id_from_email(&new_user.email)
+                .get_user_id_from_email(&new_user.email, &new_user.tenant_id)
                 .await
             {
                 Ok(user) => user,
@@ -34,9 +39,38 @@ pub async fn register_user_handler(
                 }
             };
 
+            (user.ip, user.user_agent) = client_info(&addr, &headers);
+            user.auth_methods = vec!["pwd".to_string()];
+
+            let session_policy = match services
+                .user_service
+                .get_session_policy(&user.user_id)
+                .await
+            {
+                Ok(policy) => policy,
+                Err(_) => {
+                    return Err((
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to load session policy.".to_string(),
+                    ));
+                }
+            };
+            let ttl = user.apply_policy(&session_policy, false);
+
+            if services
+                .session_service
+                .set_session(&session_id, &user, ttl)
+                .await
+                .is_err()
+            {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to create session.".to_string(),
+                ));
+            }
+
             let cookie = Cookie::build(("session_id", &session_id))
                 .path("/")
-                .max_age(cookie::time::Duration::seconds(ttl as i64))
                 .http_only(true)
                 .secure(true)
                 .same_site(cookie::SameSite::None);
@@ -59,7 +93,9 @@ pub async fn register_user_handler(
         Err(e) => {
             // Here you can customize the error message based on the description
             let error_message = e.to_string();
-            if error_message.contains("Failed to parse tenant UUID") {
+            if e.downcast_ref::<PasswordPolicyError>().is_some()
+                || error_message.contains("Failed to parse tenant UUID")
+            {
                 Err((StatusCode::BAD_REQUEST, error_message))
             } else if error_message.contains("hashing failed") {
                 Err((StatusCode::INTERNAL_SERVER_ERROR, error_message))
@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    http::{
+        HeaderMap, Method, StatusCode,
+        header::{AUTHORIZATION, WWW_AUTHENTICATE},
+    },
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+
+use crate::{
+    models::{
+        claims::AccessTokenClaims, client_certificate::ClientCertificate, dpop::DPOP_TOKEN_TYPE,
+        services_config::ServicesConfig, user_attributes::ClaimTarget,
+    },
+    utils::{
+        claims_utils::{select_claims, standard_claim_values},
+        dpop_utils::{bound_key, verify_dpop_binding},
+        mtls_utils::verify_certificate_binding,
+        tenant_issuer::TenantIssuer,
+    },
+};
+
+/// OIDC UserInfo endpoint, returns the claims granted to the bearer or DPoP access token
+pub async fn userinfo(
+    method: Method,
+    headers: HeaderMap,
+    client_certificate: Option<ClientCertificate>,
+    Extension(services): Extension<Arc<ServicesConfig>>,
+    Extension(issuer): Extension<Arc<TenantIssuer>>,
+) -> Response {
+    let Some((scheme, access_token)) = headers
+        .get(AUTHORIZATION)
+        .and_then(|authorization| authorization.to_str().ok())
+        .and_then(|authorization| authorization.split_once(' '))
+    else {
+        return (StatusCode::UNAUTHORIZED, [(WWW_AUTHENTICATE, "Bearer")]).into_response();
+    };
+
+    let claims = match issuer
+        .token_verifier
+        .verify_issued_access_token(access_token)
+    {
+        Ok(token_data) => token_data.claims,
+        Err(_) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                [(WWW_AUTHENTICATE, r#"Bearer error="invalid_token""#)],
+            )
+                .into_response();
+        }
+    };
+
+    if let Err(response) = check_dpop_binding(
+        &services,
+        &issuer,
+        &method,
+        &headers,
+        scheme,
+        access_token,
+        &claims,
+    )
+    .await
+    {
+        return response;
+    }
+    let certificate = client_certificate
+        .as_ref()
+        .map(|ClientCertificate(certificate)| certificate);
+    if !verify_certificate_binding(&claims, certificate) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(WWW_AUTHENTICATE, r#"Bearer error="invalid_token""#)],
+        )
+            .into_response();
+    }
+
+    // Pairwise subjects have to be mapped back to the user
+    let user_id = match services
+        .subject_service
+        .resolve_subject(&claims.sub, &claims.client_id)
+        .await
+    {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) | Err(_) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                [(WWW_AUTHENTICATE, r#"Bearer error="invalid_token""#)],
+            )
+                .into_response();
+        }
+    };
+
+    let user_claims = match services.user_service.get_user_claims(&user_id).await {
+        Ok(user_claims) => standard_claim_values(&user_claims),
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error while retriving user information",
+            )
+                .into_response();
+        }
+    };
+
+    let mut response = select_claims(&user_claims, claims.scope.as_deref(), None);
+
+    match services
+        .attribute_service
+        .get_mapped_claims(&user_id, &claims.aud, ClaimTarget::UserInfo)
+        .await
+    {
+        Ok(mapped_claims) => response.extend(mapped_claims),
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error while retriving user attributes",
+            )
+                .into_response();
+        }
+    }
+
+    response.insert("sub".to_owned(), json!(claims.sub));
+
+    Json(response).into_response()
+}
+
+/// Tokens bound to a DPoP key are only accepted with a fresh proof of it, never as bearer tokens
+async fn check_dpop_binding(
+    services: &ServicesConfig,
+    issuer: &TenantIssuer,
+    method: &Method,
+    headers: &HeaderMap,
+    scheme: &str,
+    access_token: &str,
+    claims: &AccessTokenClaims,
+) -> Result<(), Response> {
+    let invalid_token = |scheme: &str, error: &str| {
+        (
+            StatusCode::UNAUTHORIZED,
+            [(WWW_AUTHENTICATE, format!(r#"{scheme} error="{error}""#))],
+        )
+            .into_response()
+    };
+
+    let is_dpop = scheme.eq_ignore_ascii_case(DPOP_TOKEN_TYPE);
+    match (is_dpop, bound_key(claims)) {
+        (false, None) if scheme.eq_ignore_ascii_case("Bearer") => return Ok(()),
+        (true, Some(_)) => {}
+        _ => return Err(invalid_token("Bearer", "invalid_token")),
+    }
+
+    let Some(Ok(proof)) = headers.get("DPoP").map(|proof| proof.to_str()) else {
+        return Err(invalid_token(DPOP_TOKEN_TYPE, "invalid_dpop_proof"));
+    };
+    let userinfo_endpoint = issuer.endpoint_url("/userinfo");
+    let Ok(proof) = verify_dpop_binding(
+        claims,
+        access_token,
+        proof,
+        method.as_str(),
+        &userinfo_endpoint,
+    ) else {
+        return Err(invalid_token(DPOP_TOKEN_TYPE, "invalid_dpop_proof"));
+    };
+
+    match services.dpop_service.record_proof(&proof).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(invalid_token(DPOP_TOKEN_TYPE, "invalid_dpop_proof")),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to record DPoP proof",
+        )
+            .into_response()),
+    }
+}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// An API of a tenant that access tokens can be requested for (RFC 8707)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApiResource {
+    /// Absolute URI without fragment, used as the `aud` of its access tokens
+    pub identifier: String,
+    pub name: String,
+    /// Scopes the API defines, e.g. `orders:read`
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+pub struct ApiResourceSQL {
+    pub identifier: String,
+    pub scopes: Vec<String>,
+}
@@ -1,5 +1,148 @@
+use jsonwebtoken::jwk::JwkSet;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::{
+    device_grant::DEVICE_CODE_GRANT_TYPE, token_exchange::TOKEN_EXCHANGE_GRANT_TYPE,
+};
+
+pub const AUTHORIZATION_CODE_GRANT_TYPE: &str = "authorization_code";
+pub const REFRESH_TOKEN_GRANT_TYPE: &str = "refresh_token";
+
+/// Grant types applications can be allowed to use at the token endpoint
+pub const GRANT_TYPES: [&str; 4] = [
+    AUTHORIZATION_CODE_GRANT_TYPE,
+    REFRESH_TOKEN_GRANT_TYPE,
+    DEVICE_CODE_GRANT_TYPE,
+    TOKEN_EXCHANGE_GRANT_TYPE,
+];
+/// Response types of the authorization endpoint
+pub const RESPONSE_TYPES: [&str; 1] = ["code"];
+
 #[derive(Debug)]
 pub struct Application {
+    pub tenant_id: Uuid,
+    pub name: String,
     pub client_secret: String,
     pub redirect_uris: Vec<String>,
+    pub token_exchange_audiences: Vec<String>,
+    pub require_pushed_authorization_requests: bool,
+    pub token_endpoint_auth_method: String,
+    pub tls_client_auth_subject_dn: Option<String>,
+    pub tls_client_certificate_bound_access_tokens: bool,
+    pub grant_types: Vec<String>,
+    pub response_types: Vec<String>,
+    pub allowed_scopes: Option<Vec<String>>,
+    pub access_token_lifetime: i32,
+    pub id_token_lifetime: i32,
+    pub refresh_token_lifetime: i32,
+    pub authorization_code_lifetime: i32,
+}
+
+impl Application {
+    pub fn allows_grant_type(&self, grant_type: &str) -> bool {
+        self.grant_types.iter().any(|allowed| allowed == grant_type)
+    }
+}
+
+pub struct ClientKeysSQL {
+    pub jwks: Option<sqlx::types::Json<JwkSet>>,
+    pub jwks_uri: Option<String>,
+}
+
+/// How the `sub` claim is computed for an application (OIDC Core 8)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubjectType {
+    /// The internal user ID, the same for every application
+    #[default]
+    Public,
+    /// A different identifier per sector, so applications cannot correlate users
+    Pairwise,
+}
+
+impl SubjectType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubjectType::Public => "public",
+            SubjectType::Pairwise => "pairwise",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [Self::Public, Self::Pairwise]
+            .into_iter()
+            .find(|subject_type| subject_type.as_str() == value)
+    }
+}
+
+/// How an application authenticates at the token endpoint
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenEndpointAuthMethod {
+    #[default]
+    ClientSecretPost,
+    /// Certificate issued by a trusted CA for the registered subject DN (RFC 8705 2.1)
+    TlsClientAuth,
+    /// Self-signed certificate registered in the application's keys (RFC 8705 2.2)
+    SelfSignedTlsClientAuth,
+}
+
+impl TokenEndpointAuthMethod {
+    pub const ALL: [Self; 3] = [
+        Self::ClientSecretPost,
+        Self::TlsClientAuth,
+        Self::SelfSignedTlsClientAuth,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenEndpointAuthMethod::ClientSecretPost => "client_secret_post",
+            TokenEndpointAuthMethod::TlsClientAuth => "tls_client_auth",
+            TokenEndpointAuthMethod::SelfSignedTlsClientAuth => "self_signed_tls_client_auth",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|method| method.as_str() == value)
+    }
+}
+
+pub struct ApplicationSubjectSQL {
+    pub subject_type: String,
+    pub sector_identifier: Option<String>,
+}
+
+/// Seconds the tokens issued to an application are valid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TokenLifetimes {
+    pub access_token: i32,
+    pub id_token: i32,
+    pub refresh_token: i32,
+    pub authorization_code: i32,
+}
+
+impl Default for TokenLifetimes {
+    fn default() -> Self {
+        Self {
+            access_token: 3600,
+            id_token: 3600,
+            refresh_token: 86400,
+            authorization_code: 600,
+        }
+    }
+}
+
+pub fn default_grant_types() -> Vec<String> {
+    vec![
+        AUTHORIZATION_CODE_GRANT_TYPE.to_owned(),
+        REFRESH_TOKEN_GRANT_TYPE.to_owned(),
+    ]
+}
+
+pub fn default_response_types() -> Vec<String> {
+    RESPONSE_TYPES.map(str::to_owned).to_vec()
 }
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::models::claims::ClaimsRequest;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuthCodeData {
     pub user_id: String,
@@ -7,5 +9,14 @@ pub struct AuthCodeData {
     pub redirect_uri: String,
     pub scope: Option<String>,
     pub nonce: Option<String>,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub auth_time: Option<i64>,
+    #[serde(default)]
+    pub claims: Option<ClaimsRequest>,
+    /// Resource indicator of the authorization request (RFC 8707)
+    #[serde(default)]
+    pub resource: Option<String>,
     pub expires_in: u64,
 }
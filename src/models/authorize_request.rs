@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct AuthorizeRequest {
     pub response_type: String,
     pub client_id: String,
@@ -8,4 +8,88 @@ pub struct AuthorizeRequest {
     pub scope: Option<String>,
     pub state: Option<String>,
     pub nonce: Option<String>,
+    /// Space separated list of `none`, `login` and `select_account`
+    pub prompt: Option<String>,
+    /// Maximum seconds since the user last actively authenticated
+    pub max_age: Option<i64>,
+    /// Unix timestamp the user has to have authenticated at or after, set in place of
+    /// `prompt=login` when the request is continued after the login
+    pub login_after: Option<i64>,
+    pub login_hint: Option<String>,
+    /// Previously issued ID token identifying the expected user
+    pub id_token_hint: Option<String>,
+    /// JSON encoded OIDC `claims` request parameter
+    pub claims: Option<String>,
+    /// Absolute URI of the API the access token is requested for (RFC 8707)
+    pub resource: Option<String>,
+    /// How the response is returned to the redirect URI, see [`ResponseMode`]
+    pub response_mode: Option<String>,
+}
+
+/// Account picked in the account chooser
+#[derive(Debug, Deserialize)]
+pub struct SelectAccountRequest {
+    /// One of the browser's sessions
+    pub session_id: String,
+    /// Authorization request to continue, as passed to the chooser
+    pub return_to: String,
+}
+
+/// How the authorization response is returned to the client (OAuth 2.0 Form Post, JARM)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseMode {
+    #[default]
+    Query,
+    Fragment,
+    FormPost,
+    QueryJwt,
+    FragmentJwt,
+    FormPostJwt,
+}
+
+impl ResponseMode {
+    pub const ALL: [&str; 7] = [
+        "query",
+        "fragment",
+        "form_post",
+        "query.jwt",
+        "fragment.jwt",
+        "form_post.jwt",
+        "jwt",
+    ];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "query" => Some(Self::Query),
+            "fragment" => Some(Self::Fragment),
+            "form_post" => Some(Self::FormPost),
+            // `jwt` is the default mode of the response type, `query.jwt` for codes
+            "query.jwt" | "jwt" => Some(Self::QueryJwt),
+            "fragment.jwt" => Some(Self::FragmentJwt),
+            "form_post.jwt" => Some(Self::FormPostJwt),
+            _ => None,
+        }
+    }
+
+    /// Whether the response is signed into a single `response` parameter
+    pub fn is_jwt(self) -> bool {
+        matches!(self, Self::QueryJwt | Self::FragmentJwt | Self::FormPostJwt)
+    }
+}
+
+impl AuthorizeRequest {
+    pub fn prompt_values(&self) -> Vec<&str> {
+        self.prompt
+            .as_deref()
+            .map(|prompt| prompt.split_whitespace().collect())
+            .unwrap_or_default()
+    }
+
+    /// The requested response mode, `None` if it is not supported
+    pub fn response_mode(&self) -> Option<ResponseMode> {
+        match &self.response_mode {
+            Some(response_mode) => ResponseMode::parse(response_mode),
+            None => Some(ResponseMode::default()),
+        }
+    }
 }
@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Look of a tenant's login, consent and error pages
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TenantBranding {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logo_url: Option<String>,
+    /// `#rgb` or `#rrggbb` color of buttons and links
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub primary_color: Option<String>,
+    /// `#rgb` or `#rrggbb` color of the page background
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
+    /// Stylesheet added after the default styles
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_css: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub support_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub support_email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub privacy_policy_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub terms_of_service_url: Option<String>,
+    /// Page texts by language tag and text key, e.g. `de` → `device_title` → `Gerät verbinden`
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub texts: HashMap<String, HashMap<String, String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BrandingQuery {
+    /// Application whose tenant's branding is returned, the issuer's tenant if not set
+    pub client_id: Option<String>,
+}
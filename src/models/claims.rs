@@ -1,4 +1,7 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct IdTokenClaims {
@@ -7,9 +10,12 @@ pub struct IdTokenClaims {
     pub aud: String,
     pub exp: usize,
     pub iat: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<usize>,
     pub nonce: Option<String>,
-    pub email: Option<String>,
-    pub name: Option<String>,
+    /// Standard claims released for the granted scopes and requested claims
+    #[serde(flatten)]
+    pub user_claims: Map<String, Value>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -17,14 +23,53 @@ pub struct AccessTokenClaims {
     pub iss: String,
     pub sub: String,
     pub aud: String,
+    /// Client the token was issued to, `aud` names the API for tokens of a resource (RFC 9068 2.2)
+    pub client_id: String,
     pub exp: usize,
     pub iat: usize,
     pub scope: Option<String>,
+    /// Custom attributes released to the client by its claim mappings
+    #[serde(flatten)]
+    pub user_claims: Map<String, Value>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct RefreshTokenClaims {
     pub sub: String,
+    pub jti: String,
     pub exp: usize,
     pub iat: usize,
 }
+
+/// JWT secured authorization response (JARM)
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuthorizationResponseClaims {
+    pub iss: String,
+    pub aud: String,
+    pub exp: usize,
+    /// The parameters of the response, `code` and `state` or the error
+    #[serde(flatten)]
+    pub params: Map<String, Value>,
+}
+
+/// The OIDC `claims` request parameter
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct ClaimsRequest {
+    /// Only parsed to reject requests for it, UserInfo releases the claims of the scopes
+    #[serde(default)]
+    pub userinfo: HashMap<String, Option<ClaimRequest>>,
+    #[serde(default)]
+    pub id_token: HashMap<String, Option<ClaimRequest>>,
+}
+
+/// Options for an individual requested claim, `null` in the request means no options
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct ClaimRequest {
+    /// Makes no difference to which claims are released, see [`select_claims`]
+    ///
+    /// [`select_claims`]: crate::utils::claims_utils::select_claims
+    #[serde(default)]
+    pub essential: bool,
+    pub value: Option<Value>,
+    pub values: Option<Vec<Value>>,
+}
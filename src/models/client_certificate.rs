@@ -0,0 +1,64 @@
+use std::{convert::Infallible, net::SocketAddr};
+
+use axum::extract::{ConnectInfo, OptionalFromRequestParts};
+use http::{HeaderName, request::Parts};
+use openssl::x509::X509;
+
+use crate::utils::mtls_utils::{TrustedProxy, parse_forwarded_certificate};
+
+/// X.509 certificate the client presented in the TLS handshake (RFC 8705)
+#[derive(Clone)]
+pub struct ClientCertificate(pub X509);
+
+/// Added to requests of connections accepted by the built-in TLS server
+#[derive(Clone)]
+pub struct TlsConnection {
+    pub client_certificate: Option<X509>,
+}
+
+/// Header a trusted TLS terminating reverse proxy forwards the client certificate in
+#[derive(Clone)]
+pub struct ClientCertificateHeader {
+    pub header: HeaderName,
+    /// Addresses of the proxies, the header of other peers is ignored
+    pub trusted_proxies: Vec<TrustedProxy>,
+}
+
+impl<S: Send + Sync> OptionalFromRequestParts<S> for ClientCertificate {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        // A forwarded certificate cannot be trusted on connections that reach the server directly
+        if let Some(connection) = parts.extensions.get::<TlsConnection>() {
+            return Ok(connection.client_certificate.clone().map(ClientCertificate));
+        }
+
+        let Some(forwarded) = parts.extensions.get::<ClientCertificateHeader>() else {
+            return Ok(None);
+        };
+        // Anyone reaching the server directly could set the header
+        let from_trusted_proxy = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .is_some_and(|ConnectInfo(peer)| {
+                forwarded
+                    .trusted_proxies
+                    .iter()
+                    .any(|proxy| proxy.contains(peer.ip()))
+            });
+        if !from_trusted_proxy {
+            return Ok(None);
+        }
+
+        let certificate = parts
+            .headers
+            .get(&forwarded.header)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_forwarded_certificate);
+
+        Ok(certificate.map(ClientCertificate))
+    }
+}
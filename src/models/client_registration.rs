@@ -0,0 +1,100 @@
+use jsonwebtoken::jwk::JwkSet;
+use serde::{Deserialize, Serialize};
+
+use crate::models::application_model::{
+    SubjectType, TokenEndpointAuthMethod, default_grant_types, default_response_types,
+};
+
+/// Initial access token that allows registering clients into the tenant (RFC 7591 3)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RegistrationToken {
+    pub name: String,
+    pub token: String,
+}
+
+/// Client metadata of a registration or update request (RFC 7591 2)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ClientMetadata {
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    #[serde(default)]
+    pub post_logout_redirect_uris: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_uri: Option<String>,
+    #[serde(default = "default_grant_types")]
+    pub grant_types: Vec<String>,
+    #[serde(default = "default_response_types")]
+    pub response_types: Vec<String>,
+    /// Space separated scopes the client may request, any scope if left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub token_endpoint_auth_method: TokenEndpointAuthMethod,
+    #[serde(default)]
+    pub subject_type: SubjectType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sector_identifier_uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwks: Option<JwkSet>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwks_uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_client_auth_subject_dn: Option<String>,
+    #[serde(default)]
+    pub tls_client_certificate_bound_access_tokens: bool,
+    #[serde(default)]
+    pub require_pushed_authorization_requests: bool,
+}
+
+/// Body of a client update request, the full metadata with the client's credentials (RFC 7592 2.2)
+#[derive(Debug, Deserialize)]
+pub struct ClientUpdateRequest {
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    #[serde(flatten)]
+    pub metadata: ClientMetadata,
+}
+
+/// Client information response (RFC 7591 3.2.1, RFC 7592 3)
+#[derive(Debug, Serialize)]
+pub struct ClientInformation {
+    pub client_id: String,
+    /// Only issued to clients that authenticate with it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    pub client_id_issued_at: i64,
+    /// Secrets do not expire
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret_expires_at: Option<i64>,
+    /// Only returned when registering, the server only keeps its hash
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registration_access_token: Option<String>,
+    pub registration_client_uri: String,
+    #[serde(flatten)]
+    pub metadata: ClientMetadata,
+}
+
+pub struct RegisteredClientSQL {
+    pub id: uuid::Uuid,
+    pub tenant_id: uuid::Uuid,
+    pub client_id: String,
+    pub client_secret: String,
+    pub name: String,
+    pub uri: String,
+    pub redirect_uris: Vec<String>,
+    pub post_logout_redirect_uris: Vec<String>,
+    pub subject_type: String,
+    pub sector_identifier_uri: Option<String>,
+    pub jwks: Option<sqlx::types::Json<JwkSet>>,
+    pub jwks_uri: Option<String>,
+    pub token_endpoint_auth_method: String,
+    pub tls_client_auth_subject_dn: Option<String>,
+    pub tls_client_certificate_bound_access_tokens: bool,
+    pub require_pushed_authorization_requests: bool,
+    pub grant_types: Vec<String>,
+    pub response_types: Vec<String>,
+    pub allowed_scopes: Option<Vec<String>>,
+    pub client_id_issued_at: i64,
+}
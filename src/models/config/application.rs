@@ -1,7 +1,15 @@
 use chrono::{DateTime, Utc};
+use jsonwebtoken::jwk::JwkSet;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::models::application_model::{
+    SubjectType, TokenEndpointAuthMethod, TokenLifetimes, default_grant_types,
+    default_response_types,
+};
+use crate::models::saml::SamlServiceProvider;
+use crate::models::user_attributes::ClaimMapping;
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ApplicationsConfig {
     pub applications: Vec<Application>,
@@ -15,8 +23,49 @@ pub struct Application {
     pub client_id: String,
     pub client_secret: String,
     pub uri: String,
+    /// Can be left empty for applications that only use SAML
+    #[serde(default)]
     pub redirect_uris: Vec<String>,
+    #[serde(default)]
     pub post_logout_redirect_uris: Vec<String>,
+    #[serde(default)]
+    pub subject_type: SubjectType,
+    /// HTTPS URL of a JSON array of redirect URIs sharing the pairwise sector
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sector_identifier_uri: Option<String>,
+    /// Public keys the application signs request objects with, inline or fetched from `jwks_uri`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwks: Option<JwkSet>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwks_uri: Option<String>,
+    #[serde(default)]
+    pub token_endpoint_auth_method: TokenEndpointAuthMethod,
+    /// Subject DN of the client certificate for `tls_client_auth`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_client_auth_subject_dn: Option<String>,
+    /// Bind access tokens to the client certificate (RFC 8705 3)
+    #[serde(default)]
+    pub tls_client_certificate_bound_access_tokens: bool,
+    /// Grant types the application may use, `authorization_code` and `refresh_token` by default
+    #[serde(default = "default_grant_types")]
+    pub grant_types: Vec<String>,
+    #[serde(default = "default_response_types")]
+    pub response_types: Vec<String>,
+    /// Scopes the application may request, any scope if left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_scopes: Option<Vec<String>>,
+    #[serde(default)]
+    pub token_lifetimes: TokenLifetimes,
+    /// Only accept authorization requests pushed to `/oauth/par` (RFC 9126)
+    #[serde(default)]
+    pub require_pushed_authorization_requests: bool,
+    /// Audiences the application may exchange user access tokens into
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub token_exchange_audiences: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub claim_mappings: Vec<ClaimMapping>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saml: Option<SamlServiceProvider>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub created_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -2,6 +2,16 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::models::api_resource::ApiResource;
+use crate::models::branding::TenantBranding;
+use crate::models::client_registration::RegistrationToken;
+use crate::models::directory::DirectoryConfig;
+use crate::models::identity_provider::IdentityProvider;
+use crate::models::password_policy::PasswordPolicy;
+use crate::models::scim::ScimToken;
+use crate::models::session_policy::SessionPolicy;
+use crate::models::user_attributes::AttributeDefinition;
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TenantsConfig {
     pub tenants: Vec<Tenant>,
@@ -11,8 +21,41 @@ pub struct TenantsConfig {
 pub struct Tenant {
     pub id: Uuid,
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_policy: Option<PasswordPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_policy: Option<SessionPolicy>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<AttributeDefinition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identity_providers: Vec<IdentityProvider>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<DirectoryConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scim_tokens: Vec<ScimToken>,
+    /// Initial access tokens for dynamic client registration into the tenant
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub registration_tokens: Vec<RegistrationToken>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_resources: Vec<ApiResource>,
+    /// Hostname the tenant's issuer is served at instead of `{PUBLIC_URL}/t/{id}`, its proxy also
+    /// serves the login UI at `/login`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branding: Option<TenantBranding>,
+    /// Keys the tenant's issuer signs with, the server's keys if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_key: Option<TenantSigningKey>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub created_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub updated_at: Option<DateTime<Utc>>,
 }
+
+/// PEM files of the RSA key pair of a tenant's issuer
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TenantSigningKey {
+    pub private_key_path: String,
+    pub public_key_path: String,
+}
@@ -1,7 +1,10 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use uuid::Uuid;
 
+use crate::models::user_models::UserProfile;
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct UserConfig {
     pub users: Vec<User>
@@ -15,6 +18,10 @@ pub struct User {
     pub email: String,
     pub password_hash: String,
     pub is_active: bool,
+    #[serde(default)]
+    pub profile: UserProfile,
+    #[serde(default)]
+    pub attributes: Map<String, Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub created_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
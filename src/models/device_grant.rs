@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+pub const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+#[derive(Debug, Deserialize)]
+pub struct DeviceAuthorizationRequest {
+    pub client_id: String,
+    /// Left out by clients that authenticate with a TLS client certificate
+    #[serde(default)]
+    pub client_secret: String,
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeviceAuthorizationResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: u64,
+    pub interval: u64,
+}
+
+/// A pending device authorization, stored in Redis under its device code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceGrantData {
+    pub client_id: String,
+    pub scope: Option<String>,
+    /// Normalized user code, without separator
+    pub user_code: String,
+    pub status: DeviceGrantStatus,
+    /// Minimum seconds between token requests the device starts with, the raised interval after
+    /// a `slow_down` is kept with its polls
+    pub interval: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DeviceGrantStatus {
+    Pending,
+    Approved { user_id: String, auth_time: i64 },
+    Denied,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeviceVerificationQuery {
+    pub user_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeviceVerificationForm {
+    pub user_code: String,
+    /// `approve` or `deny`
+    pub action: String,
+}
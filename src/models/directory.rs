@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// LDAP / Active Directory users of a tenant authenticate against
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DirectoryConfig {
+    /// `ldap://` or `ldaps://` URL of the directory server
+    pub url: String,
+    #[serde(default)]
+    pub starttls: bool,
+    /// Service account used to search for users, anonymous if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind_dn: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind_password: Option<String>,
+    pub base_dn: String,
+    /// Search filter for the login email, `{login}` is replaced by the escaped value
+    #[serde(default = "default_user_filter")]
+    pub user_filter: String,
+    /// Unknown users with an email in these domains are looked up in the directory
+    #[serde(default)]
+    pub email_domains: Vec<String>,
+    #[serde(default)]
+    pub attribute_mapping: DirectoryAttributeMapping,
+    /// Attribute listing the DNs of the groups a user is member of
+    #[serde(default = "default_group_attribute")]
+    pub group_attribute: String,
+    /// Roles granted to members of a group DN
+    #[serde(default)]
+    pub group_roles: HashMap<String, Vec<String>>,
+    /// How long a successful bind is reused, `0` disables the cache
+    #[serde(default = "default_cache_ttl")]
+    pub cache_ttl_seconds: u64,
+}
+
+/// Names of the directory attributes user fields are read from
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DirectoryAttributeMapping {
+    pub email: String,
+    pub username: String,
+    pub name: String,
+    pub given_name: String,
+    pub family_name: String,
+    pub phone_number: String,
+    /// Custom attribute name to directory attribute
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub attributes: HashMap<String, String>,
+}
+
+impl Default for DirectoryAttributeMapping {
+    fn default() -> Self {
+        Self {
+            email: "mail".to_string(),
+            username: "uid".to_string(),
+            name: "cn".to_string(),
+            given_name: "givenName".to_string(),
+            family_name: "sn".to_string(),
+            phone_number: "telephoneNumber".to_string(),
+            attributes: HashMap::new(),
+        }
+    }
+}
+
+/// A directory entry the user bound as, attribute names are lowercase
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DirectoryEntry {
+    pub dn: String,
+    pub attributes: HashMap<String, Vec<String>>,
+}
+
+/// User fields, custom attributes and roles read from a directory entry
+#[derive(Debug, Default, PartialEq)]
+pub struct DirectoryUser {
+    pub dn: String,
+    pub email: String,
+    pub username: String,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub phone_number: Option<String>,
+    pub attributes: Map<String, Value>,
+    pub roles: Vec<String>,
+}
+
+fn default_user_filter() -> String {
+    "(mail={login})".to_string()
+}
+
+fn default_group_attribute() -> String {
+    "memberOf".to_string()
+}
+
+fn default_cache_ttl() -> u64 {
+    300
+}
@@ -0,0 +1,28 @@
+use serde::Deserialize;
+
+/// `token_type` of access tokens bound to a DPoP key
+pub const DPOP_TOKEN_TYPE: &str = "DPoP";
+
+/// Claims of a DPoP proof JWT (RFC 9449 4.2)
+#[derive(Debug, Deserialize)]
+pub struct DpopProofClaims {
+    pub jti: String,
+    /// HTTP method of the request the proof was created for
+    pub htm: String,
+    /// HTTP URI of the request, without query and fragment
+    pub htu: String,
+    pub iat: i64,
+    /// Hash of the access token presented with the proof at resource servers
+    pub ath: Option<String>,
+    /// Nonce previously provided by the server
+    pub nonce: Option<String>,
+}
+
+/// A DPoP proof with a valid signature for the request it was sent with
+#[derive(Debug)]
+pub struct DpopProof {
+    /// JWK SHA-256 thumbprint of the proof's key, bound to tokens as `cnf.jkt`
+    pub jkt: String,
+    pub jti: String,
+    pub nonce: Option<String>,
+}
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use uuid::Uuid;
+
+/// An upstream OpenID Connect provider users of a tenant can log in with
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IdentityProvider {
+    /// Identifier used in the federation URLs
+    pub id: String,
+    pub name: String,
+    pub discovery_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub claim_mapping: FederationClaimMapping,
+    /// Create users on their first login
+    #[serde(default = "default_true")]
+    pub auto_provision: bool,
+    /// Link existing users with the same verified email address
+    #[serde(default)]
+    pub link_by_email: bool,
+}
+
+/// Names of the upstream ID token claims our user fields are read from
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FederationClaimMapping {
+    pub email: String,
+    pub email_verified: String,
+    pub username: String,
+    pub name: String,
+    pub given_name: String,
+    pub family_name: String,
+}
+
+impl Default for FederationClaimMapping {
+    fn default() -> Self {
+        Self {
+            email: "email".to_string(),
+            email_verified: "email_verified".to_string(),
+            username: "preferred_username".to_string(),
+            name: "name".to_string(),
+            given_name: "given_name".to_string(),
+            family_name: "family_name".to_string(),
+        }
+    }
+}
+
+pub struct IdentityProviderSQL {
+    pub id: String,
+    pub tenant_id: Uuid,
+    pub discovery_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scopes: Vec<String>,
+    pub claim_mapping: Json<FederationClaimMapping>,
+    pub auto_provision: bool,
+    pub link_by_email: bool,
+}
+
+/// Provider shown on the login page
+#[derive(Debug, Serialize)]
+pub struct IdentityProviderInfo {
+    pub id: String,
+    pub name: String,
+}
+
+/// The parts of an upstream discovery document the federation flow needs
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// A federated login in progress, stored under its `state`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FederationState {
+    pub provider_id: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub return_to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FederationLoginRequest {
+    pub return_to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FederationCallbackRequest {
+    pub code: Option<String>,
+    pub state: String,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FederationProvidersRequest {
+    pub client_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpstreamTokenResponse {
+    pub id_token: String,
+}
+
+/// User fields read from an upstream ID token
+#[derive(Debug, Default, PartialEq)]
+pub struct FederatedUser {
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub username: Option<String>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+}
+
+fn default_scopes() -> Vec<String> {
+    vec![
+        "openid".to_string(),
+        "email".to_string(),
+        "profile".to_string(),
+    ]
+}
+
+fn default_true() -> bool {
+    true
+}
@@ -4,6 +4,12 @@ use serde::Deserialize;
 pub struct LoginRequest {
     pub email: String,
     pub password: String,
+    /// Keep the session in a persistent cookie if the tenant allows it
+    #[serde(default)]
+    pub remember_me: bool,
+    /// Application the user logs in to, only users of its tenant can log in
+    #[serde(default)]
+    pub client_id: Option<String>,
 }
 
 pub struct UserPasswordHashSQL {
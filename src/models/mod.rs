@@ -1,12 +1,28 @@
+pub mod api_resource;
 pub mod application_model;
 pub mod auth_code_data;
 pub mod authorize_request;
+pub mod branding;
 pub mod claims;
+pub mod client_certificate;
+pub mod client_registration;
 pub mod config;
+pub mod device_grant;
+pub mod directory;
+pub mod dpop;
+pub mod identity_provider;
+pub mod introspection;
 pub mod login;
 pub mod oidc_discovery_document;
+pub mod password_policy;
+pub mod pushed_request;
+pub mod saml;
+pub mod scim;
 pub mod services_config;
 pub mod session;
+pub mod session_policy;
+pub mod token_exchange;
 pub mod token_request;
 pub mod token_response;
+pub mod user_attributes;
 pub mod user_models;
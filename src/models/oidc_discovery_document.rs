@@ -6,11 +6,31 @@ pub struct OidcDiscoveryDocument {
     pub authorization_endpoint: String,
     pub token_endpoint: String,
     pub userinfo_endpoint: Option<String>,
+    pub introspection_endpoint: String,
+    pub device_authorization_endpoint: String,
+    pub pushed_authorization_request_endpoint: String,
+    pub require_pushed_authorization_requests: bool,
+    /// Dynamic client registration endpoint (RFC 7591)
+    pub registration_endpoint: String,
     pub jwks_uri: String,
     pub response_types_supported: Vec<String>,
+    pub response_modes_supported: Vec<String>,
+    /// Authorization responses carry the `iss` parameter (RFC 9207)
+    pub authorization_response_iss_parameter_supported: bool,
+    pub authorization_signing_alg_values_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
     pub subject_types_supported: Vec<String>,
     pub id_token_signing_alg_values_supported: Vec<String>,
     pub scopes_supported: Vec<String>,
     pub token_endpoint_auth_methods_supported: Vec<String>,
+    /// Access tokens can be bound to client certificates (RFC 8705 3)
+    pub tls_client_certificate_bound_access_tokens: bool,
     pub claims_supported: Vec<String>,
+    pub claims_parameter_supported: bool,
+    /// Algorithms accepted for DPoP proofs (RFC 9449)
+    pub dpop_signing_alg_values_supported: Vec<String>,
+    pub request_parameter_supported: bool,
+    /// Only request objects pushed to the PAR endpoint are accepted by reference
+    pub request_uri_parameter_supported: bool,
+    pub request_object_signing_alg_values_supported: Vec<String>,
 }
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// Password rules enforced for every user of a tenant.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct PasswordPolicy {
+    pub min_length: i32,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    /// Number of previous passwords that may not be reused
+    pub history_size: i32,
+    /// Days after which a password has to be changed, `None` disables expiry
+    pub max_age_days: Option<i32>,
+    /// Reject passwords found in the breached password corpus
+    pub check_breached: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            require_uppercase: false,
+            require_lowercase: false,
+            require_digit: false,
+            require_symbol: false,
+            history_size: 0,
+            max_age_days: None,
+            check_breached: true,
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+/// Prefix of the `request_uri` references returned by the PAR endpoint
+pub const REQUEST_URI_PREFIX: &str = "urn:ietf:params:oauth:request_uri:";
+
+/// Client authentication of a pushed authorization request, sent along with its parameters
+#[derive(Debug, Deserialize)]
+pub struct PushedRequestCredentials {
+    pub client_id: String,
+    /// Left out by clients that authenticate with a TLS client certificate
+    #[serde(default)]
+    pub client_secret: String,
+    /// Not allowed in pushed requests (RFC 9126 2.1)
+    pub request_uri: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PushedAuthorizationResponse {
+    pub request_uri: String,
+    pub expires_in: u64,
+}
+
+/// Query of `/authorize` referring to a pushed request instead of carrying the parameters
+#[derive(Debug, Deserialize)]
+pub struct RequestReference {
+    pub client_id: Option<String>,
+    pub request_uri: Option<String>,
+}
@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use uuid::Uuid;
+
+/// SAML 2.0 settings of an application acting as service provider
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SamlServiceProvider {
+    pub entity_id: String,
+    /// Assertion consumer service URLs, the first one is the default
+    pub acs_urls: Vec<String>,
+    #[serde(default)]
+    pub name_id_format: NameIdFormat,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<SamlAttributeMapping>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NameIdFormat {
+    /// The subject the application knows the user by, pairwise if configured
+    #[default]
+    Persistent,
+    EmailAddress,
+    Unspecified,
+}
+
+impl NameIdFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NameIdFormat::Persistent => "persistent",
+            NameIdFormat::EmailAddress => "emailAddress",
+            NameIdFormat::Unspecified => "unspecified",
+        }
+    }
+
+    pub fn urn(&self) -> &'static str {
+        match self {
+            NameIdFormat::Persistent => "urn:oasis:names:tc:SAML:2.0:nameid-format:persistent",
+            NameIdFormat::EmailAddress => "urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress",
+            NameIdFormat::Unspecified => "urn:oasis:names:tc:SAML:1.1:nameid-format:unspecified",
+        }
+    }
+}
+
+impl std::str::FromStr for NameIdFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "persistent" => Ok(NameIdFormat::Persistent),
+            "emailAddress" => Ok(NameIdFormat::EmailAddress),
+            "unspecified" => Ok(NameIdFormat::Unspecified),
+            _ => Err(anyhow::anyhow!("Unknown NameID format {value}")),
+        }
+    }
+}
+
+/// A SAML attribute released to a service provider
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SamlAttributeMapping {
+    /// Attribute name expected by the service provider
+    pub name: String,
+    /// Standard claim (e.g. `email`) or custom attribute the value is read from
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub friendly_name: Option<String>,
+}
+
+/// Attribute with the values released for a user
+#[derive(Debug, PartialEq)]
+pub struct SamlAttribute {
+    pub name: String,
+    pub friendly_name: Option<String>,
+    pub values: Vec<String>,
+}
+
+pub struct SamlServiceProviderSQL {
+    pub tenant_id: Uuid,
+    pub client_id: String,
+    pub entity_id: String,
+    pub acs_urls: Vec<String>,
+    pub name_id_format: String,
+    pub attributes: Json<Vec<SamlAttributeMapping>>,
+}
+
+/// The parts of an `<AuthnRequest>` the SSO endpoint acts on
+#[derive(Debug, Default, PartialEq)]
+pub struct AuthnRequest {
+    pub id: String,
+    pub issuer: String,
+    pub issue_instant: i64,
+    pub acs_url: Option<String>,
+    pub protocol_binding: Option<String>,
+    pub force_authn: bool,
+    pub is_passive: bool,
+}
+
+/// SP-initiated login, `SAMLRequest` is deflated for HTTP-Redirect and plain for HTTP-POST
+#[derive(Debug, Deserialize)]
+pub struct SamlSsoRequest {
+    #[serde(rename = "SAMLRequest")]
+    pub saml_request: String,
+    #[serde(rename = "RelayState")]
+    pub relay_state: Option<String>,
+}
+
+/// IdP-initiated login, `RelayState` is passed on to the service provider
+#[derive(Debug, Deserialize)]
+pub struct SamlIdpInitiatedRequest {
+    #[serde(rename = "RelayState")]
+    pub relay_state: Option<String>,
+}
@@ -0,0 +1,343 @@
+use serde::{Deserialize, Deserializer, Serialize, de::Error};
+use serde_json::Value;
+
+pub const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+pub const GROUP_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+pub const LIST_RESPONSE_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+pub const PATCH_OP_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:PatchOp";
+pub const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+
+/// Bearer token a provisioning client of the tenant authenticates with
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ScimToken {
+    pub name: String,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimUser {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    pub user_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<ScimName>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nick_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "lenient_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub active: Option<bool>,
+    /// Write-only, checked against the tenant's password policy
+    #[serde(default, skip_serializing)]
+    pub password: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub emails: Vec<ScimMultiValue>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub phone_numbers: Vec<ScimMultiValue>,
+    /// Read-only, group membership is managed through the groups
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<ScimReference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ScimMeta>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimName {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formatted: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub given_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub middle_name: Option<String>,
+}
+
+/// An email address or phone number
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ScimMultiValue {
+    pub value: String,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "lenient_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub primary: Option<bool>,
+}
+
+/// A group member or a group of a user
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ScimReference {
+    pub value: String,
+    #[serde(rename = "$ref", default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimMeta {
+    pub resource_type: String,
+    pub created: String,
+    pub last_modified: String,
+    pub location: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimGroup {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    pub display_name: String,
+    #[serde(default)]
+    pub members: Vec<ScimReference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ScimMeta>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListResponse {
+    pub schemas: Vec<&'static str>,
+    pub total_results: usize,
+    pub start_index: usize,
+    pub items_per_page: usize,
+    #[serde(rename = "Resources")]
+    pub resources: Vec<Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListQuery {
+    pub filter: Option<String>,
+    /// 1-based index of the first result
+    pub start_index: Option<i64>,
+    pub count: Option<i64>,
+    /// Comma separated top-level attributes to return
+    pub attributes: Option<String>,
+    /// Comma separated top-level attributes to leave out
+    pub excluded_attributes: Option<String>,
+}
+
+/// `eq` filters on attributes the database looks resources up by
+#[derive(Debug, Default, PartialEq)]
+pub struct ScimLookup {
+    pub id: Option<String>,
+    pub external_id: Option<String>,
+    /// `userName` of users, `displayName` of groups
+    pub name: Option<String>,
+    /// Primary email of users
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScimPatchRequest {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(rename = "Operations")]
+    pub operations: Vec<ScimPatchOperation>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScimPatchOperation {
+    /// `add`, `replace` or `remove`, case-insensitive
+    pub op: String,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub value: Option<Value>,
+}
+
+pub struct ScimUserSQL {
+    pub id: uuid::Uuid,
+    pub external_id: Option<String>,
+    pub username: String,
+    pub email: String,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub middle_name: Option<String>,
+    pub nickname: Option<String>,
+    pub locale: Option<String>,
+    pub zoneinfo: Option<String>,
+    pub phone_number: Option<String>,
+    pub is_active: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub groups: sqlx::types::Json<Vec<ScimReference>>,
+}
+
+pub struct ScimGroupSQL {
+    pub id: uuid::Uuid,
+    pub external_id: Option<String>,
+    pub name: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub members: sqlx::types::Json<Vec<ScimReference>>,
+}
+
+/// Attribute definition published at `/Schemas`, also used to resolve PATCH paths
+pub struct SchemaAttribute {
+    pub name: &'static str,
+    pub kind: &'static str,
+    pub multi_valued: bool,
+    pub required: bool,
+    pub mutability: &'static str,
+    pub uniqueness: &'static str,
+    pub sub_attributes: &'static [SchemaAttribute],
+}
+
+impl SchemaAttribute {
+    const fn new(name: &'static str, kind: &'static str) -> Self {
+        Self {
+            name,
+            kind,
+            multi_valued: false,
+            required: false,
+            mutability: "readWrite",
+            uniqueness: "none",
+            sub_attributes: &[],
+        }
+    }
+
+    const fn complex(name: &'static str, sub_attributes: &'static [SchemaAttribute]) -> Self {
+        Self {
+            sub_attributes,
+            ..Self::new(name, "complex")
+        }
+    }
+
+    const fn multi_valued(self) -> Self {
+        Self {
+            multi_valued: true,
+            ..self
+        }
+    }
+
+    const fn required(self) -> Self {
+        Self {
+            required: true,
+            ..self
+        }
+    }
+
+    const fn mutability(self, mutability: &'static str) -> Self {
+        Self { mutability, ..self }
+    }
+
+    const fn unique(self) -> Self {
+        Self {
+            uniqueness: "server",
+            ..self
+        }
+    }
+
+    /// Attribute of a list by case-insensitive name
+    pub fn find<'a>(attributes: &'a [SchemaAttribute], name: &str) -> Option<&'a SchemaAttribute> {
+        attributes
+            .iter()
+            .find(|attribute| attribute.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut attribute = serde_json::json!({
+            "name": self.name,
+            "type": self.kind,
+            "multiValued": self.multi_valued,
+            "required": self.required,
+            "caseExact": false,
+            "mutability": self.mutability,
+            "returned": if self.name == "password" { "never" } else { "default" },
+            "uniqueness": self.uniqueness,
+        });
+        if !self.sub_attributes.is_empty() {
+            attribute["subAttributes"] = self
+                .sub_attributes
+                .iter()
+                .map(SchemaAttribute::to_json)
+                .collect();
+        }
+        attribute
+    }
+}
+
+const MULTI_VALUE_ATTRIBUTES: &[SchemaAttribute] = &[
+    SchemaAttribute::new("value", "string"),
+    SchemaAttribute::new("type", "string"),
+    SchemaAttribute::new("primary", "boolean"),
+];
+
+const REFERENCE_ATTRIBUTES: &[SchemaAttribute] = &[
+    SchemaAttribute::new("value", "string").mutability("immutable"),
+    SchemaAttribute::new("$ref", "reference").mutability("immutable"),
+    SchemaAttribute::new("display", "string").mutability("readOnly"),
+];
+
+pub const USER_ATTRIBUTES: &[SchemaAttribute] = &[
+    SchemaAttribute::new("id", "string").mutability("readOnly"),
+    SchemaAttribute::new("externalId", "string"),
+    SchemaAttribute::new("userName", "string")
+        .required()
+        .unique(),
+    SchemaAttribute::complex(
+        "name",
+        &[
+            SchemaAttribute::new("formatted", "string"),
+            SchemaAttribute::new("givenName", "string"),
+            SchemaAttribute::new("familyName", "string"),
+            SchemaAttribute::new("middleName", "string"),
+        ],
+    ),
+    SchemaAttribute::new("displayName", "string"),
+    SchemaAttribute::new("nickName", "string"),
+    SchemaAttribute::new("locale", "string"),
+    SchemaAttribute::new("timezone", "string"),
+    SchemaAttribute::new("active", "boolean"),
+    SchemaAttribute::new("password", "string").mutability("writeOnly"),
+    SchemaAttribute::complex("emails", MULTI_VALUE_ATTRIBUTES).multi_valued(),
+    SchemaAttribute::complex("phoneNumbers", MULTI_VALUE_ATTRIBUTES).multi_valued(),
+    SchemaAttribute::complex("groups", REFERENCE_ATTRIBUTES)
+        .multi_valued()
+        .mutability("readOnly"),
+];
+
+pub const GROUP_ATTRIBUTES: &[SchemaAttribute] = &[
+    SchemaAttribute::new("id", "string").mutability("readOnly"),
+    SchemaAttribute::new("externalId", "string"),
+    SchemaAttribute::new("displayName", "string")
+        .required()
+        .unique(),
+    SchemaAttribute::complex("members", REFERENCE_ATTRIBUTES).multi_valued(),
+];
+
+/// Accepts `"True"` and `"False"` as sent by some provisioning clients
+fn lenient_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<bool>, D::Error> {
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Bool(value)) => Ok(Some(value)),
+        Some(Value::String(value)) if value.eq_ignore_ascii_case("true") => Ok(Some(true)),
+        Some(Value::String(value)) if value.eq_ignore_ascii_case("false") => Ok(Some(false)),
+        Some(value) => Err(D::Error::custom(format!("invalid boolean {value}"))),
+    }
+}
@@ -1,6 +1,13 @@
 use crate::services::{
-    application_service::ApplicationClientService, authorize_code_service::AuthorizeCodeService,
-    session_service::SessionService, user_service::UserService,
+    api_resource_service::ApiResourceService, application_service::ApplicationClientService,
+    attribute_service::AttributeService, authorize_code_service::AuthorizeCodeService,
+    branding_service::BrandingService, client_registration_service::ClientRegistrationService,
+    device_grant_service::DeviceGrantService, dpop_service::DpopService,
+    federation_service::FederationService, mail_service::MailService, mtls_service::MtlsService,
+    password_reset_service::PasswordResetService, pushed_request_service::PushedRequestService,
+    refresh_token_service::RefreshTokenService, saml_service::SamlService,
+    scim_service::ScimService, session_service::SessionService, subject_service::SubjectService,
+    user_service::UserService,
 };
 
 pub struct ServicesConfig {
@@ -8,4 +15,19 @@ pub struct ServicesConfig {
     pub auth_code_service: AuthorizeCodeService,
     pub session_service: SessionService,
     pub application_service: ApplicationClientService,
+    pub password_reset_service: PasswordResetService,
+    pub mail_service: MailService,
+    pub refresh_token_service: RefreshTokenService,
+    pub attribute_service: AttributeService,
+    pub subject_service: SubjectService,
+    pub federation_service: FederationService,
+    pub saml_service: SamlService,
+    pub scim_service: ScimService,
+    pub device_grant_service: DeviceGrantService,
+    pub api_resource_service: ApiResourceService,
+    pub pushed_request_service: PushedRequestService,
+    pub dpop_service: DpopService,
+    pub mtls_service: MtlsService,
+    pub client_registration_service: ClientRegistrationService,
+    pub branding_service: BrandingService,
 }
@@ -1,7 +1,180 @@
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::models::session_policy::SessionPolicy;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SessionData {
     pub user_id: String,
-    // TODO: maybe add roles, email, etc. here
+    /// Tenant of the user, the session is only used for the applications of this tenant
+    #[serde(default)]
+    pub tenant_id: Option<Uuid>,
+    /// Unix timestamp of the login that created the session
+    #[serde(default)]
+    pub created_at: i64,
+    /// Unix timestamp at which the user last actively authenticated
+    #[serde(default)]
+    pub auth_time: i64,
+    /// Unix timestamp of the last request that used the session
+    #[serde(default)]
+    pub last_activity: i64,
+    #[serde(default)]
+    pub ip: Option<String>,
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// Authentication method references (RFC 8176), e.g. `pwd`
+    #[serde(default)]
+    pub auth_methods: Vec<String>,
+    /// Client IDs that received an authorization code from this session
+    #[serde(default)]
+    pub clients: Vec<String>,
+    /// Unix timestamp after which the session ends regardless of activity, 0 if unlimited
+    #[serde(default)]
+    pub expires_at: i64,
+    /// Seconds of inactivity after which the session ends, 0 disables sliding expiry
+    #[serde(default)]
+    pub idle_timeout: i64,
+    #[serde(default)]
+    pub remember_me: bool,
+}
+
+impl SessionData {
+    pub fn new(user_id: String, tenant_id: Uuid) -> Self {
+        let now = Utc::now().timestamp();
+        Self {
+            user_id,
+            tenant_id: Some(tenant_id),
+            created_at: now,
+            auth_time: now,
+            last_activity: now,
+            ip: None,
+            user_agent: None,
+            auth_methods: Vec::new(),
+            clients: Vec::new(),
+            expires_at: 0,
+            idle_timeout: 0,
+            remember_me: false,
+        }
+    }
+
+    /// Applies a tenant's session policy and returns the initial TTL in seconds
+    pub fn apply_policy(&mut self, policy: &SessionPolicy, remember_me: bool) -> u64 {
+        self.remember_me = remember_me && policy.remember_me_enabled;
+
+        let (idle_timeout, lifetime) = if self.remember_me {
+            (
+                policy.remember_me_lifetime_seconds,
+                policy.remember_me_lifetime_seconds,
+            )
+        } else {
+            (
+                policy.idle_timeout_seconds,
+                policy.absolute_lifetime_seconds,
+            )
+        };
+
+        self.idle_timeout = idle_timeout.max(1) as i64;
+        self.expires_at = self.created_at + lifetime.max(1) as i64;
+
+        self.remaining_ttl(self.created_at).unwrap_or(1)
+    }
+
+    /// Seconds the session stays valid when used at `now`, `None` once the absolute lifetime is over.
+    /// Sessions created without a policy return `Some(0)` and keep their original TTL.
+    pub fn remaining_ttl(&self, now: i64) -> Option<u64> {
+        if self.expires_at > 0 && now >= self.expires_at {
+            return None;
+        }
+
+        let ttl = match (self.idle_timeout > 0, self.expires_at > 0) {
+            (true, true) => self.idle_timeout.min(self.expires_at - now),
+            (true, false) => self.idle_timeout,
+            (false, true) => self.expires_at - now,
+            (false, false) => return Some(0),
+        };
+
+        Some(ttl as u64)
+    }
+}
+
+/// A session as returned by the session management endpoints
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    pub id: String,
+    pub current: bool,
+    #[serde(flatten)]
+    pub session: SessionData,
+}
+
+/// Data stored for an issued refresh token, keyed by its `jti`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshTokenData {
+    pub user_id: String,
+    pub client_id: String,
+    pub session_id: Option<String>,
+    pub scope: Option<String>,
+    /// API the access tokens are issued for, the client itself if `None`
+    #[serde(default)]
+    pub resource: Option<String>,
+    /// Thumbprint of the DPoP key the tokens are bound to (RFC 9449)
+    #[serde(default)]
+    pub jkt: Option<String>,
+    /// Thumbprint of the client certificate access tokens are bound to (RFC 8705)
+    #[serde(default)]
+    pub x5t_s256: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> SessionPolicy {
+        SessionPolicy {
+            idle_timeout_seconds: 900,
+            absolute_lifetime_seconds: 3600,
+            remember_me_enabled: true,
+            remember_me_lifetime_seconds: 86400,
+        }
+    }
+
+    #[test]
+    fn test_remaining_ttl_is_capped_by_absolute_lifetime() {
+        let mut session = SessionData::new("user".to_owned(), Uuid::new_v4());
+        session.apply_policy(&policy(), false);
+        let created_at = session.created_at;
+
+        assert_eq!(session.remaining_ttl(created_at), Some(900));
+        assert_eq!(session.remaining_ttl(created_at + 3000), Some(600));
+        assert_eq!(session.remaining_ttl(created_at + 3590), Some(10));
+        assert_eq!(session.remaining_ttl(created_at + 3600), None);
+
+        // Sessions from before policies keep the TTL they were stored with
+        let legacy = SessionData::new("user".to_owned(), Uuid::new_v4());
+        assert_eq!(legacy.remaining_ttl(created_at + 86400), Some(0));
+    }
+
+    #[test]
+    fn test_apply_policy() {
+        let mut session = SessionData::new("user".to_owned(), Uuid::new_v4());
+        assert_eq!(session.apply_policy(&policy(), false), 900);
+        assert!(!session.remember_me);
+        assert_eq!(session.idle_timeout, 900);
+        assert_eq!(session.expires_at, session.created_at + 3600);
+
+        let mut session = SessionData::new("user".to_owned(), Uuid::new_v4());
+        assert_eq!(session.apply_policy(&policy(), true), 86400);
+        assert!(session.remember_me);
+        assert_eq!(session.expires_at, session.created_at + 86400);
+
+        // Tenants can turn "remember me" off
+        let policy = SessionPolicy {
+            remember_me_enabled: false,
+            ..policy()
+        };
+        let mut session = SessionData::new("user".to_owned(), Uuid::new_v4());
+        assert_eq!(session.apply_policy(&policy, true), 900);
+        assert!(!session.remember_me);
+        assert_eq!(session.expires_at, session.created_at + 3600);
+    }
 }
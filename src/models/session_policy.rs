@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+/// Session lifetime rules for every user of a tenant.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct SessionPolicy {
+    /// Seconds without activity after which a session ends
+    pub idle_timeout_seconds: i32,
+    /// Seconds after login after which a session ends regardless of activity
+    pub absolute_lifetime_seconds: i32,
+    pub remember_me_enabled: bool,
+    /// Lifetime of sessions and their persistent cookie when "remember me" was selected
+    pub remember_me_lifetime_seconds: i32,
+}
+
+impl Default for SessionPolicy {
+    fn default() -> Self {
+        Self {
+            idle_timeout_seconds: 900,
+            absolute_lifetime_seconds: 43200,
+            remember_me_enabled: true,
+            remember_me_lifetime_seconds: 2592000,
+        }
+    }
+}
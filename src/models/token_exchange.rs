@@ -0,0 +1,10 @@
+pub const TOKEN_EXCHANGE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
+
+/// Token type identifiers (RFC 8693 3)
+pub const ACCESS_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:access_token";
+pub const JWT_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:jwt";
+
+/// Subject and actor tokens are access tokens issued by this server
+pub fn is_access_token_type(token_type: &str) -> bool {
+    token_type == ACCESS_TOKEN_TYPE || token_type == JWT_TOKEN_TYPE
+}
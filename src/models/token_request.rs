@@ -3,8 +3,23 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenRequest {
     pub grant_type: String,
-    pub code: String,
-    pub redirect_uri: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
     pub client_id: String,
+    /// Left out by clients that authenticate with a TLS client certificate
+    #[serde(default)]
     pub client_secret: String,
+    pub refresh_token: Option<String>,
+    /// Device code of the device authorization grant
+    pub device_code: Option<String>,
+    pub scope: Option<String>,
+    /// Token exchange parameters (RFC 8693 2.1)
+    pub subject_token: Option<String>,
+    pub subject_token_type: Option<String>,
+    pub actor_token: Option<String>,
+    pub actor_token_type: Option<String>,
+    pub requested_token_type: Option<String>,
+    pub audience: Option<String>,
+    /// Resource indicator, has to be the one that was authorized (RFC 8707)
+    pub resource: Option<String>,
 }
@@ -3,8 +3,14 @@ use serde::Serialize;
 #[derive(Debug, Serialize)]
 pub struct TokenResponse {
     pub access_token: String,
-    pub id_token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
     pub token_type: String,
     pub expires_in: i32,
     pub refresh_token: Option<String>,
+    /// Type of the issued token, only for token exchange
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issued_token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
 }
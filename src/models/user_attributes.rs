@@ -0,0 +1,85 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// JSON type a custom attribute value must have
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttributeType {
+    String,
+    Number,
+    Boolean,
+    Array,
+    Object,
+}
+
+impl AttributeType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttributeType::String => "string",
+            AttributeType::Number => "number",
+            AttributeType::Boolean => "boolean",
+            AttributeType::Array => "array",
+            AttributeType::Object => "object",
+        }
+    }
+}
+
+impl FromStr for AttributeType {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "string" => Ok(AttributeType::String),
+            "number" => Ok(AttributeType::Number),
+            "boolean" => Ok(AttributeType::Boolean),
+            "array" => Ok(AttributeType::Array),
+            "object" => Ok(AttributeType::Object),
+            _ => Err(anyhow::anyhow!("Unknown attribute type {value}")),
+        }
+    }
+}
+
+/// A custom attribute users of a tenant may have
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AttributeDefinition {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub data_type: AttributeType,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+pub struct AttributeDefinitionSQL {
+    pub name: String,
+    pub data_type: String,
+    pub required: bool,
+    pub description: Option<String>,
+}
+
+/// Releases a custom attribute of the user under `claim` to an application
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ClaimMapping {
+    pub claim: String,
+    pub attribute: String,
+    #[serde(default = "default_true")]
+    pub id_token: bool,
+    #[serde(default)]
+    pub access_token: bool,
+    #[serde(default = "default_true")]
+    pub userinfo: bool,
+}
+
+/// Where a mapped claim is released
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimTarget {
+    IdToken,
+    AccessToken,
+    UserInfo,
+}
+
+fn default_true() -> bool {
+    true
+}
@@ -1,10 +1,5 @@
-use serde::Deserialize;
-
-pub struct UserInformation {
-    pub username: String,
-    pub email: String,
-    pub is_active: bool,
-}
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 pub struct UserIDSQL {
     pub id: String,
@@ -17,3 +12,82 @@ pub struct CreateUserRequest {
     pub email: String,
     pub password: String,
 }
+
+pub struct UserCredentialsSQL {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub password_hash: String,
+    /// Set for users whose password is verified by the tenant's directory
+    pub directory_dn: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    pub email: String,
+    /// Tenant of the account, required when the email is used in several tenants
+    pub tenant_id: Option<Uuid>,
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub new_password: String,
+}
+
+/// OIDC standard claim values of a user
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UserProfile {
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub middle_name: Option<String>,
+    pub nickname: Option<String>,
+    pub picture: Option<String>,
+    pub website: Option<String>,
+    pub gender: Option<String>,
+    /// `YYYY-MM-DD` or `YYYY`
+    pub birthdate: Option<String>,
+    pub zoneinfo: Option<String>,
+    pub locale: Option<String>,
+    pub email_verified: bool,
+    pub phone_number: Option<String>,
+    pub phone_number_verified: bool,
+    pub street_address: Option<String>,
+    pub locality: Option<String>,
+    pub region: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+}
+
+pub struct UserClaimsSQL {
+    pub username: String,
+    pub email: String,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub middle_name: Option<String>,
+    pub nickname: Option<String>,
+    pub picture: Option<String>,
+    pub website: Option<String>,
+    pub gender: Option<String>,
+    pub birthdate: Option<String>,
+    pub zoneinfo: Option<String>,
+    pub locale: Option<String>,
+    pub email_verified: bool,
+    pub phone_number: Option<String>,
+    pub phone_number_verified: bool,
+    pub street_address: Option<String>,
+    pub locality: Option<String>,
+    pub region: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+    pub updated_at: Option<i64>,
+}
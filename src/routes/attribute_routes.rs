@@ -0,0 +1,17 @@
+use std::sync::Arc;
+
+use axum::{Extension, Router, routing::get};
+
+use crate::{
+    handlers::attribute_handler::{admin_get_attributes, admin_update_attributes},
+    models::services_config::ServicesConfig,
+};
+
+pub fn attribute_routes(service_config: Arc<ServicesConfig>) -> Router {
+    Router::new()
+        .route(
+            "/admin/users/{user_id}/attributes",
+            get(admin_get_attributes).put(admin_update_attributes),
+        )
+        .layer(Extension(service_config))
+}
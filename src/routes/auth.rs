@@ -9,7 +9,9 @@ pub fn auth_routes(service_config: Arc<ServicesConfig>) -> Router {
         "/login",
         post({
             let shared_state = Arc::clone(&service_config);
-            move |json| authenticate_user(shared_state, json)
+            move |issuer, connect_info, headers, json| {
+                authenticate_user(shared_state, issuer, connect_info, headers, json)
+            }
         }),
     )
 }
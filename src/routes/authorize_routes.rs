@@ -1,12 +1,21 @@
-use axum::{Extension, Router, routing::get};
+use axum::{
+    Extension, Router,
+    routing::{get, post},
+};
 use std::sync::Arc;
 
 use crate::{
-    handlers::authorization_code_handler::authorize, models::services_config::ServicesConfig,
+    handlers::{
+        authorization_code_handler::{authorize, select_account},
+        pushed_authorization_handler::pushed_authorization_request,
+    },
+    models::services_config::ServicesConfig,
 };
 
 pub fn authorize_routes(service_config: Arc<ServicesConfig>) -> Router {
     Router::new()
         .route("/authorize", get(authorize))
+        .route("/par", post(pushed_authorization_request))
+        .route("/select_account", post(select_account))
         .layer(Extension(service_config))
 }
@@ -0,0 +1,11 @@
+use std::sync::Arc;
+
+use axum::{Extension, Router, routing::get};
+
+use crate::{handlers::branding_handler::get_branding, models::services_config::ServicesConfig};
+
+pub fn branding_routes(service_config: Arc<ServicesConfig>) -> Router {
+    Router::new()
+        .route("/branding", get(get_branding))
+        .layer(Extension(service_config))
+}
@@ -0,0 +1,25 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Router,
+    routing::{get, post},
+};
+
+use crate::{
+    handlers::client_registration_handler::{
+        delete_client_handler, get_client_handler, register_client_handler, update_client_handler,
+    },
+    models::services_config::ServicesConfig,
+};
+
+pub fn client_registration_routes(service_config: Arc<ServicesConfig>) -> Router {
+    Router::new()
+        .route("/register/client", post(register_client_handler))
+        .route(
+            "/register/client/{client_id}",
+            get(get_client_handler)
+                .put(update_client_handler)
+                .delete(delete_client_handler),
+        )
+        .layer(Extension(service_config))
+}
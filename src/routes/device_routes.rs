@@ -0,0 +1,18 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Router,
+    routing::{get, post},
+};
+
+use crate::{
+    handlers::device_handler::{device_authorization, device_decision, device_verification},
+    models::services_config::ServicesConfig,
+};
+
+pub fn device_routes(service_config: Arc<ServicesConfig>) -> Router {
+    Router::new()
+        .route("/device_authorization", post(device_authorization))
+        .route("/device", get(device_verification).post(device_decision))
+        .layer(Extension(service_config))
+}
@@ -0,0 +1,15 @@
+use std::sync::Arc;
+
+use axum::{Extension, Router, routing::get};
+
+use crate::{
+    handlers::{jwk_set_handler::jwk_set_handler, oidc_discovery_handler::discovery_handler},
+    models::services_config::ServicesConfig,
+};
+
+pub fn discovery_routes(service_config: Arc<ServicesConfig>) -> Router {
+    Router::new()
+        .route("/.well-known/openid-configuration", get(discovery_handler))
+        .route("/.well-known/jwks.json", get(jwk_set_handler))
+        .layer(Extension(service_config))
+}
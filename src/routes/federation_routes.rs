@@ -0,0 +1,16 @@
+use std::sync::Arc;
+
+use axum::{Extension, Router, routing::get};
+
+use crate::{
+    handlers::federation_handler::{federation_callback, federation_login, federation_providers},
+    models::services_config::ServicesConfig,
+};
+
+pub fn federation_routes(service_config: Arc<ServicesConfig>) -> Router {
+    Router::new()
+        .route("/federation/providers", get(federation_providers))
+        .route("/federation/{provider}/login", get(federation_login))
+        .route("/federation/{provider}/callback", get(federation_callback))
+        .layer(Extension(service_config))
+}
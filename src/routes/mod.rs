@@ -1,6 +1,19 @@
+mod attribute_routes;
 mod auth;
 mod authorize_routes;
+mod branding_routes;
+mod client_registration_routes;
+mod device_routes;
+mod discovery_routes;
+mod federation_routes;
 mod logout_routes;
+mod password_routes;
+#[allow(clippy::module_inception)]
 pub mod routes;
+mod saml_routes;
+mod scim_routes;
+mod session_routes;
+mod subject_routes;
 mod token_routes;
 mod user_routes;
+mod userinfo_routes;
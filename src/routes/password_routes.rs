@@ -0,0 +1,16 @@
+use std::sync::Arc;
+
+use axum::{Extension, Router, routing::post};
+
+use crate::{
+    handlers::password_handler::{change_password, forgot_password, reset_password},
+    models::services_config::ServicesConfig,
+};
+
+pub fn password_routes(service_config: Arc<ServicesConfig>) -> Router {
+    Router::new()
+        .route("/password/change", post(change_password))
+        .route("/password/forgot", post(forgot_password))
+        .route("/password/reset", post(reset_password))
+        .layer(Extension(service_config))
+}
@@ -1,39 +1,55 @@
 use std::sync::Arc;
 
-use axum::{Router, routing::get};
-use serde_json::Value;
+use axum::Router;
 
-use crate::{
-    handlers::{jwk_set_handler::jwk_set_handler, oidc_discovery_handler::discovery_handler},
-    models::services_config::ServicesConfig,
-    utils::token_issuer::TokenIssuer,
-};
+use crate::{models::services_config::ServicesConfig, utils::saml_issuer::SamlIssuer};
 
 use super::{
-    auth::auth_routes, authorize_routes::authorize_routes, logout_routes::logout_routes,
-    token_routes::token_routes, user_routes::user_routes,
+    attribute_routes::attribute_routes, auth::auth_routes, authorize_routes::authorize_routes,
+    branding_routes::branding_routes, client_registration_routes::client_registration_routes,
+    device_routes::device_routes, discovery_routes::discovery_routes,
+    federation_routes::federation_routes, logout_routes::logout_routes,
+    password_routes::password_routes, saml_routes::saml_routes, scim_routes::scim_routes,
+    session_routes::session_routes, subject_routes::subject_routes, token_routes::token_routes,
+    user_routes::user_routes, userinfo_routes::userinfo_routes,
 };
 
-pub fn setup_routes(
-    services: Arc<ServicesConfig>,
-    token_issuer: Arc<TokenIssuer>,
-    jwks: Value,
-) -> Router {
+/// Routes of the server's issuer, the issuer of a request is in its extensions
+pub fn setup_routes(services: Arc<ServicesConfig>, saml_issuer: Arc<SamlIssuer>) -> Router {
+    let discovery_routes = discovery_routes(services.clone());
     let authorize_routes = authorize_routes(services.clone());
-    let token_routes = token_routes(services.clone(), token_issuer);
+    let userinfo_routes = userinfo_routes(services.clone());
+    let subject_routes = subject_routes(services.clone());
+    let token_routes = token_routes(services.clone());
     let auth_routes = auth_routes(services.clone());
     let user_routes = user_routes(services.clone());
+    let client_registration_routes = client_registration_routes(services.clone());
+    let password_routes = password_routes(services.clone());
+    let session_routes = session_routes(services.clone());
+    let attribute_routes = attribute_routes(services.clone());
+    let federation_routes = federation_routes(services.clone());
+    let saml_routes = saml_routes(services.clone(), saml_issuer);
+    let scim_routes = scim_routes(services.clone());
+    let device_routes = device_routes(services.clone());
+    let branding_routes = branding_routes(services.clone());
     let logout_routes = logout_routes(services);
 
-    let sharred_jwks = Arc::new(jwks);
-
     Router::new()
-        .route("/.well-known/openid-configuration", get(discovery_handler))
-        .route("/.well-known/jwks.json", get(jwk_set_handler))
-        .with_state(sharred_jwks)
+        .merge(discovery_routes)
         .nest("/oauth", authorize_routes)
         .nest("/oauth", token_routes)
         .nest("/oauth", auth_routes)
         .nest("/oauth", user_routes)
+        .nest("/oauth", client_registration_routes)
         .nest("/oauth", logout_routes)
+        .nest("/oauth", password_routes)
+        .nest("/oauth", session_routes)
+        .nest("/oauth", attribute_routes)
+        .nest("/oauth", userinfo_routes)
+        .nest("/oauth", subject_routes)
+        .nest("/oauth", federation_routes)
+        .nest("/oauth", saml_routes)
+        .nest("/oauth", scim_routes)
+        .nest("/oauth", device_routes)
+        .nest("/oauth", branding_routes)
 }
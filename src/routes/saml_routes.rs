@@ -0,0 +1,18 @@
+use std::sync::Arc;
+
+use axum::{Extension, Router, routing::get};
+
+use crate::{
+    handlers::saml_handler::{saml_idp_initiated, saml_metadata, saml_sso_post, saml_sso_redirect},
+    models::services_config::ServicesConfig,
+    utils::saml_issuer::SamlIssuer,
+};
+
+pub fn saml_routes(service_config: Arc<ServicesConfig>, saml_issuer: Arc<SamlIssuer>) -> Router {
+    Router::new()
+        .route("/saml/metadata", get(saml_metadata))
+        .route("/saml/sso", get(saml_sso_redirect).post(saml_sso_post))
+        .route("/saml/init/{client_id}", get(saml_idp_initiated))
+        .layer(Extension(service_config))
+        .layer(Extension(saml_issuer))
+}
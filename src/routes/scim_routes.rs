@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use axum::{Extension, Router, routing::get};
+
+use crate::{
+    handlers::scim_handler::{
+        scim_create_group, scim_create_user, scim_delete_group, scim_delete_user, scim_get_group,
+        scim_get_user, scim_list_groups, scim_list_users, scim_patch_group, scim_patch_user,
+        scim_replace_group, scim_replace_user, scim_resource_type, scim_resource_types,
+        scim_schema, scim_schemas, scim_service_provider_config,
+    },
+    models::services_config::ServicesConfig,
+};
+
+pub fn scim_routes(service_config: Arc<ServicesConfig>) -> Router {
+    Router::new()
+        .route(
+            "/scim/v2/ServiceProviderConfig",
+            get(scim_service_provider_config),
+        )
+        .route("/scim/v2/Schemas", get(scim_schemas))
+        .route("/scim/v2/Schemas/{id}", get(scim_schema))
+        .route("/scim/v2/ResourceTypes", get(scim_resource_types))
+        .route("/scim/v2/ResourceTypes/{name}", get(scim_resource_type))
+        .route(
+            "/scim/v2/Users",
+            get(scim_list_users).post(scim_create_user),
+        )
+        .route(
+            "/scim/v2/Users/{user_id}",
+            get(scim_get_user)
+                .put(scim_replace_user)
+                .patch(scim_patch_user)
+                .delete(scim_delete_user),
+        )
+        .route(
+            "/scim/v2/Groups",
+            get(scim_list_groups).post(scim_create_group),
+        )
+        .route(
+            "/scim/v2/Groups/{group_id}",
+            get(scim_get_group)
+                .put(scim_replace_group)
+                .patch(scim_patch_group)
+                .delete(scim_delete_group),
+        )
+        .layer(Extension(service_config))
+}
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Router,
+    routing::{delete, get},
+};
+
+use crate::{
+    handlers::session_handler::{
+        admin_list_sessions, admin_revoke_all_sessions, admin_revoke_session, list_sessions,
+        revoke_other_sessions, revoke_session,
+    },
+    models::services_config::ServicesConfig,
+};
+
+pub fn session_routes(service_config: Arc<ServicesConfig>) -> Router {
+    Router::new()
+        .route(
+            "/sessions",
+            get(list_sessions).delete(revoke_other_sessions),
+        )
+        .route("/sessions/{session_id}", delete(revoke_session))
+        .route(
+            "/admin/users/{user_id}/sessions",
+            get(admin_list_sessions).delete(admin_revoke_all_sessions),
+        )
+        .route(
+            "/admin/users/{user_id}/sessions/{session_id}",
+            delete(admin_revoke_session),
+        )
+        .layer(Extension(service_config))
+}
@@ -0,0 +1,21 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Router,
+    routing::{get, post},
+};
+
+use crate::{
+    handlers::{introspection_handler::introspect, subject_handler::admin_resolve_subject},
+    models::services_config::ServicesConfig,
+};
+
+pub fn subject_routes(service_config: Arc<ServicesConfig>) -> Router {
+    Router::new()
+        .route("/introspect", post(introspect))
+        .route(
+            "/admin/applications/{client_id}/subjects/{subject}",
+            get(admin_resolve_subject),
+        )
+        .layer(Extension(service_config))
+}
@@ -0,0 +1,11 @@
+use std::sync::Arc;
+
+use axum::{Extension, Router, routing::get};
+
+use crate::{handlers::userinfo_handler::userinfo, models::services_config::ServicesConfig};
+
+pub fn userinfo_routes(service_config: Arc<ServicesConfig>) -> Router {
+    Router::new()
+        .route("/userinfo", get(userinfo).post(userinfo))
+        .layer(Extension(service_config))
+}
@@ -0,0 +1,36 @@
+use sqlx::{Pool, Postgres};
+
+use crate::models::api_resource::ApiResourceSQL;
+
+pub struct ApiResourceService {
+    db_pool: Pool<Postgres>,
+}
+
+impl ApiResourceService {
+    pub fn new(db_pool: Pool<Postgres>) -> Self {
+        Self { db_pool }
+    }
+
+    /// The resource `identifier` if it belongs to the tenant of the application `client_id`
+    pub async fn get_resource_for_client(
+        &self,
+        identifier: &str,
+        client_id: &str,
+    ) -> Result<Option<ApiResourceSQL>, anyhow::Error> {
+        let resource = sqlx::query_as!(
+            ApiResourceSQL,
+            r#"
+            SELECT r.identifier, r.scopes
+            FROM ApiResources r
+            JOIN Applications a ON a.tenant_id = r.tenant_id
+            WHERE r.identifier = $1 AND a.client_id = $2
+            "#,
+            identifier,
+            client_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(resource)
+    }
+}
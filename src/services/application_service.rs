@@ -1,27 +1,70 @@
 use anyhow::Error;
+use jsonwebtoken::jwk::JwkSet;
 use sqlx::{Pool, Postgres};
-use uuid::Uuid;
 
-use crate::models::application_model::Application;
+use crate::models::application_model::{Application, ClientKeysSQL};
 
 pub struct ApplicationClientService {
     db_pool: Pool<Postgres>,
+    http_client: reqwest::Client,
 }
 
 impl ApplicationClientService {
     pub fn new(db_pool: Pool<Postgres>) -> Self {
-        Self { db_pool }
+        Self {
+            db_pool,
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    /// Public keys of the application, `None` if it registered none
+    pub async fn get_client_jwks(&self, client_id: &str) -> Result<Option<JwkSet>, Error> {
+        let keys = sqlx::query_as!(
+            ClientKeysSQL,
+            r#"
+            SELECT jwks AS "jwks: _", jwks_uri
+            FROM Applications WHERE client_id = $1
+            "#,
+            client_id,
+        )
+        .fetch_one(&self.db_pool)
+        .await?;
+
+        if let Some(jwks) = keys.jwks {
+            return Ok(Some(jwks.0));
+        }
+        let Some(jwks_uri) = keys.jwks_uri else {
+            return Ok(None);
+        };
+
+        let jwks = self
+            .http_client
+            .get(&jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(Some(jwks))
     }
 
     pub async fn get_client_information(&self, client_id: &str) -> Result<Application, Error> {
         let result = sqlx::query_as!(
             Application,
-            "SELECT client_secret, redirect_uris FROM Applications WHERE client_id = $1",
+            r#"
+            SELECT tenant_id, name, client_secret, redirect_uris, token_exchange_audiences,
+                   require_pushed_authorization_requests, token_endpoint_auth_method,
+                   tls_client_auth_subject_dn, tls_client_certificate_bound_access_tokens,
+                   grant_types, response_types, allowed_scopes, access_token_lifetime,
+                   id_token_lifetime, refresh_token_lifetime, authorization_code_lifetime
+            FROM Applications WHERE client_id = $1
+            "#,
             client_id,
         )
         .fetch_one(&self.db_pool)
         .await;
 
-        return Ok(result?);
+        Ok(result?)
     }
 }
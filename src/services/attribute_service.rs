@@ -0,0 +1,135 @@
+use serde_json::{Map, Value};
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use crate::{
+    models::user_attributes::{
+        AttributeDefinition, AttributeDefinitionSQL, ClaimMapping, ClaimTarget,
+    },
+    utils::attribute_utils::{mapped_claims, validate_attributes},
+};
+
+pub struct AttributeService {
+    db_pool: Pool<Postgres>,
+}
+
+impl AttributeService {
+    pub fn new(db_pool: Pool<Postgres>) -> Self {
+        Self { db_pool }
+    }
+
+    /// Attribute definitions of the tenant a user belongs to
+    pub async fn get_definitions(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<AttributeDefinition>, anyhow::Error> {
+        let user_uuid = Uuid::parse_str(user_id)?;
+
+        let rows = sqlx::query_as!(
+            AttributeDefinitionSQL,
+            r#"
+            SELECT d.name, d.data_type, d.required, d.description
+            FROM AttributeDefinitions d
+            JOIN Users u ON u.tenant_id = d.tenant_id
+            WHERE u.id = $1
+            ORDER BY d.name
+            "#,
+            user_uuid
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(AttributeDefinition {
+                    name: row.name,
+                    data_type: row.data_type.parse()?,
+                    required: row.required,
+                    description: row.description,
+                })
+            })
+            .collect()
+    }
+
+    pub async fn get_user_attributes(
+        &self,
+        user_id: &str,
+    ) -> Result<Map<String, Value>, anyhow::Error> {
+        let user_uuid = Uuid::parse_str(user_id)?;
+
+        let attributes =
+            sqlx::query_scalar!("SELECT attributes FROM Users WHERE id = $1", user_uuid)
+                .fetch_one(&self.db_pool)
+                .await?;
+
+        match attributes {
+            Value::Object(attributes) => Ok(attributes),
+            _ => Ok(Map::new()),
+        }
+    }
+
+    /// Replaces all custom attributes of a user after validating them against the tenant's definitions
+    pub async fn set_user_attributes(
+        &self,
+        user_id: &str,
+        attributes: Map<String, Value>,
+    ) -> Result<(), anyhow::Error> {
+        let user_uuid = Uuid::parse_str(user_id)?;
+        let definitions = self.get_definitions(user_id).await?;
+        validate_attributes(&attributes, &definitions)?;
+
+        // Cleared optional attributes are not stored
+        let attributes: Map<String, Value> = attributes
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .collect();
+
+        sqlx::query!(
+            "UPDATE Users SET attributes = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2",
+            Value::Object(attributes),
+            user_uuid
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_claim_mappings(
+        &self,
+        client_id: &str,
+    ) -> Result<Vec<ClaimMapping>, anyhow::Error> {
+        let mappings = sqlx::query_as!(
+            ClaimMapping,
+            r#"
+            SELECT m.claim_name AS claim, m.attribute, m.in_id_token AS id_token,
+                   m.in_access_token AS access_token, m.in_userinfo AS userinfo
+            FROM ClaimMappings m
+            JOIN Applications a ON a.id = m.application_id
+            WHERE a.client_id = $1
+            ORDER BY m.claim_name
+            "#,
+            client_id
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(mappings)
+    }
+
+    /// Claims for `target` the application `client_id` receives from the user's attributes
+    pub async fn get_mapped_claims(
+        &self,
+        user_id: &str,
+        client_id: &str,
+        target: ClaimTarget,
+    ) -> Result<Map<String, Value>, anyhow::Error> {
+        let mappings = self.get_claim_mappings(client_id).await?;
+        if mappings.is_empty() {
+            return Ok(Map::new());
+        }
+
+        let attributes = self.get_user_attributes(user_id).await?;
+        Ok(mapped_claims(&attributes, &mappings, target))
+    }
+}
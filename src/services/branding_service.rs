@@ -0,0 +1,29 @@
+use sqlx::{Pool, Postgres, types::Json};
+use uuid::Uuid;
+
+use crate::models::branding::TenantBranding;
+
+pub struct BrandingService {
+    db_pool: Pool<Postgres>,
+}
+
+impl BrandingService {
+    pub fn new(db_pool: Pool<Postgres>) -> Self {
+        Self { db_pool }
+    }
+
+    /// Branding of the tenant, `None` if it uses the default look
+    pub async fn get_branding(
+        &self,
+        tenant_id: Uuid,
+    ) -> Result<Option<TenantBranding>, anyhow::Error> {
+        let branding = sqlx::query_scalar!(
+            r#"SELECT branding AS "branding: Json<TenantBranding>" FROM Tenants WHERE id = $1"#,
+            tenant_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(branding.flatten().map(|branding| branding.0))
+    }
+}
@@ -0,0 +1,216 @@
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        application_model::TokenEndpointAuthMethod,
+        client_registration::{ClientInformation, ClientMetadata, RegisteredClientSQL},
+        config::application::Application,
+    },
+    services::config::application_service::{
+        ApplicationService, sector_identifier, validate_application,
+    },
+    utils::{
+        client_registration_utils::{
+            RegistrationError, client_metadata, registered_application, validate_client_metadata,
+        },
+        federation_utils::random_token,
+        scim_utils::hash_token,
+    },
+};
+
+/// Applications registered and managed by their clients (RFC 7591, RFC 7592)
+pub struct ClientRegistrationService {
+    db_pool: Pool<Postgres>,
+    application_service: ApplicationService,
+    base_url: String,
+    open_registration_tenant: Option<Uuid>,
+}
+
+impl ClientRegistrationService {
+    pub fn new(
+        db_pool: Pool<Postgres>,
+        public_url: String,
+        open_registration_tenant: Option<Uuid>,
+    ) -> Self {
+        Self {
+            application_service: ApplicationService::new(db_pool.clone()),
+            db_pool,
+            base_url: format!("{}/oauth/register/client", public_url.trim_end_matches('/')),
+            open_registration_tenant,
+        }
+    }
+
+    /// Tenant a client registers into, the one of its initial access token or the tenant open
+    /// for registration without one
+    pub async fn get_registration_tenant(
+        &self,
+        initial_access_token: Option<&str>,
+    ) -> Result<Option<Uuid>, anyhow::Error> {
+        let Some(token) = initial_access_token else {
+            return Ok(self.open_registration_tenant);
+        };
+
+        let tenant_id = sqlx::query_scalar!(
+            "SELECT tenant_id FROM RegistrationTokens WHERE token_hash = $1",
+            hash_token(token)
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(tenant_id)
+    }
+
+    /// Registers a client, the response has its credentials and registration access token
+    pub async fn register_client(
+        &self,
+        tenant_id: Uuid,
+        metadata: ClientMetadata,
+    ) -> Result<ClientInformation, RegistrationError> {
+        let client_id = Uuid::new_v4().to_string();
+        let client_secret = random_token()?;
+        let application = registered_application(
+            Uuid::new_v4(),
+            tenant_id,
+            &client_id,
+            &client_secret,
+            &metadata,
+        );
+        let sector_identifier = validated_sector_identifier(&metadata, &application).await?;
+
+        let registration_access_token = random_token()?;
+        self.application_service
+            .insert_application(
+                &application,
+                sector_identifier,
+                Some(hash_token(&registration_access_token)),
+            )
+            .await?;
+
+        let mut client = self
+            .get_client(&client_id, &registration_access_token)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Registered client {client_id} not found"))?;
+        client.registration_access_token = Some(registration_access_token);
+
+        Ok(client)
+    }
+
+    /// A registered client, `None` unless the registration access token was issued for it
+    pub async fn get_client(
+        &self,
+        client_id: &str,
+        registration_access_token: &str,
+    ) -> Result<Option<ClientInformation>, anyhow::Error> {
+        let client = self
+            .registered_client(client_id, registration_access_token)
+            .await?;
+
+        Ok(client.map(|client| self.client_information(client)))
+    }
+
+    /// Replaces the metadata of a registered client, keeping its credentials
+    pub async fn update_client(
+        &self,
+        client: RegisteredClientSQL,
+        metadata: ClientMetadata,
+    ) -> Result<ClientInformation, RegistrationError> {
+        let application = registered_application(
+            client.id,
+            client.tenant_id,
+            &client.client_id,
+            &client.client_secret,
+            &metadata,
+        );
+        let sector_identifier = validated_sector_identifier(&metadata, &application).await?;
+
+        self.application_service
+            .update_application(&application, sector_identifier)
+            .await?;
+
+        Ok(self.client_information(self.stored_client(client.id).await?))
+    }
+
+    pub async fn delete_client(&self, client: RegisteredClientSQL) -> Result<(), anyhow::Error> {
+        self.application_service.delete_application(client.id).await
+    }
+
+    /// The stored application of a registered client, if the registration access token matches
+    pub async fn registered_client(
+        &self,
+        client_id: &str,
+        registration_access_token: &str,
+    ) -> Result<Option<RegisteredClientSQL>, anyhow::Error> {
+        let client = sqlx::query_as!(
+            RegisteredClientSQL,
+            r#"
+            SELECT id, tenant_id, client_id, client_secret, name, uri, redirect_uris, post_logout_redirect_uris,
+                   subject_type, sector_identifier_uri, jwks AS "jwks: _", jwks_uri,
+                   token_endpoint_auth_method, tls_client_auth_subject_dn,
+                   tls_client_certificate_bound_access_tokens, require_pushed_authorization_requests,
+                   grant_types, response_types, allowed_scopes,
+                   EXTRACT(EPOCH FROM created_at)::BIGINT AS "client_id_issued_at!"
+            FROM Applications WHERE client_id = $1 AND registration_access_token_hash = $2
+            "#,
+            client_id,
+            hash_token(registration_access_token),
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(client)
+    }
+
+    async fn stored_client(&self, id: Uuid) -> Result<RegisteredClientSQL, anyhow::Error> {
+        let client = sqlx::query_as!(
+            RegisteredClientSQL,
+            r#"
+            SELECT id, tenant_id, client_id, client_secret, name, uri, redirect_uris, post_logout_redirect_uris,
+                   subject_type, sector_identifier_uri, jwks AS "jwks: _", jwks_uri,
+                   token_endpoint_auth_method, tls_client_auth_subject_dn,
+                   tls_client_certificate_bound_access_tokens, require_pushed_authorization_requests,
+                   grant_types, response_types, allowed_scopes,
+                   EXTRACT(EPOCH FROM created_at)::BIGINT AS "client_id_issued_at!"
+            FROM Applications WHERE id = $1
+            "#,
+            id,
+        )
+        .fetch_one(&self.db_pool)
+        .await?;
+
+        Ok(client)
+    }
+
+    fn client_information(&self, client: RegisteredClientSQL) -> ClientInformation {
+        let client_id = client.client_id.clone();
+        let client_secret = client.client_secret.clone();
+        let client_id_issued_at = client.client_id_issued_at;
+        let metadata = client_metadata(client);
+        let uses_secret =
+            metadata.token_endpoint_auth_method == TokenEndpointAuthMethod::ClientSecretPost;
+
+        ClientInformation {
+            registration_client_uri: format!("{}/{client_id}", self.base_url),
+            client_id,
+            client_secret: uses_secret.then_some(client_secret),
+            client_id_issued_at,
+            client_secret_expires_at: uses_secret.then_some(0),
+            registration_access_token: None,
+            metadata,
+        }
+    }
+}
+
+/// Validates the metadata and resolves the pairwise sector of the application it describes
+async fn validated_sector_identifier(
+    metadata: &ClientMetadata,
+    application: &Application,
+) -> Result<Option<String>, RegistrationError> {
+    validate_client_metadata(metadata)?;
+    validate_application(application)
+        .map_err(|e| RegistrationError::InvalidClientMetadata(e.to_string()))?;
+
+    sector_identifier(application)
+        .await
+        .map_err(|e| RegistrationError::InvalidClientMetadata(e.to_string()))
+}
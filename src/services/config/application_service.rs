@@ -1,5 +1,14 @@
+use crate::models::application_model::{
+    AUTHORIZATION_CODE_GRANT_TYPE, GRANT_TYPES, RESPONSE_TYPES, SubjectType,
+    TokenEndpointAuthMethod,
+};
 use crate::models::config::application::Application;
+use crate::models::saml::SamlServiceProvider;
+use crate::models::user_attributes::ClaimMapping;
+use crate::utils::attribute_utils::validate_claim_mapping;
+use crate::utils::subject_utils::{redirect_uri_sector, resolve_sector_identifier_uri};
 use anyhow::Result;
+use sqlx::types::Json;
 use sqlx::{Pool, Postgres};
 use uuid::Uuid;
 
@@ -13,36 +22,280 @@ impl ApplicationService {
     }
 
     pub async fn create_application(&self, mut application: Application) -> Result<Uuid> {
-        if application.name.trim().is_empty() {
-            return Err(anyhow::anyhow!("Application name cannot be empty"));
-        }
-        if application.client_id.trim().is_empty() {
-            return Err(anyhow::anyhow!("Client ID cannot be empty"));
-        }
+        validate_application(&application)?;
 
         if application.id == Uuid::nil() {
             application.id = Uuid::new_v4();
         }
 
+        let sector_identifier = sector_identifier(&application).await?;
+        self.insert_application(&application, sector_identifier, None)
+            .await?;
+
+        Ok(application.id)
+    }
+
+    /// Stores a validated application, dynamically registered ones with the hash of their
+    /// registration access token (RFC 7592)
+    pub async fn insert_application(
+        &self,
+        application: &Application,
+        sector_identifier: Option<String>,
+        registration_access_token_hash: Option<String>,
+    ) -> Result<()> {
         sqlx::query!(
-        r#"
-        INSERT INTO applications
-        (id, tenant_id, name, client_id, client_secret, uri, redirect_uris, post_logout_redirect_uris)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-        "#,
-        application.id,
-        application.tenant_id,
-        application.name,
-        application.client_id,
-        application.client_secret,
-        application.uri,
-        &application.redirect_uris,
-        &application.post_logout_redirect_uris
-    )
+            r#"
+            INSERT INTO applications
+            (id, tenant_id, name, client_id, client_secret, uri, redirect_uris, post_logout_redirect_uris,
+             subject_type, sector_identifier_uri, sector_identifier, token_exchange_audiences,
+             require_pushed_authorization_requests, jwks, jwks_uri, token_endpoint_auth_method,
+             tls_client_auth_subject_dn, tls_client_certificate_bound_access_tokens,
+             registration_access_token_hash, grant_types, response_types, allowed_scopes,
+             access_token_lifetime, id_token_lifetime, refresh_token_lifetime,
+             authorization_code_lifetime)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18,
+                    $19, $20, $21, $22, $23, $24, $25, $26)
+            "#,
+            application.id,
+            application.tenant_id,
+            application.name,
+            application.client_id,
+            application.client_secret,
+            application.uri,
+            &application.redirect_uris,
+            &application.post_logout_redirect_uris,
+            application.subject_type.as_str(),
+            application.sector_identifier_uri,
+            sector_identifier,
+            &application.token_exchange_audiences,
+            application.require_pushed_authorization_requests,
+            application.jwks.as_ref().map(Json) as _,
+            application.jwks_uri,
+            application.token_endpoint_auth_method.as_str(),
+            application.tls_client_auth_subject_dn,
+            application.tls_client_certificate_bound_access_tokens,
+            registration_access_token_hash,
+            &application.grant_types,
+            &application.response_types,
+            application.allowed_scopes.as_deref(),
+            application.token_lifetimes.access_token,
+            application.token_lifetimes.id_token,
+            application.token_lifetimes.refresh_token,
+            application.token_lifetimes.authorization_code,
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to create application: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Replaces the metadata of a validated application, keeping its identifiers and secret
+    pub async fn update_application(
+        &self,
+        application: &Application,
+        sector_identifier: Option<String>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE applications SET
+                name = $2, uri = $3, redirect_uris = $4, post_logout_redirect_uris = $5,
+                subject_type = $6, sector_identifier_uri = $7, sector_identifier = $8,
+                require_pushed_authorization_requests = $9, jwks = $10, jwks_uri = $11,
+                token_endpoint_auth_method = $12, tls_client_auth_subject_dn = $13,
+                tls_client_certificate_bound_access_tokens = $14, grant_types = $15,
+                response_types = $16, allowed_scopes = $17, access_token_lifetime = $18,
+                id_token_lifetime = $19, refresh_token_lifetime = $20,
+                authorization_code_lifetime = $21, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            "#,
+            application.id,
+            application.name,
+            application.uri,
+            &application.redirect_uris,
+            &application.post_logout_redirect_uris,
+            application.subject_type.as_str(),
+            application.sector_identifier_uri,
+            sector_identifier,
+            application.require_pushed_authorization_requests,
+            application.jwks.as_ref().map(Json) as _,
+            application.jwks_uri,
+            application.token_endpoint_auth_method.as_str(),
+            application.tls_client_auth_subject_dn,
+            application.tls_client_certificate_bound_access_tokens,
+            &application.grant_types,
+            &application.response_types,
+            application.allowed_scopes.as_deref(),
+            application.token_lifetimes.access_token,
+            application.token_lifetimes.id_token,
+            application.token_lifetimes.refresh_token,
+            application.token_lifetimes.authorization_code,
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to update application: {}", e))?;
+
+        Ok(())
+    }
+
+    pub async fn delete_application(&self, application_id: Uuid) -> Result<()> {
+        sqlx::query!("DELETE FROM applications WHERE id = $1", application_id)
             .execute(&self.db_pool)
             .await
-            .map_err(|e| anyhow::anyhow!("Failed to create application: {}", e))?;
+            .map_err(|e| anyhow::anyhow!("Failed to delete application: {}", e))?;
 
-        Ok(application.id)
+        Ok(())
+    }
+
+    /// Creates or replaces a claim mapping rule of an application
+    pub async fn upsert_claim_mapping(
+        &self,
+        application_id: Uuid,
+        mapping: &ClaimMapping,
+    ) -> Result<()> {
+        validate_claim_mapping(mapping)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO ClaimMappings
+            (application_id, claim_name, attribute, in_id_token, in_access_token, in_userinfo)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (application_id, claim_name) DO UPDATE SET
+                attribute = EXCLUDED.attribute,
+                in_id_token = EXCLUDED.in_id_token,
+                in_access_token = EXCLUDED.in_access_token,
+                in_userinfo = EXCLUDED.in_userinfo,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+            application_id,
+            mapping.claim,
+            mapping.attribute,
+            mapping.id_token,
+            mapping.access_token,
+            mapping.userinfo,
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to store claim mapping: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Creates or replaces the SAML service provider settings of an application
+    pub async fn upsert_saml_service_provider(
+        &self,
+        application_id: Uuid,
+        service_provider: &SamlServiceProvider,
+    ) -> Result<()> {
+        if service_provider.acs_urls.is_empty() {
+            return Err(anyhow::anyhow!(
+                "SAML service provider {} needs an assertion consumer service URL",
+                service_provider.entity_id
+            ));
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO SamlServiceProviders
+            (application_id, entity_id, acs_urls, name_id_format, attributes)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (application_id) DO UPDATE SET
+                entity_id = EXCLUDED.entity_id,
+                acs_urls = EXCLUDED.acs_urls,
+                name_id_format = EXCLUDED.name_id_format,
+                attributes = EXCLUDED.attributes,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+            application_id,
+            service_provider.entity_id,
+            &service_provider.acs_urls,
+            service_provider.name_id_format.as_str(),
+            Json(&service_provider.attributes) as _,
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to store SAML service provider: {}", e))?;
+
+        Ok(())
+    }
+}
+
+/// Checks the settings of an application that do not depend on other resources
+pub fn validate_application(application: &Application) -> Result<()> {
+    if application.name.trim().is_empty() {
+        return Err(anyhow::anyhow!("Application name cannot be empty"));
+    }
+    if application.client_id.trim().is_empty() {
+        return Err(anyhow::anyhow!("Client ID cannot be empty"));
+    }
+
+    if let Some(grant_type) = application
+        .grant_types
+        .iter()
+        .find(|grant_type| !GRANT_TYPES.contains(&grant_type.as_str()))
+    {
+        return Err(anyhow::anyhow!("Unsupported grant type {grant_type}"));
+    }
+    if let Some(response_type) = application
+        .response_types
+        .iter()
+        .find(|response_type| !RESPONSE_TYPES.contains(&response_type.as_str()))
+    {
+        return Err(anyhow::anyhow!("Unsupported response type {response_type}"));
+    }
+    // The code response type is only useful if the code can be exchanged for tokens
+    if application
+        .response_types
+        .iter()
+        .any(|response_type| response_type == "code")
+        && !application
+            .grant_types
+            .iter()
+            .any(|grant_type| grant_type == AUTHORIZATION_CODE_GRANT_TYPE)
+    {
+        return Err(anyhow::anyhow!(
+            "The code response type requires the authorization_code grant type"
+        ));
+    }
+    let lifetimes = application.token_lifetimes;
+    if [
+        lifetimes.access_token,
+        lifetimes.id_token,
+        lifetimes.refresh_token,
+        lifetimes.authorization_code,
+    ]
+    .iter()
+    .any(|lifetime| *lifetime <= 0)
+    {
+        return Err(anyhow::anyhow!("Token lifetimes must be positive"));
+    }
+
+    match application.token_endpoint_auth_method {
+        TokenEndpointAuthMethod::TlsClientAuth
+            if application.tls_client_auth_subject_dn.is_none() =>
+        {
+            Err(anyhow::anyhow!(
+                "tls_client_auth requires tls_client_auth_subject_dn"
+            ))
+        }
+        TokenEndpointAuthMethod::SelfSignedTlsClientAuth
+            if application.jwks.is_none() && application.jwks_uri.is_none() =>
+        {
+            Err(anyhow::anyhow!(
+                "self_signed_tls_client_auth requires jwks or jwks_uri"
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Pairwise sector of an application, from its `sector_identifier_uri` or redirect URIs
+pub async fn sector_identifier(application: &Application) -> Result<Option<String>> {
+    match (application.subject_type, &application.sector_identifier_uri) {
+        (SubjectType::Public, _) => Ok(None),
+        (SubjectType::Pairwise, Some(uri)) => Ok(Some(
+            resolve_sector_identifier_uri(uri, &application.redirect_uris).await?,
+        )),
+        (SubjectType::Pairwise, None) => Ok(Some(redirect_uri_sector(&application.redirect_uris)?)),
     }
 }
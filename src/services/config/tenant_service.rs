@@ -1,7 +1,18 @@
+use crate::models::api_resource::ApiResource;
+use crate::models::branding::TenantBranding;
+use crate::models::client_registration::RegistrationToken;
 use crate::models::config::tenant::Tenant;
+use crate::models::directory::DirectoryConfig;
+use crate::models::identity_provider::IdentityProvider;
+use crate::models::password_policy::PasswordPolicy;
+use crate::models::scim::ScimToken;
+use crate::models::session_policy::SessionPolicy;
+use crate::models::user_attributes::AttributeDefinition;
+use crate::utils::resource_utils::is_valid_resource_indicator;
+use crate::utils::scim_utils::hash_token;
+use sqlx::types::Json;
 use sqlx::{Pool, Postgres};
 use uuid::Uuid;
-// Add this import
 
 pub struct TenantService {
     db_pool: Pool<Postgres>,
@@ -32,4 +43,298 @@ impl TenantService {
 
         Ok(tenant.id)
     }
+
+    /// Creates or replaces the password policy of a tenant
+    pub async fn upsert_password_policy(
+        &self,
+        tenant_id: Uuid,
+        policy: &PasswordPolicy,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO PasswordPolicies
+            (tenant_id, min_length, require_uppercase, require_lowercase, require_digit, require_symbol,
+             history_size, max_age_days, check_breached)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (tenant_id) DO UPDATE SET
+                min_length = EXCLUDED.min_length,
+                require_uppercase = EXCLUDED.require_uppercase,
+                require_lowercase = EXCLUDED.require_lowercase,
+                require_digit = EXCLUDED.require_digit,
+                require_symbol = EXCLUDED.require_symbol,
+                history_size = EXCLUDED.history_size,
+                max_age_days = EXCLUDED.max_age_days,
+                check_breached = EXCLUDED.check_breached,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+            tenant_id,
+            policy.min_length,
+            policy.require_uppercase,
+            policy.require_lowercase,
+            policy.require_digit,
+            policy.require_symbol,
+            policy.history_size,
+            policy.max_age_days,
+            policy.check_breached,
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to store password policy: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Creates or replaces the session policy of a tenant
+    pub async fn upsert_session_policy(
+        &self,
+        tenant_id: Uuid,
+        policy: &SessionPolicy,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO SessionPolicies
+            (tenant_id, idle_timeout_seconds, absolute_lifetime_seconds, remember_me_enabled,
+             remember_me_lifetime_seconds)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (tenant_id) DO UPDATE SET
+                idle_timeout_seconds = EXCLUDED.idle_timeout_seconds,
+                absolute_lifetime_seconds = EXCLUDED.absolute_lifetime_seconds,
+                remember_me_enabled = EXCLUDED.remember_me_enabled,
+                remember_me_lifetime_seconds = EXCLUDED.remember_me_lifetime_seconds,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+            tenant_id,
+            policy.idle_timeout_seconds,
+            policy.absolute_lifetime_seconds,
+            policy.remember_me_enabled,
+            policy.remember_me_lifetime_seconds,
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to store session policy: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Creates or replaces a custom attribute definition of a tenant
+    pub async fn upsert_attribute_definition(
+        &self,
+        tenant_id: Uuid,
+        definition: &AttributeDefinition,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO AttributeDefinitions (tenant_id, name, data_type, required, description)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (tenant_id, name) DO UPDATE SET
+                data_type = EXCLUDED.data_type,
+                required = EXCLUDED.required,
+                description = EXCLUDED.description,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+            tenant_id,
+            definition.name,
+            definition.data_type.as_str(),
+            definition.required,
+            definition.description,
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to store attribute definition: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Creates or replaces an upstream identity provider of a tenant
+    pub async fn upsert_identity_provider(
+        &self,
+        tenant_id: Uuid,
+        provider: &IdentityProvider,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO IdentityProviders
+            (id, tenant_id, name, discovery_url, client_id, client_secret, scopes, claim_mapping,
+             auto_provision, link_by_email)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (id) DO UPDATE SET
+                tenant_id = EXCLUDED.tenant_id,
+                name = EXCLUDED.name,
+                discovery_url = EXCLUDED.discovery_url,
+                client_id = EXCLUDED.client_id,
+                client_secret = EXCLUDED.client_secret,
+                scopes = EXCLUDED.scopes,
+                claim_mapping = EXCLUDED.claim_mapping,
+                auto_provision = EXCLUDED.auto_provision,
+                link_by_email = EXCLUDED.link_by_email,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+            provider.id,
+            tenant_id,
+            provider.name,
+            provider.discovery_url,
+            provider.client_id,
+            provider.client_secret,
+            &provider.scopes,
+            Json(&provider.claim_mapping) as _,
+            provider.auto_provision,
+            provider.link_by_email,
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to store identity provider: {}", e))?;
+
+        Ok(())
+    }
+
+    pub async fn upsert_directory(
+        &self,
+        tenant_id: Uuid,
+        directory: &DirectoryConfig,
+    ) -> Result<(), anyhow::Error> {
+        let email_domains: Vec<String> = directory
+            .email_domains
+            .iter()
+            .map(|domain| domain.to_lowercase())
+            .collect();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO TenantDirectories (tenant_id, config, email_domains)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (tenant_id) DO UPDATE SET
+                config = EXCLUDED.config,
+                email_domains = EXCLUDED.email_domains,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+            tenant_id,
+            Json(directory) as _,
+            &email_domains,
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to store directory: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Store a provisioning token, only its hash is kept
+    pub async fn upsert_scim_token(
+        &self,
+        tenant_id: Uuid,
+        token: &ScimToken,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO ScimTokens (id, tenant_id, name, token_hash)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (tenant_id, name) DO UPDATE SET
+                token_hash = EXCLUDED.token_hash,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+            Uuid::new_v4(),
+            tenant_id,
+            token.name,
+            hash_token(&token.token),
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to store SCIM token: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Creates or replaces an initial access token for client registration (RFC 7591)
+    pub async fn upsert_registration_token(
+        &self,
+        tenant_id: Uuid,
+        token: &RegistrationToken,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO RegistrationTokens (id, tenant_id, name, token_hash)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (tenant_id, name) DO UPDATE SET
+                token_hash = EXCLUDED.token_hash,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+            Uuid::new_v4(),
+            tenant_id,
+            token.name,
+            hash_token(&token.token),
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to store registration token: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Creates or replaces an API resource, identifiers are unique across tenants
+    pub async fn upsert_api_resource(
+        &self,
+        tenant_id: Uuid,
+        resource: &ApiResource,
+    ) -> Result<(), anyhow::Error> {
+        if !is_valid_resource_indicator(&resource.identifier) {
+            return Err(anyhow::anyhow!(
+                "API resource identifier {} must be an absolute URI without fragment",
+                resource.identifier
+            ));
+        }
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO ApiResources (id, tenant_id, identifier, name, scopes)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (identifier) DO UPDATE SET
+                name = EXCLUDED.name,
+                scopes = EXCLUDED.scopes,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE ApiResources.tenant_id = EXCLUDED.tenant_id
+            "#,
+            Uuid::new_v4(),
+            tenant_id,
+            resource.identifier,
+            resource.name,
+            &resource.scopes,
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to store API resource: {}", e))?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!(
+                "API resource {} belongs to another tenant",
+                resource.identifier
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Sets the custom domain and the branding of a tenant, both cleared if not configured
+    pub async fn update_branding(
+        &self,
+        tenant_id: Uuid,
+        domain: Option<&str>,
+        branding: Option<&TenantBranding>,
+    ) -> Result<(), anyhow::Error> {
+        let domain = domain.map(str::to_lowercase);
+
+        sqlx::query!(
+            r#"
+            UPDATE Tenants SET domain = $2, branding = $3, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            "#,
+            tenant_id,
+            domain,
+            branding.map(Json) as _,
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to store branding: {}", e))?;
+
+        Ok(())
+    }
 }
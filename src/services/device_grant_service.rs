@@ -0,0 +1,192 @@
+use bb8_redis::RedisConnectionManager;
+use chrono::Utc;
+use redis::AsyncCommands;
+
+use crate::{
+    models::device_grant::{DeviceGrantData, DeviceGrantStatus},
+    utils::{device_utils::generate_user_code, federation_utils::random_token},
+};
+
+/// How long a device has to be approved
+pub const DEVICE_CODE_LIFETIME: u64 = 600;
+/// Seconds a device waits between token requests unless told to slow down
+pub const POLLING_INTERVAL: u64 = 5;
+
+/// Records a token request in the poll state `KEYS[1]` and returns 1 if it came sooner than the
+/// interval allows, which raises the interval by 5 seconds.
+/// ARGV: now, initial interval, lifetime of the state
+const RECORD_POLL_SCRIPT: &str = r"
+local now = tonumber(ARGV[1])
+local interval = tonumber(redis.call('HGET', KEYS[1], 'interval') or ARGV[2])
+local last_polled_at = redis.call('HGET', KEYS[1], 'last_polled_at')
+local too_fast = last_polled_at and now - tonumber(last_polled_at) < interval
+if too_fast then
+  interval = interval + 5
+end
+redis.call('HSET', KEYS[1], 'interval', interval, 'last_polled_at', now)
+redis.call('EXPIRE', KEYS[1], ARGV[3])
+if too_fast then
+  return 1
+end
+return 0
+";
+
+/// Replaces the grant `KEYS[1]` with ARGV[1] if it is still pending, keeping its expiry.
+/// Returns 1 if it was replaced. The status is tagged within the grant's `status` object.
+const DECIDE_GRANT_SCRIPT: &str = r"
+local grant = redis.call('GET', KEYS[1])
+if not grant or cjson.decode(grant).status.status ~= 'pending' then
+  return 0
+end
+redis.call('SET', KEYS[1], ARGV[1], 'KEEPTTL')
+return 1
+";
+
+pub struct DeviceGrantService {
+    redis_pool: bb8::Pool<RedisConnectionManager>,
+}
+
+impl DeviceGrantService {
+    pub fn new(redis_pool: bb8::Pool<RedisConnectionManager>) -> Self {
+        Self { redis_pool }
+    }
+
+    /// Start a device authorization, returns the device code and the pending grant
+    pub async fn create_grant(
+        &self,
+        client_id: &str,
+        scope: Option<String>,
+    ) -> Result<(String, DeviceGrantData), anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+        let device_code = random_token()?;
+
+        // User codes are short, reserve one that is not pending already
+        let mut user_code = generate_user_code()?;
+        loop {
+            let reserved: bool = redis::cmd("SET")
+                .arg(format!("device_user:{}", user_code))
+                .arg(&device_code)
+                .arg("NX")
+                .arg("EX")
+                .arg(DEVICE_CODE_LIFETIME)
+                .query_async::<Option<String>>(&mut *conn)
+                .await?
+                .is_some();
+            if reserved {
+                break;
+            }
+            user_code = generate_user_code()?;
+        }
+
+        let data = DeviceGrantData {
+            client_id: client_id.to_owned(),
+            scope,
+            user_code,
+            status: DeviceGrantStatus::Pending,
+            interval: POLLING_INTERVAL,
+        };
+        let serialized = serde_json::to_string(&data)?;
+        let _: () = conn
+            .set_ex(
+                format!("device:{}", device_code),
+                serialized,
+                DEVICE_CODE_LIFETIME,
+            )
+            .await?;
+
+        Ok((device_code, data))
+    }
+
+    pub async fn get_grant(
+        &self,
+        device_code: &str,
+    ) -> Result<Option<DeviceGrantData>, anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let data: Option<String> = conn.get(format!("device:{}", device_code)).await?;
+
+        match data {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Look up a grant by the normalized code the user entered
+    pub async fn get_grant_by_user_code(
+        &self,
+        user_code: &str,
+    ) -> Result<Option<(String, DeviceGrantData)>, anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let device_code: Option<String> = conn.get(format!("device_user:{}", user_code)).await?;
+        let Some(device_code) = device_code else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .get_grant(&device_code)
+            .await?
+            .map(|data| (device_code, data)))
+    }
+
+    /// Store the user's decision on a pending grant, it still expires when it was going to.
+    /// Returns `false` if the grant expired or was decided meanwhile.
+    pub async fn decide_grant(
+        &self,
+        device_code: &str,
+        data: &DeviceGrantData,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let serialized = serde_json::to_string(data)?;
+        let decided: i64 = redis::Script::new(DECIDE_GRANT_SCRIPT)
+            .key(format!("device:{}", device_code))
+            .arg(serialized)
+            .invoke_async(&mut *conn)
+            .await?;
+
+        Ok(decided == 1)
+    }
+
+    /// Record a token request of the device, returns whether it polled faster than allowed.
+    /// Polls are tracked apart from the grant, so they never overwrite the user's decision.
+    pub async fn record_poll(
+        &self,
+        device_code: &str,
+        data: &DeviceGrantData,
+    ) -> Result<bool, anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let too_fast: i64 = redis::Script::new(RECORD_POLL_SCRIPT)
+            .key(format!("device_poll:{}", device_code))
+            .arg(Utc::now().timestamp())
+            .arg(data.interval)
+            .arg(DEVICE_CODE_LIFETIME)
+            .invoke_async(&mut *conn)
+            .await?;
+
+        Ok(too_fast == 1)
+    }
+
+    /// Remove a grant once tokens were issued or the user denied it (one-time use)
+    pub async fn consume_grant(
+        &self,
+        device_code: &str,
+    ) -> Result<Option<DeviceGrantData>, anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let data: Option<String> = conn.get_del(format!("device:{}", device_code)).await?;
+        let Some(json) = data else {
+            return Ok(None);
+        };
+        let data: DeviceGrantData = serde_json::from_str(&json)?;
+        let _: () = conn
+            .del(&[
+                format!("device_user:{}", data.user_code),
+                format!("device_poll:{}", device_code),
+            ])
+            .await?;
+
+        Ok(Some(data))
+    }
+}
@@ -0,0 +1,75 @@
+use sqlx::{Pool, Postgres, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    models::directory::{DirectoryConfig, DirectoryUser},
+    utils::{
+        ldap_client::{DirectoryClient, LdapClient},
+        ldap_utils::{DirectoryCache, authenticate_cached, directory_user},
+    },
+};
+
+pub struct DirectoryService<C: DirectoryClient = LdapClient> {
+    db_pool: Pool<Postgres>,
+    client: C,
+    cache: DirectoryCache,
+}
+
+impl<C: DirectoryClient> DirectoryService<C> {
+    pub fn new(db_pool: Pool<Postgres>, client: C) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            db_pool,
+            client,
+            cache: DirectoryCache::new()?,
+        })
+    }
+
+    pub async fn get_directory(
+        &self,
+        tenant_id: Uuid,
+    ) -> Result<Option<DirectoryConfig>, anyhow::Error> {
+        let config = sqlx::query_scalar!(
+            r#"SELECT config AS "config: Json<DirectoryConfig>" FROM TenantDirectories WHERE tenant_id = $1"#,
+            tenant_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(config.map(|config| config.0))
+    }
+
+    /// The tenant directory responsible for the domain of an email address
+    pub async fn find_directory_for_email(
+        &self,
+        email: &str,
+    ) -> Result<Option<(Uuid, DirectoryConfig)>, anyhow::Error> {
+        let Some((_, domain)) = email.rsplit_once('@') else {
+            return Ok(None);
+        };
+
+        let directory = sqlx::query!(
+            r#"
+            SELECT tenant_id, config AS "config: Json<DirectoryConfig>"
+            FROM TenantDirectories
+            WHERE lower($1) = ANY(email_domains)
+            "#,
+            domain
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(directory.map(|directory| (directory.tenant_id, directory.config.0)))
+    }
+
+    /// Bind as the user and read its fields, `None` if the credentials are invalid
+    pub async fn authenticate(
+        &self,
+        config: &DirectoryConfig,
+        login: &str,
+        password: &str,
+    ) -> Result<Option<DirectoryUser>, anyhow::Error> {
+        let entry = authenticate_cached(&self.client, &self.cache, config, login, password).await?;
+
+        Ok(entry.map(|entry| directory_user(&entry, config, login)))
+    }
+}
@@ -0,0 +1,64 @@
+use bb8_redis::RedisConnectionManager;
+use redis::AsyncCommands;
+
+use crate::{
+    models::dpop::DpopProof,
+    utils::{dpop_utils::DPOP_PROOF_MAX_AGE, federation_utils::random_token},
+};
+
+/// How long a nonce provided to clients is accepted in their proofs
+pub const DPOP_NONCE_LIFETIME: u64 = 300;
+
+pub struct DpopService {
+    redis_pool: bb8::Pool<RedisConnectionManager>,
+    /// Whether proofs have to carry a nonce provided by the server (RFC 9449 8)
+    require_nonce: bool,
+}
+
+impl DpopService {
+    pub fn new(redis_pool: bb8::Pool<RedisConnectionManager>, require_nonce: bool) -> Self {
+        Self {
+            redis_pool,
+            require_nonce,
+        }
+    }
+
+    pub fn requires_nonce(&self) -> bool {
+        self.require_nonce
+    }
+
+    pub async fn issue_nonce(&self) -> Result<String, anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let nonce = random_token()?;
+        let _: () = conn
+            .set_ex(format!("dpop_nonce:{}", nonce), "", DPOP_NONCE_LIFETIME)
+            .await?;
+
+        Ok(nonce)
+    }
+
+    pub async fn is_valid_nonce(&self, nonce: &str) -> Result<bool, anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        Ok(conn.exists(format!("dpop_nonce:{}", nonce)).await?)
+    }
+
+    /// Remember the `jti` of a proof, `false` if the proof was used before (replay)
+    pub async fn record_proof(&self, proof: &DpopProof) -> Result<bool, anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        // A proof is accepted until its `iat` is too far in the past
+        let recorded = redis::cmd("SET")
+            .arg(format!("dpop_jti:{}:{}", proof.jkt, proof.jti))
+            .arg("")
+            .arg("NX")
+            .arg("EX")
+            .arg(2 * DPOP_PROOF_MAX_AGE)
+            .query_async::<Option<String>>(&mut *conn)
+            .await?
+            .is_some();
+
+        Ok(recorded)
+    }
+}
@@ -0,0 +1,259 @@
+use bb8_redis::RedisConnectionManager;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header, jwk::JwkSet};
+use redis::AsyncCommands;
+use serde_json::{Map, Value};
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use crate::models::identity_provider::{
+    FederationState, IdentityProviderInfo, IdentityProviderSQL, ProviderMetadata,
+    UpstreamTokenResponse,
+};
+
+/// Signature algorithms accepted for upstream ID tokens
+const ALLOWED_ALGORITHMS: [Algorithm; 6] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::ES256,
+    Algorithm::ES384,
+];
+
+pub struct FederationService {
+    db_pool: Pool<Postgres>,
+    redis_pool: bb8::Pool<RedisConnectionManager>,
+    http_client: reqwest::Client,
+    /// Externally reachable base URL of this server, for upstream redirect URIs
+    public_url: String,
+}
+
+impl FederationService {
+    pub fn new(
+        db_pool: Pool<Postgres>,
+        redis_pool: bb8::Pool<RedisConnectionManager>,
+        public_url: String,
+    ) -> Self {
+        Self {
+            db_pool,
+            redis_pool,
+            http_client: reqwest::Client::new(),
+            public_url,
+        }
+    }
+
+    /// The callback URL registered at the upstream provider
+    pub fn redirect_uri(&self, provider_id: &str) -> String {
+        format!(
+            "{}/oauth/federation/{}/callback",
+            self.public_url.trim_end_matches('/'),
+            provider_id
+        )
+    }
+
+    pub async fn get_provider(
+        &self,
+        provider_id: &str,
+    ) -> Result<Option<IdentityProviderSQL>, anyhow::Error> {
+        let provider = sqlx::query_as!(
+            IdentityProviderSQL,
+            r#"
+            SELECT id, tenant_id, discovery_url, client_id, client_secret, scopes,
+                   claim_mapping AS "claim_mapping: _", auto_provision, link_by_email
+            FROM IdentityProviders
+            WHERE id = $1
+            "#,
+            provider_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(provider)
+    }
+
+    /// Providers of the tenant the application `client_id` belongs to
+    pub async fn list_providers_for_client(
+        &self,
+        client_id: &str,
+    ) -> Result<Vec<IdentityProviderInfo>, anyhow::Error> {
+        let providers = sqlx::query_as!(
+            IdentityProviderInfo,
+            r#"
+            SELECT p.id, p.name
+            FROM IdentityProviders p
+            JOIN Applications a ON a.tenant_id = p.tenant_id
+            WHERE a.client_id = $1
+            ORDER BY p.name
+            "#,
+            client_id
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(providers)
+    }
+
+    pub async fn discover(
+        &self,
+        provider: &IdentityProviderSQL,
+    ) -> Result<ProviderMetadata, anyhow::Error> {
+        let metadata = self
+            .http_client
+            .get(&provider.discovery_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(metadata)
+    }
+
+    /// Remember a login redirected to an upstream provider until its callback
+    pub async fn store_state(
+        &self,
+        state: &str,
+        data: &FederationState,
+        ttl_seconds: u64,
+    ) -> Result<(), anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let key = format!("fed:{}", state);
+        let serialized = serde_json::to_string(data)?;
+        let _: () = conn.set_ex(key, serialized, ttl_seconds).await?;
+
+        Ok(())
+    }
+
+    /// Consume the login state of a callback (one-time use)
+    pub async fn consume_state(
+        &self,
+        state: &str,
+    ) -> Result<Option<FederationState>, anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let key = format!("fed:{}", state);
+        let data: Option<String> = conn.get_del(key).await?;
+
+        match data {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Redeem an upstream authorization code and return the verified ID token claims
+    pub async fn exchange_code(
+        &self,
+        provider: &IdentityProviderSQL,
+        metadata: &ProviderMetadata,
+        code: &str,
+        redirect_uri: &str,
+        state: &FederationState,
+    ) -> Result<Map<String, Value>, anyhow::Error> {
+        let token_response: UpstreamTokenResponse = self
+            .http_client
+            .post(&metadata.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect_uri),
+                ("client_id", &provider.client_id),
+                ("client_secret", &provider.client_secret),
+                ("code_verifier", &state.code_verifier),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let claims = self
+            .verify_id_token(provider, metadata, &token_response.id_token)
+            .await?;
+
+        if claims.get("nonce").and_then(Value::as_str) != Some(state.nonce.as_str()) {
+            return Err(anyhow::anyhow!("Upstream ID token nonce mismatch"));
+        }
+
+        Ok(claims)
+    }
+
+    async fn verify_id_token(
+        &self,
+        provider: &IdentityProviderSQL,
+        metadata: &ProviderMetadata,
+        id_token: &str,
+    ) -> Result<Map<String, Value>, anyhow::Error> {
+        let header = decode_header(id_token)?;
+        if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+            return Err(anyhow::anyhow!(
+                "Unsupported upstream ID token algorithm {:?}",
+                header.alg
+            ));
+        }
+
+        let jwks: JwkSet = self
+            .http_client
+            .get(&metadata.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None => jwks.keys.first(),
+        }
+        .ok_or_else(|| anyhow::anyhow!("No upstream signing key found"))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[provider.client_id.as_str()]);
+        validation.set_issuer(&[metadata.issuer.as_str()]);
+
+        let token_data =
+            decode::<Map<String, Value>>(id_token, &DecodingKey::from_jwk(jwk)?, &validation)?;
+
+        Ok(token_data.claims)
+    }
+
+    /// The user linked to an upstream subject
+    pub async fn find_linked_user(
+        &self,
+        provider_id: &str,
+        subject: &str,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let user_id = sqlx::query_scalar!(
+            r#"
+            SELECT user_id::text AS "user_id!"
+            FROM FederatedIdentities
+            WHERE provider_id = $1 AND subject = $2
+            "#,
+            provider_id,
+            subject
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(user_id)
+    }
+
+    pub async fn link_user(
+        &self,
+        provider_id: &str,
+        subject: &str,
+        user_id: &str,
+    ) -> Result<(), anyhow::Error> {
+        let user_uuid = Uuid::parse_str(user_id)?;
+
+        sqlx::query!(
+            "INSERT INTO FederatedIdentities (provider_id, subject, user_id) VALUES ($1, $2, $3)",
+            provider_id,
+            subject,
+            user_uuid
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(())
+    }
+}
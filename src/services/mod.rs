@@ -1,5 +1,21 @@
+pub mod api_resource_service;
 pub mod application_service;
+pub mod attribute_service;
 pub mod authorize_code_service;
+pub mod branding_service;
+pub mod client_registration_service;
 pub mod config;
+pub mod device_grant_service;
+pub mod directory_service;
+pub mod dpop_service;
+pub mod federation_service;
+pub mod mail_service;
+pub mod mtls_service;
+pub mod password_reset_service;
+pub mod pushed_request_service;
+pub mod refresh_token_service;
+pub mod saml_service;
+pub mod scim_service;
 pub mod session_service;
+pub mod subject_service;
 pub mod user_service;
@@ -0,0 +1,46 @@
+use openssl::{
+    stack::Stack,
+    x509::{
+        X509, X509StoreContext,
+        store::{X509Store, X509StoreBuilder},
+    },
+};
+
+pub struct MtlsService {
+    /// CAs whose certificates `tls_client_auth` applications can authenticate with
+    trusted_roots: Option<X509Store>,
+}
+
+impl MtlsService {
+    pub fn new(ca_path: Option<String>) -> Result<Self, anyhow::Error> {
+        let trusted_roots = match ca_path {
+            Some(ca_path) => {
+                let mut builder = X509StoreBuilder::new()?;
+                for certificate in X509::stack_from_pem(&std::fs::read(ca_path)?)? {
+                    builder.add_cert(certificate)?;
+                }
+                Some(builder.build())
+            }
+            None => None,
+        };
+
+        Ok(Self { trusted_roots })
+    }
+
+    /// Whether the certificate was issued by a trusted CA (PKI method, RFC 8705 2.1)
+    pub fn is_trusted(&self, certificate: &X509) -> bool {
+        let Some(trusted_roots) = &self.trusted_roots else {
+            return false;
+        };
+
+        let verify = || -> Result<bool, anyhow::Error> {
+            let mut context = X509StoreContext::new()?;
+            let chain = Stack::new()?;
+            Ok(context.init(trusted_roots, certificate, &chain, |context| {
+                context.verify_cert()
+            })?)
+        };
+
+        verify().unwrap_or(false)
+    }
+}
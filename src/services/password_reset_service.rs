@@ -0,0 +1,37 @@
+use bb8_redis::RedisConnectionManager;
+use redis::AsyncCommands;
+
+pub struct PasswordResetService {
+    redis_pool: bb8::Pool<RedisConnectionManager>,
+}
+
+impl PasswordResetService {
+    pub fn new(redis_pool: bb8::Pool<RedisConnectionManager>) -> Self {
+        Self { redis_pool }
+    }
+
+    /// Store a password reset token for a user with TTL.
+    pub async fn store_token(
+        &self,
+        token: &str,
+        user_id: &str,
+        ttl_seconds: u64,
+    ) -> Result<(), anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let key = format!("pwreset:{}", token);
+        let _: () = conn.set_ex(key, user_id, ttl_seconds).await?;
+
+        Ok(())
+    }
+
+    /// Consume a reset token (one-time use) and return the user it was issued for.
+    pub async fn consume_token(&self, token: &str) -> Result<Option<String>, anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let key = format!("pwreset:{}", token);
+        let user_id: Option<String> = conn.get_del(key).await?;
+
+        Ok(user_id)
+    }
+}
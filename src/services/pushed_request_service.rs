@@ -0,0 +1,89 @@
+use bb8_redis::RedisConnectionManager;
+use redis::AsyncCommands;
+
+use crate::{
+    models::{authorize_request::AuthorizeRequest, pushed_request::REQUEST_URI_PREFIX},
+    utils::federation_utils::random_token,
+};
+
+/// How long a pushed request can be used, including the login it may lead to
+pub const PUSHED_REQUEST_LIFETIME: u64 = 300;
+
+pub struct PushedRequestService {
+    redis_pool: bb8::Pool<RedisConnectionManager>,
+}
+
+impl PushedRequestService {
+    pub fn new(redis_pool: bb8::Pool<RedisConnectionManager>) -> Self {
+        Self { redis_pool }
+    }
+
+    /// Store a validated authorization request, returns the `request_uri` referring to it
+    pub async fn store_request(&self, request: &AuthorizeRequest) -> Result<String, anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let reference = random_token()?;
+        let serialized = serde_json::to_string(request)?;
+        let _: () = conn
+            .set_ex(
+                format!("par:{}", reference),
+                serialized,
+                PUSHED_REQUEST_LIFETIME,
+            )
+            .await?;
+
+        Ok(format!("{}{}", REQUEST_URI_PREFIX, reference))
+    }
+
+    pub async fn get_request(
+        &self,
+        request_uri: &str,
+    ) -> Result<Option<AuthorizeRequest>, anyhow::Error> {
+        let Some(reference) = request_uri.strip_prefix(REQUEST_URI_PREFIX) else {
+            return Ok(None);
+        };
+        let mut conn = self.redis_pool.get().await?;
+
+        let data: Option<String> = conn.get(format!("par:{}", reference)).await?;
+
+        match data {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Replace a pushed request, it still expires when it was going to
+    pub async fn update_request(
+        &self,
+        request_uri: &str,
+        request: &AuthorizeRequest,
+    ) -> Result<(), anyhow::Error> {
+        let Some(reference) = request_uri.strip_prefix(REQUEST_URI_PREFIX) else {
+            return Ok(());
+        };
+        let mut conn = self.redis_pool.get().await?;
+
+        let serialized = serde_json::to_string(request)?;
+        let _: Option<String> = redis::cmd("SET")
+            .arg(format!("par:{}", reference))
+            .arg(serialized)
+            .arg("XX")
+            .arg("KEEPTTL")
+            .query_async(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Remove a pushed request once a code was issued for it (one-time use)
+    pub async fn delete_request(&self, request_uri: &str) -> Result<(), anyhow::Error> {
+        let Some(reference) = request_uri.strip_prefix(REQUEST_URI_PREFIX) else {
+            return Ok(());
+        };
+        let mut conn = self.redis_pool.get().await?;
+
+        let _: () = conn.del(format!("par:{}", reference)).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use bb8_redis::RedisConnectionManager;
+use redis::AsyncCommands;
+
+use crate::models::session::RefreshTokenData;
+
+pub struct RefreshTokenService {
+    redis_pool: bb8::Pool<RedisConnectionManager>,
+}
+
+impl RefreshTokenService {
+    pub fn new(redis_pool: bb8::Pool<RedisConnectionManager>) -> Self {
+        Self { redis_pool }
+    }
+
+    /// Store an issued refresh token by its `jti` and index it under its session and user
+    pub async fn store_token(
+        &self,
+        jti: &str,
+        data: &RefreshTokenData,
+        ttl_seconds: u64,
+    ) -> Result<(), anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let key = format!("rt:{}", jti);
+        let serialized = serde_json::to_string(data)?;
+
+        let mut index_keys = vec![format!("user_rt:{}", data.user_id)];
+        if let Some(session_id) = &data.session_id {
+            index_keys.push(format!("sess_rt:{}", session_id));
+        }
+
+        // Applications have their own refresh token lifetimes, an index lives as long as the
+        // longest token it contains
+        let mut pipe = redis::pipe();
+        pipe.set_ex(key, serialized, ttl_seconds);
+        for index_key in &index_keys {
+            pipe.sadd(index_key, jti)
+                .cmd("EXPIRE")
+                .arg(index_key)
+                .arg(ttl_seconds)
+                .arg("NX")
+                .cmd("EXPIRE")
+                .arg(index_key)
+                .arg(ttl_seconds)
+                .arg("GT");
+        }
+        let _: () = pipe.query_async(&mut *conn).await?;
+
+        Ok(())
+    }
+
+    /// Look up a refresh token without consuming it
+    pub async fn get_token(&self, jti: &str) -> Result<Option<RefreshTokenData>, anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let key = format!("rt:{}", jti);
+        let data: Option<String> = conn.get(key).await?;
+
+        match data {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Consume a refresh token (one-time use, a new one is issued on every refresh)
+    pub async fn consume_token(
+        &self,
+        jti: &str,
+    ) -> Result<Option<RefreshTokenData>, anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let key = format!("rt:{}", jti);
+        let data: Option<String> = conn.get_del(key).await?;
+
+        match data {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Revoke all refresh tokens that were issued from a session
+    pub async fn revoke_session_tokens(&self, session_id: &str) -> Result<(), anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let index_key = format!("sess_rt:{}", session_id);
+        let jtis: Vec<String> = conn.smembers(&index_key).await?;
+
+        for jti in jtis {
+            let _: () = conn.del(format!("rt:{}", jti)).await?;
+        }
+        let _: () = conn.del(index_key).await?;
+
+        Ok(())
+    }
+
+    /// Revoke all refresh tokens of a user, including those not issued from a session
+    pub async fn revoke_user_tokens(&self, user_id: &str) -> Result<(), anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let index_key = format!("user_rt:{}", user_id);
+        let jtis: Vec<String> = conn.smembers(&index_key).await?;
+
+        for jti in jtis {
+            let _: () = conn.del(format!("rt:{}", jti)).await?;
+        }
+        let _: () = conn.del(index_key).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::utils::redis_utils::test_redis_pool;
+
+    fn token_data(user_id: &str, session_id: &str) -> RefreshTokenData {
+        RefreshTokenData {
+            user_id: user_id.to_owned(),
+            client_id: "app".to_owned(),
+            session_id: Some(session_id.to_owned()),
+            scope: Some("openid".to_owned()),
+            resource: None,
+            jkt: None,
+            x5t_s256: None,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn test_short_lived_token_keeps_longer_tokens_revocable() {
+        let pool = test_redis_pool().await;
+        let service = RefreshTokenService::new(pool.clone());
+        let (user_id, session_id) = (Uuid::new_v4().to_string(), Uuid::new_v4().to_string());
+        let (long_jti, short_jti) = (Uuid::new_v4().to_string(), Uuid::new_v4().to_string());
+
+        // An application with a long refresh lifetime, then one with a short lifetime
+        let data = token_data(&user_id, &session_id);
+        service.store_token(&long_jti, &data, 3600).await.unwrap();
+        service.store_token(&short_jti, &data, 60).await.unwrap();
+
+        let mut conn = pool.get().await.unwrap();
+        for index_key in [
+            format!("user_rt:{user_id}"),
+            format!("sess_rt:{session_id}"),
+        ] {
+            let ttl: i64 = conn.ttl(&index_key).await.unwrap();
+            assert!(ttl > 60, "{index_key} expires in {ttl}s");
+        }
+
+        service.revoke_user_tokens(&user_id).await.unwrap();
+        assert!(service.get_token(&long_jti).await.unwrap().is_none());
+        assert!(service.get_token(&short_jti).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn test_revoke_session_tokens() {
+        let service = RefreshTokenService::new(test_redis_pool().await);
+        let (user_id, session_id) = (Uuid::new_v4().to_string(), Uuid::new_v4().to_string());
+        let (jti, other_jti) = (Uuid::new_v4().to_string(), Uuid::new_v4().to_string());
+
+        service
+            .store_token(&jti, &token_data(&user_id, &session_id), 3600)
+            .await
+            .unwrap();
+        let other_session = Uuid::new_v4().to_string();
+        service
+            .store_token(&other_jti, &token_data(&user_id, &other_session), 3600)
+            .await
+            .unwrap();
+
+        service.revoke_session_tokens(&session_id).await.unwrap();
+        assert!(service.get_token(&jti).await.unwrap().is_none());
+        assert!(service.get_token(&other_jti).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,57 @@
+use sqlx::{Pool, Postgres};
+
+use crate::models::saml::SamlServiceProviderSQL;
+
+pub struct SamlService {
+    db_pool: Pool<Postgres>,
+}
+
+impl SamlService {
+    pub fn new(db_pool: Pool<Postgres>) -> Self {
+        Self { db_pool }
+    }
+
+    /// Service provider sending an AuthnRequest with `entity_id` as issuer
+    pub async fn get_by_entity_id(
+        &self,
+        entity_id: &str,
+    ) -> Result<Option<SamlServiceProviderSQL>, anyhow::Error> {
+        let service_provider = sqlx::query_as!(
+            SamlServiceProviderSQL,
+            r#"
+            SELECT a.tenant_id, a.client_id, s.entity_id, s.acs_urls, s.name_id_format,
+                   s.attributes AS "attributes: _"
+            FROM SamlServiceProviders s
+            JOIN Applications a ON a.id = s.application_id
+            WHERE s.entity_id = $1
+            "#,
+            entity_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(service_provider)
+    }
+
+    /// SAML settings of the application `client_id`, for IdP-initiated logins
+    pub async fn get_by_client_id(
+        &self,
+        client_id: &str,
+    ) -> Result<Option<SamlServiceProviderSQL>, anyhow::Error> {
+        let service_provider = sqlx::query_as!(
+            SamlServiceProviderSQL,
+            r#"
+            SELECT a.tenant_id, a.client_id, s.entity_id, s.acs_urls, s.name_id_format,
+                   s.attributes AS "attributes: _"
+            FROM SamlServiceProviders s
+            JOIN Applications a ON a.id = s.application_id
+            WHERE a.client_id = $1
+            "#,
+            client_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(service_provider)
+    }
+}
@@ -0,0 +1,639 @@
+use std::collections::BTreeSet;
+
+use sqlx::{Pool, Postgres, Transaction, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    models::scim::{
+        GROUP_SCHEMA, ScimGroup, ScimGroupSQL, ScimLookup, ScimMeta, ScimMultiValue, ScimName,
+        ScimReference, ScimUser, ScimUserSQL, USER_SCHEMA,
+    },
+    utils::scim_utils::{ScimError, hash_token, primary_value, scim_datetime},
+};
+
+/// Users and roles of a tenant as SCIM resources
+pub struct ScimService {
+    db_pool: Pool<Postgres>,
+    base_url: String,
+}
+
+/// Columns a SCIM user is stored in
+struct UserColumns<'a> {
+    email: &'a str,
+    name: Option<&'a str>,
+    given_name: Option<&'a str>,
+    family_name: Option<&'a str>,
+    middle_name: Option<&'a str>,
+    phone_number: Option<&'a str>,
+}
+
+impl<'a> UserColumns<'a> {
+    fn from_user(user: &'a ScimUser) -> Result<Self, ScimError> {
+        // Users need an email, user names often are one
+        let email = primary_value(&user.emails)
+            .or_else(|| {
+                user.user_name
+                    .contains('@')
+                    .then_some(user.user_name.as_str())
+            })
+            .ok_or_else(|| ScimError::InvalidValue("emails is required".to_string()))?;
+        let name = user.name.as_ref();
+
+        Ok(Self {
+            email,
+            name: user
+                .display_name
+                .as_deref()
+                .or_else(|| name.and_then(|name| name.formatted.as_deref())),
+            given_name: name.and_then(|name| name.given_name.as_deref()),
+            family_name: name.and_then(|name| name.family_name.as_deref()),
+            middle_name: name.and_then(|name| name.middle_name.as_deref()),
+            phone_number: primary_value(&user.phone_numbers),
+        })
+    }
+}
+
+impl ScimService {
+    pub fn new(db_pool: Pool<Postgres>, public_url: String) -> Self {
+        Self {
+            db_pool,
+            base_url: format!("{}/oauth/scim/v2", public_url.trim_end_matches('/')),
+        }
+    }
+
+    /// Absolute URL of an endpoint relative to the SCIM base URL
+    pub fn location(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path)
+    }
+
+    /// Tenant a provisioning token was issued for
+    pub async fn get_tenant_for_token(&self, token: &str) -> Result<Option<Uuid>, anyhow::Error> {
+        let tenant_id = sqlx::query_scalar!(
+            "SELECT tenant_id FROM ScimTokens WHERE token_hash = $1",
+            hash_token(token)
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(tenant_id)
+    }
+
+    /// A page of the users matching `lookup` and the number of all matching users,
+    /// all of them without `limit`
+    pub async fn list_users(
+        &self,
+        tenant_id: Uuid,
+        lookup: &ScimLookup,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<(Vec<ScimUser>, usize), anyhow::Error> {
+        let total_results = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM Users u
+            WHERE u.tenant_id = $1
+              AND ($2::TEXT IS NULL OR u.id::TEXT = lower($2))
+              AND ($3::TEXT IS NULL OR u.external_id = $3)
+              AND ($4::TEXT IS NULL OR lower(u.username) = lower($4))
+              AND ($5::TEXT IS NULL OR lower(u.email) = lower($5))
+            "#,
+            tenant_id,
+            lookup.id,
+            lookup.external_id,
+            lookup.name,
+            lookup.email
+        )
+        .fetch_one(&self.db_pool)
+        .await?;
+        let users = self.query_users(tenant_id, lookup, offset, limit).await?;
+
+        Ok((users, total_results as usize))
+    }
+
+    pub async fn get_user(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<ScimUser>, anyhow::Error> {
+        let lookup = ScimLookup {
+            id: Some(user_id.to_string()),
+            ..Default::default()
+        };
+        Ok(self
+            .query_users(tenant_id, &lookup, 0, Some(1))
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    async fn query_users(
+        &self,
+        tenant_id: Uuid,
+        lookup: &ScimLookup,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<ScimUser>, anyhow::Error> {
+        let rows = sqlx::query_as!(
+            ScimUserSQL,
+            r#"
+            SELECT u.id, u.external_id, u.username, u.email, u.name, u.given_name, u.family_name,
+                   u.middle_name, u.nickname, u.locale, u.zoneinfo, u.phone_number, u.is_active,
+                   COALESCE(EXTRACT(EPOCH FROM u.created_at), 0)::BIGINT AS "created_at!",
+                   COALESCE(EXTRACT(EPOCH FROM u.updated_at), 0)::BIGINT AS "updated_at!",
+                   COALESCE(
+                       (SELECT json_agg(json_build_object('value', r.id, 'display', r.name) ORDER BY r.name)
+                        FROM UserRoles ur
+                        JOIN Roles r ON r.id = ur.role_id
+                        WHERE ur.user_id = u.id),
+                       '[]'::json
+                   ) AS "groups!: Json<Vec<ScimReference>>"
+            FROM Users u
+            WHERE u.tenant_id = $1
+              AND ($2::TEXT IS NULL OR u.id::TEXT = lower($2))
+              AND ($3::TEXT IS NULL OR u.external_id = $3)
+              AND ($4::TEXT IS NULL OR lower(u.username) = lower($4))
+              AND ($5::TEXT IS NULL OR lower(u.email) = lower($5))
+            ORDER BY u.created_at, u.id
+            OFFSET $6 LIMIT $7
+            "#,
+            tenant_id,
+            lookup.id,
+            lookup.external_id,
+            lookup.name,
+            lookup.email,
+            offset as i64,
+            limit.map(|limit| limit as i64)
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| self.user_resource(row))
+            .collect())
+    }
+
+    fn user_resource(&self, row: ScimUserSQL) -> ScimUser {
+        let id = row.id.to_string();
+        let has_name = row.name.is_some()
+            || row.given_name.is_some()
+            || row.family_name.is_some()
+            || row.middle_name.is_some();
+
+        ScimUser {
+            schemas: vec![USER_SCHEMA.to_string()],
+            external_id: row.external_id,
+            user_name: row.username,
+            name: has_name.then(|| ScimName {
+                formatted: row.name.clone(),
+                given_name: row.given_name,
+                family_name: row.family_name,
+                middle_name: row.middle_name,
+            }),
+            display_name: row.name,
+            nick_name: row.nickname,
+            locale: row.locale,
+            timezone: row.zoneinfo,
+            active: Some(row.is_active),
+            password: None,
+            emails: vec![ScimMultiValue {
+                value: row.email,
+                kind: Some("work".to_string()),
+                primary: Some(true),
+            }],
+            phone_numbers: row
+                .phone_number
+                .map(|phone_number| ScimMultiValue {
+                    value: phone_number,
+                    kind: Some("work".to_string()),
+                    primary: Some(true),
+                })
+                .into_iter()
+                .collect(),
+            groups: row
+                .groups
+                .0
+                .into_iter()
+                .map(|group| ScimReference {
+                    reference: Some(self.location(&format!("Groups/{}", group.value))),
+                    ..group
+                })
+                .collect(),
+            meta: Some(ScimMeta {
+                resource_type: "User".to_string(),
+                created: scim_datetime(row.created_at),
+                last_modified: scim_datetime(row.updated_at),
+                location: self.location(&format!("Users/{id}")),
+            }),
+            id: Some(id),
+        }
+    }
+
+    /// Provision a user together with the hash of its password, if it has one
+    pub async fn create_user(
+        &self,
+        tenant_id: Uuid,
+        user: &ScimUser,
+        password_hash: Option<&str>,
+    ) -> Result<Uuid, anyhow::Error> {
+        let columns = UserColumns::from_user(user)?;
+        let user_id = Uuid::new_v4();
+        let mut transaction = self.db_pool.begin().await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO Users (id, tenant_id, username, email, password_hash, external_id, name,
+                               given_name, family_name, middle_name, nickname, locale, zoneinfo,
+                               phone_number, is_active)
+            VALUES ($1, $2, $3, $4, COALESCE($15, ''), $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            "#,
+            user_id,
+            tenant_id,
+            user.user_name,
+            columns.email,
+            user.external_id,
+            columns.name,
+            columns.given_name,
+            columns.family_name,
+            columns.middle_name,
+            user.nick_name,
+            user.locale,
+            user.timezone,
+            columns.phone_number,
+            user.active.unwrap_or(true),
+            password_hash,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(unique_violation)?;
+        if let Some(password_hash) = password_hash {
+            record_password(&mut transaction, user_id, password_hash).await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(user_id)
+    }
+
+    /// Replace all attributes of a user and, if given, the hash of its password,
+    /// `false` if the tenant has no such user
+    pub async fn replace_user(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        user: &ScimUser,
+        password_hash: Option<&str>,
+    ) -> Result<bool, anyhow::Error> {
+        let columns = UserColumns::from_user(user)?;
+        let mut transaction = self.db_pool.begin().await?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE Users SET
+                username = $3,
+                email = $4,
+                external_id = $5,
+                name = $6,
+                given_name = $7,
+                family_name = $8,
+                middle_name = $9,
+                nickname = $10,
+                locale = $11,
+                zoneinfo = $12,
+                phone_number = $13,
+                is_active = $14,
+                password_hash = COALESCE($15, password_hash),
+                password_changed_at = CASE WHEN $15 IS NULL THEN password_changed_at
+                                           ELSE CURRENT_TIMESTAMP END,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND tenant_id = $2
+            "#,
+            user_id,
+            tenant_id,
+            user.user_name,
+            columns.email,
+            user.external_id,
+            columns.name,
+            columns.given_name,
+            columns.family_name,
+            columns.middle_name,
+            user.nick_name,
+            user.locale,
+            user.timezone,
+            columns.phone_number,
+            user.active.unwrap_or(true),
+            password_hash,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(unique_violation)?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        if let Some(password_hash) = password_hash {
+            record_password(&mut transaction, user_id, password_hash).await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+
+    pub async fn delete_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<bool, anyhow::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM Users WHERE id = $1 AND tenant_id = $2",
+            user_id,
+            tenant_id
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// A page of the groups matching `lookup` and the number of all matching groups,
+    /// all of them without `limit`
+    pub async fn list_groups(
+        &self,
+        tenant_id: Uuid,
+        lookup: &ScimLookup,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<(Vec<ScimGroup>, usize), anyhow::Error> {
+        let total_results = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM Roles r
+            WHERE r.tenant_id = $1
+              AND ($2::TEXT IS NULL OR r.id::TEXT = lower($2))
+              AND ($3::TEXT IS NULL OR r.external_id = $3)
+              AND ($4::TEXT IS NULL OR lower(r.name) = lower($4))
+            "#,
+            tenant_id,
+            lookup.id,
+            lookup.external_id,
+            lookup.name
+        )
+        .fetch_one(&self.db_pool)
+        .await?;
+        let groups = self.query_groups(tenant_id, lookup, offset, limit).await?;
+
+        Ok((groups, total_results as usize))
+    }
+
+    pub async fn get_group(
+        &self,
+        tenant_id: Uuid,
+        group_id: Uuid,
+    ) -> Result<Option<ScimGroup>, anyhow::Error> {
+        let lookup = ScimLookup {
+            id: Some(group_id.to_string()),
+            ..Default::default()
+        };
+        Ok(self
+            .query_groups(tenant_id, &lookup, 0, Some(1))
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    async fn query_groups(
+        &self,
+        tenant_id: Uuid,
+        lookup: &ScimLookup,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<ScimGroup>, anyhow::Error> {
+        let rows = sqlx::query_as!(
+            ScimGroupSQL,
+            r#"
+            SELECT r.id, r.external_id, r.name,
+                   COALESCE(EXTRACT(EPOCH FROM r.created_at), 0)::BIGINT AS "created_at!",
+                   COALESCE(EXTRACT(EPOCH FROM r.updated_at), 0)::BIGINT AS "updated_at!",
+                   COALESCE(
+                       (SELECT json_agg(json_build_object('value', u.id, 'display', u.username) ORDER BY u.username)
+                        FROM UserRoles ur
+                        JOIN Users u ON u.id = ur.user_id
+                        WHERE ur.role_id = r.id),
+                       '[]'::json
+                   ) AS "members!: Json<Vec<ScimReference>>"
+            FROM Roles r
+            WHERE r.tenant_id = $1
+              AND ($2::TEXT IS NULL OR r.id::TEXT = lower($2))
+              AND ($3::TEXT IS NULL OR r.external_id = $3)
+              AND ($4::TEXT IS NULL OR lower(r.name) = lower($4))
+            ORDER BY r.name, r.id
+            OFFSET $5 LIMIT $6
+            "#,
+            tenant_id,
+            lookup.id,
+            lookup.external_id,
+            lookup.name,
+            offset as i64,
+            limit.map(|limit| limit as i64)
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| self.group_resource(row))
+            .collect())
+    }
+
+    fn group_resource(&self, row: ScimGroupSQL) -> ScimGroup {
+        let id = row.id.to_string();
+
+        ScimGroup {
+            schemas: vec![GROUP_SCHEMA.to_string()],
+            external_id: row.external_id,
+            display_name: row.name,
+            members: row
+                .members
+                .0
+                .into_iter()
+                .map(|member| ScimReference {
+                    reference: Some(self.location(&format!("Users/{}", member.value))),
+                    ..member
+                })
+                .collect(),
+            meta: Some(ScimMeta {
+                resource_type: "Group".to_string(),
+                created: scim_datetime(row.created_at),
+                last_modified: scim_datetime(row.updated_at),
+                location: self.location(&format!("Groups/{id}")),
+            }),
+            id: Some(id),
+        }
+    }
+
+    pub async fn create_group(
+        &self,
+        tenant_id: Uuid,
+        group: &ScimGroup,
+    ) -> Result<Uuid, anyhow::Error> {
+        let group_id = Uuid::new_v4();
+        let mut transaction = self.db_pool.begin().await?;
+
+        check_group_name(&mut transaction, tenant_id, group_id, &group.display_name).await?;
+        sqlx::query!(
+            "INSERT INTO Roles (id, tenant_id, name, external_id) VALUES ($1, $2, $3, $4)",
+            group_id,
+            tenant_id,
+            group.display_name,
+            group.external_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+        set_members(&mut transaction, tenant_id, group_id, &group.members).await?;
+
+        transaction.commit().await?;
+
+        Ok(group_id)
+    }
+
+    /// Replace the name and members of a group, `false` if the tenant has no such group
+    pub async fn replace_group(
+        &self,
+        tenant_id: Uuid,
+        group_id: Uuid,
+        group: &ScimGroup,
+    ) -> Result<bool, anyhow::Error> {
+        let mut transaction = self.db_pool.begin().await?;
+
+        check_group_name(&mut transaction, tenant_id, group_id, &group.display_name).await?;
+        let result = sqlx::query!(
+            r#"
+            UPDATE Roles SET name = $3, external_id = $4, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND tenant_id = $2
+            "#,
+            group_id,
+            tenant_id,
+            group.display_name,
+            group.external_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        set_members(&mut transaction, tenant_id, group_id, &group.members).await?;
+
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+
+    pub async fn delete_group(
+        &self,
+        tenant_id: Uuid,
+        group_id: Uuid,
+    ) -> Result<bool, anyhow::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM Roles WHERE id = $1 AND tenant_id = $2",
+            group_id,
+            tenant_id
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Add a password to the user's history, the password policy checks new ones against it
+async fn record_password(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    password_hash: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        "INSERT INTO PasswordHistory (id, user_id, password_hash) VALUES ($1, $2, $3)",
+        Uuid::new_v4(),
+        user_id,
+        password_hash
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Group names are unique per tenant, case-insensitively
+async fn check_group_name(
+    transaction: &mut Transaction<'_, Postgres>,
+    tenant_id: Uuid,
+    group_id: Uuid,
+    name: &str,
+) -> Result<(), anyhow::Error> {
+    let taken = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM Roles WHERE tenant_id = $1 AND lower(name) = lower($2) AND id <> $3
+        ) AS "taken!"
+        "#,
+        tenant_id,
+        name,
+        group_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+
+    if taken {
+        return Err(ScimError::Uniqueness("displayName").into());
+    }
+    Ok(())
+}
+
+/// Replace the members of a group, all of which must be users of the tenant
+async fn set_members(
+    transaction: &mut Transaction<'_, Postgres>,
+    tenant_id: Uuid,
+    group_id: Uuid,
+    members: &[ScimReference],
+) -> Result<(), anyhow::Error> {
+    let user_ids = members
+        .iter()
+        .map(|member| {
+            Uuid::parse_str(&member.value)
+                .map_err(|_| ScimError::InvalidValue(format!("Unknown member {}", member.value)))
+        })
+        .collect::<Result<BTreeSet<Uuid>, _>>()?;
+    let user_ids: Vec<Uuid> = user_ids.into_iter().collect();
+
+    sqlx::query!("DELETE FROM UserRoles WHERE role_id = $1", group_id)
+        .execute(&mut **transaction)
+        .await?;
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO UserRoles (user_id, role_id)
+        SELECT id, $1 FROM Users WHERE tenant_id = $2 AND id = ANY($3)
+        "#,
+        group_id,
+        tenant_id,
+        &user_ids
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    if result.rows_affected() != user_ids.len() as u64 {
+        return Err(
+            ScimError::InvalidValue("Members must be users of the tenant".to_string()).into(),
+        );
+    }
+    Ok(())
+}
+
+fn unique_violation(e: sqlx::Error) -> anyhow::Error {
+    match &e {
+        sqlx::Error::Database(database_error) if database_error.is_unique_violation() => {
+            match database_error.constraint() {
+                Some(constraint) if constraint.contains("email") => {
+                    ScimError::Uniqueness("emails").into()
+                }
+                _ => ScimError::Uniqueness("userName").into(),
+            }
+        }
+        _ => e.into(),
+    }
+}
@@ -1,24 +1,118 @@
 use bb8_redis::RedisConnectionManager;
+use chrono::Utc;
 use redis::AsyncCommands;
 
 use crate::models::session::SessionData;
 
+/// How often a change is retried when other requests keep changing the session meanwhile
+const UPDATE_ATTEMPTS: usize = 5;
+
+/// Replaces the session `KEYS[1]` with ARGV[2] if it still is ARGV[1], so a deleted session is
+/// not re-created and concurrent changes are not lost. With a TTL in ARGV[3] the session and
+/// the user's session index `KEYS[2]` live at least that long, otherwise the expiry is kept.
+/// Returns 1 if the session was replaced.
+const COMPARE_AND_SET_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) ~= ARGV[1] then
+  return 0
+end
+if ARGV[3] == '' then
+  redis.call('SET', KEYS[1], ARGV[2], 'KEEPTTL')
+else
+  redis.call('SET', KEYS[1], ARGV[2], 'EX', ARGV[3])
+  redis.call('EXPIRE', KEYS[2], ARGV[3], 'GT')
+end
+return 1
+";
+
+/// How a session changed by [`SessionService::update_session`] is written back
+enum SessionUpdate {
+    Unchanged,
+    KeepTtl,
+    Expire(u64),
+}
+
 pub struct SessionService {
     redis_pool: bb8::Pool<RedisConnectionManager>,
 }
 
 impl SessionService {
     pub fn new(redis_pool: bb8::Pool<RedisConnectionManager>) -> Self {
-        Self {
-            redis_pool,
+        Self { redis_pool }
+    }
+
+    /// Load a session for a request and extend its idle timeout, bounded by the absolute lifetime
+    pub async fn touch_session(
+        &self,
+        session_id: &str,
+    ) -> Result<Option<SessionData>, anyhow::Error> {
+        let now = Utc::now().timestamp();
+        let mut expired = false;
+        let session = self
+            .update_session(session_id, |session| match session.remaining_ttl(now) {
+                None => {
+                    expired = true;
+                    SessionUpdate::Unchanged
+                }
+                Some(0) => SessionUpdate::Unchanged,
+                Some(ttl) => {
+                    session.last_activity = now;
+                    SessionUpdate::Expire(ttl)
+                }
+            })
+            .await?;
+
+        if expired {
+            self.delete_session(session_id).await?;
+            return Ok(None);
         }
+        Ok(session)
     }
 
-    // Validate a session with a session_id from a cookie
-    pub async fn validate_session(
+    /// Change a stored session with `update`, `None` if it does not exist (anymore)
+    async fn update_session(
         &self,
         session_id: &str,
-    ) -> Result<Option<String>, anyhow::Error> {
+        mut update: impl FnMut(&mut SessionData) -> SessionUpdate,
+    ) -> Result<Option<SessionData>, anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let key = format!("sess:{}", session_id);
+        for _ in 0..UPDATE_ATTEMPTS {
+            let raw: Option<String> = conn.get(&key).await?;
+            let Some(raw) = raw else {
+                return Ok(None);
+            };
+            let mut session: SessionData = serde_json::from_str(&raw)?;
+
+            let ttl = match update(&mut session) {
+                SessionUpdate::Unchanged => return Ok(Some(session)),
+                SessionUpdate::KeepTtl => String::new(),
+                SessionUpdate::Expire(ttl) => ttl.to_string(),
+            };
+            let replaced: i64 = redis::Script::new(COMPARE_AND_SET_SCRIPT)
+                .key(&key)
+                .key(format!("user_sess:{}", session.user_id))
+                .arg(&raw)
+                .arg(serde_json::to_string(&session)?)
+                .arg(ttl)
+                .invoke_async(&mut *conn)
+                .await?;
+            if replaced == 1 {
+                return Ok(Some(session));
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "Session {} kept changing during an update",
+            session_id
+        ))
+    }
+
+    /// Load the full session data
+    pub async fn get_session(
+        &self,
+        session_id: &str,
+    ) -> Result<Option<SessionData>, anyhow::Error> {
         let mut conn = self.redis_pool.get().await?;
 
         // Use "sess:{session_id}" as Redis key convention
@@ -28,15 +122,12 @@ impl SessionService {
         let raw: Option<String> = conn.get(&key).await?;
 
         match raw {
-            Some(json) => {
-                let session: SessionData = serde_json::from_str(&json)?;
-                Ok(Some(session.user_id))
-            }
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
             None => Ok(None),
         }
     }
 
-    /// Store a session in redis
+    /// Store a session in redis and add it to the user's session index
     pub async fn set_session(
         &self,
         session_id: &str,
@@ -46,20 +137,223 @@ impl SessionService {
         let mut conn = self.redis_pool.get().await?;
 
         let key = format!("sess:{}", session_id);
+        let index_key = format!("user_sess:{}", session.user_id);
         let value = serde_json::to_string(session)?;
 
-        let _: () = conn.set_ex(key, value, ttl_seconds).await?;
+        // The index lives as long as the longest session it contains
+        let _: () = redis::pipe()
+            .set_ex(key, value, ttl_seconds)
+            .sadd(&index_key, session_id)
+            .cmd("EXPIRE")
+            .arg(&index_key)
+            .arg(ttl_seconds)
+            .arg("NX")
+            .cmd("EXPIRE")
+            .arg(&index_key)
+            .arg(ttl_seconds)
+            .arg("GT")
+            .query_async(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Remember that a client received an authorization code from this session
+    pub async fn add_client(&self, session_id: &str, client_id: &str) -> Result<(), anyhow::Error> {
+        self.update_session(session_id, |session| {
+            if session.clients.iter().any(|client| client == client_id) {
+                return SessionUpdate::Unchanged;
+            }
+            session.clients.push(client_id.to_owned());
+            SessionUpdate::KeepTtl
+        })
+        .await?;
 
         Ok(())
     }
 
+    /// List all active sessions of a user. Expired sessions are removed from the index.
+    pub async fn list_user_sessions(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<(String, SessionData)>, anyhow::Error> {
+        let mut conn = self.redis_pool.get().await?;
+
+        let index_key = format!("user_sess:{}", user_id);
+        let session_ids: Vec<String> = conn.smembers(&index_key).await?;
+
+        let mut sessions = Vec::new();
+        for session_id in session_ids {
+            let raw: Option<String> = conn.get(format!("sess:{}", session_id)).await?;
+
+            match raw {
+                Some(json) => sessions.push((session_id, serde_json::from_str(&json)?)),
+                None => {
+                    let _: () = conn.srem(&index_key, &session_id).await?;
+                }
+            }
+        }
+
+        sessions.sort_by_key(|(_, session): &(String, SessionData)| -session.created_at);
+
+        Ok(sessions)
+    }
+
     /// Delete a session from redis
     pub async fn delete_session(&self, session_id: &str) -> Result<(), anyhow::Error> {
+        let session = self.get_session(session_id).await?;
+
         let mut conn = self.redis_pool.get().await?;
 
         let key = format!("sess:{}", session_id);
         let _: () = conn.del(key).await?;
 
+        if let Some(session) = session {
+            let index_key = format!("user_sess:{}", session.user_id);
+            let _: () = conn.srem(index_key, session_id).await?;
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::runtime::Handle;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::utils::redis_utils::test_redis_pool;
+
+    fn session(user_id: &str, age: i64, idle_timeout: i64, lifetime: i64) -> SessionData {
+        let mut session = SessionData::new(user_id.to_owned(), Uuid::new_v4());
+        session.created_at -= age;
+        session.last_activity = session.created_at;
+        session.idle_timeout = idle_timeout;
+        session.expires_at = session.created_at + lifetime;
+        session
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn test_revoked_session_is_not_restored() {
+        let service = SessionService::new(test_redis_pool().await);
+        let session_id = Uuid::new_v4().to_string();
+        let user_id = Uuid::new_v4().to_string();
+        service
+            .set_session(&session_id, &session(&user_id, 0, 900, 3600), 900)
+            .await
+            .unwrap();
+
+        // The session is revoked between reading it and writing the refreshed expiry back
+        let mut attempts = 0;
+        let updated = service
+            .update_session(&session_id, |session| {
+                attempts += 1;
+                if attempts == 1 {
+                    tokio::task::block_in_place(|| {
+                        Handle::current().block_on(service.delete_session(&session_id))
+                    })
+                    .unwrap();
+                }
+                session.last_activity += 1;
+                SessionUpdate::Expire(900)
+            })
+            .await
+            .unwrap();
+
+        assert!(updated.is_none());
+        assert_eq!(attempts, 1);
+        assert!(service.get_session(&session_id).await.unwrap().is_none());
+        assert!(
+            service
+                .list_user_sessions(&user_id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn test_touch_session_never_outlives_absolute_lifetime() {
+        let pool = test_redis_pool().await;
+        let service = SessionService::new(pool.clone());
+        let session_id = Uuid::new_v4().to_string();
+        let user_id = Uuid::new_v4().to_string();
+
+        // Ten seconds of the absolute lifetime are left, less than the idle timeout
+        let stored = session(&user_id, 3590, 900, 3600);
+        service.set_session(&session_id, &stored, 60).await.unwrap();
+
+        let touched = service.touch_session(&session_id).await.unwrap().unwrap();
+        assert!(touched.last_activity > stored.last_activity);
+        let mut conn = pool.get().await.unwrap();
+        let ttl: i64 = conn.ttl(format!("sess:{session_id}")).await.unwrap();
+        assert!((1..=10).contains(&ttl), "session expires in {ttl}s");
+
+        // Once the lifetime is over the session ends even though it is still stored
+        let expired = session(&user_id, 3600, 900, 3600);
+        service
+            .set_session(&session_id, &expired, 60)
+            .await
+            .unwrap();
+        assert!(service.touch_session(&session_id).await.unwrap().is_none());
+        assert!(service.get_session(&session_id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn test_add_client_keeps_expiry() {
+        let pool = test_redis_pool().await;
+        let service = SessionService::new(pool.clone());
+        let session_id = Uuid::new_v4().to_string();
+        service
+            .set_session(&session_id, &session("user", 0, 900, 3600), 100)
+            .await
+            .unwrap();
+
+        service.add_client(&session_id, "app").await.unwrap();
+        service.add_client(&session_id, "app").await.unwrap();
+
+        let stored = service.get_session(&session_id).await.unwrap().unwrap();
+        assert_eq!(stored.clients, vec!["app".to_owned()]);
+        let mut conn = pool.get().await.unwrap();
+        let ttl: i64 = conn.ttl(format!("sess:{session_id}")).await.unwrap();
+        assert!((1..=100).contains(&ttl), "session expires in {ttl}s");
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn test_user_session_index() {
+        let pool = test_redis_pool().await;
+        let service = SessionService::new(pool.clone());
+        let user_id = Uuid::new_v4().to_string();
+        let session_ids: Vec<String> = (0..3).map(|_| Uuid::new_v4().to_string()).collect();
+
+        for (age, session_id) in session_ids.iter().enumerate() {
+            let mut session = SessionData::new(user_id.clone(), Uuid::new_v4());
+            session.created_at -= age as i64;
+            service
+                .set_session(session_id, &session, 3600)
+                .await
+                .unwrap();
+        }
+
+        // Newest first
+        let sessions = service.list_user_sessions(&user_id).await.unwrap();
+        let listed: Vec<&String> = sessions.iter().map(|(id, _)| id).collect();
+        assert_eq!(listed, session_ids.iter().collect::<Vec<_>>());
+
+        // Expired sessions are dropped from the index, deleted ones are removed right away
+        let mut conn = pool.get().await.unwrap();
+        let _: () = conn.del(format!("sess:{}", session_ids[0])).await.unwrap();
+        service.delete_session(&session_ids[1]).await.unwrap();
+
+        let sessions = service.list_user_sessions(&user_id).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].0, session_ids[2]);
+        let indexed: Vec<String> = conn.smembers(format!("user_sess:{user_id}")).await.unwrap();
+        assert_eq!(indexed, vec![session_ids[2].clone()]);
+    }
+}
@@ -0,0 +1,132 @@
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use crate::{
+    models::application_model::{ApplicationSubjectSQL, SubjectType},
+    utils::subject_utils::pairwise_subject,
+};
+
+pub struct SubjectService {
+    db_pool: Pool<Postgres>,
+    /// Without a salt, applications cannot use pairwise subjects
+    pairwise_salt: Option<String>,
+}
+
+impl SubjectService {
+    pub fn new(db_pool: Pool<Postgres>, pairwise_salt: Option<String>) -> Self {
+        Self {
+            db_pool,
+            pairwise_salt,
+        }
+    }
+
+    /// Whether pairwise subjects can be issued
+    pub fn supports_pairwise(&self) -> bool {
+        self.pairwise_salt.is_some()
+    }
+
+    /// Fails if applications use pairwise subjects but there is no salt to derive them with
+    pub async fn check_pairwise_salt(&self) -> Result<(), anyhow::Error> {
+        if self.supports_pairwise() {
+            return Ok(());
+        }
+
+        let pairwise_applications = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM Applications WHERE subject_type = $1"#,
+            SubjectType::Pairwise.as_str()
+        )
+        .fetch_one(&self.db_pool)
+        .await?;
+        if pairwise_applications > 0 {
+            return Err(anyhow::anyhow!(
+                "PAIRWISE_SALT must be set, {pairwise_applications} applications use pairwise subjects"
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The `sub` value the application `client_id` knows the user by
+    pub async fn subject_for(
+        &self,
+        user_id: &str,
+        client_id: &str,
+    ) -> Result<String, anyhow::Error> {
+        let Some(sector_identifier) = self.pairwise_sector(client_id).await? else {
+            return Ok(user_id.to_owned());
+        };
+
+        let Some(pairwise_salt) = &self.pairwise_salt else {
+            return Err(anyhow::anyhow!(
+                "Application {client_id} uses pairwise subjects but PAIRWISE_SALT is not set"
+            ));
+        };
+        let user_uuid = Uuid::parse_str(user_id)?;
+        let subject = pairwise_subject(&sector_identifier, user_id, pairwise_salt);
+
+        // Remember the subject so it can be resolved back to the user
+        sqlx::query!(
+            r#"
+            INSERT INTO PairwiseSubjects (sector_identifier, subject, user_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
+            "#,
+            sector_identifier,
+            subject,
+            user_uuid
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(subject)
+    }
+
+    /// The internal user ID behind a `sub` issued to the application `client_id`
+    pub async fn resolve_subject(
+        &self,
+        subject: &str,
+        client_id: &str,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let Some(sector_identifier) = self.pairwise_sector(client_id).await? else {
+            return Ok(Some(subject.to_owned()));
+        };
+
+        let user_id = sqlx::query_scalar!(
+            r#"
+            SELECT user_id::text AS "user_id!"
+            FROM PairwiseSubjects
+            WHERE sector_identifier = $1 AND subject = $2
+            "#,
+            sector_identifier,
+            subject
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(user_id)
+    }
+
+    /// The sector of a pairwise application, `None` for public subjects and audiences that are
+    /// no application, e.g. an API
+    async fn pairwise_sector(&self, client_id: &str) -> Result<Option<String>, anyhow::Error> {
+        let Some(application) = sqlx::query_as!(
+            ApplicationSubjectSQL,
+            "SELECT subject_type, sector_identifier FROM Applications WHERE client_id = $1",
+            client_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        if application.subject_type != SubjectType::Pairwise.as_str() {
+            return Ok(None);
+        }
+
+        application
+            .sector_identifier
+            .map(Some)
+            .ok_or_else(|| anyhow::anyhow!("Pairwise application {client_id} has no sector"))
+    }
+}
@@ -1,35 +1,63 @@
 use crate::models::config::user::User;
+use crate::models::directory::DirectoryUser;
+use crate::models::identity_provider::FederatedUser;
+use crate::models::password_policy::PasswordPolicy;
+use crate::models::session_policy::SessionPolicy;
+use crate::models::user_models::UserClaimsSQL;
+use crate::models::user_models::UserCredentialsSQL;
 use crate::models::user_models::UserIDSQL;
-use crate::models::user_models::UserInformation;
+use crate::services::directory_service::DirectoryService;
 use crate::utils;
+use crate::utils::breached_password_utils::BreachedPasswordCorpus;
+use crate::utils::password_policy_utils::{PasswordPolicyError, validate_password};
 use crate::{
     models::{
         login::{LoginRequest, UserPasswordHashSQL},
         session::SessionData,
         user_models::CreateUserRequest,
     },
-    utils::password_hash_utils::verify_password,
+    utils::password_hash_utils::{needs_rehash, verify_password},
 };
 use anyhow::Result;
-use sqlx::query;
+use argon2::Params;
 use sqlx::Error as SqlxError;
+use sqlx::query;
 use sqlx::{Pool, Postgres};
+use std::sync::Arc;
 use uuid::Uuid;
 
 pub struct UserService {
     db_pool: Pool<Postgres>,
+    breached_passwords: Arc<BreachedPasswordCorpus>,
+    argon2_params: Params,
+    directory_service: Arc<DirectoryService>,
 }
 
 impl UserService {
-    pub fn new(db_pool: Pool<Postgres>) -> Self {
-        Self { db_pool }
+    pub fn new(
+        db_pool: Pool<Postgres>,
+        breached_passwords: Arc<BreachedPasswordCorpus>,
+        argon2_params: Params,
+        directory_service: Arc<DirectoryService>,
+    ) -> Self {
+        Self {
+            db_pool,
+            breached_passwords,
+            argon2_params,
+            directory_service,
+        }
     }
+
     pub async fn create_user(&self, new_user: &CreateUserRequest) -> Result<(), anyhow::Error> {
         let tenant_uuid = Uuid::parse_str(&new_user.tenant_id)
             .map_err(|e| anyhow::anyhow!("Failed to parse tenant UUID: {}", e))?;
 
-        let hashed_password = utils::password_hash_utils::hash_password(&new_user.password)
-            .map_err(|e| anyhow::anyhow!("Password hashing failed: {}", e))?;
+        self.check_password_policy(tenant_uuid, None, &new_user.password)
+            .await?;
+
+        let hashed_password =
+            utils::password_hash_utils::hash_password(&new_user.password, &self.argon2_params)
+                .map_err(|e| anyhow::anyhow!("Password hashing failed: {}", e))?;
 
         let user_uuid = Uuid::new_v4();
 
@@ -59,6 +87,8 @@ impl UserService {
                 ))
             } else {
                 println!("User successfully inserted!");
+                self.record_password_history(user_uuid, &hashed_password.1)
+                    .await?;
                 Ok(())
             }
         } else {
@@ -88,10 +118,16 @@ impl UserService {
             new_user.id = Uuid::new_v4();
         }
 
+        let profile = new_user.profile;
         sqlx::query!(
             "
-    INSERT INTO Users (id, tenant_id, username, email, password_hash, is_active)
-    VALUES ($1, $2, $3, $4, $5, $6)
+    INSERT INTO Users (id, tenant_id, username, email, password_hash, is_active,
+                       name, given_name, family_name, middle_name, nickname, picture, website,
+                       gender, birthdate, zoneinfo, locale, email_verified, phone_number,
+                       phone_number_verified, street_address, locality, region, postal_code, country,
+                       attributes)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19,
+            $20, $21, $22, $23, $24, $25, $26)
     ",
             new_user.id,
             new_user.tenant_id,
@@ -99,6 +135,26 @@ impl UserService {
             new_user.email,
             new_user.password_hash,
             new_user.is_active,
+            profile.name,
+            profile.given_name,
+            profile.family_name,
+            profile.middle_name,
+            profile.nickname,
+            profile.picture,
+            profile.website,
+            profile.gender,
+            profile.birthdate,
+            profile.zoneinfo,
+            profile.locale,
+            profile.email_verified,
+            profile.phone_number,
+            profile.phone_number_verified,
+            profile.street_address,
+            profile.locality,
+            profile.region,
+            profile.postal_code,
+            profile.country,
+            serde_json::Value::Object(new_user.attributes),
         )
         .execute(&self.db_pool)
         .await
@@ -110,49 +166,643 @@ impl UserService {
     pub async fn get_user_id_from_email(
         &self,
         email: &String,
+        tenant_id: &str,
     ) -> Result<SessionData, anyhow::Error> {
-        let result = sqlx::query_as!(UserIDSQL, "SELECT id FROM Users where email = $1", email)
-            .fetch_one(&self.db_pool)
-            .await?;
+        let tenant_uuid = Uuid::parse_str(tenant_id)?;
+
+        let result = sqlx::query_as!(
+            UserIDSQL,
+            "SELECT id FROM Users where email = $1 AND tenant_id = $2",
+            email,
+            tenant_uuid
+        )
+        .fetch_one(&self.db_pool)
+        .await?;
+
+        Ok(SessionData::new(result.id, tenant_uuid))
+    }
+
+    /// IDs of the users with the email address, one per tenant it is used in
+    pub async fn get_user_ids_from_email(&self, email: &str) -> Result<Vec<String>, anyhow::Error> {
+        let user_ids = sqlx::query_scalar!(
+            r#"SELECT id::text AS "id!" FROM Users WHERE email = $1"#,
+            email
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
 
-        Ok(SessionData { user_id: result.id })
+        Ok(user_ids)
     }
 
-    /// Authorizes the user with a cookie if the credentials passed are valid
-    pub async fn auth_user(&self, login_request: &LoginRequest) -> Option<bool> {
+    /// Returns the session of the user if the credentials passed are valid
+    /// Hashes in legacy formats or with outdated Argon2 parameters are upgraded on success
+    ///
+    /// Directory users and unknown users of a tenant directory's email domains are
+    /// authenticated against the directory instead of the local password hash.
+    /// With a `tenant_id` only the users of that tenant can log in, without one the
+    /// email has to be used in a single tenant.
+    pub async fn auth_user(
+        &self,
+        login_request: &LoginRequest,
+        tenant_id: Option<Uuid>,
+    ) -> Option<SessionData> {
         let result = sqlx::query_as!(
-            UserPasswordHashSQL,
-            "SELECT password_hash FROM Users WHERE email = $1",
-            login_request.email
+            UserCredentialsSQL,
+            r#"
+            SELECT id, tenant_id, password_hash, directory_dn FROM Users
+            WHERE email = $1 AND ($2::uuid IS NULL OR tenant_id = $2)
+            "#,
+            login_request.email,
+            tenant_id
         )
-        .fetch_one(&self.db_pool)
+        .fetch_all(&self.db_pool)
         .await;
 
-        let stored_hash = match result {
-            Ok(row) => row.password_hash,
-            Err(_) => return None,
+        let credentials = match result {
+            Ok(rows) if rows.is_empty() => {
+                return self.auth_directory_user(tenant_id, login_request).await;
+            }
+            Ok(mut rows) if rows.len() == 1 => rows.remove(0),
+            Ok(_) | Err(_) => return None,
         };
+        if credentials.directory_dn.is_some() {
+            return self
+                .auth_directory_user(Some(credentials.tenant_id), login_request)
+                .await;
+        }
+
+        if !verify_password(login_request.password.as_str(), &credentials.password_hash).ok()? {
+            return None;
+        }
 
-        match verify_password(login_request.password.as_str(), &stored_hash) {
-            Ok(is_authenticated) => Some(is_authenticated),
-            Err(_) => return None,
+        if needs_rehash(&credentials.password_hash, &self.argon2_params)
+            && let Err(e) = self
+                .upgrade_password_hash(credentials.id, &login_request.password)
+                .await
+        {
+            eprintln!("Failed to upgrade password hash: {e:?}");
         }
+
+        Some(SessionData::new(
+            credentials.id.to_string(),
+            credentials.tenant_id,
+        ))
     }
 
-    pub async fn get_user_information(
+    /// Bind against the tenant's directory and update the user from its entry
+    async fn auth_directory_user(
+        &self,
+        tenant_id: Option<Uuid>,
+        login_request: &LoginRequest,
+    ) -> Option<SessionData> {
+        let directory = match tenant_id {
+            Some(tenant_id) => self
+                .directory_service
+                .get_directory(tenant_id)
+                .await
+                .map(|config| config.map(|config| (tenant_id, config))),
+            None => {
+                self.directory_service
+                    .find_directory_for_email(&login_request.email)
+                    .await
+            }
+        };
+        let (tenant_id, config) = match directory {
+            Ok(directory) => directory?,
+            Err(e) => {
+                eprintln!("Failed to load directory: {e:?}");
+                return None;
+            }
+        };
+
+        let user = match self
+            .directory_service
+            .authenticate(&config, &login_request.email, &login_request.password)
+            .await
+        {
+            Ok(Some(user)) => user,
+            Ok(None) => return None,
+            Err(e) => {
+                eprintln!("Directory authentication failed: {e:?}");
+                return None;
+            }
+        };
+
+        // Without group mappings roles stay managed locally
+        let manage_roles = !config.group_roles.is_empty();
+        match self
+            .sync_directory_user(tenant_id, &login_request.email, &user, manage_roles)
+            .await
+        {
+            Ok(user_id) => Some(SessionData::new(user_id.to_string(), tenant_id)),
+            Err(e) => {
+                eprintln!("Failed to sync directory user: {e:?}");
+                None
+            }
+        }
+    }
+
+    /// Create or update the local copy of a directory user, replacing its roles if `manage_roles`
+    async fn sync_directory_user(
+        &self,
+        tenant_id: Uuid,
+        email: &str,
+        user: &DirectoryUser,
+        manage_roles: bool,
+    ) -> Result<Uuid, anyhow::Error> {
+        let mut transaction = self.db_pool.begin().await?;
+
+        // Local users with the same email keep their password, they are not taken over
+        let user_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO Users (id, tenant_id, username, email, password_hash, directory_dn, name,
+                               given_name, family_name, phone_number, email_verified, attributes)
+            VALUES ($1, $2, $3, $4, '', $5, $6, $7, $8, $9, TRUE, $10)
+            ON CONFLICT (tenant_id, email) DO UPDATE SET
+                directory_dn = EXCLUDED.directory_dn,
+                name = EXCLUDED.name,
+                given_name = EXCLUDED.given_name,
+                family_name = EXCLUDED.family_name,
+                phone_number = EXCLUDED.phone_number,
+                attributes = Users.attributes || EXCLUDED.attributes,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE Users.directory_dn IS NOT NULL
+            RETURNING id
+            "#,
+            Uuid::new_v4(),
+            tenant_id,
+            user.username,
+            email,
+            user.dn,
+            user.name,
+            user.given_name,
+            user.family_name,
+            user.phone_number,
+            serde_json::Value::Object(user.attributes.clone()),
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        if manage_roles {
+            sqlx::query!("DELETE FROM UserRoles WHERE user_id = $1", user_id)
+                .execute(&mut *transaction)
+                .await?;
+
+            for role in &user.roles {
+                let role_id = sqlx::query_scalar!(
+                    "SELECT id FROM Roles WHERE tenant_id = $1 AND name = $2 LIMIT 1",
+                    tenant_id,
+                    role
+                )
+                .fetch_optional(&mut *transaction)
+                .await?;
+                let role_id = match role_id {
+                    Some(role_id) => role_id,
+                    None => {
+                        let role_id = Uuid::new_v4();
+                        sqlx::query!(
+                            "INSERT INTO Roles (id, tenant_id, name) VALUES ($1, $2, $3)",
+                            role_id,
+                            tenant_id,
+                            role
+                        )
+                        .execute(&mut *transaction)
+                        .await?;
+                        role_id
+                    }
+                };
+
+                sqlx::query!(
+                    "INSERT INTO UserRoles (user_id, role_id) VALUES ($1, $2)",
+                    user_id,
+                    role_id
+                )
+                .execute(&mut *transaction)
+                .await?;
+            }
+        }
+
+        transaction.commit().await?;
+
+        Ok(user_id)
+    }
+
+    async fn upgrade_password_hash(
+        &self,
+        user_id: Uuid,
+        password: &str,
+    ) -> Result<(), anyhow::Error> {
+        let (_salt, password_hash) =
+            utils::password_hash_utils::hash_password(password, &self.argon2_params)
+                .map_err(|e| anyhow::anyhow!("Password hashing failed: {}", e))?;
+
+        sqlx::query!(
+            "UPDATE Users SET password_hash = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2",
+            password_hash,
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Returns true if the tenant's policy has a maximum password age and the user's password exceeded it
+    pub async fn is_password_expired(&self, user_id: &str) -> Result<bool, anyhow::Error> {
+        let user_uuid = Uuid::parse_str(user_id)?;
+
+        let expired = sqlx::query_scalar!(
+            r#"
+            SELECT (p.max_age_days IS NOT NULL
+                AND u.password_changed_at + make_interval(days => p.max_age_days) < LOCALTIMESTAMP) AS "expired!"
+            FROM Users u
+            LEFT JOIN PasswordPolicies p ON p.tenant_id = u.tenant_id
+            WHERE u.id = $1 AND u.directory_dn IS NULL
+            "#,
+            user_uuid
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(expired.unwrap_or(false))
+    }
+
+    /// Changes the password of a user after verifying the current one
+    pub async fn change_password(
+        &self,
+        email: &String,
+        tenant_id: Option<Uuid>,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<bool, anyhow::Error> {
+        let mut credentials = sqlx::query_as!(
+            UserCredentialsSQL,
+            r#"
+            SELECT id, tenant_id, password_hash, directory_dn FROM Users
+            WHERE email = $1 AND ($2::uuid IS NULL OR tenant_id = $2)
+            "#,
+            email,
+            tenant_id
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        // Ambiguous emails need the tenant, directory passwords are changed in the directory
+        if credentials.len() != 1 || credentials[0].directory_dn.is_some() {
+            return Ok(false);
+        }
+        let credentials = credentials.remove(0);
+
+        if !verify_password(current_password, &credentials.password_hash).unwrap_or(false) {
+            return Ok(false);
+        }
+
+        self.set_password(credentials.id, credentials.tenant_id, new_password)
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Sets a new password without knowing the current one, e.g. after a reset link was used
+    pub async fn reset_password(
         &self,
         user_id: &str,
-    ) -> Result<UserInformation, anyhow::Error> {
+        new_password: &str,
+    ) -> Result<(), anyhow::Error> {
+        let user_uuid = Uuid::parse_str(user_id)?;
+
+        let tenant_id = sqlx::query_scalar!("SELECT tenant_id FROM Users WHERE id = $1", user_uuid)
+            .fetch_one(&self.db_pool)
+            .await?;
+
+        self.set_password(user_uuid, tenant_id, new_password).await
+    }
+
+    pub async fn get_password_policy(
+        &self,
+        tenant_id: Uuid,
+    ) -> Result<PasswordPolicy, anyhow::Error> {
+        let policy = sqlx::query_as!(
+            PasswordPolicy,
+            r#"
+            SELECT min_length, require_uppercase, require_lowercase, require_digit, require_symbol,
+                   history_size, max_age_days, check_breached
+            FROM PasswordPolicies
+            WHERE tenant_id = $1
+            "#,
+            tenant_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(policy.unwrap_or_default())
+    }
+
+    /// Returns the session policy of the tenant the user belongs to
+    pub async fn get_session_policy(&self, user_id: &str) -> Result<SessionPolicy, anyhow::Error> {
+        let user_uuid = Uuid::parse_str(user_id)?;
+
+        let policy = sqlx::query_as!(
+            SessionPolicy,
+            r#"
+            SELECT p.idle_timeout_seconds, p.absolute_lifetime_seconds,
+                   p.remember_me_enabled, p.remember_me_lifetime_seconds
+            FROM Users u
+            JOIN SessionPolicies p ON p.tenant_id = u.tenant_id
+            WHERE u.id = $1
+            "#,
+            user_uuid
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(policy.unwrap_or_default())
+    }
+
+    pub async fn set_password(
+        &self,
+        user_id: Uuid,
+        tenant_id: Uuid,
+        new_password: &str,
+    ) -> Result<(), anyhow::Error> {
+        let password_hash = self
+            .hash_new_password(tenant_id, Some(user_id), new_password)
+            .await?;
+
+        sqlx::query!(
+            "UPDATE Users SET password_hash = $1, password_changed_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP WHERE id = $2",
+            password_hash,
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        self.record_password_history(user_id, &password_hash).await
+    }
+
+    /// Hash of a new password that satisfies the tenant's password policy
+    pub async fn hash_new_password(
+        &self,
+        tenant_id: Uuid,
+        user_id: Option<Uuid>,
+        new_password: &str,
+    ) -> Result<String, anyhow::Error> {
+        self.check_password_policy(tenant_id, user_id, new_password)
+            .await?;
+
+        let (_salt, password_hash) =
+            utils::password_hash_utils::hash_password(new_password, &self.argon2_params)
+                .map_err(|e| anyhow::anyhow!("Password hashing failed: {}", e))?;
+
+        Ok(password_hash)
+    }
+
+    /// Enforces the tenant's password policy, the breached password corpus and,
+    /// for existing users, the password history
+    pub async fn check_password_policy(
+        &self,
+        tenant_id: Uuid,
+        user_id: Option<Uuid>,
+        password: &str,
+    ) -> Result<(), anyhow::Error> {
+        let policy = self.get_password_policy(tenant_id).await?;
+
+        validate_password(password, &policy)?;
+
+        if policy.check_breached && self.breached_passwords.contains(password) {
+            return Err(PasswordPolicyError::Breached.into());
+        }
+
+        if let Some(user_id) = user_id
+            && policy.history_size > 0
+        {
+            let previous_hashes = sqlx::query_as!(
+                UserPasswordHashSQL,
+                "SELECT password_hash FROM PasswordHistory WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2",
+                user_id,
+                policy.history_size as i64
+            )
+            .fetch_all(&self.db_pool)
+            .await?;
+
+            if previous_hashes
+                .iter()
+                .any(|previous| verify_password(password, &previous.password_hash).unwrap_or(false))
+            {
+                return Err(PasswordPolicyError::Reused.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn record_password_history(
+        &self,
+        user_id: Uuid,
+        password_hash: &str,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO PasswordHistory (id, user_id, password_hash) VALUES ($1, $2, $3)",
+            Uuid::new_v4(),
+            user_id,
+            password_hash
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn is_user_active(&self, user_id: &str) -> Result<bool, anyhow::Error> {
         let user_uuid = uuid::Uuid::parse_str(user_id)?;
 
+        let result = sqlx::query_scalar!("SELECT is_active FROM Users where id = $1", user_uuid)
+            .fetch_one(&self.db_pool)
+            .await;
+
+        Ok(result?)
+    }
+
+    /// Whether the user belongs to the tenant, users only use the applications of their own tenant
+    pub async fn is_user_in_tenant(
+        &self,
+        user_id: &str,
+        tenant_id: Uuid,
+    ) -> Result<bool, anyhow::Error> {
+        let user_uuid = Uuid::parse_str(user_id)?;
+
+        let result = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM Users WHERE id = $1 AND tenant_id = $2) AS "exists!""#,
+            user_uuid,
+            tenant_id
+        )
+        .fetch_one(&self.db_pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    /// Email of the user and name of their tenant, as shown in the account chooser
+    pub async fn get_account_label(
+        &self,
+        user_id: &str,
+    ) -> Result<Option<(String, String)>, anyhow::Error> {
+        let user_uuid = Uuid::parse_str(user_id)?;
+
+        let result = sqlx::query!(
+            r#"
+            SELECT u.email, t.name AS tenant_name FROM Users u
+            JOIN Tenants t ON t.id = u.tenant_id
+            WHERE u.id = $1
+            "#,
+            user_uuid
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(result.map(|row| (row.email, row.tenant_name)))
+    }
+
+    /// Find a user of a tenant by email address
+    pub async fn find_user_id_in_tenant(
+        &self,
+        tenant_id: Uuid,
+        email: &str,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let user_id = sqlx::query_scalar!(
+            r#"SELECT id::text AS "id!" FROM Users WHERE tenant_id = $1 AND email = $2"#,
+            tenant_id,
+            email
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(user_id)
+    }
+
+    /// Create a user on the first login through an upstream identity provider
+    ///
+    /// The user gets no password and can only log in through the provider until one is set.
+    pub async fn provision_federated_user(
+        &self,
+        tenant_id: Uuid,
+        user: &FederatedUser,
+    ) -> Result<String, anyhow::Error> {
+        let Some(email) = &user.email else {
+            return Err(anyhow::anyhow!("Upstream identity has no email address"));
+        };
+        let username = user.username.clone().unwrap_or_else(|| email.clone());
+
+        // Usernames are only unique per tenant, so fall back to a suffixed one on conflicts
+        for candidate in [
+            username.clone(),
+            format!("{username}-{}", &Uuid::new_v4().to_string()[..8]),
+        ] {
+            let user_id = Uuid::new_v4();
+            let result = sqlx::query!(
+                r#"
+                INSERT INTO Users (id, tenant_id, username, email, password_hash, name, given_name,
+                                   family_name, email_verified)
+                VALUES ($1, $2, $3, $4, '', $5, $6, $7, $8)
+                ON CONFLICT ON CONSTRAINT users_tenant_id_username_key DO NOTHING
+                "#,
+                user_id,
+                tenant_id,
+                candidate,
+                email,
+                user.name,
+                user.given_name,
+                user.family_name,
+                user.email_verified,
+            )
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to provision user: {}", e))?;
+
+            if result.rows_affected() == 1 {
+                return Ok(user_id.to_string());
+            }
+        }
+
+        Err(anyhow::anyhow!("Failed to provision user: username taken"))
+    }
+
+    /// Load the values for the OIDC standard claims of a user
+    pub async fn get_user_claims(&self, user_id: &str) -> Result<UserClaimsSQL, anyhow::Error> {
+        let user_uuid = Uuid::parse_str(user_id)?;
+
         let result = sqlx::query_as!(
-            UserInformation,
-            "SELECT username, email, is_active FROM Users where id = $1",
+            UserClaimsSQL,
+            r#"
+            SELECT username, email, name, given_name, family_name, middle_name,
+                   nickname, picture, website, gender, birthdate, zoneinfo, locale, email_verified,
+                   phone_number, phone_number_verified, street_address, locality, region,
+                   postal_code, country,
+                   EXTRACT(EPOCH FROM updated_at)::BIGINT AS updated_at
+            FROM Users
+            WHERE id = $1
+            "#,
             user_uuid
         )
         .fetch_one(&self.db_pool)
-        .await;
+        .await?;
 
-        Ok(result?)
+        Ok(result)
+    }
+
+    /// Returns true if `admin_id` has the `admin` role in the tenant `user_id` belongs to
+    /// Whether the user is an admin of the tenant `tenant_id`
+    pub async fn is_tenant_admin(
+        &self,
+        admin_id: &str,
+        tenant_id: Uuid,
+    ) -> Result<bool, anyhow::Error> {
+        let admin_uuid = Uuid::parse_str(admin_id)?;
+
+        let is_admin = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM Users admin
+                JOIN UserRoles ur ON ur.user_id = admin.id
+                JOIN Roles r ON r.id = ur.role_id AND r.tenant_id = admin.tenant_id
+                WHERE admin.id = $1 AND admin.tenant_id = $2 AND r.name = 'admin'
+            ) AS "is_admin!"
+            "#,
+            admin_uuid,
+            tenant_id
+        )
+        .fetch_one(&self.db_pool)
+        .await?;
+
+        Ok(is_admin)
+    }
+
+    pub async fn is_tenant_admin_of(
+        &self,
+        admin_id: &str,
+        user_id: &str,
+    ) -> Result<bool, anyhow::Error> {
+        let admin_uuid = Uuid::parse_str(admin_id)?;
+        let user_uuid = Uuid::parse_str(user_id)?;
+
+        let is_admin = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM Users admin
+                JOIN UserRoles ur ON ur.user_id = admin.id
+                JOIN Roles r ON r.id = ur.role_id AND r.tenant_id = admin.tenant_id
+                JOIN Users target ON target.tenant_id = admin.tenant_id
+                WHERE admin.id = $1 AND target.id = $2 AND r.name = 'admin'
+            ) AS "is_admin!"
+            "#,
+            admin_uuid,
+            user_uuid
+        )
+        .fetch_one(&self.db_pool)
+        .await?;
+
+        Ok(is_admin)
     }
 }
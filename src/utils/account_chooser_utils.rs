@@ -0,0 +1,98 @@
+use cookie::Cookie;
+use uuid::Uuid;
+
+use crate::utils::{
+    branding_utils::{PageBranding, page},
+    device_utils::escape_html,
+};
+
+/// Cookie listing the sessions the browser holds, e.g. in several tenants
+pub const SESSION_IDS_COOKIE: &str = "session_ids";
+/// Sessions beyond this are dropped from the cookie, oldest first
+const MAX_BROWSER_SESSIONS: usize = 10;
+
+/// An account the browser is logged in with, as offered by the account chooser
+pub struct AccountChoice {
+    pub session_id: String,
+    pub email: String,
+    pub tenant_name: String,
+}
+
+/// Session IDs in the `session_ids` cookie, entries that cannot be session IDs are ignored
+pub fn parse_session_ids(value: Option<&str>) -> Vec<String> {
+    let mut session_ids: Vec<String> = Vec::new();
+    for session_id in value.unwrap_or_default().split('.') {
+        if Uuid::parse_str(session_id).is_ok() && !session_ids.iter().any(|id| id == session_id) {
+            session_ids.push(session_id.to_owned());
+        }
+    }
+    session_ids
+}
+
+/// `session_ids` cookie with the newest session last
+pub fn session_ids_cookie(mut session_ids: Vec<String>) -> Cookie<'static> {
+    if session_ids.len() > MAX_BROWSER_SESSIONS {
+        session_ids.drain(..session_ids.len() - MAX_BROWSER_SESSIONS);
+    }
+
+    Cookie::build((SESSION_IDS_COOKIE, session_ids.join(".")))
+        .path("/")
+        .http_only(true)
+        .secure(true)
+        .same_site(cookie::SameSite::Lax)
+        .permanent()
+        .build()
+}
+
+/// Lets the user continue with one of the browser's sessions or log in with another account
+pub fn account_chooser_page(
+    branding: &PageBranding,
+    accounts: &[AccountChoice],
+    action: &str,
+    return_to: &str,
+    login_url: &str,
+) -> String {
+    let accounts: String = accounts
+        .iter()
+        .map(|account| {
+            format!(
+                r#"<li><button type="submit" name="session_id" value="{session_id}"><strong>{email}</strong><br>{tenant}</button></li>"#,
+                session_id = escape_html(&account.session_id),
+                email = escape_html(&account.email),
+                tenant = escape_html(&account.tenant_name),
+            )
+        })
+        .collect();
+
+    page(
+        branding,
+        branding.text("account_chooser_title", "Choose an account"),
+        &format!(
+            r#"<form method="post" action="{action}"><input type="hidden" name="return_to" value="{return_to}"><ul style="list-style: none; padding: 0">{accounts}</ul></form><p><a href="{login_url}">{another_account}</a></p>"#,
+            action = escape_html(action),
+            return_to = escape_html(return_to),
+            login_url = escape_html(login_url),
+            another_account = escape_html(branding.text("another_account", "Use another account")),
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_ids_cookie() {
+        let session_ids: Vec<String> = (0..12).map(|_| Uuid::new_v4().to_string()).collect();
+        let value = format!("{}.{}.not-a-session", session_ids.join("."), session_ids[0]);
+        assert_eq!(parse_session_ids(Some(&value)), session_ids);
+        assert!(parse_session_ids(None).is_empty());
+
+        // Only the newest sessions are kept
+        let cookie = session_ids_cookie(session_ids.clone());
+        assert_eq!(
+            parse_session_ids(Some(cookie.value())),
+            session_ids[2..].to_vec()
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::{
+    models::user_attributes::{AttributeDefinition, AttributeType, ClaimMapping, ClaimTarget},
+    utils::claims_utils::is_standard_claim,
+};
+
+/// Claims set by the token issuer which claim mappings must not override,
+/// the standard claims of the user's profile are reserved as well
+const RESERVED_CLAIMS: [&str; 14] = [
+    "iss",
+    "sub",
+    "aud",
+    "exp",
+    "iat",
+    "nbf",
+    "jti",
+    "auth_time",
+    "nonce",
+    "scope",
+    "azp",
+    "client_id",
+    "cnf",
+    "act",
+];
+
+#[derive(Debug, Error, PartialEq)]
+pub enum AttributeError {
+    #[error("Unknown attribute {0}")]
+    Unknown(String),
+    #[error("Attribute {0} must be of type {1}")]
+    InvalidType(String, &'static str),
+    #[error("Attribute {0} is required")]
+    MissingRequired(String),
+    #[error("Claim {0} is reserved")]
+    ReservedClaim(String),
+}
+
+/// Check a full set of user attributes against the tenant's attribute definitions
+pub fn validate_attributes(
+    attributes: &Map<String, Value>,
+    definitions: &[AttributeDefinition],
+) -> Result<(), AttributeError> {
+    for (name, value) in attributes {
+        let Some(definition) = definitions.iter().find(|d| &d.name == name) else {
+            return Err(AttributeError::Unknown(name.clone()));
+        };
+
+        // null clears an optional attribute
+        if value.is_null() && !definition.required {
+            continue;
+        }
+
+        let matches = match definition.data_type {
+            AttributeType::String => value.is_string(),
+            AttributeType::Number => value.is_number(),
+            AttributeType::Boolean => value.is_boolean(),
+            AttributeType::Array => value.is_array(),
+            AttributeType::Object => value.is_object(),
+        };
+        if !matches {
+            return Err(AttributeError::InvalidType(
+                name.clone(),
+                definition.data_type.as_str(),
+            ));
+        }
+    }
+
+    for definition in definitions.iter().filter(|d| d.required) {
+        if attributes.get(&definition.name).is_none_or(Value::is_null) {
+            return Err(AttributeError::MissingRequired(definition.name.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn validate_claim_mapping(mapping: &ClaimMapping) -> Result<(), AttributeError> {
+    if RESERVED_CLAIMS.contains(&mapping.claim.as_str()) || is_standard_claim(&mapping.claim) {
+        return Err(AttributeError::ReservedClaim(mapping.claim.clone()));
+    }
+    Ok(())
+}
+
+/// Claims for `target` built from the user's attributes by the application's mapping rules
+pub fn mapped_claims(
+    attributes: &Map<String, Value>,
+    mappings: &[ClaimMapping],
+    target: ClaimTarget,
+) -> Map<String, Value> {
+    mappings
+        .iter()
+        .filter(|mapping| match target {
+            ClaimTarget::IdToken => mapping.id_token,
+            ClaimTarget::AccessToken => mapping.access_token,
+            ClaimTarget::UserInfo => mapping.userinfo,
+        })
+        .filter(|mapping| validate_claim_mapping(mapping).is_ok())
+        .filter_map(|mapping| {
+            attributes
+                .get(&mapping.attribute)
+                .filter(|value| !value.is_null())
+                .map(|value| (mapping.claim.clone(), value.clone()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn definitions() -> Vec<AttributeDefinition> {
+        serde_json::from_value(json!([
+            { "name": "department", "type": "string", "required": true },
+            { "name": "cost_center", "type": "number" },
+            { "name": "groups", "type": "array" },
+        ]))
+        .unwrap()
+    }
+
+    fn attributes(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_validate_attributes() {
+        let definitions = definitions();
+
+        assert_eq!(
+            validate_attributes(
+                &attributes(json!({ "department": "Finance", "cost_center": 4711 })),
+                &definitions
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate_attributes(
+                &attributes(json!({ "department": "Finance", "team": "A" })),
+                &definitions
+            ),
+            Err(AttributeError::Unknown("team".to_string()))
+        );
+        assert_eq!(
+            validate_attributes(
+                &attributes(json!({ "department": "Finance", "cost_center": "4711" })),
+                &definitions
+            ),
+            Err(AttributeError::InvalidType(
+                "cost_center".to_string(),
+                "number"
+            ))
+        );
+        assert_eq!(
+            validate_attributes(&attributes(json!({ "groups": [] })), &definitions),
+            Err(AttributeError::MissingRequired("department".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_mapped_claims() {
+        let mappings: Vec<ClaimMapping> = serde_json::from_value(json!([
+            { "claim": "dept", "attribute": "department" },
+            { "claim": "groups", "attribute": "groups", "id_token": false, "access_token": true },
+            { "claim": "sub", "attribute": "department" },
+            { "claim": "cnf", "attribute": "department", "access_token": true },
+            { "claim": "act", "attribute": "department", "access_token": true },
+            { "claim": "email_verified", "attribute": "department" },
+            { "claim": "name", "attribute": "department" },
+            { "claim": "cost_center", "attribute": "cost_center" },
+        ]))
+        .unwrap();
+        let attributes = attributes(json!({ "department": "Finance", "groups": ["admins"] }));
+
+        let id_token = mapped_claims(&attributes, &mappings, ClaimTarget::IdToken);
+        assert_eq!(id_token, self::attributes(json!({ "dept": "Finance" })));
+
+        let access_token = mapped_claims(&attributes, &mappings, ClaimTarget::AccessToken);
+        assert_eq!(
+            access_token,
+            self::attributes(json!({ "groups": ["admins"] }))
+        );
+
+        let userinfo = mapped_claims(&attributes, &mappings, ClaimTarget::UserInfo);
+        assert_eq!(
+            userinfo,
+            self::attributes(json!({ "dept": "Finance", "groups": ["admins"] }))
+        );
+
+        for claim in ["sub", "cnf", "act", "email_verified", "name"] {
+            let mapping = mappings
+                .iter()
+                .find(|mapping| mapping.claim == claim)
+                .unwrap();
+            assert_eq!(
+                validate_claim_mapping(mapping),
+                Err(AttributeError::ReservedClaim(claim.to_string()))
+            );
+        }
+        assert_eq!(validate_claim_mapping(&mappings[0]), Ok(()));
+    }
+}
@@ -0,0 +1,69 @@
+use crate::utils::device_utils::escape_html;
+
+/// The redirect URI with the response parameters in its query or fragment
+pub fn response_url(redirect_uri: &str, params: &[(&str, String)], fragment: bool) -> String {
+    let separator = match fragment {
+        true => '#',
+        // Registered redirect URIs may carry a query of their own
+        false if redirect_uri.contains('?') => '&',
+        false => '?',
+    };
+
+    format!(
+        "{}{}{}",
+        redirect_uri,
+        separator,
+        serde_urlencoded::to_string(params).unwrap_or_default()
+    )
+}
+
+/// Page that posts the response parameters to the redirect URI as soon as it loads (OAuth 2.0 Form Post)
+pub fn form_post_page(redirect_uri: &str, params: &[(&str, String)]) -> String {
+    let inputs: String = params
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                r#"<input type="hidden" name="{}" value="{}">"#,
+                escape_html(name),
+                escape_html(value)
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<!DOCTYPE html><html><head><meta charset="utf-8"><title>Submit this form</title></head><body onload="document.forms[0].submit()"><form method="post" action="{}">{}<noscript><button type="submit">Continue</button></noscript></form></body></html>"#,
+        escape_html(redirect_uri),
+        inputs
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_url_and_form_post_page() {
+        let params = [
+            ("code", "abc".to_string()),
+            ("state", "a b&c".to_string()),
+            ("iss", "https://sso.example.com".to_string()),
+        ];
+
+        assert_eq!(
+            response_url("https://client.example/cb", &params, false),
+            "https://client.example/cb?code=abc&state=a+b%26c&iss=https%3A%2F%2Fsso.example.com"
+        );
+        assert!(
+            response_url("https://client.example/cb?tenant=1", &params, false)
+                .starts_with("https://client.example/cb?tenant=1&code=abc&")
+        );
+        assert!(
+            response_url("https://client.example/cb", &params, true)
+                .starts_with("https://client.example/cb#code=abc&")
+        );
+
+        let page = form_post_page("https://client.example/cb", &params);
+        assert!(page.contains(r#"action="https://client.example/cb""#));
+        assert!(page.contains(r#"name="state" value="a b&amp;c""#));
+    }
+}
@@ -0,0 +1,198 @@
+use axum::http::{HeaderMap, header::ACCEPT_LANGUAGE};
+
+use crate::{models::branding::TenantBranding, utils::device_utils::escape_html};
+
+/// A tenant's branding for a page, with the languages the browser accepts
+#[derive(Default)]
+pub struct PageBranding {
+    branding: TenantBranding,
+    locales: Vec<String>,
+}
+
+impl PageBranding {
+    pub fn new(branding: Option<TenantBranding>, headers: &HeaderMap) -> Self {
+        let accept_language = headers
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok());
+        Self {
+            branding: branding.unwrap_or_default(),
+            locales: accepted_locales(accept_language),
+        }
+    }
+
+    /// The tenant's text for `key` in the first accepted language it has one in, `default` otherwise
+    pub fn text<'a>(&'a self, key: &str, default: &'a str) -> &'a str {
+        self.locales
+            .iter()
+            .filter_map(|locale| self.branding.texts.get(locale))
+            .find_map(|texts| texts.get(key))
+            .map_or(default, String::as_str)
+    }
+}
+
+/// Language tags of an `Accept-Language` header by preference, each followed by its primary
+/// language, e.g. `de-CH` then `de`
+pub fn accepted_locales(accept_language: Option<&str>) -> Vec<String> {
+    let mut weighted: Vec<(f32, String)> = accept_language
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let tag = parts.next()?.trim().to_lowercase();
+            let quality = parts
+                .find_map(|part| part.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |quality| quality.parse().ok())?;
+            (!tag.is_empty() && tag != "*" && quality > 0.0).then_some((quality, tag))
+        })
+        .collect();
+    // Stable, so tags of equal quality keep their order
+    weighted.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+    let mut locales: Vec<String> = Vec::new();
+    for (_, tag) in weighted {
+        let primary = tag.split('-').next().unwrap_or_default().to_owned();
+        for locale in [tag, primary] {
+            if !locales.contains(&locale) {
+                locales.push(locale);
+            }
+        }
+    }
+    locales
+}
+
+/// `#rgb` or `#rrggbb`, anything else could break out of the stylesheet
+fn is_valid_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Page in the tenant's look with its logo, colors, stylesheet and support links
+pub fn page(branding: &PageBranding, title: &str, body: &str) -> String {
+    let tenant = &branding.branding;
+
+    let mut style = String::from(
+        "body { font-family: sans-serif; max-width: 28rem; margin: 4rem auto; padding: 0 1rem }",
+    );
+    if let Some(color) = tenant
+        .background_color
+        .as_deref()
+        .filter(|c| is_valid_color(c))
+    {
+        style.push_str(&format!(" body {{ background: {color} }}"));
+    }
+    if let Some(color) = tenant
+        .primary_color
+        .as_deref()
+        .filter(|c| is_valid_color(c))
+    {
+        style.push_str(&format!(
+            " a {{ color: {color} }} button {{ background: {color}; border-color: {color}; color: #fff }}"
+        ));
+    }
+    if let Some(custom_css) = &tenant.custom_css {
+        // The stylesheet must not end the style element
+        style.push(' ');
+        style.push_str(&custom_css.replace("</", "<\\/"));
+    }
+
+    let logo = tenant
+        .logo_url
+        .as_ref()
+        .map(|logo_url| {
+            format!(
+                r#"<img src="{}" alt="" style="max-height: 3rem">"#,
+                escape_html(logo_url)
+            )
+        })
+        .unwrap_or_default();
+
+    let links: Vec<String> = [
+        (
+            tenant.support_url.clone(),
+            branding.text("support", "Support"),
+        ),
+        (
+            tenant
+                .support_email
+                .as_ref()
+                .map(|email| format!("mailto:{email}")),
+            branding.text("support_email", "Contact"),
+        ),
+        (
+            tenant.privacy_policy_url.clone(),
+            branding.text("privacy_policy", "Privacy policy"),
+        ),
+        (
+            tenant.terms_of_service_url.clone(),
+            branding.text("terms_of_service", "Terms of service"),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(url, label)| {
+        Some(format!(
+            r#"<a href="{}">{}</a>"#,
+            escape_html(&url?),
+            escape_html(label)
+        ))
+    })
+    .collect();
+    let footer = match links.is_empty() {
+        true => String::new(),
+        false => format!(
+            r#"<footer style="margin-top: 2rem">{}</footer>"#,
+            links.join(" · ")
+        ),
+    };
+
+    format!(
+        r#"<!DOCTYPE html><html><head><meta charset="utf-8"><meta name="viewport" content="width=device-width, initial-scale=1"><title>{title}</title><style>{style}</style></head><body>{logo}<h1>{title}</h1>{body}{footer}</body></html>"#,
+        title = escape_html(title)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_branded_page() {
+        assert_eq!(
+            accepted_locales(Some("fr;q=0.5, de-CH, en;q=0.8, *;q=0.1")),
+            ["de-ch", "de", "en", "fr"]
+        );
+        assert!(accepted_locales(None).is_empty());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("de-CH"));
+        let branding = PageBranding::new(
+            Some(TenantBranding {
+                primary_color: Some("#0a6ed1".to_string()),
+                background_color: Some("red; } body { display: none".to_string()),
+                custom_css: Some("h1 { font-weight: 300 } </style><script>".to_string()),
+                support_url: Some("https://support.acme.example".to_string()),
+                texts: HashMap::from([(
+                    "de".to_string(),
+                    HashMap::from([("support".to_string(), "Hilfe".to_string())]),
+                )]),
+                ..Default::default()
+            }),
+            &headers,
+        );
+        assert_eq!(branding.text("support", "Support"), "Hilfe");
+        assert_eq!(
+            branding.text("device_title", "Connect a device"),
+            "Connect a device"
+        );
+
+        let html = page(&branding, "Title", "<p>Body</p>");
+        assert!(html.contains("button { background: #0a6ed1"));
+        assert!(!html.contains("display: none"));
+        assert!(!html.contains("</style><script>"));
+        assert!(html.contains(r#"<a href="https://support.acme.example">Hilfe</a>"#));
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use openssl::sha::sha1;
+
+/// Offline copy of a breached password corpus in the k-anonymity format used by
+/// Have I Been Pwned: SHA-1 hashes split into a 5 character prefix and the remaining suffix.
+#[derive(Default)]
+pub struct BreachedPasswordCorpus {
+    ranges: HashMap<String, HashSet<String>>,
+}
+
+impl BreachedPasswordCorpus {
+    /// Loads the corpus from either a single file with `HASH[:COUNT]` lines
+    /// or a directory of range files named after their prefix containing `SUFFIX[:COUNT]` lines.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, anyhow::Error> {
+        let path = path.as_ref();
+        let mut corpus = Self::default();
+
+        if path.is_dir() {
+            for entry in fs::read_dir(path)? {
+                let entry = entry?;
+                let file_name = entry.file_name();
+                let prefix = file_name
+                    .to_string_lossy()
+                    .split('.')
+                    .next()
+                    .unwrap_or_default()
+                    .to_uppercase();
+
+                if prefix.len() != 5 {
+                    continue;
+                }
+
+                let content = fs::read_to_string(entry.path())?;
+                for line in content.lines() {
+                    corpus.insert_suffix(&prefix, line);
+                }
+            }
+        } else {
+            let content = fs::read_to_string(path)?;
+            for line in content.lines() {
+                let line = line.trim();
+                if line.len() < 40 {
+                    continue;
+                }
+                let (prefix, suffix) = line.split_at(5);
+                corpus.insert_suffix(&prefix.to_uppercase(), suffix);
+            }
+        }
+
+        Ok(corpus)
+    }
+
+    fn insert_suffix(&mut self, prefix: &str, line: &str) {
+        let suffix = line.split(':').next().unwrap_or_default().trim();
+        if suffix.len() != 35 {
+            return;
+        }
+
+        self.ranges
+            .entry(prefix.to_owned())
+            .or_default()
+            .insert(suffix.to_uppercase());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn contains(&self, password: &str) -> bool {
+        let hash = sha1_hex(password);
+        let (prefix, suffix) = hash.split_at(5);
+
+        self.ranges
+            .get(prefix)
+            .is_some_and(|suffixes| suffixes.contains(suffix))
+    }
+}
+
+fn sha1_hex(password: &str) -> String {
+    sha1(password.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SHA-1 of "password"
+    const PASSWORD_SHA1: &str = "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8";
+
+    #[test]
+    fn test_sha1_hex_matches_known_digest() {
+        assert_eq!(sha1_hex("password"), PASSWORD_SHA1);
+    }
+
+    #[test]
+    fn test_contains_from_hash_file() {
+        let path = std::env::temp_dir().join(format!("breached-{}.txt", uuid::Uuid::new_v4()));
+        fs::write(&path, format!("{PASSWORD_SHA1}:3861493\n")).unwrap();
+
+        let corpus = BreachedPasswordCorpus::from_path(&path).expect("failed to load corpus");
+        fs::remove_file(&path).ok();
+
+        assert!(corpus.contains("password"));
+        assert!(!corpus.contains("correct horse battery staple"));
+    }
+
+    #[test]
+    fn test_contains_from_range_directory() {
+        let dir = std::env::temp_dir().join(format!("breached-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let (prefix, suffix) = PASSWORD_SHA1.split_at(5);
+        fs::write(
+            dir.join(format!("{prefix}.txt")),
+            format!("{suffix}:3861493\r\n"),
+        )
+        .unwrap();
+
+        let corpus = BreachedPasswordCorpus::from_path(&dir).expect("failed to load corpus");
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(corpus.contains("password"));
+    }
+
+    #[test]
+    fn test_empty_corpus_contains_nothing() {
+        let corpus = BreachedPasswordCorpus::default();
+        assert!(corpus.is_empty());
+        assert!(!corpus.contains("password"));
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Value, json};
+
+use crate::models::{
+    claims::{ClaimRequest, ClaimsRequest},
+    user_models::UserClaimsSQL,
+};
+
+/// Standard claims released by each scope (OIDC Core 5.4)
+const SCOPE_CLAIMS: [(&str, &[&str]); 4] = [
+    (
+        "profile",
+        &[
+            "name",
+            "family_name",
+            "given_name",
+            "middle_name",
+            "nickname",
+            "preferred_username",
+            "picture",
+            "website",
+            "gender",
+            "birthdate",
+            "zoneinfo",
+            "locale",
+            "updated_at",
+        ],
+    ),
+    ("email", &["email", "email_verified"]),
+    ("address", &["address"]),
+    ("phone", &["phone_number", "phone_number_verified"]),
+];
+
+/// Whether `name` is one of the standard claims about the user, e.g. `email_verified`
+pub fn is_standard_claim(name: &str) -> bool {
+    SCOPE_CLAIMS
+        .iter()
+        .any(|(_, claims)| claims.contains(&name))
+}
+
+pub fn has_scope(scope: Option<&str>, value: &str) -> bool {
+    scope.is_some_and(|scope| scope.split_whitespace().any(|s| s == value))
+}
+
+/// Whether every requested scope is one the application may request, `None` allows any scope
+pub fn scopes_allowed(scope: Option<&str>, allowed_scopes: Option<&[String]>) -> bool {
+    allowed_scopes.is_none_or(|allowed_scopes| {
+        scope
+            .unwrap_or_default()
+            .split_whitespace()
+            .all(|scope| allowed_scopes.iter().any(|allowed| allowed == scope))
+    })
+}
+
+/// Narrow `granted` to the `requested` scopes, `None` if a scope was requested that is not granted
+pub fn downscope(granted: Option<&str>, requested: Option<&str>) -> Option<Option<String>> {
+    let Some(requested) = requested.filter(|requested| !requested.trim().is_empty()) else {
+        return Some(granted.map(str::to_owned));
+    };
+
+    requested
+        .split_whitespace()
+        .all(|scope| has_scope(granted, scope))
+        .then(|| Some(requested.split_whitespace().collect::<Vec<_>>().join(" ")))
+}
+
+/// All standard claims with a value for the user, `sub` excluded
+pub fn standard_claim_values(user: &UserClaimsSQL) -> Map<String, Value> {
+    let mut claims = Map::new();
+    let mut insert = |name: &str, value: &Option<String>| {
+        if let Some(value) = value {
+            claims.insert(name.to_owned(), json!(value));
+        }
+    };
+
+    insert("name", &user.name.clone().or(Some(user.username.clone())));
+    insert("given_name", &user.given_name);
+    insert("family_name", &user.family_name);
+    insert("middle_name", &user.middle_name);
+    insert("nickname", &user.nickname);
+    insert("preferred_username", &Some(user.username.clone()));
+    insert("picture", &user.picture);
+    insert("website", &user.website);
+    insert("gender", &user.gender);
+    insert("birthdate", &user.birthdate);
+    insert("zoneinfo", &user.zoneinfo);
+    insert("locale", &user.locale);
+    insert("email", &Some(user.email.clone()));
+    insert("phone_number", &user.phone_number);
+
+    claims.insert("email_verified".to_owned(), json!(user.email_verified));
+    if user.phone_number.is_some() {
+        claims.insert(
+            "phone_number_verified".to_owned(),
+            json!(user.phone_number_verified),
+        );
+    }
+    if let Some(updated_at) = user.updated_at {
+        claims.insert("updated_at".to_owned(), json!(updated_at));
+    }
+
+    let mut address = Map::new();
+    for (name, value) in [
+        ("street_address", &user.street_address),
+        ("locality", &user.locality),
+        ("region", &user.region),
+        ("postal_code", &user.postal_code),
+        ("country", &user.country),
+    ] {
+        if let Some(value) = value {
+            address.insert(name.to_owned(), json!(value));
+        }
+    }
+    if !address.is_empty() {
+        claims.insert("address".to_owned(), Value::Object(address));
+    }
+
+    claims
+}
+
+/// Select the claims released for the granted `scope` plus the individually requested ones
+///
+/// Claims requested with `value` or `values` are only released if the user's value matches.
+/// Essential and voluntary claims are treated alike: there is no consent step at which the user
+/// could withhold voluntary ones, and essential ones the user has no (matching) value for are
+/// left out rather than failing the request (OIDC Core 5.5.1).
+pub fn select_claims(
+    available: &Map<String, Value>,
+    scope: Option<&str>,
+    requested: Option<&HashMap<String, Option<ClaimRequest>>>,
+) -> Map<String, Value> {
+    let mut names: Vec<&str> = SCOPE_CLAIMS
+        .iter()
+        .filter(|(claim_scope, _)| has_scope(scope, claim_scope))
+        .flat_map(|(_, claims)| claims.iter().copied())
+        .collect();
+    if let Some(requested) = requested {
+        names.extend(requested.keys().map(String::as_str));
+    }
+
+    let mut selected = Map::new();
+    for name in names {
+        let Some(value) = available.get(name) else {
+            continue;
+        };
+        let request = requested
+            .and_then(|requested| requested.get(name))
+            .and_then(Option::as_ref);
+        if request.is_none_or(|request| claim_value_matches(request, value)) {
+            selected.insert(name.to_owned(), value.clone());
+        }
+    }
+
+    selected
+}
+
+/// Returns false if the `claims` parameter asks for an ID token about a different subject
+pub fn subject_matches(claims_request: Option<&ClaimsRequest>, subject: &str) -> bool {
+    claims_request
+        .and_then(|claims_request| claims_request.id_token.get("sub"))
+        .and_then(Option::as_ref)
+        .is_none_or(|request| claim_value_matches(request, &json!(subject)))
+}
+
+fn claim_value_matches(request: &ClaimRequest, value: &Value) -> bool {
+    if let Some(expected) = &request.value
+        && expected != value
+    {
+        return false;
+    }
+    if let Some(expected) = &request.values
+        && !expected.contains(value)
+    {
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn available() -> Map<String, Value> {
+        json!({
+            "name": "Test User",
+            "preferred_username": "testuser1",
+            "email": "testuser1@example.com",
+            "email_verified": true,
+            "phone_number": "+49 30 1234567",
+            "phone_number_verified": false,
+            "address": { "locality": "Berlin", "country": "DE" },
+        })
+        .as_object()
+        .unwrap()
+        .clone()
+    }
+
+    #[test]
+    fn test_scopes_release_matching_claims() {
+        let claims = select_claims(&available(), Some("openid email"), None);
+
+        assert_eq!(claims.len(), 2);
+        assert_eq!(claims["email"], "testuser1@example.com");
+        assert_eq!(claims["email_verified"], true);
+
+        let claims = select_claims(&available(), Some("openid profile address"), None);
+        assert_eq!(claims["name"], "Test User");
+        assert_eq!(claims["address"]["country"], "DE");
+        assert!(!claims.contains_key("email"));
+        assert!(!claims.contains_key("phone_number"));
+
+        assert!(select_claims(&available(), Some("openid"), None).is_empty());
+    }
+
+    #[test]
+    fn test_claims_parameter_requests_individual_claims() {
+        let claims_request: ClaimsRequest = serde_json::from_value(json!({
+            "id_token": {
+                "phone_number": null,
+                "email": { "essential": true },
+                "name": { "value": "Someone Else" },
+                "picture": null,
+                // Essential claims without a (matching) value are left out, too
+                "nickname": { "essential": true },
+                "preferred_username": { "essential": true, "value": "someone" },
+            }
+        }))
+        .unwrap();
+
+        let claims = select_claims(&available(), Some("openid"), Some(&claims_request.id_token));
+
+        assert_eq!(claims.len(), 2);
+        assert_eq!(claims["phone_number"], "+49 30 1234567");
+        assert_eq!(claims["email"], "testuser1@example.com");
+    }
+
+    #[test]
+    fn test_downscope() {
+        let granted = Some("openid email orders:read orders:write");
+
+        assert_eq!(downscope(granted, None), Some(granted.map(str::to_owned)));
+        assert_eq!(
+            downscope(granted, Some(" orders:read  email")),
+            Some(Some("orders:read email".to_owned()))
+        );
+        assert_eq!(downscope(granted, Some("orders:read admin")), None);
+        assert_eq!(downscope(None, Some("openid")), None);
+    }
+
+    #[test]
+    fn test_scopes_allowed() {
+        let allowed_scopes = ["openid".to_owned(), "email".to_owned()];
+
+        assert!(scopes_allowed(Some("openid profile"), None));
+        assert!(scopes_allowed(Some("email openid"), Some(&allowed_scopes)));
+        assert!(scopes_allowed(None, Some(&allowed_scopes)));
+        assert!(!scopes_allowed(
+            Some("openid profile"),
+            Some(&allowed_scopes)
+        ));
+    }
+
+    #[test]
+    fn test_subject_matches() {
+        let claims_request: ClaimsRequest = serde_json::from_value(json!({
+            "id_token": { "sub": { "value": "user-1" } }
+        }))
+        .unwrap();
+
+        assert!(subject_matches(None, "user-1"));
+        assert!(subject_matches(Some(&claims_request), "user-1"));
+        assert!(!subject_matches(Some(&claims_request), "user-2"));
+    }
+}
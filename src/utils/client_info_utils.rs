@@ -0,0 +1,13 @@
+use std::net::SocketAddr;
+
+use axum::http::{HeaderMap, header::USER_AGENT};
+
+/// Returns the client IP address and user agent of a request
+pub fn client_info(addr: &SocketAddr, headers: &HeaderMap) -> (Option<String>, Option<String>) {
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+
+    (Some(addr.ip().to_string()), user_agent)
+}
@@ -0,0 +1,219 @@
+use reqwest::Url;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::models::{
+    application_model::{SubjectType, TokenEndpointAuthMethod, TokenLifetimes},
+    client_registration::{ClientMetadata, RegisteredClientSQL},
+    config::application::Application,
+};
+
+#[derive(Debug, Error)]
+pub enum RegistrationError {
+    #[error("{0}")]
+    InvalidRedirectUri(String),
+    #[error("{0}")]
+    InvalidClientMetadata(String),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl RegistrationError {
+    /// `error` of the error response (RFC 7591 3.2.2)
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            RegistrationError::InvalidRedirectUri(_) => "invalid_redirect_uri",
+            RegistrationError::InvalidClientMetadata(_) => "invalid_client_metadata",
+            RegistrationError::Internal(_) => "server_error",
+        }
+    }
+}
+
+/// Checks the URIs of client metadata, the rest is validated like configured applications
+pub fn validate_client_metadata(metadata: &ClientMetadata) -> Result<(), RegistrationError> {
+    if metadata.redirect_uris.is_empty() {
+        return Err(RegistrationError::InvalidRedirectUri(
+            "redirect_uris is required".to_string(),
+        ));
+    }
+    if let Some(uri) = metadata
+        .redirect_uris
+        .iter()
+        .chain(&metadata.post_logout_redirect_uris)
+        .find(|uri| !is_valid_redirect_uri(uri))
+    {
+        return Err(RegistrationError::InvalidRedirectUri(format!(
+            "{uri} is not a valid redirect URI"
+        )));
+    }
+
+    for (name, uri) in [
+        ("client_uri", &metadata.client_uri),
+        ("jwks_uri", &metadata.jwks_uri),
+        ("sector_identifier_uri", &metadata.sector_identifier_uri),
+    ] {
+        if let Some(uri) = uri
+            && Url::parse(uri).map_or(true, |url| url.scheme() != "https")
+        {
+            return Err(RegistrationError::InvalidClientMetadata(format!(
+                "{name} must be an https URL"
+            )));
+        }
+    }
+    if metadata.jwks.is_some() && metadata.jwks_uri.is_some() {
+        return Err(RegistrationError::InvalidClientMetadata(
+            "jwks and jwks_uri cannot both be present".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Absolute URIs without fragment, plain http only for loopback redirects of native apps.
+/// Other schemes have to be private-use schemes of native apps (RFC 8252 7.1), so scripts
+/// like `javascript:` cannot be the target of redirects and form posts.
+fn is_valid_redirect_uri(uri: &str) -> bool {
+    let Ok(url) = Url::parse(uri) else {
+        return false;
+    };
+    if url.fragment().is_some() {
+        return false;
+    }
+
+    match url.scheme() {
+        "https" => url.host_str().is_some(),
+        "http" => matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")),
+        scheme => is_private_use_scheme(scheme),
+    }
+}
+
+/// Reverse domain name scheme like `com.example.app`
+fn is_private_use_scheme(scheme: &str) -> bool {
+    scheme.contains('.') && scheme.split('.').all(|label| !label.is_empty())
+}
+
+/// Application a client is stored as, registered clients get no SAML or token exchange settings
+pub fn registered_application(
+    id: Uuid,
+    tenant_id: Uuid,
+    client_id: &str,
+    client_secret: &str,
+    metadata: &ClientMetadata,
+) -> Application {
+    Application {
+        id,
+        tenant_id,
+        name: metadata
+            .client_name
+            .clone()
+            .unwrap_or_else(|| client_id.to_string()),
+        client_id: client_id.to_string(),
+        client_secret: client_secret.to_string(),
+        uri: metadata.client_uri.clone().unwrap_or_default(),
+        redirect_uris: metadata.redirect_uris.clone(),
+        post_logout_redirect_uris: metadata.post_logout_redirect_uris.clone(),
+        subject_type: metadata.subject_type,
+        sector_identifier_uri: metadata.sector_identifier_uri.clone(),
+        jwks: metadata.jwks.clone(),
+        jwks_uri: metadata.jwks_uri.clone(),
+        token_endpoint_auth_method: metadata.token_endpoint_auth_method,
+        tls_client_auth_subject_dn: metadata.tls_client_auth_subject_dn.clone(),
+        tls_client_certificate_bound_access_tokens: metadata
+            .tls_client_certificate_bound_access_tokens,
+        require_pushed_authorization_requests: metadata.require_pushed_authorization_requests,
+        grant_types: metadata.grant_types.clone(),
+        response_types: metadata.response_types.clone(),
+        allowed_scopes: metadata
+            .scope
+            .as_ref()
+            .map(|scope| scope.split_whitespace().map(str::to_owned).collect()),
+        token_lifetimes: TokenLifetimes::default(),
+        token_exchange_audiences: Vec::new(),
+        claim_mappings: Vec::new(),
+        saml: None,
+        created_at: None,
+        updated_at: None,
+    }
+}
+
+/// Client metadata of a stored application
+pub fn client_metadata(client: RegisteredClientSQL) -> ClientMetadata {
+    ClientMetadata {
+        redirect_uris: client.redirect_uris,
+        post_logout_redirect_uris: client.post_logout_redirect_uris,
+        client_name: Some(client.name),
+        client_uri: Some(client.uri).filter(|uri| !uri.is_empty()),
+        grant_types: client.grant_types,
+        response_types: client.response_types,
+        scope: client.allowed_scopes.map(|scopes| scopes.join(" ")),
+        token_endpoint_auth_method: TokenEndpointAuthMethod::parse(
+            &client.token_endpoint_auth_method,
+        )
+        .unwrap_or_default(),
+        subject_type: SubjectType::parse(&client.subject_type).unwrap_or_default(),
+        sector_identifier_uri: client.sector_identifier_uri,
+        jwks: client.jwks.map(|jwks| jwks.0),
+        jwks_uri: client.jwks_uri,
+        tls_client_auth_subject_dn: client.tls_client_auth_subject_dn,
+        tls_client_certificate_bound_access_tokens: client
+            .tls_client_certificate_bound_access_tokens,
+        require_pushed_authorization_requests: client.require_pushed_authorization_requests,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(redirect_uris: &[&str]) -> ClientMetadata {
+        serde_json::from_value(serde_json::json!({ "redirect_uris": redirect_uris })).unwrap()
+    }
+
+    #[test]
+    fn test_validate_client_metadata() {
+        assert!(
+            validate_client_metadata(&metadata(&[
+                "https://app.example.com/callback",
+                "http://127.0.0.1:8400/callback",
+                "com.example.app:/callback",
+            ]))
+            .is_ok()
+        );
+        assert!(matches!(
+            validate_client_metadata(&metadata(&[])),
+            Err(RegistrationError::InvalidRedirectUri(_))
+        ));
+        assert!(matches!(
+            validate_client_metadata(&metadata(&["http://app.example.com/callback"])),
+            Err(RegistrationError::InvalidRedirectUri(_))
+        ));
+        assert!(matches!(
+            validate_client_metadata(&metadata(&["https://app.example.com/callback#token"])),
+            Err(RegistrationError::InvalidRedirectUri(_))
+        ));
+        for uri in [
+            "javascript:alert(document.domain)//",
+            "data:text/html,<script>alert(1)</script>",
+            "vbscript:msgbox(1)",
+            "file:///etc/passwd",
+            "myapp:/callback",
+        ] {
+            assert!(
+                matches!(
+                    validate_client_metadata(&metadata(&[uri])),
+                    Err(RegistrationError::InvalidRedirectUri(_))
+                ),
+                "{uri} was accepted"
+            );
+        }
+
+        let mut insecure_keys = metadata(&["https://app.example.com/callback"]);
+        insecure_keys.jwks_uri = Some("http://app.example.com/jwks.json".to_string());
+        assert_eq!(
+            validate_client_metadata(&insecure_keys)
+                .unwrap_err()
+                .error_code(),
+            "invalid_client_metadata"
+        );
+    }
+}
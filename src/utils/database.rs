@@ -23,3 +23,100 @@ pub async fn create_postgres_pool() -> Result<Pool<Postgres>, sqlx::Error> {
 
     Ok(pool)
 }
+
+/// Pool for tests against the migrated database at `DATABASE_URL`
+#[cfg(test)]
+pub async fn test_postgres_pool() -> Pool<Postgres> {
+    dotenv().ok();
+
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    PgPoolOptions::new()
+        .max_connections(2)
+        .connect(&database_url)
+        .await
+        .expect("Failed to connect to the test database")
+}
+
+/// Creates a tenant for a test, deleting it deletes everything the test created in it
+#[cfg(test)]
+pub async fn create_test_tenant(pool: &Pool<Postgres>) -> uuid::Uuid {
+    let tenant_id = uuid::Uuid::new_v4();
+    sqlx::query("INSERT INTO Tenants (id, name) VALUES ($1, $2)")
+        .bind(tenant_id)
+        .bind(format!("test-{tenant_id}"))
+        .execute(pool)
+        .await
+        .expect("Failed to create test tenant");
+
+    tenant_id
+}
+
+/// Creates an active user of `tenant_id`, an admin of the tenant if `admin` is set
+#[cfg(test)]
+pub async fn create_test_user(
+    pool: &Pool<Postgres>,
+    tenant_id: uuid::Uuid,
+    admin: bool,
+) -> uuid::Uuid {
+    let user_id = uuid::Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO Users (id, tenant_id, username, email, password_hash) \
+         VALUES ($1, $2, $3, $4, '')",
+    )
+    .bind(user_id)
+    .bind(tenant_id)
+    .bind(user_id.to_string())
+    .bind(format!("{user_id}@example.com"))
+    .execute(pool)
+    .await
+    .expect("Failed to create test user");
+
+    if admin {
+        let role_id = uuid::Uuid::new_v4();
+        sqlx::query("INSERT INTO Roles (id, tenant_id, name) VALUES ($1, $2, 'admin')")
+            .bind(role_id)
+            .bind(tenant_id)
+            .execute(pool)
+            .await
+            .expect("Failed to create admin role");
+        sqlx::query("INSERT INTO UserRoles (user_id, role_id) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(role_id)
+            .execute(pool)
+            .await
+            .expect("Failed to assign admin role");
+    }
+
+    user_id
+}
+
+/// Creates an application of `tenant_id` with the secret `secret` that redirects to
+/// `https://app.example/callback`, returns its client ID
+#[cfg(test)]
+pub async fn create_test_application(pool: &Pool<Postgres>, tenant_id: uuid::Uuid) -> String {
+    let client_id = uuid::Uuid::new_v4().to_string();
+    sqlx::query(
+        "INSERT INTO Applications (id, tenant_id, name, client_id, client_secret, uri, \
+         redirect_uris, post_logout_redirect_uris) \
+         VALUES ($1, $2, 'Test app', $3, 'secret', 'https://app.example', \
+         ARRAY['https://app.example/callback'], ARRAY[]::TEXT[])",
+    )
+    .bind(uuid::Uuid::new_v4())
+    .bind(tenant_id)
+    .bind(&client_id)
+    .execute(pool)
+    .await
+    .expect("Failed to create test application");
+
+    client_id
+}
+
+/// Deletes a tenant created by [`create_test_tenant`] with its users
+#[cfg(test)]
+pub async fn delete_test_tenant(pool: &Pool<Postgres>, tenant_id: uuid::Uuid) {
+    sqlx::query("DELETE FROM Tenants WHERE id = $1")
+        .bind(tenant_id)
+        .execute(pool)
+        .await
+        .expect("Failed to delete test tenant");
+}
@@ -0,0 +1,143 @@
+use openssl::rand::rand_bytes;
+
+use crate::utils::branding_utils::{PageBranding, page};
+
+/// Consonants only, so codes cannot spell words and are not confused with digits (RFC 8628 6.1)
+const USER_CODE_ALPHABET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+const USER_CODE_LENGTH: usize = 8;
+
+/// Random user code, normalized without separator
+pub fn generate_user_code() -> Result<String, anyhow::Error> {
+    let mut code = String::with_capacity(USER_CODE_LENGTH);
+    // Rejection sampling keeps every character equally likely
+    let limit = 256 - 256 % USER_CODE_ALPHABET.len();
+    while code.len() < USER_CODE_LENGTH {
+        let mut bytes = [0u8; 16];
+        rand_bytes(&mut bytes)?;
+        code.extend(
+            bytes
+                .iter()
+                .filter(|byte| (**byte as usize) < limit)
+                .map(|byte| USER_CODE_ALPHABET[*byte as usize % USER_CODE_ALPHABET.len()] as char)
+                .take(USER_CODE_LENGTH - code.len()),
+        );
+    }
+    Ok(code)
+}
+
+/// Uppercase without separators and whitespace, `None` if it cannot be a user code
+pub fn normalize_user_code(user_code: &str) -> Option<String> {
+    let normalized: String = user_code
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    (normalized.len() == USER_CODE_LENGTH
+        && normalized.bytes().all(|c| USER_CODE_ALPHABET.contains(&c)))
+    .then_some(normalized)
+}
+
+/// `BCDF-GHJK`, as shown to the user
+pub fn format_user_code(user_code: &str) -> String {
+    let (first, second) = user_code.split_at(user_code.len() / 2);
+    format!("{first}-{second}")
+}
+
+pub fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Form to enter the code shown on the device
+pub fn user_code_page(branding: &PageBranding, error: Option<&str>) -> String {
+    let error = error
+        .map(|error| format!(r#"<p style="color: #b91c1c">{}</p>"#, escape_html(error)))
+        .unwrap_or_default();
+    page(
+        branding,
+        branding.text("device_title", "Connect a device"),
+        &format!(
+            r#"{error}<p>{prompt}</p><form method="get" action="/oauth/device"><input name="user_code" autocomplete="off" autofocus placeholder="XXXX-XXXX" style="font-size: 1.5rem; letter-spacing: 0.2rem; text-transform: uppercase"> <button type="submit">{continue_label}</button></form>"#,
+            prompt = escape_html(
+                branding.text("device_code_prompt", "Enter the code shown on your device.")
+            ),
+            continue_label = escape_html(branding.text("continue", "Continue")),
+        ),
+    )
+}
+
+/// Confirmation of the application and scopes the device asks for
+pub fn approval_page(
+    branding: &PageBranding,
+    user_code: &str,
+    application_name: &str,
+    scope: Option<&str>,
+) -> String {
+    let scopes: String = scope
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(|scope| format!("<li>{}</li>", escape_html(scope)))
+        .collect();
+    let scopes = match scopes.is_empty() {
+        true => String::new(),
+        false => format!("<p>It asks for access to:</p><ul>{scopes}</ul>"),
+    };
+
+    page(
+        branding,
+        branding.text("device_title", "Connect a device"),
+        &format!(
+            r#"<p><strong>{application}</strong> wants to sign in on a device showing the code <strong>{code}</strong>.</p>{scopes}<p>Only continue if you started this sign-in and the code matches.</p><form method="post" action="/oauth/device"><input type="hidden" name="user_code" value="{user_code}"><button type="submit" name="action" value="approve">{approve}</button> <button type="submit" name="action" value="deny">{deny}</button></form>"#,
+            application = escape_html(application_name),
+            code = format_user_code(user_code),
+            user_code = escape_html(user_code),
+            approve = escape_html(branding.text("approve", "Approve")),
+            deny = escape_html(branding.text("deny", "Deny")),
+        ),
+    )
+}
+
+pub fn result_page(branding: &PageBranding, approved: bool) -> String {
+    match approved {
+        true => page(
+            branding,
+            branding.text("device_connected_title", "Device connected"),
+            "<p>You can close this window and return to your device.</p>",
+        ),
+        false => page(
+            branding,
+            branding.text("device_denied_title", "Request denied"),
+            "<p>The device was not signed in. You can close this window.</p>",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_codes() {
+        let user_code = generate_user_code().unwrap();
+        assert_eq!(user_code.len(), USER_CODE_LENGTH);
+        assert_eq!(normalize_user_code(&user_code), Some(user_code.clone()));
+
+        // Codes are typed by users, in any case and with or without separator
+        let formatted = format_user_code(&user_code).to_lowercase();
+        assert_eq!(formatted.len(), USER_CODE_LENGTH + 1);
+        assert_eq!(normalize_user_code(&formatted), Some(user_code));
+        assert_eq!(
+            normalize_user_code(" wdjb mjht "),
+            Some("WDJBMJHT".to_string())
+        );
+
+        assert_eq!(normalize_user_code("WDJB-MJH"), None);
+        assert_eq!(normalize_user_code("WDJB-MJH1"), None);
+        assert_eq!(normalize_user_code("AEIO-UAEI"), None);
+    }
+}
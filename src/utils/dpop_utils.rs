@@ -0,0 +1,212 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::Utc;
+use jsonwebtoken::{
+    Algorithm, DecodingKey, Validation, decode, decode_header,
+    jwk::{AlgorithmParameters, Jwk},
+};
+use openssl::sha::sha256;
+use serde_json::Value;
+
+use crate::models::{
+    claims::AccessTokenClaims,
+    dpop::{DpopProof, DpopProofClaims},
+};
+
+/// Signature algorithms accepted for DPoP proofs, symmetric keys cannot prove possession
+pub const DPOP_ALGORITHMS: [Algorithm; 9] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+/// Seconds a proof's `iat` may differ from the server's clock
+pub const DPOP_PROOF_MAX_AGE: i64 = 300;
+
+/// Verify a DPoP proof sent with a `method` request to `url`. Proofs sent to resource servers
+/// also have to carry the hash of the `access_token` (RFC 9449 4.3)
+pub fn verify_dpop_proof(
+    proof: &str,
+    method: &str,
+    url: &str,
+    access_token: Option<&str>,
+) -> Result<DpopProof, anyhow::Error> {
+    let header = decode_header(proof)?;
+    if header.typ.as_deref() != Some("dpop+jwt") {
+        return Err(anyhow::anyhow!("DPoP proof has the wrong type"));
+    }
+    if !DPOP_ALGORITHMS.contains(&header.alg) {
+        return Err(anyhow::anyhow!(
+            "Unsupported DPoP proof algorithm {:?}",
+            header.alg
+        ));
+    }
+    let Some(jwk) = &header.jwk else {
+        return Err(anyhow::anyhow!("DPoP proof without key"));
+    };
+
+    // Proofs carry no `exp`, their age is checked against `iat` instead
+    let mut validation = Validation::new(header.alg);
+    validation.validate_exp = false;
+    validation.validate_aud = false;
+    validation.required_spec_claims.clear();
+    let claims =
+        decode::<DpopProofClaims>(proof, &DecodingKey::from_jwk(jwk)?, &validation)?.claims;
+
+    if (Utc::now().timestamp() - claims.iat).abs() > DPOP_PROOF_MAX_AGE {
+        return Err(anyhow::anyhow!("DPoP proof is too old"));
+    }
+    if claims.htm != method || !htu_matches(&claims.htu, url) {
+        return Err(anyhow::anyhow!("DPoP proof is for another request"));
+    }
+    if let Some(access_token) = access_token
+        && claims.ath.as_deref() != Some(access_token_hash(access_token).as_str())
+    {
+        return Err(anyhow::anyhow!("DPoP proof is for another access token"));
+    }
+
+    Ok(DpopProof {
+        jkt: jwk_thumbprint(jwk)?,
+        jti: claims.jti,
+        nonce: claims.nonce,
+    })
+}
+
+/// Verify that an access token bound to a DPoP key is presented with a proof of that key
+pub fn verify_dpop_binding(
+    claims: &AccessTokenClaims,
+    access_token: &str,
+    proof: &str,
+    method: &str,
+    url: &str,
+) -> Result<DpopProof, anyhow::Error> {
+    let proof = verify_dpop_proof(proof, method, url, Some(access_token))?;
+
+    if bound_key(claims) != Some(proof.jkt.as_str()) {
+        return Err(anyhow::anyhow!("DPoP proof key does not match the token"));
+    }
+
+    Ok(proof)
+}
+
+/// The `cnf.jkt` thumbprint of a sender-constrained access token
+pub fn bound_key(claims: &AccessTokenClaims) -> Option<&str> {
+    claims
+        .user_claims
+        .get("cnf")
+        .and_then(|cnf| cnf.get("jkt"))
+        .and_then(Value::as_str)
+}
+
+/// JWK SHA-256 thumbprint (RFC 7638)
+pub fn jwk_thumbprint(jwk: &Jwk) -> Result<String, anyhow::Error> {
+    // Only the required members, in lexicographic order and without whitespace
+    let members = match &jwk.algorithm {
+        AlgorithmParameters::RSA(rsa) => format!(
+            r#"{{"e":{},"kty":"RSA","n":{}}}"#,
+            serde_json::to_string(&rsa.e)?,
+            serde_json::to_string(&rsa.n)?
+        ),
+        AlgorithmParameters::EllipticCurve(ec) => format!(
+            r#"{{"crv":{},"kty":"EC","x":{},"y":{}}}"#,
+            serde_json::to_string(&ec.curve)?,
+            serde_json::to_string(&ec.x)?,
+            serde_json::to_string(&ec.y)?
+        ),
+        AlgorithmParameters::OctetKeyPair(okp) => format!(
+            r#"{{"crv":{},"kty":"OKP","x":{}}}"#,
+            serde_json::to_string(&okp.curve)?,
+            serde_json::to_string(&okp.x)?
+        ),
+        AlgorithmParameters::OctetKey(_) => {
+            return Err(anyhow::anyhow!("Symmetric keys have no public thumbprint"));
+        }
+    };
+
+    Ok(URL_SAFE_NO_PAD.encode(sha256(members.as_bytes())))
+}
+
+/// `ath` claim of proofs for `access_token`
+fn access_token_hash(access_token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(sha256(access_token.as_bytes()))
+}
+
+/// The URI of a request and a proof match without their query and fragment (RFC 9449 4.3)
+fn htu_matches(htu: &str, url: &str) -> bool {
+    let without_query = |uri: &str| uri.split(['?', '#']).next().unwrap_or_default().to_owned();
+
+    without_query(htu) == without_query(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{EncodingKey, Header, encode};
+    use rand::rngs::OsRng;
+    use rsa::{PublicKeyParts, RsaPrivateKey, pkcs8::EncodePrivateKey};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_jwk_thumbprint() {
+        // Example of RFC 7638 3.1
+        let jwk: Jwk = serde_json::from_value(json!({
+            "kty": "RSA",
+            "n": "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw",
+            "e": "AQAB",
+            "alg": "RS256",
+            "kid": "2011-04-29"
+        }))
+        .unwrap();
+
+        assert_eq!(
+            jwk_thumbprint(&jwk).unwrap(),
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+    }
+
+    #[test]
+    fn test_verify_dpop_proof() {
+        let private_key = RsaPrivateKey::new(&mut OsRng, 2048).unwrap();
+        let private_pem = private_key.to_pkcs8_pem(Default::default()).unwrap();
+        let jwk: Jwk = serde_json::from_value(json!({
+            "kty": "RSA",
+            "n": URL_SAFE_NO_PAD.encode(private_key.n().to_bytes_be()),
+            "e": URL_SAFE_NO_PAD.encode(private_key.e().to_bytes_be()),
+        }))
+        .unwrap();
+
+        let mut header = Header::new(Algorithm::RS256);
+        header.typ = Some("dpop+jwt".to_owned());
+        header.jwk = Some(jwk.clone());
+        let proof = encode(
+            &header,
+            &json!({
+                "jti": "proof-1",
+                "htm": "GET",
+                "htu": "https://sso.example.com/oauth/userinfo",
+                "iat": Utc::now().timestamp(),
+                "ath": access_token_hash("access-token"),
+            }),
+            &EncodingKey::from_rsa_pem(private_pem.as_bytes()).unwrap(),
+        )
+        .unwrap();
+
+        let url = "https://sso.example.com/oauth/userinfo?schema=openid";
+        let verified = verify_dpop_proof(&proof, "GET", url, Some("access-token")).unwrap();
+        assert_eq!(verified.jkt, jwk_thumbprint(&jwk).unwrap());
+        assert_eq!(verified.jti, "proof-1");
+
+        // Proofs only count for their request and access token
+        assert!(verify_dpop_proof(&proof, "POST", url, Some("access-token")).is_err());
+        assert!(
+            verify_dpop_proof(&proof, "GET", "https://sso.example.com/oauth/token", None).is_err()
+        );
+        assert!(verify_dpop_proof(&proof, "GET", url, Some("other-token")).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use cookie::{Cookie, time::Duration};
+use openssl::{memcmp, rand::rand_bytes, sha::sha256};
+use serde_json::{Map, Value};
+
+use crate::models::identity_provider::{FederatedUser, FederationClaimMapping};
+
+/// Cookie binding a federated login to the browser that started it
+pub const FEDERATION_STATE_COOKIE: &str = "federation_state";
+
+/// URL safe random value for `state`, `nonce` and PKCE verifiers
+pub fn random_token() -> Result<String, anyhow::Error> {
+    let mut bytes = [0u8; 32];
+    rand_bytes(&mut bytes)?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// PKCE `S256` code challenge (RFC 7636)
+pub fn pkce_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(sha256(code_verifier.as_bytes()))
+}
+
+/// Cookie with the hash of the login's `state`, so the callback of a login started in another
+/// browser is rejected (login CSRF)
+pub fn state_cookie(state: &str, max_age_seconds: u64) -> Cookie<'static> {
+    Cookie::build((FEDERATION_STATE_COOKIE, pkce_challenge(state)))
+        .path("/")
+        .http_only(true)
+        .secure(true)
+        .same_site(cookie::SameSite::Lax)
+        .max_age(Duration::seconds(max_age_seconds as i64))
+        .build()
+}
+
+/// Whether the `state` of a callback is the one of the browser's `federation_state` cookie
+pub fn state_matches_cookie(state: &str, cookie_value: Option<&str>) -> bool {
+    let Some(cookie_value) = cookie_value else {
+        return false;
+    };
+    let expected = pkce_challenge(state);
+
+    expected.len() == cookie_value.len() && memcmp::eq(expected.as_bytes(), cookie_value.as_bytes())
+}
+
+/// Only authorization, SAML and device requests on this server may be continued after a federated login
+pub fn is_valid_return_to(return_to: &str) -> bool {
+    return_to.starts_with("/authorize?")
+        || return_to.starts_with("/saml/")
+        || return_to == "/device"
+        || return_to.starts_with("/device?")
+}
+
+/// Read the user fields from verified upstream ID token claims
+pub fn federated_user(
+    claims: &Map<String, Value>,
+    mapping: &FederationClaimMapping,
+) -> Result<FederatedUser, anyhow::Error> {
+    let subject = claims
+        .get("sub")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow::anyhow!("Upstream ID token has no subject"))?;
+    let string_claim = |name: &str| {
+        claims
+            .get(name)
+            .and_then(Value::as_str)
+            .filter(|value| !value.is_empty())
+            .map(str::to_owned)
+    };
+
+    // Some providers send email_verified as a string
+    let email_verified = match claims.get(&mapping.email_verified) {
+        Some(Value::Bool(verified)) => *verified,
+        Some(Value::String(verified)) => verified == "true",
+        _ => false,
+    };
+
+    Ok(FederatedUser {
+        subject: subject.to_owned(),
+        email: string_claim(&mapping.email),
+        email_verified,
+        username: string_claim(&mapping.username),
+        name: string_claim(&mapping.name),
+        given_name: string_claim(&mapping.given_name),
+        family_name: string_claim(&mapping.family_name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_pkce_challenge() {
+        // Example from RFC 7636 Appendix B
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn test_federated_user_with_custom_mapping() {
+        let claims = json!({
+            "sub": "upstream-1",
+            "upn": "jane@corp.example",
+            "email_verified": "true",
+            "name": "Jane Doe",
+            "given_name": "",
+        });
+        let mapping = FederationClaimMapping {
+            email: "upn".to_string(),
+            ..Default::default()
+        };
+
+        let user = federated_user(claims.as_object().unwrap(), &mapping).unwrap();
+
+        assert_eq!(
+            user,
+            FederatedUser {
+                subject: "upstream-1".to_string(),
+                email: Some("jane@corp.example".to_string()),
+                email_verified: true,
+                name: Some("Jane Doe".to_string()),
+                ..Default::default()
+            }
+        );
+        assert!(federated_user(&Map::new(), &mapping).is_err());
+    }
+
+    #[test]
+    fn test_state_cookie() {
+        let cookie = state_cookie("state123", 600);
+        assert!(cookie.http_only().unwrap());
+        assert!(state_matches_cookie("state123", Some(cookie.value())));
+        assert!(!state_matches_cookie("state456", Some(cookie.value())));
+        assert!(!state_matches_cookie("state123", None));
+    }
+
+    #[test]
+    fn test_is_valid_return_to() {
+        assert!(is_valid_return_to("/authorize?client_id=abc"));
+        assert!(is_valid_return_to("/saml/sso?SAMLRequest=abc"));
+        assert!(is_valid_return_to("/device?user_code=WDJB-MJHT"));
+        assert!(!is_valid_return_to("/devicefoo"));
+        assert!(!is_valid_return_to("https://evil.example/authorize?"));
+        assert!(!is_valid_return_to("//evil.example"));
+    }
+}
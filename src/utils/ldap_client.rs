@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use ldap3::{LdapConnAsync, LdapConnSettings, Scope, SearchEntry};
+
+use crate::{
+    models::directory::{DirectoryConfig, DirectoryEntry},
+    utils::ldap_utils::{requested_attributes, user_search_filter},
+};
+
+/// LDAP result code for a failed bind with wrong credentials
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// Authenticates users against a directory, implemented by a mock in tests
+pub trait DirectoryClient: Send + Sync {
+    /// Bind as the entry `login` resolves to, `None` if the credentials are invalid
+    fn authenticate(
+        &self,
+        config: &DirectoryConfig,
+        login: &str,
+        password: &str,
+    ) -> impl Future<Output = Result<Option<DirectoryEntry>, anyhow::Error>> + Send;
+}
+
+/// Simple bind and search against an LDAP server
+pub struct LdapClient {
+    timeout: Duration,
+}
+
+impl LdapClient {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl DirectoryClient for LdapClient {
+    async fn authenticate(
+        &self,
+        config: &DirectoryConfig,
+        login: &str,
+        password: &str,
+    ) -> Result<Option<DirectoryEntry>, anyhow::Error> {
+        // A bind without password is an unauthenticated bind, which servers accept for any DN
+        if password.is_empty() {
+            return Ok(None);
+        }
+
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(self.timeout)
+            .set_starttls(config.starttls);
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &config.url).await?;
+        ldap3::drive!(conn);
+
+        if let Some(bind_dn) = &config.bind_dn {
+            ldap.with_timeout(self.timeout)
+                .simple_bind(bind_dn, config.bind_password.as_deref().unwrap_or_default())
+                .await?
+                .success()?;
+        }
+
+        let (entries, _) = ldap
+            .with_timeout(self.timeout)
+            .search(
+                &config.base_dn,
+                Scope::Subtree,
+                &user_search_filter(&config.user_filter, login),
+                requested_attributes(config),
+            )
+            .await?
+            .success()?;
+
+        // Ambiguous logins are rejected rather than guessing the entry
+        let mut entries = entries.into_iter();
+        let (Some(entry), None) = (entries.next(), entries.next()) else {
+            ldap.unbind().await?;
+            return Ok(None);
+        };
+        let entry = SearchEntry::construct(entry);
+
+        let result = ldap
+            .with_timeout(self.timeout)
+            .simple_bind(&entry.dn, password)
+            .await?;
+        ldap.unbind().await?;
+
+        match result.rc {
+            0 => Ok(Some(DirectoryEntry {
+                dn: entry.dn,
+                attributes: entry
+                    .attrs
+                    .into_iter()
+                    .map(|(name, values)| (name.to_lowercase(), values))
+                    .collect(),
+            })),
+            INVALID_CREDENTIALS => Ok(None),
+            _ => Err(anyhow::anyhow!("LDAP bind failed: {}", result)),
+        }
+    }
+}
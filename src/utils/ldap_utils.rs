@@ -0,0 +1,307 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use ldap3::ldap_escape;
+use openssl::{memcmp, rand::rand_bytes, sha::Sha256};
+use serde_json::Value;
+
+use crate::{
+    models::directory::{DirectoryConfig, DirectoryEntry, DirectoryUser},
+    utils::ldap_client::DirectoryClient,
+};
+
+/// Successful binds kept in memory, so logins keep working during short directory outages
+pub struct DirectoryCache {
+    salt: [u8; 16],
+    entries: Mutex<HashMap<String, CachedBind>>,
+}
+
+struct CachedBind {
+    password_digest: [u8; 32],
+    entry: DirectoryEntry,
+    expires_at: Instant,
+}
+
+impl DirectoryCache {
+    pub fn new() -> Result<Self, anyhow::Error> {
+        let mut salt = [0u8; 16];
+        rand_bytes(&mut salt)?;
+
+        Ok(Self {
+            salt,
+            entries: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn digest(&self, password: &str) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(&self.salt);
+        hasher.update(password.as_bytes());
+        hasher.finish()
+    }
+
+    fn get(&self, key: &str, password: &str) -> Option<DirectoryEntry> {
+        let mut entries = self.entries.lock().ok()?;
+        entries.retain(|_, cached| cached.expires_at > Instant::now());
+
+        entries
+            .get(key)
+            .filter(|cached| memcmp::eq(&cached.password_digest, &self.digest(password)))
+            .map(|cached| cached.entry.clone())
+    }
+
+    fn insert(&self, key: String, password: &str, entry: DirectoryEntry, ttl: Duration) {
+        let cached = CachedBind {
+            password_digest: self.digest(password),
+            entry,
+            expires_at: Instant::now() + ttl,
+        };
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key, cached);
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(key);
+        }
+    }
+}
+
+/// Authenticate against the directory unless the same credentials succeeded recently
+pub async fn authenticate_cached<C: DirectoryClient>(
+    client: &C,
+    cache: &DirectoryCache,
+    config: &DirectoryConfig,
+    login: &str,
+    password: &str,
+) -> Result<Option<DirectoryEntry>, anyhow::Error> {
+    let key = format!("{}|{}|{}", config.url, config.base_dn, login.to_lowercase());
+    if config.cache_ttl_seconds > 0
+        && let Some(entry) = cache.get(&key, password)
+    {
+        return Ok(Some(entry));
+    }
+
+    let entry = client.authenticate(config, login, password).await?;
+    match &entry {
+        Some(entry) if config.cache_ttl_seconds > 0 => cache.insert(
+            key,
+            password,
+            entry.clone(),
+            Duration::from_secs(config.cache_ttl_seconds),
+        ),
+        // The password was changed in the directory
+        None => cache.remove(&key),
+        Some(_) => {}
+    }
+
+    Ok(entry)
+}
+
+/// The user search filter with `{login}` replaced by the escaped (RFC 4515) login
+pub fn user_search_filter(filter: &str, login: &str) -> String {
+    filter.replace("{login}", &ldap_escape(login))
+}
+
+/// Directory attributes needed to map an entry onto a user
+pub fn requested_attributes(config: &DirectoryConfig) -> Vec<String> {
+    let mapping = &config.attribute_mapping;
+    let mut attributes = vec![
+        mapping.email.clone(),
+        mapping.username.clone(),
+        mapping.name.clone(),
+        mapping.given_name.clone(),
+        mapping.family_name.clone(),
+        mapping.phone_number.clone(),
+        config.group_attribute.clone(),
+    ];
+    attributes.extend(mapping.attributes.values().cloned());
+    attributes.sort();
+    attributes.dedup();
+    attributes
+}
+
+/// Map a directory entry onto user fields, custom attributes and roles
+pub fn directory_user(
+    entry: &DirectoryEntry,
+    config: &DirectoryConfig,
+    login: &str,
+) -> DirectoryUser {
+    let values = |attribute: &str| {
+        entry
+            .attributes
+            .get(&attribute.to_lowercase())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    };
+    let first = |attribute: &str| {
+        values(attribute)
+            .iter()
+            .find(|value| !value.is_empty())
+            .cloned()
+    };
+
+    let mapping = &config.attribute_mapping;
+    let email = first(&mapping.email).unwrap_or_else(|| login.to_owned());
+    let username = first(&mapping.username).unwrap_or_else(|| email.clone());
+
+    let attributes = mapping
+        .attributes
+        .iter()
+        .filter_map(|(name, attribute)| {
+            let value = match values(attribute) {
+                [] => return None,
+                [value] => Value::String(value.clone()),
+                values => Value::Array(values.iter().cloned().map(Value::String).collect()),
+            };
+            Some((name.clone(), value))
+        })
+        .collect();
+
+    // DNs compare case-insensitively and without spaces around separators
+    let groups: Vec<String> = values(&config.group_attribute)
+        .iter()
+        .map(|group| normalize_dn(group))
+        .collect();
+    let mut roles: Vec<String> = config
+        .group_roles
+        .iter()
+        .filter(|(group, _)| groups.contains(&normalize_dn(group)))
+        .flat_map(|(_, roles)| roles.iter().cloned())
+        .collect();
+    roles.sort();
+    roles.dedup();
+
+    DirectoryUser {
+        dn: entry.dn.clone(),
+        email,
+        username,
+        name: first(&mapping.name),
+        given_name: first(&mapping.given_name),
+        family_name: first(&mapping.family_name),
+        phone_number: first(&mapping.phone_number),
+        attributes,
+        roles,
+    }
+}
+
+fn normalize_dn(dn: &str) -> String {
+    dn.split(',')
+        .map(|component| component.trim().to_lowercase())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn config() -> DirectoryConfig {
+        serde_yaml::from_str(
+            r#"
+            url: "ldap://localhost:389"
+            base_dn: "ou=people,dc=example,dc=com"
+            attribute_mapping:
+              attributes:
+                department: "departmentNumber"
+            group_roles:
+              "cn=Admins, ou=groups,dc=example,dc=com": ["admin"]
+              "cn=staff,ou=groups,dc=example,dc=com": ["user"]
+            "#,
+        )
+        .unwrap()
+    }
+
+    /// In-process directory with a single user
+    struct MockDirectory {
+        binds: AtomicUsize,
+    }
+
+    impl DirectoryClient for MockDirectory {
+        async fn authenticate(
+            &self,
+            _config: &DirectoryConfig,
+            login: &str,
+            password: &str,
+        ) -> Result<Option<DirectoryEntry>, anyhow::Error> {
+            self.binds.fetch_add(1, Ordering::SeqCst);
+            Ok(
+                (login == "jane@example.com" && password == "secret").then(|| DirectoryEntry {
+                    dn: "uid=jane,ou=people,dc=example,dc=com".to_string(),
+                    attributes: HashMap::new(),
+                }),
+            )
+        }
+    }
+
+    #[test]
+    fn test_user_search_filter_escapes_login() {
+        assert_eq!(
+            user_search_filter("(&(objectClass=person)(mail={login}))", "*)(uid=*"),
+            r"(&(objectClass=person)(mail=\2a\29\28uid=\2a))"
+        );
+    }
+
+    #[test]
+    fn test_directory_user_maps_attributes_and_groups() {
+        let entry = DirectoryEntry {
+            dn: "uid=jane,ou=people,dc=example,dc=com".to_string(),
+            attributes: HashMap::from([
+                ("mail".to_string(), vec!["jane@example.com".to_string()]),
+                ("uid".to_string(), vec!["jane".to_string()]),
+                ("givenname".to_string(), vec!["Jane".to_string()]),
+                ("departmentnumber".to_string(), vec!["42".to_string()]),
+                (
+                    "memberof".to_string(),
+                    vec![
+                        "CN=admins,OU=groups,DC=example,DC=com".to_string(),
+                        "cn=other,ou=groups,dc=example,dc=com".to_string(),
+                    ],
+                ),
+            ]),
+        };
+
+        let user = directory_user(&entry, &config(), "jane@example.com");
+
+        assert_eq!(user.username, "jane");
+        assert_eq!(user.given_name.as_deref(), Some("Jane"));
+        assert_eq!(user.name, None);
+        assert_eq!(user.attributes.get("department"), Some(&json!("42")));
+        assert_eq!(user.roles, vec!["admin"]);
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_cached_reuses_successful_binds() {
+        let client = MockDirectory {
+            binds: AtomicUsize::new(0),
+        };
+        let cache = DirectoryCache::new().unwrap();
+        let config = config();
+
+        for _ in 0..2 {
+            let entry = authenticate_cached(&client, &cache, &config, "jane@example.com", "secret")
+                .await
+                .unwrap();
+            assert!(entry.is_some());
+        }
+        assert_eq!(client.binds.load(Ordering::SeqCst), 1);
+
+        // Other passwords always go to the directory and invalidate the cached bind
+        let entry = authenticate_cached(&client, &cache, &config, "jane@example.com", "guess")
+            .await
+            .unwrap();
+        assert!(entry.is_none());
+        authenticate_cached(&client, &cache, &config, "jane@example.com", "secret")
+            .await
+            .unwrap();
+        assert_eq!(client.binds.load(Ordering::SeqCst), 3);
+    }
+}
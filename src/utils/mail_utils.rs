@@ -0,0 +1,45 @@
+use lettre::{
+    Message,
+    message::{Mailbox, header::ContentType},
+};
+
+/// Mail with the code a user resets their password with
+pub fn password_reset_message(
+    from: &Mailbox,
+    to: &str,
+    token: &str,
+    lifetime_minutes: u64,
+) -> Result<Message, anyhow::Error> {
+    Ok(Message::builder()
+        .from(from.clone())
+        .to(to.parse()?)
+        .subject("Reset your password")
+        .header(ContentType::TEXT_PLAIN)
+        .body(format!(
+            "Use this code to reset your password: {token}\n\nIt expires in {lifetime_minutes} minutes. If you did not ask to reset your password, ignore this mail.\n"
+        ))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use lettre::{AsyncTransport, transport::stub::AsyncStubTransport};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_password_reset_message() {
+        let from: Mailbox = "SSO <no-reply@sso.example>".parse().unwrap();
+        let message = password_reset_message(&from, "user@example.com", "token123", 15)
+            .expect("Failed to build message");
+
+        let transport = AsyncStubTransport::new_ok();
+        transport.send(message).await.expect("Failed to send");
+        let messages = transport.messages().await;
+        assert_eq!(messages.len(), 1);
+        let (envelope, body) = &messages[0];
+        assert_eq!(envelope.to()[0].to_string(), "user@example.com");
+        assert!(body.contains("token123"));
+
+        assert!(password_reset_message(&from, "not an address", "token123", 15).is_err());
+    }
+}
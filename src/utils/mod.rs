@@ -1,8 +1,31 @@
+pub mod account_chooser_utils;
+pub mod attribute_utils;
+pub mod authorization_response_utils;
+pub mod branding_utils;
+pub mod breached_password_utils;
+pub mod claims_utils;
+pub mod client_info_utils;
+pub mod client_registration_utils;
 mod config_loader;
 pub mod database;
+pub mod device_utils;
+pub mod dpop_utils;
+pub mod federation_utils;
 pub mod jwks_utils;
+pub mod ldap_client;
+pub mod ldap_utils;
+pub mod mail_utils;
+pub mod mtls_utils;
 pub mod password_hash_utils;
+pub mod password_policy_utils;
 pub mod redis_utils;
+pub mod request_object_utils;
+pub mod resource_utils;
+pub mod saml_issuer;
+pub mod saml_utils;
+pub mod scim_utils;
 pub mod setup;
+pub mod subject_utils;
+pub mod tenant_issuer;
 pub mod token_issuer;
 pub mod token_verifier;
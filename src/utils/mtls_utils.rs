@@ -0,0 +1,302 @@
+use std::{io, net::IpAddr};
+
+use axum_server::{accept::Accept, tls_openssl::OpenSSLAcceptor};
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
+use futures::future::BoxFuture;
+use jsonwebtoken::jwk::JwkSet;
+use openssl::{
+    sha::sha256,
+    ssl::{SslAcceptor, SslFiletype, SslMethod, SslVerifyMode},
+    x509::X509,
+};
+use serde_json::Value;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tower_http::add_extension::AddExtension;
+
+use crate::models::{claims::AccessTokenClaims, client_certificate::TlsConnection};
+
+/// TLS server configuration that asks clients for a certificate without requiring one
+pub fn tls_acceptor(certificate_path: &str, key_path: &str) -> Result<SslAcceptor, anyhow::Error> {
+    let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls_server())?;
+    builder.set_certificate_chain_file(certificate_path)?;
+    builder.set_private_key_file(key_path, SslFiletype::PEM)?;
+    builder.check_private_key()?;
+
+    // Whether a certificate is trusted depends on the authentication method of its application,
+    // the handshake only proves the client holds its private key
+    builder.set_verify_callback(SslVerifyMode::PEER, |_, _| true);
+
+    Ok(builder.build())
+}
+
+/// Accepts TLS connections and hands the client certificate to the requests of the connection
+#[derive(Clone)]
+pub struct ClientCertificateAcceptor {
+    inner: OpenSSLAcceptor,
+}
+
+impl ClientCertificateAcceptor {
+    pub fn new(inner: OpenSSLAcceptor) -> Self {
+        Self { inner }
+    }
+}
+
+impl<I, S> Accept<I, S> for ClientCertificateAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = <OpenSSLAcceptor as Accept<I, S>>::Stream;
+    type Service = AddExtension<S, TlsConnection>;
+    type Future = BoxFuture<'static, io::Result<(Self::Stream, Self::Service)>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let accept = self.inner.accept(stream, service);
+
+        Box::pin(async move {
+            let (stream, service) = accept.await?;
+            let connection = TlsConnection {
+                client_certificate: stream.ssl().peer_certificate(),
+            };
+
+            Ok((stream, AddExtension::new(service, connection)))
+        })
+    }
+}
+
+/// Address or CIDR range of a reverse proxy trusted to forward client certificates
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrustedProxy {
+    network: IpAddr,
+    prefix_length: u32,
+}
+
+impl TrustedProxy {
+    /// `10.0.0.5`, `10.0.0.0/8` or `fd00::/8`
+    pub fn parse(value: &str) -> Result<Self, anyhow::Error> {
+        let (address, prefix_length) = match value.trim().split_once('/') {
+            Some((address, prefix_length)) => (address, Some(prefix_length.parse::<u32>()?)),
+            None => (value.trim(), None),
+        };
+        let network: IpAddr = address.parse()?;
+        let max_length = match network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix_length = prefix_length.unwrap_or(max_length);
+        if prefix_length > max_length {
+            return Err(anyhow::anyhow!("Invalid prefix length in {value}"));
+        }
+
+        Ok(Self {
+            network,
+            prefix_length,
+        })
+    }
+
+    pub fn contains(&self, address: IpAddr) -> bool {
+        // IPv4 peers of dual-stack sockets show up as mapped IPv6 addresses
+        let address = match address {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(address, IpAddr::V4),
+            address => address,
+        };
+        match (self.network, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_length).unwrap_or(0);
+                u32::from(network) & mask == u32::from(address) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_length).unwrap_or(0);
+                u128::from(network) & mask == u128::from(address) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Certificate forwarded by a reverse proxy, URL encoded PEM or base64 DER
+pub fn parse_forwarded_certificate(value: &str) -> Option<X509> {
+    let value = urlencoding::decode(value).ok()?;
+
+    match value.contains("-----BEGIN CERTIFICATE-----") {
+        true => X509::from_pem(value.as_bytes()).ok(),
+        false => {
+            let der: String = value.split_whitespace().collect();
+            X509::from_der(&STANDARD.decode(der).ok()?).ok()
+        }
+    }
+}
+
+/// `x5t#S256` thumbprint certificate-bound tokens carry in `cnf` (RFC 8705 3.1)
+pub fn certificate_thumbprint(certificate: &X509) -> Result<String, anyhow::Error> {
+    Ok(URL_SAFE_NO_PAD.encode(sha256(&certificate.to_der()?)))
+}
+
+/// Subject distinguished name in its RFC 4514 string representation
+pub fn subject_dn(certificate: &X509) -> Result<String, anyhow::Error> {
+    let mut rdns = Vec::new();
+    for entry in certificate.subject_name().entries() {
+        let attribute = entry.object().nid().short_name()?;
+        let value = entry.data().as_utf8()?;
+        rdns.push(format!("{}={}", attribute, escape_dn_value(&value)));
+    }
+
+    // RFC 4514 lists the most specific RDN first
+    rdns.reverse();
+    Ok(rdns.join(","))
+}
+
+/// Whether a registered `tls_client_auth_subject_dn` names the certificate's subject
+pub fn subject_dn_matches(certificate_dn: &str, registered_dn: &str) -> bool {
+    let normalize = |dn: &str| {
+        dn.split(',')
+            .map(|rdn| {
+                rdn.split_once('=')
+                    .map(|(attribute, value)| format!("{}={}", attribute.trim(), value.trim()))
+                    .unwrap_or_else(|| rdn.trim().to_owned())
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    normalize(certificate_dn).eq_ignore_ascii_case(&normalize(registered_dn))
+}
+
+/// Whether the self-signed certificate is one the application registered in `x5c` of its keys
+pub fn jwks_contains_certificate(jwks: &JwkSet, certificate: &X509) -> bool {
+    let Ok(der) = certificate.to_der() else {
+        return false;
+    };
+
+    jwks.keys.iter().any(|jwk| {
+        jwk.common
+            .x509_chain
+            .as_ref()
+            .and_then(|chain| chain.first())
+            .and_then(|x5c| STANDARD.decode(x5c).ok())
+            .is_some_and(|x5c| x5c == der)
+    })
+}
+
+/// The `cnf.x5t#S256` thumbprint of a certificate-bound access token
+pub fn bound_certificate(claims: &AccessTokenClaims) -> Option<&str> {
+    claims
+        .user_claims
+        .get("cnf")
+        .and_then(|cnf| cnf.get("x5t#S256"))
+        .and_then(Value::as_str)
+}
+
+/// Verify that a certificate-bound access token is presented over a connection with its
+/// certificate, unbound tokens are accepted from any client (RFC 8705 3)
+pub fn verify_certificate_binding(claims: &AccessTokenClaims, certificate: Option<&X509>) -> bool {
+    match bound_certificate(claims) {
+        Some(thumbprint) => certificate
+            .and_then(|certificate| certificate_thumbprint(certificate).ok())
+            .is_some_and(|certificate_thumbprint| certificate_thumbprint == thumbprint),
+        None => true,
+    }
+}
+
+fn escape_dn_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for (index, character) in value.chars().enumerate() {
+        let special = matches!(character, ',' | '+' | '"' | '\\' | '<' | '>' | ';' | '=')
+            || (index == 0 && matches!(character, '#' | ' '))
+            || (index == value.chars().count() - 1 && character == ' ');
+        if special {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::{
+        asn1::Asn1Time,
+        hash::MessageDigest,
+        pkey::PKey,
+        rsa::Rsa,
+        x509::{X509Builder, X509NameBuilder},
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    fn self_signed_certificate() -> X509 {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("C", "DE").unwrap();
+        name.append_entry_by_text("O", "Example, Inc.").unwrap();
+        name.append_entry_by_text("CN", "client-1").unwrap();
+        let name = name.build();
+
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        builder.build()
+    }
+
+    #[test]
+    fn test_client_certificates() {
+        let certificate = self_signed_certificate();
+
+        let dn = subject_dn(&certificate).unwrap();
+        assert_eq!(dn, r"CN=client-1,O=Example\, Inc.,C=DE");
+        assert!(subject_dn_matches(
+            &dn,
+            r"cn=client-1, O=Example\, Inc., C=DE"
+        ));
+        assert!(!subject_dn_matches(&dn, "CN=client-2,O=Example,C=DE"));
+
+        // Proxies forward URL encoded PEM or plain base64 DER
+        let pem = String::from_utf8(certificate.to_pem().unwrap()).unwrap();
+        let der = STANDARD.encode(certificate.to_der().unwrap());
+        let thumbprint = certificate_thumbprint(&certificate).unwrap();
+        for forwarded in [urlencoding::encode(&pem).into_owned(), der.clone()] {
+            let forwarded = parse_forwarded_certificate(&forwarded).unwrap();
+            assert_eq!(certificate_thumbprint(&forwarded).unwrap(), thumbprint);
+        }
+
+        let jwks: JwkSet = serde_json::from_value(json!({
+            "keys": [{ "kty": "RSA", "n": "AQAB", "e": "AQAB", "x5c": [der] }]
+        }))
+        .unwrap();
+        assert!(jwks_contains_certificate(&jwks, &certificate));
+        assert!(!jwks_contains_certificate(
+            &jwks,
+            &self_signed_certificate()
+        ));
+    }
+
+    #[test]
+    fn test_trusted_proxies() {
+        let proxy = TrustedProxy::parse("10.0.0.0/8").unwrap();
+        assert!(proxy.contains("10.1.2.3".parse().unwrap()));
+        assert!(proxy.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!proxy.contains("192.168.0.1".parse().unwrap()));
+
+        let proxy = TrustedProxy::parse("fd00::1").unwrap();
+        assert!(proxy.contains("fd00::1".parse().unwrap()));
+        assert!(!proxy.contains("fd00::2".parse().unwrap()));
+
+        assert!(TrustedProxy::parse("0.0.0.0/0").is_ok());
+        assert!(TrustedProxy::parse("10.0.0.0/33").is_err());
+        assert!(TrustedProxy::parse("proxy.internal").is_err());
+    }
+}
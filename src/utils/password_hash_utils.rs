@@ -1,13 +1,38 @@
+use std::env;
+
 use argon2::password_hash::{
     Error as PasswordHashError, PasswordHash, SaltString, rand_core::OsRng,
 };
-use argon2::{Argon2, PasswordHasher, PasswordVerifier};
+use argon2::{Algorithm, Argon2, Params, PasswordHasher, PasswordVerifier, Version};
+use pbkdf2::Pbkdf2;
+use scrypt::Scrypt;
+
+/// Reads the Argon2id cost parameters from `ARGON2_MEMORY_KIB`, `ARGON2_ITERATIONS`
+/// and `ARGON2_PARALLELISM`, falling back to the Argon2 defaults for unset values.
+pub fn argon2_params_from_env() -> Result<Params, anyhow::Error> {
+    let memory_kib = env_or("ARGON2_MEMORY_KIB", Params::DEFAULT_M_COST)?;
+    let iterations = env_or("ARGON2_ITERATIONS", Params::DEFAULT_T_COST)?;
+    let parallelism = env_or("ARGON2_PARALLELISM", Params::DEFAULT_P_COST)?;
+
+    Params::new(memory_kib, iterations, parallelism, None)
+        .map_err(|e| anyhow::anyhow!("Invalid Argon2 parameters: {}", e))
+}
+
+fn env_or(name: &str, default: u32) -> Result<u32, anyhow::Error> {
+    match env::var(name) {
+        Ok(value) if !value.is_empty() => Ok(value.parse()?),
+        _ => Ok(default),
+    }
+}
 
-/// Hashes a password with a new random salt.
+/// Hashes a password with a new random salt using Argon2id with the given parameters.
 /// Returns (salt, password_hash)
-pub fn hash_password(password: &str) -> Result<(String, String), PasswordHashError> {
+pub fn hash_password(
+    password: &str,
+    params: &Params,
+) -> Result<(String, String), PasswordHashError> {
     let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params.clone());
 
     let hash = argon2
         .hash_password(password.as_bytes(), &salt)?
@@ -16,14 +41,58 @@ pub fn hash_password(password: &str) -> Result<(String, String), PasswordHashErr
     Ok((salt.to_string(), hash))
 }
 
-/// Verifies a password against its hash and salt
+/// Verifies a password against its hash.
+/// Besides Argon2 this accepts the legacy formats of imported users:
+/// bcrypt (`$2a$`, `$2b$`, `$2y$`) and PBKDF2-SHA256 / scrypt PHC strings.
 pub fn verify_password(password: &str, password_hash: &str) -> Result<bool, PasswordHashError> {
+    if is_bcrypt_hash(password_hash) {
+        return bcrypt::verify(password, password_hash)
+            .map_err(|_| PasswordHashError::PhcStringField);
+    }
+
     let parsed_hash = PasswordHash::new(password_hash)?;
-    let argon2 = Argon2::default();
 
-    Ok(argon2
-        .verify_password(password.as_bytes(), &parsed_hash)
-        .is_ok())
+    let is_valid = match parsed_hash.algorithm.as_str() {
+        "argon2id" | "argon2i" | "argon2d" => Argon2::default()
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_ok(),
+        "pbkdf2-sha256" => Pbkdf2
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_ok(),
+        "scrypt" => Scrypt
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_ok(),
+        _ => return Err(PasswordHashError::Algorithm),
+    };
+
+    Ok(is_valid)
+}
+
+/// Returns true if a hash was not produced by Argon2id with the given parameters
+/// and should be replaced after the next successful login.
+pub fn needs_rehash(password_hash: &str, params: &Params) -> bool {
+    let Ok(parsed_hash) = PasswordHash::new(password_hash) else {
+        return true;
+    };
+
+    if parsed_hash.algorithm.as_str() != "argon2id" {
+        return true;
+    }
+
+    match Params::try_from(&parsed_hash) {
+        Ok(current) => {
+            current.m_cost() != params.m_cost()
+                || current.t_cost() != params.t_cost()
+                || current.p_cost() != params.p_cost()
+        }
+        Err(_) => true,
+    }
+}
+
+fn is_bcrypt_hash(password_hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| password_hash.starts_with(prefix))
 }
 
 #[cfg(test)]
@@ -33,7 +102,7 @@ mod tests {
     #[test]
     fn test_hash_and_verify_success() {
         let password = "original-password";
-        let (_salt, hash) = hash_password(password).expect("hashing failed");
+        let (_salt, hash) = hash_password(password, &Params::default()).expect("hashing failed");
 
         let is_valid = verify_password(password, &hash).expect("verification failed");
         assert!(is_valid, "Password should validate successfully");
@@ -44,7 +113,7 @@ mod tests {
         let password = "original-password";
         let wrong_password = "wrong-password";
 
-        let (_salt, hash) = hash_password(password).expect("hashing failed");
+        let (_salt, hash) = hash_password(password, &Params::default()).expect("hashing failed");
 
         let is_valid = verify_password(wrong_password, &hash).expect("verification failed");
         assert!(!is_valid, "Wrong password should not validate");
@@ -53,7 +122,8 @@ mod tests {
     #[test]
     fn test_verify_fails_on_tampered_hash() {
         let password = "password123";
-        let (_salt, mut hash) = hash_password(password).expect("hashing failed");
+        let (_salt, mut hash) =
+            hash_password(password, &Params::default()).expect("hashing failed");
 
         // Tamper the hash string (e.g., change one character)
         hash.replace_range(10..11, "x");
@@ -69,12 +139,68 @@ mod tests {
     fn test_multiple_hashes_are_different() {
         let password = "same-password";
 
-        let (_, hash1) = hash_password(password).expect("hashing failed");
-        let (_, hash2) = hash_password(password).expect("hashing failed");
+        let (_, hash1) = hash_password(password, &Params::default()).expect("hashing failed");
+        let (_, hash2) = hash_password(password, &Params::default()).expect("hashing failed");
 
         assert_ne!(
             hash1, hash2,
             "Hashes for same password should differ due to random salt"
         );
     }
+
+    #[test]
+    fn test_verify_bcrypt_hash() {
+        let hash = bcrypt::hash("legacy-password", 4).expect("hashing failed");
+
+        assert!(verify_password("legacy-password", &hash).expect("verification failed"));
+        assert!(!verify_password("wrong-password", &hash).expect("verification failed"));
+    }
+
+    #[test]
+    fn test_verify_pbkdf2_sha256_hash() {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Pbkdf2
+            .hash_password_customized(
+                b"legacy-password",
+                Some(pbkdf2::Algorithm::Pbkdf2Sha256.ident()),
+                None,
+                pbkdf2::Params {
+                    rounds: 1_000,
+                    output_length: 32,
+                },
+                &salt,
+            )
+            .expect("hashing failed")
+            .to_string();
+
+        assert!(hash.starts_with("$pbkdf2-sha256$"));
+        assert!(verify_password("legacy-password", &hash).expect("verification failed"));
+        assert!(!verify_password("wrong-password", &hash).expect("verification failed"));
+    }
+
+    #[test]
+    fn test_verify_scrypt_hash() {
+        let salt = SaltString::generate(&mut OsRng);
+        let params = scrypt::Params::new(4, 8, 1, 32).expect("invalid scrypt params");
+        let hash = Scrypt
+            .hash_password_customized(b"legacy-password", None, None, params, &salt)
+            .expect("hashing failed")
+            .to_string();
+
+        assert!(verify_password("legacy-password", &hash).expect("verification failed"));
+        assert!(!verify_password("wrong-password", &hash).expect("verification failed"));
+    }
+
+    #[test]
+    fn test_needs_rehash() {
+        let params = Params::default();
+        let (_, current) = hash_password("password", &params).expect("hashing failed");
+        assert!(!needs_rehash(&current, &params));
+
+        let stronger = Params::new(params.m_cost() * 2, params.t_cost(), 1, None).unwrap();
+        assert!(needs_rehash(&current, &stronger));
+
+        let legacy = bcrypt::hash("password", 4).expect("hashing failed");
+        assert!(needs_rehash(&legacy, &params));
+    }
 }
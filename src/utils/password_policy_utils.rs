@@ -0,0 +1,111 @@
+use thiserror::Error;
+
+use crate::models::password_policy::PasswordPolicy;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum PasswordPolicyError {
+    #[error("Password must be at least {0} characters long")]
+    TooShort(i32),
+    #[error("Password must contain an uppercase letter")]
+    MissingUppercase,
+    #[error("Password must contain a lowercase letter")]
+    MissingLowercase,
+    #[error("Password must contain a digit")]
+    MissingDigit,
+    #[error("Password must contain a symbol")]
+    MissingSymbol,
+    #[error("Password was used recently and cannot be reused")]
+    Reused,
+    #[error("Password has appeared in a data breach")]
+    Breached,
+}
+
+/// Checks the composition rules of a policy (length and character classes).
+/// History and breach checks need stored data and are done by the caller.
+pub fn validate_password(
+    password: &str,
+    policy: &PasswordPolicy,
+) -> Result<(), PasswordPolicyError> {
+    if (password.chars().count() as i32) < policy.min_length.max(1) {
+        return Err(PasswordPolicyError::TooShort(policy.min_length.max(1)));
+    }
+
+    if policy.require_uppercase && !password.chars().any(char::is_uppercase) {
+        return Err(PasswordPolicyError::MissingUppercase);
+    }
+
+    if policy.require_lowercase && !password.chars().any(char::is_lowercase) {
+        return Err(PasswordPolicyError::MissingLowercase);
+    }
+
+    if policy.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+        return Err(PasswordPolicyError::MissingDigit);
+    }
+
+    if policy.require_symbol && !password.chars().any(|c| !c.is_alphanumeric()) {
+        return Err(PasswordPolicyError::MissingSymbol);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strict_policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 10,
+            require_uppercase: true,
+            require_lowercase: true,
+            require_digit: true,
+            require_symbol: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_empty_password_is_rejected_by_default_policy() {
+        let result = validate_password("", &PasswordPolicy::default());
+        assert_eq!(result, Err(PasswordPolicyError::TooShort(8)));
+    }
+
+    #[test]
+    fn test_zero_min_length_still_rejects_empty_password() {
+        let policy = PasswordPolicy {
+            min_length: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            validate_password("", &policy),
+            Err(PasswordPolicyError::TooShort(1))
+        );
+    }
+
+    #[test]
+    fn test_character_classes_are_enforced() {
+        let policy = strict_policy();
+
+        assert_eq!(
+            validate_password("lowercase1!", &policy),
+            Err(PasswordPolicyError::MissingUppercase)
+        );
+        assert_eq!(
+            validate_password("UPPERCASE1!", &policy),
+            Err(PasswordPolicyError::MissingLowercase)
+        );
+        assert_eq!(
+            validate_password("NoDigitsHere!", &policy),
+            Err(PasswordPolicyError::MissingDigit)
+        );
+        assert_eq!(
+            validate_password("NoSymbols123", &policy),
+            Err(PasswordPolicyError::MissingSymbol)
+        );
+    }
+
+    #[test]
+    fn test_compliant_password_is_accepted() {
+        assert!(validate_password("C0rrect-Horse", &strict_policy()).is_ok());
+    }
+}
@@ -30,3 +30,17 @@ pub async fn create_redis_pool() -> Result<Pool<RedisConnectionManager>, anyhow:
 
     Ok(pool)
 }
+
+/// Pool for tests against the Redis server at `REDIS_URL`, `redis://127.0.0.1/` by default
+#[cfg(test)]
+pub async fn test_redis_pool() -> Pool<RedisConnectionManager> {
+    dotenv().ok();
+
+    let redis_url = env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+    let manager = RedisConnectionManager::new(redis_url).expect("Invalid Redis URL");
+    Pool::builder()
+        .max_size(2)
+        .build(manager)
+        .await
+        .expect("Failed to create Redis connection pool")
+}
@@ -0,0 +1,126 @@
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header, jwk::JwkSet};
+use serde_json::{Map, Value};
+
+/// Signature algorithms accepted for request objects, `none` is never accepted
+pub const REQUEST_OBJECT_ALGORITHMS: [Algorithm; 8] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+];
+
+/// JWT claims of the request object that are not authorization parameters
+const JWT_CLAIMS: [&str; 6] = ["iss", "aud", "exp", "iat", "nbf", "jti"];
+
+/// Verify a request object signed by the client `client_id` for this server (RFC 9101 6.2)
+pub fn verify_request_object(
+    request: &str,
+    jwks: &JwkSet,
+    client_id: &str,
+    issuer: &str,
+) -> Result<Map<String, Value>, anyhow::Error> {
+    let header = decode_header(request)?;
+    if !REQUEST_OBJECT_ALGORITHMS.contains(&header.alg) {
+        return Err(anyhow::anyhow!(
+            "Unsupported request object algorithm {:?}",
+            header.alg
+        ));
+    }
+
+    let jwk = match &header.kid {
+        Some(kid) => jwks.find(kid),
+        None => jwks.keys.first(),
+    }
+    .ok_or_else(|| anyhow::anyhow!("No signing key of the client found"))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_audience(&[issuer]);
+    validation.set_issuer(&[client_id]);
+
+    let claims =
+        decode::<Map<String, Value>>(request, &DecodingKey::from_jwk(jwk)?, &validation)?.claims;
+
+    if claims
+        .get("client_id")
+        .is_some_and(|claim| claim.as_str() != Some(client_id))
+    {
+        return Err(anyhow::anyhow!("Request object client_id mismatch"));
+    }
+
+    Ok(claims)
+}
+
+/// Authorization parameters with those of the request object taking precedence (OIDC Core 6.3.3)
+pub fn merge_request_object(
+    query: Vec<(String, String)>,
+    claims: &Map<String, Value>,
+) -> Vec<(String, String)> {
+    let mut params: Vec<(String, String)> = query
+        .into_iter()
+        .filter(|(name, _)| {
+            name != "request" && name != "request_uri" && !claims.contains_key(name)
+        })
+        .collect();
+
+    for (name, value) in claims {
+        if JWT_CLAIMS.contains(&name.as_str()) || name == "request" || name == "request_uri" {
+            continue;
+        }
+        // The `claims` parameter is a JSON object in request objects
+        let value = match value {
+            Value::String(value) => value.clone(),
+            Value::Null => continue,
+            value => value.to_string(),
+        };
+        params.push((name.clone(), value));
+    }
+
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_merge_request_object() {
+        let query = vec![
+            ("client_id".to_owned(), "client-1".to_owned()),
+            ("response_type".to_owned(), "code".to_owned()),
+            ("scope".to_owned(), "openid admin".to_owned()),
+            ("request".to_owned(), "eyJ...".to_owned()),
+        ];
+        let claims = json!({
+            "iss": "client-1",
+            "aud": "https://sso.example.com",
+            "exp": 1_900_000_000,
+            "scope": "openid",
+            "redirect_uri": "https://client.example/callback",
+            "max_age": 300,
+            "claims": { "id_token": { "email": null } },
+        });
+
+        let params = merge_request_object(query, claims.as_object().unwrap());
+        let value = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+
+        assert_eq!(value("client_id"), Some("client-1"));
+        assert_eq!(value("response_type"), Some("code"));
+        assert_eq!(value("scope"), Some("openid"));
+        assert_eq!(value("max_age"), Some("300"));
+        assert_eq!(value("claims"), Some(r#"{"id_token":{"email":null}}"#));
+        assert_eq!(value("request"), None);
+        assert_eq!(value("iss"), None);
+        assert_eq!(params.iter().filter(|(key, _)| key == "scope").count(), 1);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::utils::claims_utils::has_scope;
+
+/// Scopes of OpenID Connect itself, every other scope belongs to an API
+const OPENID_SCOPES: [&str; 6] = [
+    "openid",
+    "profile",
+    "email",
+    "address",
+    "phone",
+    "offline_access",
+];
+
+/// Resource indicators are absolute URIs without fragment (RFC 8707 2)
+pub fn is_valid_resource_indicator(resource: &str) -> bool {
+    let Some((scheme, rest)) = resource.split_once(':') else {
+        return false;
+    };
+
+    !rest.is_empty()
+        && !resource.contains('#')
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+/// Whether every API scope requested along with a resource is one of the resource's scopes
+pub fn scopes_allowed_for_resource(scope: Option<&str>, resource_scopes: &[String]) -> bool {
+    scope
+        .unwrap_or_default()
+        .split_whitespace()
+        .all(|scope| OPENID_SCOPES.contains(&scope) || resource_scopes.iter().any(|s| s == scope))
+}
+
+/// The granted scopes an access token for the resource carries, scopes of other APIs are left out
+pub fn resource_token_scope(granted: Option<&str>, resource_scopes: &[String]) -> Option<String> {
+    let scope = resource_scopes
+        .iter()
+        .filter(|scope| has_scope(granted, scope))
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    (!scope.is_empty()).then_some(scope)
+}
+
+/// A resource sent to the token endpoint has to be the one that was granted
+pub fn resource_matches(requested: Option<&str>, granted: Option<&str>) -> bool {
+    requested.is_none_or(|requested| Some(requested) == granted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_indicators() {
+        assert!(is_valid_resource_indicator(
+            "https://api.example.com/orders"
+        ));
+        assert!(is_valid_resource_indicator("urn:example:orders"));
+        assert!(!is_valid_resource_indicator("orders"));
+        assert!(!is_valid_resource_indicator("/orders"));
+        assert!(!is_valid_resource_indicator(
+            "https://api.example.com/#orders"
+        ));
+    }
+
+    #[test]
+    fn test_resource_scopes() {
+        let resource_scopes = vec!["orders:read".to_owned(), "orders:write".to_owned()];
+
+        assert!(scopes_allowed_for_resource(
+            Some("openid email orders:read"),
+            &resource_scopes
+        ));
+        assert!(!scopes_allowed_for_resource(
+            Some("openid invoices:read"),
+            &resource_scopes
+        ));
+        assert_eq!(
+            resource_token_scope(Some("openid orders:write email"), &resource_scopes),
+            Some("orders:write".to_owned())
+        );
+        assert_eq!(resource_token_scope(Some("openid"), &resource_scopes), None);
+    }
+}
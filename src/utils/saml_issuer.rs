@@ -0,0 +1,209 @@
+use std::{fs, path::Path};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use openssl::{
+    asn1::Asn1Time,
+    bn::{BigNum, MsbOption},
+    hash::MessageDigest,
+    pkey::{PKey, Private},
+    sha::sha256,
+    sign::Signer,
+    x509::{X509, X509NameBuilder},
+};
+
+use crate::{
+    models::saml::NameIdFormat,
+    utils::saml_utils::{HTTP_POST_BINDING, HTTP_REDIRECT_BINDING, escape_attribute},
+};
+
+const DSIG_NS: &str = "http://www.w3.org/2000/09/xmldsig#";
+const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+
+/// Signs SAML assertions with the same key the tokens are signed with
+pub struct SamlIssuer {
+    pub entity_id: String,
+    pub sso_url: String,
+    private_key: PKey<Private>,
+    certificate: X509,
+}
+
+impl SamlIssuer {
+    /// Without a certificate a self-signed one is created for the signing key
+    pub fn new(
+        private_key_pem: &[u8],
+        certificate_pem: Option<&[u8]>,
+        entity_id: &str,
+        sso_url: &str,
+    ) -> Result<Self, anyhow::Error> {
+        let private_key = PKey::private_key_from_pem(private_key_pem)?;
+        let certificate = match certificate_pem {
+            Some(certificate_pem) => X509::from_pem(certificate_pem)?,
+            None => self_signed_certificate(&private_key, entity_id)?,
+        };
+
+        if !certificate.public_key()?.public_eq(&private_key) {
+            return Err(anyhow::anyhow!(
+                "SAML certificate does not match the signing key"
+            ));
+        }
+
+        Ok(Self {
+            entity_id: entity_id.to_owned(),
+            sso_url: sso_url.to_owned(),
+            private_key,
+            certificate,
+        })
+    }
+
+    pub fn from_pem_files(
+        private_key_path: &str,
+        certificate_path: &str,
+        entity_id: &str,
+        sso_url: &str,
+    ) -> Result<Self, anyhow::Error> {
+        let private_key_pem = fs::read(private_key_path)?;
+        let certificate_pem = if Path::new(certificate_path).exists() {
+            Some(fs::read(certificate_path)?)
+        } else {
+            println!(
+                "{certificate_path} not found. Using a self-signed SAML certificate, service providers have to trust it again after every restart"
+            );
+            None
+        };
+
+        Self::new(
+            &private_key_pem,
+            certificate_pem.as_deref(),
+            entity_id,
+            sso_url,
+        )
+    }
+
+    /// DER certificate as used in `<ds:X509Certificate>`
+    pub fn certificate_base64(&self) -> Result<String, anyhow::Error> {
+        Ok(STANDARD.encode(self.certificate.to_der()?))
+    }
+
+    /// Insert an enveloped XML signature after the `<saml:Issuer>` of a canonical assertion
+    pub fn sign_assertion(&self, assertion: &str, id: &str) -> Result<String, anyhow::Error> {
+        let digest = STANDARD.encode(sha256(assertion.as_bytes()));
+        let signed_info = format!(
+            r##"<ds:CanonicalizationMethod Algorithm="{EXC_C14N}"></ds:CanonicalizationMethod><ds:SignatureMethod Algorithm="http://www.w3.org/2001/04/xmldsig-more#rsa-sha256"></ds:SignatureMethod><ds:Reference URI="#{id}"><ds:Transforms><ds:Transform Algorithm="http://www.w3.org/2000/09/xmldsig#enveloped-signature"></ds:Transform><ds:Transform Algorithm="{EXC_C14N}"></ds:Transform></ds:Transforms><ds:DigestMethod Algorithm="http://www.w3.org/2001/04/xmlenc#sha256"></ds:DigestMethod><ds:DigestValue>{digest}</ds:DigestValue></ds:Reference>"##
+        );
+
+        // Canonicalized on its own, SignedInfo carries the namespace declaration of its parent
+        let canonical_signed_info =
+            format!(r#"<ds:SignedInfo xmlns:ds="{DSIG_NS}">{signed_info}</ds:SignedInfo>"#);
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.private_key)?;
+        signer.update(canonical_signed_info.as_bytes())?;
+        let signature_value = STANDARD.encode(signer.sign_to_vec()?);
+
+        let signature = format!(
+            r#"<ds:Signature xmlns:ds="{DSIG_NS}"><ds:SignedInfo>{signed_info}</ds:SignedInfo><ds:SignatureValue>{signature_value}</ds:SignatureValue><ds:KeyInfo><ds:X509Data><ds:X509Certificate>{}</ds:X509Certificate></ds:X509Data></ds:KeyInfo></ds:Signature>"#,
+            self.certificate_base64()?
+        );
+
+        let issuer_end = assertion
+            .find("</saml:Issuer>")
+            .map(|index| index + "</saml:Issuer>".len())
+            .ok_or_else(|| anyhow::anyhow!("Assertion has no issuer"))?;
+        let mut signed = assertion.to_owned();
+        signed.insert_str(issuer_end, &signature);
+
+        Ok(signed)
+    }
+
+    /// IdP metadata service providers are configured with
+    pub fn metadata(&self) -> Result<String, anyhow::Error> {
+        let name_id_formats: String = [
+            NameIdFormat::Persistent,
+            NameIdFormat::EmailAddress,
+            NameIdFormat::Unspecified,
+        ]
+        .iter()
+        .map(|format| format!("<md:NameIDFormat>{}</md:NameIDFormat>", format.urn()))
+        .collect();
+        let sso_url = escape_attribute(&self.sso_url);
+
+        Ok(format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><md:EntityDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" xmlns:ds="{DSIG_NS}" entityID="{}"><md:IDPSSODescriptor WantAuthnRequestsSigned="false" protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol"><md:KeyDescriptor use="signing"><ds:KeyInfo><ds:X509Data><ds:X509Certificate>{}</ds:X509Certificate></ds:X509Data></ds:KeyInfo></md:KeyDescriptor>{name_id_formats}<md:SingleSignOnService Binding="{HTTP_REDIRECT_BINDING}" Location="{sso_url}"/><md:SingleSignOnService Binding="{HTTP_POST_BINDING}" Location="{sso_url}"/></md:IDPSSODescriptor></md:EntityDescriptor>"#,
+            escape_attribute(&self.entity_id),
+            self.certificate_base64()?
+        ))
+    }
+}
+
+fn self_signed_certificate(
+    private_key: &PKey<Private>,
+    entity_id: &str,
+) -> Result<X509, anyhow::Error> {
+    let mut name = X509NameBuilder::new()?;
+    // Common names are limited to 64 characters
+    let common_name: String = entity_id.chars().take(64).collect();
+    name.append_entry_by_text("CN", &common_name)?;
+    let name = name.build();
+
+    let mut serial = BigNum::new()?;
+    serial.rand(127, MsbOption::MAYBE_ZERO, false)?;
+
+    let mut builder = X509::builder()?;
+    builder.set_version(2)?;
+    let serial = serial.to_asn1_integer()?;
+    builder.set_serial_number(&serial)?;
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_pubkey(private_key)?;
+    let not_before = Asn1Time::days_from_now(0)?;
+    let not_after = Asn1Time::days_from_now(3650)?;
+    builder.set_not_before(&not_before)?;
+    builder.set_not_after(&not_after)?;
+    builder.sign(private_key, MessageDigest::sha256())?;
+
+    Ok(builder.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::{rsa::Rsa, sign::Verifier};
+
+    use super::*;
+
+    #[test]
+    fn test_signed_assertion_verifies() {
+        let private_key_pem = Rsa::generate(2048).unwrap().private_key_to_pem().unwrap();
+        let issuer = SamlIssuer::new(
+            &private_key_pem,
+            None,
+            "https://idp.example/saml/metadata",
+            "https://idp.example/saml/sso",
+        )
+        .unwrap();
+        let assertion = r#"<saml:Assertion xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion" ID="_a1" IssueInstant="2026-10-19T12:00:00Z" Version="2.0"><saml:Issuer>https://idp.example/saml/metadata</saml:Issuer></saml:Assertion>"#;
+
+        let signed = issuer.sign_assertion(assertion, "_a1").unwrap();
+
+        // The enveloped signature transform restores the digested assertion
+        let start = signed.find("<ds:Signature").unwrap();
+        let end = signed.find("</ds:Signature>").unwrap() + "</ds:Signature>".len();
+        assert_eq!(format!("{}{}", &signed[..start], &signed[end..]), assertion);
+
+        let signed_info_start = signed.find("<ds:SignedInfo>").unwrap() + "<ds:SignedInfo>".len();
+        let signed_info_end = signed.find("</ds:SignedInfo>").unwrap();
+        let signed_info = &signed[signed_info_start..signed_info_end];
+        assert!(signed_info.contains(&STANDARD.encode(sha256(assertion.as_bytes()))));
+
+        let value_start = signed.find("<ds:SignatureValue>").unwrap() + "<ds:SignatureValue>".len();
+        let value_end = signed.find("</ds:SignatureValue>").unwrap();
+        let signature_value = STANDARD.decode(&signed[value_start..value_end]).unwrap();
+
+        let public_key = issuer.certificate.public_key().unwrap();
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &public_key).unwrap();
+        verifier
+            .update(
+                format!(r#"<ds:SignedInfo xmlns:ds="{DSIG_NS}">{signed_info}</ds:SignedInfo>"#)
+                    .as_bytes(),
+            )
+            .unwrap();
+        assert!(verifier.verify(&signature_value).unwrap());
+    }
+}
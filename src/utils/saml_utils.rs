@@ -0,0 +1,394 @@
+use std::io::{Read, Write};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Utc};
+use flate2::{Compression, read::DeflateDecoder, write::DeflateEncoder};
+use openssl::rand::rand_bytes;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::models::saml::{AuthnRequest, NameIdFormat, SamlAttribute, SamlAttributeMapping};
+
+pub const PROTOCOL_NS: &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+pub const ASSERTION_NS: &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+pub const HTTP_POST_BINDING: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST";
+pub const HTTP_REDIRECT_BINDING: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect";
+
+/// Upper bound for inflated requests, AuthnRequests are a few kilobytes at most
+const MAX_REQUEST_SIZE: u64 = 64 * 1024;
+
+#[derive(Debug, Error)]
+pub enum SamlError {
+    #[error("SAMLRequest is not valid base64")]
+    InvalidEncoding,
+    #[error("SAMLRequest could not be inflated")]
+    InvalidDeflate,
+    #[error("SAMLRequest is not valid XML: {0}")]
+    InvalidXml(String),
+    #[error("Expected an AuthnRequest")]
+    NotAnAuthnRequest,
+    #[error("AuthnRequest is missing {0}")]
+    MissingField(&'static str),
+}
+
+/// Status of a `<samlp:Response>`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SamlStatus {
+    Success,
+    /// `IsPassive` was requested but the user has to log in
+    NoPassive,
+    /// The user belongs to another tenant than the service provider
+    RequestDenied,
+}
+
+impl SamlStatus {
+    fn codes(&self) -> (&'static str, Option<&'static str>) {
+        match self {
+            SamlStatus::Success => ("urn:oasis:names:tc:SAML:2.0:status:Success", None),
+            SamlStatus::NoPassive => (
+                "urn:oasis:names:tc:SAML:2.0:status:Responder",
+                Some("urn:oasis:names:tc:SAML:2.0:status:NoPassive"),
+            ),
+            SamlStatus::RequestDenied => (
+                "urn:oasis:names:tc:SAML:2.0:status:Responder",
+                Some("urn:oasis:names:tc:SAML:2.0:status:RequestDenied"),
+            ),
+        }
+    }
+}
+
+/// Everything an assertion states about an authenticated user
+pub struct AssertionContext<'a> {
+    pub issuer: &'a str,
+    pub audience: &'a str,
+    pub recipient: &'a str,
+    pub in_response_to: Option<&'a str>,
+    pub name_id: &'a str,
+    pub name_id_format: NameIdFormat,
+    pub auth_instant: i64,
+    pub attributes: &'a [SamlAttribute],
+}
+
+/// Decode a `SAMLRequest` sent with the HTTP-Redirect binding (base64 of raw DEFLATE)
+pub fn decode_redirect_request(saml_request: &str) -> Result<String, SamlError> {
+    let deflated = STANDARD
+        .decode(saml_request.trim())
+        .map_err(|_| SamlError::InvalidEncoding)?;
+
+    let mut xml = String::new();
+    DeflateDecoder::new(deflated.as_slice())
+        .take(MAX_REQUEST_SIZE)
+        .read_to_string(&mut xml)
+        .map_err(|_| SamlError::InvalidDeflate)?;
+
+    Ok(xml)
+}
+
+/// Decode a `SAMLRequest` sent with the HTTP-POST binding (plain base64)
+pub fn decode_post_request(saml_request: &str) -> Result<String, SamlError> {
+    // Form posts may wrap the base64 value over several lines
+    let compact: String = saml_request.split_whitespace().collect();
+    let xml = STANDARD
+        .decode(compact)
+        .map_err(|_| SamlError::InvalidEncoding)?;
+
+    String::from_utf8(xml).map_err(|_| SamlError::InvalidEncoding)
+}
+
+/// Encode a request for the HTTP-Redirect binding, used to resume it after the login
+pub fn encode_redirect_request(xml: &str) -> Result<String, anyhow::Error> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(xml.as_bytes())?;
+    Ok(STANDARD.encode(encoder.finish()?))
+}
+
+pub fn parse_authn_request(xml: &str) -> Result<AuthnRequest, SamlError> {
+    // DTDs are rejected by the parser, so entity expansion attacks are not possible
+    let document =
+        roxmltree::Document::parse(xml).map_err(|e| SamlError::InvalidXml(e.to_string()))?;
+    let root = document.root_element();
+    if !root.has_tag_name((PROTOCOL_NS, "AuthnRequest")) {
+        return Err(SamlError::NotAnAuthnRequest);
+    }
+
+    let id = root.attribute("ID").ok_or(SamlError::MissingField("ID"))?;
+    let issuer = root
+        .children()
+        .find(|node| node.has_tag_name((ASSERTION_NS, "Issuer")))
+        .and_then(|node| node.text())
+        .map(str::trim)
+        .ok_or(SamlError::MissingField("Issuer"))?;
+    let issue_instant = root
+        .attribute("IssueInstant")
+        .and_then(|instant| DateTime::parse_from_rfc3339(instant).ok())
+        .ok_or(SamlError::MissingField("IssueInstant"))?;
+    let flag = |name: &str| root.attribute(name).is_some_and(|value| value == "true");
+
+    Ok(AuthnRequest {
+        id: id.to_owned(),
+        issuer: issuer.to_owned(),
+        issue_instant: issue_instant.timestamp(),
+        acs_url: root
+            .attribute("AssertionConsumerServiceURL")
+            .map(str::to_owned),
+        protocol_binding: root.attribute("ProtocolBinding").map(str::to_owned),
+        force_authn: flag("ForceAuthn"),
+        is_passive: flag("IsPassive"),
+    })
+}
+
+/// A unique XML ID, which must not start with a digit
+pub fn new_id() -> Result<String, anyhow::Error> {
+    let mut bytes = [0u8; 20];
+    rand_bytes(&mut bytes)?;
+    Ok(format!(
+        "_{}",
+        bytes.iter().map(|b| format!("{b:02x}")).collect::<String>()
+    ))
+}
+
+/// `xs:dateTime` in UTC as required by SAML
+pub fn saml_instant(timestamp: i64) -> String {
+    DateTime::<Utc>::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string()
+}
+
+/// SAML attribute values of a claim or custom attribute, objects are not released
+pub fn attribute_values(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(value) => vec![value.clone()],
+        Value::Bool(value) => vec![value.to_string()],
+        Value::Number(value) => vec![value.to_string()],
+        Value::Array(values) => values.iter().flat_map(attribute_values).collect(),
+        Value::Null | Value::Object(_) => Vec::new(),
+    }
+}
+
+/// Attributes released to a service provider from the user's claims and custom attributes
+pub fn released_attributes(
+    values: &Map<String, Value>,
+    mappings: &[SamlAttributeMapping],
+) -> Vec<SamlAttribute> {
+    mappings
+        .iter()
+        .filter_map(|mapping| {
+            let values = values
+                .get(&mapping.source)
+                .map(attribute_values)
+                .unwrap_or_default();
+
+            (!values.is_empty()).then(|| SamlAttribute {
+                name: mapping.name.clone(),
+                friendly_name: mapping.friendly_name.clone(),
+                values,
+            })
+        })
+        .collect()
+}
+
+/// Build an assertion in exclusive canonical form, so its digest can be computed over the string
+pub fn build_assertion(
+    id: &str,
+    issue_instant: i64,
+    lifetime_seconds: i64,
+    context: &AssertionContext,
+) -> String {
+    let now = saml_instant(issue_instant);
+    let not_on_or_after = saml_instant(issue_instant + lifetime_seconds);
+    let in_response_to = context
+        .in_response_to
+        .map(|request_id| format!(r#" InResponseTo="{}""#, escape_attribute(request_id)))
+        .unwrap_or_default();
+
+    let mut xml = format!(
+        r#"<saml:Assertion xmlns:saml="{ASSERTION_NS}" ID="{id}" IssueInstant="{now}" Version="2.0"><saml:Issuer>{}</saml:Issuer>"#,
+        escape_text(context.issuer)
+    );
+    xml.push_str(&format!(
+        r#"<saml:Subject><saml:NameID Format="{}">{}</saml:NameID><saml:SubjectConfirmation Method="urn:oasis:names:tc:SAML:2.0:cm:bearer"><saml:SubjectConfirmationData{in_response_to} NotOnOrAfter="{not_on_or_after}" Recipient="{}"></saml:SubjectConfirmationData></saml:SubjectConfirmation></saml:Subject>"#,
+        context.name_id_format.urn(),
+        escape_text(context.name_id),
+        escape_attribute(context.recipient)
+    ));
+    xml.push_str(&format!(
+        r#"<saml:Conditions NotBefore="{now}" NotOnOrAfter="{not_on_or_after}"><saml:AudienceRestriction><saml:Audience>{}</saml:Audience></saml:AudienceRestriction></saml:Conditions>"#,
+        escape_text(context.audience)
+    ));
+    xml.push_str(&format!(
+        r#"<saml:AuthnStatement AuthnInstant="{}" SessionIndex="{id}"><saml:AuthnContext><saml:AuthnContextClassRef>urn:oasis:names:tc:SAML:2.0:ac:classes:PasswordProtectedTransport</saml:AuthnContextClassRef></saml:AuthnContext></saml:AuthnStatement>"#,
+        saml_instant(context.auth_instant)
+    ));
+
+    if !context.attributes.is_empty() {
+        xml.push_str("<saml:AttributeStatement>");
+        for attribute in context.attributes {
+            // Canonical attribute order: FriendlyName, Name, NameFormat
+            let friendly_name = attribute
+                .friendly_name
+                .as_deref()
+                .map(|friendly_name| {
+                    format!(r#" FriendlyName="{}""#, escape_attribute(friendly_name))
+                })
+                .unwrap_or_default();
+            xml.push_str(&format!(
+                r#"<saml:Attribute{friendly_name} Name="{}" NameFormat="urn:oasis:names:tc:SAML:2.0:attrname-format:basic">"#,
+                escape_attribute(&attribute.name)
+            ));
+            for value in &attribute.values {
+                xml.push_str(&format!(
+                    "<saml:AttributeValue>{}</saml:AttributeValue>",
+                    escape_text(value)
+                ));
+            }
+            xml.push_str("</saml:Attribute>");
+        }
+        xml.push_str("</saml:AttributeStatement>");
+    }
+
+    xml.push_str("</saml:Assertion>");
+    xml
+}
+
+pub fn build_response(
+    id: &str,
+    issue_instant: i64,
+    issuer: &str,
+    destination: &str,
+    in_response_to: Option<&str>,
+    status: SamlStatus,
+    assertion: Option<&str>,
+) -> String {
+    let in_response_to = in_response_to
+        .map(|request_id| format!(r#" InResponseTo="{}""#, escape_attribute(request_id)))
+        .unwrap_or_default();
+    let (status_code, sub_status_code) = status.codes();
+    let status_code = match sub_status_code {
+        Some(sub_status_code) => format!(
+            r#"<samlp:StatusCode Value="{status_code}"><samlp:StatusCode Value="{sub_status_code}"></samlp:StatusCode></samlp:StatusCode>"#
+        ),
+        None => format!(r#"<samlp:StatusCode Value="{status_code}"></samlp:StatusCode>"#),
+    };
+
+    format!(
+        r#"<samlp:Response xmlns:samlp="{PROTOCOL_NS}" xmlns:saml="{ASSERTION_NS}" Destination="{}" ID="{id}"{in_response_to} IssueInstant="{}" Version="2.0"><saml:Issuer>{}</saml:Issuer><samlp:Status>{status_code}</samlp:Status>{}</samlp:Response>"#,
+        escape_attribute(destination),
+        saml_instant(issue_instant),
+        escape_text(issuer),
+        assertion.unwrap_or_default()
+    )
+}
+
+/// Auto-submitting form delivering a response with the HTTP-POST binding
+pub fn post_form(acs_url: &str, saml_response: &str, relay_state: Option<&str>) -> String {
+    let relay_state = relay_state
+        .map(|relay_state| {
+            format!(
+                r#"<input type="hidden" name="RelayState" value="{}"/>"#,
+                escape_attribute(relay_state)
+            )
+        })
+        .unwrap_or_default();
+
+    format!(
+        r#"<!DOCTYPE html><html><body onload="document.forms[0].submit()"><noscript><p>JavaScript is disabled, press Continue to proceed.</p></noscript><form method="post" action="{}"><input type="hidden" name="SAMLResponse" value="{}"/>{relay_state}<noscript><button type="submit">Continue</button></noscript></form></body></html>"#,
+        escape_attribute(acs_url),
+        STANDARD.encode(saml_response)
+    )
+}
+
+/// Escape character data as in canonical XML
+pub fn escape_text(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\r', "&#xD;")
+}
+
+/// Escape an attribute value as in canonical XML
+pub fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+        .replace('\t', "&#x9;")
+        .replace('\n', "&#xA;")
+        .replace('\r', "&#xD;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AUTHN_REQUEST: &str = r#"<samlp:AuthnRequest xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol" xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion" ID="_req1" Version="2.0" IssueInstant="2026-10-19T12:00:00Z" AssertionConsumerServiceURL="https://sp.example/acs" ForceAuthn="true">
+  <saml:Issuer>https://sp.example</saml:Issuer>
+</samlp:AuthnRequest>"#;
+
+    #[test]
+    fn test_redirect_request_roundtrip() {
+        let encoded = encode_redirect_request(AUTHN_REQUEST).unwrap();
+        let request = parse_authn_request(&decode_redirect_request(&encoded).unwrap()).unwrap();
+
+        assert_eq!(
+            request,
+            AuthnRequest {
+                id: "_req1".to_string(),
+                issuer: "https://sp.example".to_string(),
+                issue_instant: 1792411200,
+                acs_url: Some("https://sp.example/acs".to_string()),
+                protocol_binding: None,
+                force_authn: true,
+                is_passive: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_other_documents() {
+        assert!(matches!(
+            parse_authn_request(r#"<Response xmlns="urn:oasis:names:tc:SAML:2.0:protocol"/>"#),
+            Err(SamlError::NotAnAuthnRequest)
+        ));
+        assert!(matches!(
+            parse_authn_request(
+                r#"<!DOCTYPE x [<!ENTITY a "b">]><samlp:AuthnRequest xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol"/>"#
+            ),
+            Err(SamlError::InvalidXml(_))
+        ));
+    }
+
+    #[test]
+    fn test_released_attributes_and_escaping() {
+        let values = serde_json::json!({ "email": "a@b.c", "groups": ["a", 1, true, null] });
+        let mappings = [
+            SamlAttributeMapping {
+                name: "urn:oid:0.9.2342.19200300.100.1.3".to_string(),
+                source: "email".to_string(),
+                friendly_name: Some("mail".to_string()),
+            },
+            SamlAttributeMapping {
+                name: "groups".to_string(),
+                source: "groups".to_string(),
+                friendly_name: None,
+            },
+            SamlAttributeMapping {
+                name: "department".to_string(),
+                source: "department".to_string(),
+                friendly_name: None,
+            },
+        ];
+
+        let attributes = released_attributes(values.as_object().unwrap(), &mappings);
+
+        assert_eq!(attributes.len(), 2);
+        assert_eq!(attributes[0].values, vec!["a@b.c"]);
+        assert_eq!(attributes[1].values, vec!["a", "1", "true"]);
+        assert_eq!(escape_text(r#"<a & "b">"#), r#"&lt;a &amp; "b"&gt;"#);
+        assert_eq!(
+            escape_attribute(r#"<a & "b">"#),
+            "&lt;a &amp; &quot;b&quot;>"
+        );
+    }
+}
@@ -0,0 +1,1085 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, SecondsFormat};
+use openssl::sha::sha256;
+use serde_json::{Map, Value, json};
+use thiserror::Error;
+
+use crate::models::scim::{
+    LIST_RESPONSE_SCHEMA, SchemaAttribute, ScimListResponse, ScimLookup, ScimMultiValue,
+    ScimPatchOperation,
+};
+
+/// Default and maximum page size of list responses
+pub const MAX_RESULTS: usize = 200;
+
+/// Attributes returned regardless of `attributes` and `excludedAttributes`
+const ALWAYS_RETURNED: [&str; 2] = ["id", "schemas"];
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ScimError {
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(String),
+    #[error("Invalid path: {0}")]
+    InvalidPath(String),
+    #[error("Invalid syntax: {0}")]
+    InvalidSyntax(String),
+    #[error("Invalid value: {0}")]
+    InvalidValue(String),
+    #[error("No target: {0}")]
+    NoTarget(String),
+    #[error("Attribute {0} is read-only")]
+    Mutability(&'static str),
+    #[error("{0} is already taken")]
+    Uniqueness(&'static str),
+}
+
+impl ScimError {
+    /// `scimType` of the error response (RFC 7644 section 3.12)
+    pub fn scim_type(&self) -> &'static str {
+        match self {
+            ScimError::InvalidFilter(_) => "invalidFilter",
+            ScimError::InvalidPath(_) => "invalidPath",
+            ScimError::InvalidSyntax(_) => "invalidSyntax",
+            ScimError::InvalidValue(_) => "invalidValue",
+            ScimError::NoTarget(_) => "noTarget",
+            ScimError::Mutability(_) => "mutability",
+            ScimError::Uniqueness(_) => "uniqueness",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Co,
+    Sw,
+    Ew,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl CompareOp {
+    fn parse(op: &str) -> Option<Self> {
+        Some(match op.to_ascii_lowercase().as_str() {
+            "eq" => CompareOp::Eq,
+            "ne" => CompareOp::Ne,
+            "co" => CompareOp::Co,
+            "sw" => CompareOp::Sw,
+            "ew" => CompareOp::Ew,
+            "gt" => CompareOp::Gt,
+            "ge" => CompareOp::Ge,
+            "lt" => CompareOp::Lt,
+            "le" => CompareOp::Le,
+            _ => return None,
+        })
+    }
+}
+
+/// A parsed SCIM filter (RFC 7644 section 3.4.2.2)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Present(String),
+    Compare(String, CompareOp, Value),
+    /// `emails[type eq "work"]`, matches if any value matches the inner filter
+    ValuePath(String, Box<Filter>),
+}
+
+impl Filter {
+    /// Evaluate against a resource, string comparisons are case-insensitive
+    pub fn matches(&self, resource: &Value) -> bool {
+        match self {
+            Filter::And(left, right) => left.matches(resource) && right.matches(resource),
+            Filter::Or(left, right) => left.matches(resource) || right.matches(resource),
+            Filter::Not(filter) => !filter.matches(resource),
+            Filter::Present(path) => values_at(resource, path).into_iter().any(|value| {
+                !matches!(value, Value::Null)
+                    && value.as_str() != Some("")
+                    && value.as_array().is_none_or(|values| !values.is_empty())
+            }),
+            Filter::Compare(path, CompareOp::Ne, expected) => !values_at(resource, path)
+                .into_iter()
+                .any(|value| compare(value, CompareOp::Eq, expected)),
+            Filter::Compare(path, op, expected) => values_at(resource, path)
+                .into_iter()
+                .any(|value| compare(value, *op, expected)),
+            Filter::ValuePath(path, filter) => values_at(resource, path)
+                .into_iter()
+                .any(|value| filter.matches(value)),
+        }
+    }
+
+    /// Value with the attributes of an equality filter, used to add a value a PATCH path selects
+    fn new_value(&self) -> Option<Map<String, Value>> {
+        match self {
+            Filter::Compare(path, CompareOp::Eq, value) if !path.contains('.') => {
+                Some(Map::from_iter([(path.clone(), value.clone())]))
+            }
+            Filter::And(left, right) => {
+                let mut value = left.new_value()?;
+                value.extend(right.new_value()?);
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    OpenBracket,
+    CloseBracket,
+    Word(String),
+    Text(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ScimError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            c if c.is_whitespace() => i += 1,
+            '(' | ')' | '[' | ']' => {
+                tokens.push(match chars[i] {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    '[' => Token::OpenBracket,
+                    _ => Token::CloseBracket,
+                });
+                i += 1;
+            }
+            '"' => {
+                let start = i;
+                let mut escaped = false;
+                i += 1;
+                loop {
+                    let Some(&c) = chars.get(i) else {
+                        return Err(ScimError::InvalidFilter("Unterminated string".to_string()));
+                    };
+                    i += 1;
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+                let literal: String = chars[start..i].iter().collect();
+                let text = serde_json::from_str(&literal)
+                    .map_err(|_| ScimError::InvalidFilter(format!("Invalid string {literal}")))?;
+                tokens.push(Token::Text(text));
+            }
+            _ => {
+                let start = i;
+                while i < chars.len()
+                    && !chars[i].is_whitespace()
+                    && !matches!(chars[i], '(' | ')' | '[' | ']' | '"')
+                {
+                    i += 1;
+                }
+                tokens.push(Token::Word(chars[start..i].iter().collect()));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct FilterParser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl FilterParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found =
+            matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ScimError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            token => Err(ScimError::InvalidFilter(format!(
+                "Expected {expected:?}, found {token:?}"
+            ))),
+        }
+    }
+
+    fn or(&mut self) -> Result<Filter, ScimError> {
+        let mut filter = self.and()?;
+        while self.keyword("or") {
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, ScimError> {
+        let mut filter = self.unary()?;
+        while self.keyword("and") {
+            filter = Filter::And(Box::new(filter), Box::new(self.unary()?));
+        }
+        Ok(filter)
+    }
+
+    fn unary(&mut self) -> Result<Filter, ScimError> {
+        if self.keyword("not") {
+            self.expect(Token::Open)?;
+            let filter = self.or()?;
+            self.expect(Token::Close)?;
+            return Ok(Filter::Not(Box::new(filter)));
+        }
+
+        match self.next() {
+            Some(Token::Open) => {
+                let filter = self.or()?;
+                self.expect(Token::Close)?;
+                Ok(filter)
+            }
+            Some(Token::Word(path)) => {
+                let path = strip_schema(&path).to_string();
+                if self.peek() == Some(&Token::OpenBracket) {
+                    self.position += 1;
+                    let filter = self.or()?;
+                    self.expect(Token::CloseBracket)?;
+                    return Ok(Filter::ValuePath(path, Box::new(filter)));
+                }
+
+                let op = match self.next() {
+                    Some(Token::Word(op)) if op.eq_ignore_ascii_case("pr") => {
+                        return Ok(Filter::Present(path));
+                    }
+                    Some(Token::Word(op)) => CompareOp::parse(&op).ok_or_else(|| {
+                        ScimError::InvalidFilter(format!("Unknown operator {op}"))
+                    })?,
+                    token => {
+                        return Err(ScimError::InvalidFilter(format!(
+                            "Expected operator, found {token:?}"
+                        )));
+                    }
+                };
+                let value = match self.next() {
+                    Some(Token::Text(text)) => Value::String(text),
+                    Some(Token::Word(word)) => match serde_json::from_str(&word) {
+                        Ok(value @ (Value::Bool(_) | Value::Null | Value::Number(_))) => value,
+                        _ => return Err(ScimError::InvalidFilter(format!("Invalid value {word}"))),
+                    },
+                    token => {
+                        return Err(ScimError::InvalidFilter(format!(
+                            "Expected value, found {token:?}"
+                        )));
+                    }
+                };
+                Ok(Filter::Compare(path, op, value))
+            }
+            token => Err(ScimError::InvalidFilter(format!(
+                "Expected attribute, found {token:?}"
+            ))),
+        }
+    }
+}
+
+pub fn parse_filter(filter: &str) -> Result<Filter, ScimError> {
+    let mut parser = FilterParser {
+        tokens: tokenize(filter)?,
+        position: 0,
+    };
+    let parsed = parser.or()?;
+    if parser.position < parser.tokens.len() {
+        return Err(ScimError::InvalidFilter(format!(
+            "Unexpected {:?}",
+            parser.tokens[parser.position]
+        )));
+    }
+    Ok(parsed)
+}
+
+/// `urn:ietf:params:scim:schemas:core:2.0:User:name.givenName` to `name.givenName`
+fn strip_schema(path: &str) -> &str {
+    match path.strip_prefix("urn:") {
+        Some(_) => path
+            .rsplit_once(':')
+            .map_or(path, |(_, attribute)| attribute),
+        None => path,
+    }
+}
+
+/// Values at an attribute path, multi-valued attributes are flattened
+fn values_at<'a>(resource: &'a Value, path: &str) -> Vec<&'a Value> {
+    let mut values = vec![resource];
+    for segment in path.split('.') {
+        values = values
+            .into_iter()
+            .filter_map(|value| value.as_object())
+            .filter_map(|object| {
+                object
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(segment))
+                    .map(|(_, value)| value)
+            })
+            .flat_map(|value| match value {
+                Value::Array(values) => values.iter().collect(),
+                value => vec![value],
+            })
+            .collect();
+    }
+    values
+}
+
+fn compare(actual: &Value, op: CompareOp, expected: &Value) -> bool {
+    // Complex values like emails compare by their `value`
+    let actual = match actual {
+        Value::Object(object) => match object.get("value") {
+            Some(value) => value,
+            None => return false,
+        },
+        actual => actual,
+    };
+
+    match (actual, expected) {
+        (Value::String(actual), Value::String(expected)) => {
+            let (actual, expected) = (actual.to_lowercase(), expected.to_lowercase());
+            match op {
+                CompareOp::Eq => actual == expected,
+                CompareOp::Ne => actual != expected,
+                CompareOp::Co => actual.contains(&expected),
+                CompareOp::Sw => actual.starts_with(&expected),
+                CompareOp::Ew => actual.ends_with(&expected),
+                CompareOp::Gt => actual > expected,
+                CompareOp::Ge => actual >= expected,
+                CompareOp::Lt => actual < expected,
+                CompareOp::Le => actual <= expected,
+            }
+        }
+        (Value::Number(actual), Value::Number(expected)) => {
+            let (Some(actual), Some(expected)) = (actual.as_f64(), expected.as_f64()) else {
+                return false;
+            };
+            match op {
+                CompareOp::Eq => actual == expected,
+                CompareOp::Ne => actual != expected,
+                CompareOp::Gt => actual > expected,
+                CompareOp::Ge => actual >= expected,
+                CompareOp::Lt => actual < expected,
+                CompareOp::Le => actual <= expected,
+                CompareOp::Co | CompareOp::Sw | CompareOp::Ew => false,
+            }
+        }
+        (Value::Bool(actual), Value::Bool(expected)) => match op {
+            CompareOp::Eq => actual == expected,
+            CompareOp::Ne => actual != expected,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Target of a PATCH operation, e.g. `emails[type eq "work"].value`
+#[derive(Debug, PartialEq)]
+pub struct PatchPath {
+    pub attribute: String,
+    pub filter: Option<Filter>,
+    pub sub_attribute: Option<String>,
+}
+
+pub fn parse_patch_path(path: &str) -> Result<PatchPath, ScimError> {
+    let path = path.trim();
+    let (head, selection) = match path.find('[') {
+        Some(index) => (&path[..index], Some(&path[index..])),
+        None => (path, None),
+    };
+    let head = strip_schema(head);
+
+    let (attribute, filter, sub_attribute) = match selection {
+        None => match head.split_once('.') {
+            Some((attribute, sub_attribute)) => (attribute, None, Some(sub_attribute)),
+            None => (head, None, None),
+        },
+        Some(selection) => {
+            let end = selection
+                .rfind(']')
+                .ok_or_else(|| ScimError::InvalidPath(path.to_string()))?;
+            let filter = parse_filter(&selection[1..end])?;
+            let sub_attribute = match &selection[end + 1..] {
+                "" => None,
+                rest => Some(
+                    rest.strip_prefix('.')
+                        .ok_or_else(|| ScimError::InvalidPath(path.to_string()))?,
+                ),
+            };
+            (head, Some(filter), sub_attribute)
+        }
+    };
+
+    if attribute.is_empty() || sub_attribute.is_some_and(str::is_empty) {
+        return Err(ScimError::InvalidPath(path.to_string()));
+    }
+
+    Ok(PatchPath {
+        attribute: attribute.to_string(),
+        filter,
+        sub_attribute: sub_attribute.map(str::to_string),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PatchOp {
+    Add,
+    Replace,
+    Remove,
+}
+
+/// Apply a PATCH operation (RFC 7644 section 3.5.2) to the JSON representation of a resource
+pub fn apply_patch(
+    resource: &mut Value,
+    operation: &ScimPatchOperation,
+    attributes: &[SchemaAttribute],
+) -> Result<(), ScimError> {
+    let op = match operation.op.to_ascii_lowercase().as_str() {
+        "add" => PatchOp::Add,
+        "replace" => PatchOp::Replace,
+        "remove" => PatchOp::Remove,
+        op => return Err(ScimError::InvalidSyntax(format!("Unknown operation {op}"))),
+    };
+    let Some(object) = resource.as_object_mut() else {
+        return Err(ScimError::InvalidValue(
+            "Resource is not an object".to_string(),
+        ));
+    };
+
+    match (&operation.path, op) {
+        (Some(path), _) => apply_at(
+            object,
+            &parse_patch_path(path)?,
+            op,
+            operation.value.as_ref(),
+            attributes,
+        ),
+        (None, PatchOp::Remove) => Err(ScimError::NoTarget(
+            "Remove operations require a path".to_string(),
+        )),
+        (None, _) => {
+            let Some(Value::Object(values)) = &operation.value else {
+                return Err(ScimError::InvalidValue(
+                    "Operations without path require an object value".to_string(),
+                ));
+            };
+            for (key, value) in values {
+                let path = parse_patch_path(key)?;
+                // Like unknown attributes of a PUT, e.g. unsupported schema extensions
+                if SchemaAttribute::find(attributes, &path.attribute).is_none() {
+                    continue;
+                }
+                apply_at(object, &path, op, Some(value), attributes)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn apply_at(
+    object: &mut Map<String, Value>,
+    path: &PatchPath,
+    op: PatchOp,
+    value: Option<&Value>,
+    attributes: &[SchemaAttribute],
+) -> Result<(), ScimError> {
+    let attribute = SchemaAttribute::find(attributes, &path.attribute)
+        .ok_or_else(|| ScimError::InvalidPath(path.attribute.clone()))?;
+    if attribute.mutability == "readOnly" {
+        return Err(ScimError::Mutability(attribute.name));
+    }
+    let value = match (op, value) {
+        (PatchOp::Remove, value) => value.map(|value| canonical_value(value, attribute)),
+        (_, Some(value)) => Some(canonical_value(value, attribute)),
+        (_, None) => {
+            return Err(ScimError::InvalidValue(format!(
+                "Operation on {} requires a value",
+                attribute.name
+            )));
+        }
+    };
+    let sub_attribute = match &path.sub_attribute {
+        Some(name) => Some(
+            SchemaAttribute::find(attribute.sub_attributes, name)
+                .ok_or_else(|| ScimError::InvalidPath(format!("{}.{name}", attribute.name)))?
+                .name,
+        ),
+        None => None,
+    };
+
+    match (&path.filter, sub_attribute) {
+        (None, None) => {
+            apply_to_attribute(object, attribute, op, value);
+            Ok(())
+        }
+        (None, Some(sub_attribute)) => {
+            let target =
+                object
+                    .entry(attribute.name)
+                    .or_insert_with(|| match attribute.multi_valued {
+                        true => Value::Array(Vec::new()),
+                        false => Value::Object(Map::new()),
+                    });
+            if !target.is_object() && !target.is_array() {
+                *target = Value::Object(Map::new());
+            }
+            // Without a filter, a sub-attribute of a multi-valued attribute applies to all values
+            let targets: Vec<&mut Value> = match target {
+                Value::Array(values) => values.iter_mut().collect(),
+                target => vec![target],
+            };
+            for target in targets {
+                if let Some(target) = target.as_object_mut() {
+                    set_or_remove(target, sub_attribute, value.clone());
+                }
+            }
+            Ok(())
+        }
+        (Some(filter), sub_attribute) => {
+            if !attribute.multi_valued {
+                return Err(ScimError::InvalidPath(format!(
+                    "{} is not multi-valued",
+                    attribute.name
+                )));
+            }
+            let target = object
+                .entry(attribute.name)
+                .or_insert_with(|| Value::Array(Vec::new()));
+            if !target.is_array() {
+                *target = Value::Array(Vec::new());
+            }
+            let Value::Array(values) = target else {
+                unreachable!()
+            };
+
+            if !values.iter().any(|element| filter.matches(element)) {
+                match (op, filter.new_value()) {
+                    (PatchOp::Remove, _) => return Ok(()),
+                    (_, Some(new_value)) => {
+                        values.push(canonical_value(&Value::Object(new_value), attribute))
+                    }
+                    (_, None) => {
+                        return Err(ScimError::NoTarget(format!(
+                            "No value of {} matches the filter",
+                            attribute.name
+                        )));
+                    }
+                }
+            }
+
+            match (op, sub_attribute) {
+                (PatchOp::Remove, None) => values.retain(|element| !filter.matches(element)),
+                (_, Some(sub_attribute)) => {
+                    for element in values.iter_mut().filter(|element| filter.matches(element)) {
+                        if let Some(element) = element.as_object_mut() {
+                            set_or_remove(element, sub_attribute, value.clone());
+                        }
+                    }
+                }
+                (_, None) => {
+                    for element in values.iter_mut().filter(|element| filter.matches(element)) {
+                        match (op, &value, element.as_object_mut()) {
+                            (PatchOp::Add, Some(Value::Object(fields)), Some(element)) => {
+                                element.extend(fields.clone());
+                            }
+                            (_, Some(value), _) => *element = value.clone(),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn apply_to_attribute(
+    object: &mut Map<String, Value>,
+    attribute: &SchemaAttribute,
+    op: PatchOp,
+    value: Option<Value>,
+) {
+    let key = attribute.name;
+    match (op, value) {
+        // Removing listed values, e.g. group members
+        (PatchOp::Remove, Some(Value::Array(removed))) if attribute.multi_valued => {
+            if let Some(Value::Array(values)) = object.get_mut(key) {
+                values.retain(|element| !removed.iter().any(|item| same_value(element, item)));
+            }
+        }
+        (PatchOp::Remove, _) => {
+            object.remove(key);
+        }
+        (PatchOp::Add, Some(value)) if attribute.multi_valued => {
+            let added = match value {
+                Value::Array(values) => values,
+                value => vec![value],
+            };
+            let target = object
+                .entry(key)
+                .or_insert_with(|| Value::Array(Vec::new()));
+            if !target.is_array() {
+                *target = Value::Array(Vec::new());
+            }
+            if let Value::Array(values) = target {
+                for item in added {
+                    if !values.iter().any(|element| same_value(element, &item)) {
+                        values.push(item);
+                    }
+                }
+            }
+        }
+        (PatchOp::Replace, Some(value)) if attribute.multi_valued => {
+            let values = match value {
+                Value::Array(values) => values,
+                value => vec![value],
+            };
+            object.insert(key.to_string(), Value::Array(values));
+        }
+        // Sub-attributes of complex attributes are merged, others left unchanged
+        (_, Some(Value::Object(fields))) if attribute.kind == "complex" => {
+            match object.get_mut(key) {
+                Some(Value::Object(target)) => target.extend(fields),
+                _ => {
+                    object.insert(key.to_string(), Value::Object(fields));
+                }
+            }
+        }
+        (_, Some(value)) => {
+            object.insert(key.to_string(), value);
+        }
+        (_, None) => {}
+    }
+}
+
+fn set_or_remove(object: &mut Map<String, Value>, key: &str, value: Option<Value>) {
+    match value {
+        Some(value) => {
+            object.insert(key.to_string(), value);
+        }
+        None => {
+            object.remove(key);
+        }
+    }
+}
+
+/// Values of multi-valued attributes are identified by their `value`
+fn same_value(left: &Value, right: &Value) -> bool {
+    match (left.get("value"), right.get("value")) {
+        (Some(left), Some(right)) => left == right,
+        _ => left == right,
+    }
+}
+
+/// Rename sub-attributes to the casing of the schema
+fn canonical_value(value: &Value, attribute: &SchemaAttribute) -> Value {
+    match value {
+        Value::Array(values) => values
+            .iter()
+            .map(|value| canonical_value(value, attribute))
+            .collect(),
+        Value::Object(fields) if !attribute.sub_attributes.is_empty() => fields
+            .iter()
+            .map(|(name, value)| {
+                let name = SchemaAttribute::find(attribute.sub_attributes, name).map_or_else(
+                    || name.clone(),
+                    |sub_attribute| sub_attribute.name.to_string(),
+                );
+                (name, value.clone())
+            })
+            .collect(),
+        value => value.clone(),
+    }
+}
+
+/// Keep only the requested top-level attributes
+pub fn project(resource: &mut Value, attributes: Option<&str>, excluded_attributes: Option<&str>) {
+    let Some(object) = resource.as_object_mut() else {
+        return;
+    };
+    let listed = |list: &str, key: &str| {
+        list.split(',')
+            .map(|name| strip_schema(name.trim()))
+            .any(|name| {
+                name.split('.')
+                    .next()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(key))
+            })
+    };
+
+    object.retain(|key, _| {
+        ALWAYS_RETURNED.contains(&key.as_str())
+            || (attributes.is_none_or(|attributes| listed(attributes, key))
+                && excluded_attributes.is_none_or(|excluded| !listed(excluded, key)))
+    });
+}
+
+/// Page of a list response, `start_index` is 1-based
+pub fn paginate(
+    resources: Vec<Value>,
+    start_index: Option<i64>,
+    count: Option<i64>,
+) -> ScimListResponse {
+    let total_results = resources.len();
+    let (start_index, count) = page_bounds(start_index, count);
+
+    let resources: Vec<Value> = resources
+        .into_iter()
+        .skip(start_index - 1)
+        .take(count)
+        .collect();
+
+    list_page(resources, total_results, start_index)
+}
+
+/// 1-based index of the first result and page size of a list request
+pub fn page_bounds(start_index: Option<i64>, count: Option<i64>) -> (usize, usize) {
+    let start_index = start_index.unwrap_or(1).max(1) as usize;
+    let count = count.map_or(MAX_RESULTS, |count| {
+        count.clamp(0, MAX_RESULTS as i64) as usize
+    });
+    (start_index, count)
+}
+
+/// List response for a page of `total_results` resources starting at `start_index`
+pub fn list_page(
+    resources: Vec<Value>,
+    total_results: usize,
+    start_index: usize,
+) -> ScimListResponse {
+    ScimListResponse {
+        schemas: vec![LIST_RESPONSE_SCHEMA],
+        total_results,
+        start_index,
+        items_per_page: resources.len(),
+        resources,
+    }
+}
+
+/// Lookup for a user filter the database can evaluate, `None` if it has to be evaluated in memory
+pub fn user_lookup(filter: Option<&Filter>) -> Option<ScimLookup> {
+    lookup(filter, |lookup, attribute| {
+        match attribute.to_ascii_lowercase().as_str() {
+            "id" => Some(&mut lookup.id),
+            "externalid" => Some(&mut lookup.external_id),
+            "username" => Some(&mut lookup.name),
+            "emails" | "emails.value" => Some(&mut lookup.email),
+            _ => None,
+        }
+    })
+}
+
+/// Lookup for a group filter the database can evaluate, `None` if it has to be evaluated in memory
+pub fn group_lookup(filter: Option<&Filter>) -> Option<ScimLookup> {
+    lookup(filter, |lookup, attribute| {
+        match attribute.to_ascii_lowercase().as_str() {
+            "id" => Some(&mut lookup.id),
+            "externalid" => Some(&mut lookup.external_id),
+            "displayname" => Some(&mut lookup.name),
+            _ => None,
+        }
+    })
+}
+
+/// The lookup field an attribute is looked up by, if any
+type LookupField = for<'a> fn(&'a mut ScimLookup, &str) -> Option<&'a mut Option<String>>;
+
+/// Only `eq` comparisons of strings joined by `and`, at most one per attribute
+fn lookup(filter: Option<&Filter>, field: LookupField) -> Option<ScimLookup> {
+    fn add(lookup: &mut ScimLookup, filter: &Filter, field: LookupField) -> bool {
+        match filter {
+            Filter::And(left, right) => add(lookup, left, field) && add(lookup, right, field),
+            Filter::Compare(attribute, CompareOp::Eq, Value::String(value)) => {
+                match field(lookup, attribute) {
+                    Some(slot) if slot.is_none() => {
+                        *slot = Some(value.clone());
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    let mut lookup = ScimLookup::default();
+    match filter {
+        Some(filter) => add(&mut lookup, filter, field).then_some(lookup),
+        None => Some(lookup),
+    }
+}
+
+/// The primary value, or the first one if none is marked primary
+pub fn primary_value(values: &[ScimMultiValue]) -> Option<&str> {
+    values
+        .iter()
+        .find(|value| value.primary == Some(true))
+        .or_else(|| values.first())
+        .map(|value| value.value.as_str())
+}
+
+/// Provisioning tokens are stored hashed
+pub fn hash_token(token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(sha256(token.as_bytes()))
+}
+
+/// `meta.created` and `meta.lastModified` from a Unix timestamp
+pub fn scim_datetime(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Schema resource published at `/Schemas/{id}`
+pub fn schema_resource(
+    id: &str,
+    name: &str,
+    attributes: &[SchemaAttribute],
+    location: &str,
+) -> Value {
+    json!({
+        "schemas": ["urn:ietf:params:scim:schemas:core:2.0:Schema"],
+        "id": id,
+        "name": name,
+        "attributes": attributes.iter().map(SchemaAttribute::to_json).collect::<Vec<_>>(),
+        "meta": { "resourceType": "Schema", "location": location },
+    })
+}
+
+/// Resource type published at `/ResourceTypes/{name}`
+pub fn resource_type(name: &str, endpoint: &str, schema: &str, location: &str) -> Value {
+    json!({
+        "schemas": ["urn:ietf:params:scim:schemas:core:2.0:ResourceType"],
+        "id": name,
+        "name": name,
+        "endpoint": endpoint,
+        "schema": schema,
+        "meta": { "resourceType": "ResourceType", "location": location },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::scim::{GROUP_ATTRIBUTES, USER_ATTRIBUTES};
+
+    use super::*;
+
+    fn operation(op: &str, path: Option<&str>, value: Value) -> ScimPatchOperation {
+        ScimPatchOperation {
+            op: op.to_string(),
+            path: path.map(str::to_string),
+            value: Some(value),
+        }
+    }
+
+    #[test]
+    fn test_filter_matches_users() {
+        let user = json!({
+            "userName": "Jane.Doe",
+            "name": { "familyName": "Doe" },
+            "active": true,
+            "emails": [
+                { "value": "jane@example.com", "type": "work", "primary": true },
+                { "value": "jane@home.example", "type": "home" }
+            ],
+            "meta": { "lastModified": "2026-10-19T12:00:00Z" }
+        });
+        let matches = |filter: &str| parse_filter(filter).unwrap().matches(&user);
+
+        assert!(matches(r#"userName eq "jane.doe""#));
+        assert!(matches(
+            r#"urn:ietf:params:scim:schemas:core:2.0:User:name.familyName sw "D""#
+        ));
+        assert!(matches(
+            r#"emails[type eq "work" and value ew "@example.com"]"#
+        ));
+        assert!(matches(r#"emails co "home" and not (active eq false)"#));
+        assert!(matches(
+            r#"meta.lastModified gt "2026-10-01T00:00:00Z" or title pr"#
+        ));
+        assert!(!matches(r#"emails[type eq "work" and value co "home"]"#));
+        assert!(!matches("title pr"));
+        assert!(!matches(r#"userName ne "Jane.Doe""#));
+
+        assert!(matches!(
+            parse_filter(r#"userName eq "jane"#),
+            Err(ScimError::InvalidFilter(_))
+        ));
+        assert!(matches!(
+            parse_filter(r#"userName is "jane""#),
+            Err(ScimError::InvalidFilter(_))
+        ));
+    }
+
+    #[test]
+    fn test_patch_operations() {
+        let mut user = json!({
+            "userName": "jane",
+            "active": true,
+            "name": { "givenName": "Jane", "familyName": "Doe" },
+            "emails": [{ "value": "jane@example.com", "type": "work", "primary": true }]
+        });
+
+        // As sent by Azure AD: capitalized operations, string booleans and dotted keys
+        let operations = [
+            operation(
+                "Replace",
+                None,
+                json!({ "active": "False", "name.givenName": "Janet" }),
+            ),
+            operation(
+                "Add",
+                Some(r#"emails[type eq "home"].value"#),
+                json!("janet@home.example"),
+            ),
+            operation(
+                "replace",
+                Some(r#"emails[type eq "work"].Value"#),
+                json!("janet@example.com"),
+            ),
+            operation("add", Some("NAME"), json!({ "MiddleName": "Q" })),
+        ];
+        for operation in &operations {
+            apply_patch(&mut user, operation, USER_ATTRIBUTES).unwrap();
+        }
+
+        assert_eq!(
+            user,
+            json!({
+                "userName": "jane",
+                "active": "False",
+                "name": { "givenName": "Janet", "familyName": "Doe", "middleName": "Q" },
+                "emails": [
+                    { "value": "janet@example.com", "type": "work", "primary": true },
+                    { "type": "home", "value": "janet@home.example" }
+                ]
+            })
+        );
+
+        let remove = ScimPatchOperation {
+            op: "remove".to_string(),
+            path: Some(r#"emails[type eq "home"]"#.to_string()),
+            value: None,
+        };
+        apply_patch(&mut user, &remove, USER_ATTRIBUTES).unwrap();
+        assert_eq!(user["emails"].as_array().unwrap().len(), 1);
+
+        assert_eq!(
+            apply_patch(
+                &mut user,
+                &operation("add", Some("groups"), json!([])),
+                USER_ATTRIBUTES
+            ),
+            Err(ScimError::Mutability("groups"))
+        );
+        assert!(matches!(
+            apply_patch(
+                &mut user,
+                &operation("add", Some("title"), json!("CEO")),
+                USER_ATTRIBUTES
+            ),
+            Err(ScimError::InvalidPath(_))
+        ));
+    }
+
+    #[test]
+    fn test_patch_group_members() {
+        let mut group = json!({
+            "displayName": "Admins",
+            "members": [{ "value": "a" }, { "value": "b" }]
+        });
+
+        let operations = [
+            operation(
+                "add",
+                Some("members"),
+                json!([{ "value": "b" }, { "value": "c" }]),
+            ),
+            operation("remove", Some("members"), json!([{ "value": "a" }])),
+        ];
+        for operation in &operations {
+            apply_patch(&mut group, operation, GROUP_ATTRIBUTES).unwrap();
+        }
+        let remove = ScimPatchOperation {
+            op: "remove".to_string(),
+            path: Some(r#"members[value eq "c"]"#.to_string()),
+            value: None,
+        };
+        apply_patch(&mut group, &remove, GROUP_ATTRIBUTES).unwrap();
+
+        assert_eq!(group["members"], json!([{ "value": "b" }]));
+    }
+
+    #[test]
+    fn test_lookup_filters() {
+        let filter =
+            parse_filter(r#"userName eq "BJensen" and emails.value eq "b@example.com""#).unwrap();
+        assert_eq!(
+            user_lookup(Some(&filter)),
+            Some(ScimLookup {
+                name: Some("BJensen".to_string()),
+                email: Some("b@example.com".to_string()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(user_lookup(None), Some(ScimLookup::default()));
+
+        // Anything else is evaluated in memory
+        for filter in [
+            r#"userName sw "b""#,
+            r#"userName eq "a" or userName eq "b""#,
+            r#"userName eq "a" and userName eq "b""#,
+            r#"displayName eq "Admins""#,
+            "active eq true",
+        ] {
+            assert_eq!(user_lookup(Some(&parse_filter(filter).unwrap())), None);
+        }
+
+        let filter = parse_filter(r#"displayName eq "Admins""#).unwrap();
+        assert_eq!(
+            group_lookup(Some(&filter)).and_then(|lookup| lookup.name),
+            Some("Admins".to_string())
+        );
+    }
+
+    #[test]
+    fn test_paginate_and_project() {
+        let resources: Vec<Value> = (0..5)
+            .map(|i| json!({ "id": i.to_string(), "displayName": "Group", "members": [] }))
+            .collect();
+
+        let page = paginate(resources, Some(2), Some(2));
+        assert_eq!(page.total_results, 5);
+        assert_eq!(page.items_per_page, 2);
+        assert_eq!(page.resources[0]["id"], "1");
+
+        let mut resource = page.resources[0].clone();
+        project(&mut resource, None, Some("members"));
+        assert_eq!(resource, json!({ "id": "1", "displayName": "Group" }));
+        project(&mut resource, Some("members"), None);
+        assert_eq!(resource, json!({ "id": "1" }));
+    }
+}
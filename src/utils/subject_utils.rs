@@ -0,0 +1,111 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use openssl::sha::Sha256;
+use reqwest::Url;
+
+/// Stable `sub` for a user within a sector, unlinkable across sectors without the salt
+pub fn pairwise_subject(sector_identifier: &str, user_id: &str, salt: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(sector_identifier.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(user_id.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(salt.as_bytes());
+    URL_SAFE_NO_PAD.encode(hasher.finish())
+}
+
+/// The sector of an application without a `sector_identifier_uri` is the single host of its redirect URIs
+pub fn redirect_uri_sector(redirect_uris: &[String]) -> Result<String, anyhow::Error> {
+    let mut hosts = redirect_uris
+        .iter()
+        .map(|uri| uri_host(uri))
+        .collect::<Result<Vec<_>, _>>()?;
+    hosts.sort();
+    hosts.dedup();
+
+    match hosts.as_slice() {
+        [host] => Ok(host.clone()),
+        [] => Err(anyhow::anyhow!("Pairwise applications need a redirect URI")),
+        _ => Err(anyhow::anyhow!(
+            "Redirect URIs with multiple hosts require a sector_identifier_uri"
+        )),
+    }
+}
+
+/// Fetch a `sector_identifier_uri` and check it lists all redirect URIs of the application
+///
+/// Returns the sector identifier, the host of the URI.
+pub async fn resolve_sector_identifier_uri(
+    sector_identifier_uri: &str,
+    redirect_uris: &[String],
+) -> Result<String, anyhow::Error> {
+    let url = Url::parse(sector_identifier_uri)?;
+    if url.scheme() != "https" {
+        return Err(anyhow::anyhow!("sector_identifier_uri must use https"));
+    }
+
+    let listed_uris: Vec<String> = reqwest::get(url.clone())
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    if let Some(missing) = redirect_uris.iter().find(|uri| !listed_uris.contains(uri)) {
+        return Err(anyhow::anyhow!(
+            "Redirect URI {missing} is not listed in the sector_identifier_uri"
+        ));
+    }
+
+    uri_host(sector_identifier_uri)
+}
+
+fn uri_host(uri: &str) -> Result<String, anyhow::Error> {
+    Url::parse(uri)?
+        .host_str()
+        .map(str::to_owned)
+        .ok_or_else(|| anyhow::anyhow!("URI {uri} has no host"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairwise_subject() {
+        let subject = pairwise_subject("app.example.com", "user-1", "salt");
+
+        assert_eq!(
+            subject,
+            pairwise_subject("app.example.com", "user-1", "salt")
+        );
+        assert_ne!(
+            subject,
+            pairwise_subject("other.example.com", "user-1", "salt")
+        );
+        assert_ne!(
+            subject,
+            pairwise_subject("app.example.com", "user-2", "salt")
+        );
+        assert_ne!(
+            subject,
+            pairwise_subject("app.example.com", "user-1", "pepper")
+        );
+        assert!(!subject.contains("user-1"));
+    }
+
+    #[test]
+    fn test_redirect_uri_sector() {
+        let single_host = vec![
+            "https://app.example.com/callback".to_string(),
+            "https://app.example.com/other".to_string(),
+        ];
+        assert_eq!(
+            redirect_uri_sector(&single_host).unwrap(),
+            "app.example.com"
+        );
+
+        let multiple_hosts = vec![
+            "https://app.example.com/callback".to_string(),
+            "https://app.example.org/callback".to_string(),
+        ];
+        assert!(redirect_uri_sector(&multiple_hosts).is_err());
+    }
+}
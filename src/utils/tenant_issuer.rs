@@ -0,0 +1,211 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Request, State},
+    http::{Uri, header::HOST, uri::PathAndQuery},
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::utils::{token_issuer::TokenIssuer, token_verifier::TokenVerifier};
+
+/// Issuer a request is served by, the server's own or the one of a tenant below `/t/{tenant_id}`
+pub struct TenantIssuer {
+    /// `None` for the server's own issuer, which serves the applications of every tenant
+    pub tenant_id: Option<Uuid>,
+    /// Lowercase custom hostname the tenant's issuer is served at
+    pub domain: Option<String>,
+    /// URL the issuer's endpoints are below
+    pub base_url: String,
+    /// URL the login UI of the issuer's users is served at
+    pub login_ui_url: String,
+    pub token_issuer: Arc<TokenIssuer>,
+    pub token_verifier: Arc<TokenVerifier>,
+    pub jwks: Arc<Value>,
+}
+
+impl TenantIssuer {
+    /// Whether the issuer serves the applications and users of the tenant
+    pub fn serves(&self, tenant_id: Uuid) -> bool {
+        self.tenant_id
+            .is_none_or(|own_tenant_id| own_tenant_id == tenant_id)
+    }
+
+    /// URL of the endpoint at `path` below `/oauth`
+    pub fn endpoint_url(&self, path: &str) -> String {
+        format!("{}/oauth{}", self.base_url, path)
+    }
+
+    /// URL of the login UI, continuing at the endpoint `return_to` of this issuer afterwards
+    pub fn login_url(&self, return_to: &str) -> String {
+        format!(
+            "{}/login?return_to={}",
+            self.login_ui_url,
+            urlencoding::encode(&self.endpoint_url(return_to))
+        )
+    }
+}
+
+/// The server's own issuer and the issuers of its tenants
+pub struct TenantIssuers {
+    default: Arc<TenantIssuer>,
+    tenants: HashMap<Uuid, Arc<TenantIssuer>>,
+    domains: HashMap<String, Arc<TenantIssuer>>,
+}
+
+impl TenantIssuers {
+    pub fn new(default: TenantIssuer) -> Self {
+        Self {
+            default: Arc::new(default),
+            tenants: HashMap::new(),
+            domains: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, issuer: TenantIssuer) {
+        if let Some(tenant_id) = issuer.tenant_id {
+            let issuer = Arc::new(issuer);
+            if let Some(domain) = &issuer.domain {
+                self.domains.insert(domain.clone(), issuer.clone());
+            }
+            self.tenants.insert(tenant_id, issuer);
+        }
+    }
+
+    /// The issuer of a request's host and path and the path below its tenant prefix, `None` for
+    /// unknown tenants. A tenant's custom domain serves its issuer at the root.
+    fn resolve<'a>(
+        &self,
+        host: Option<&str>,
+        path: &'a str,
+    ) -> Option<(Arc<TenantIssuer>, Option<&'a str>)> {
+        if let Some(issuer) = host
+            .map(|host| host.split(':').next().unwrap_or_default().to_lowercase())
+            .and_then(|host| self.domains.get(&host))
+        {
+            return Some((issuer.clone(), None));
+        }
+
+        let Some(tenant_path) = path.strip_prefix("/t/") else {
+            return Some((self.default.clone(), None));
+        };
+        let (tenant_id, path) = match tenant_path.find('/') {
+            Some(index) => tenant_path.split_at(index),
+            None => (tenant_path, "/"),
+        };
+
+        let issuer = self.tenants.get(&Uuid::parse_str(tenant_id).ok()?)?;
+        Some((issuer.clone(), Some(path)))
+    }
+}
+
+/// Serves a tenant's endpoints below `/t/{tenant_id}` or at its custom domain with the server's
+/// routes, the issuer of the request is added to its extensions. Paths of unknown tenants are
+/// left to the fallback.
+pub async fn route_tenant(
+    State(issuers): State<Arc<TenantIssuers>>,
+    mut request: Request,
+) -> Request {
+    // HTTP/2 requests carry the host in the URI instead of the `Host` header
+    let host = request
+        .uri()
+        .host()
+        .or_else(|| request.headers().get(HOST)?.to_str().ok());
+    let Some((issuer, tenant_path)) = issuers.resolve(host, request.uri().path()) else {
+        return request;
+    };
+
+    if let Some(path) = tenant_path {
+        let path_and_query = match request.uri().query() {
+            Some(query) => format!("{path}?{query}"),
+            None => path.to_owned(),
+        };
+        let mut parts = request.uri().clone().into_parts();
+        parts.path_and_query = PathAndQuery::try_from(path_and_query).ok();
+        if let Ok(uri) = Uri::from_parts(parts) {
+            *request.uri_mut() = uri;
+        }
+    }
+
+    request.extensions_mut().insert(issuer);
+    request
+}
+
+/// Issuer with a fresh signing key for tests, of `tenant_id` or the default one
+#[cfg(test)]
+pub fn test_issuer(tenant_id: Option<Uuid>, domain: Option<&str>) -> TenantIssuer {
+    let key = openssl::rsa::Rsa::generate(2048).unwrap();
+    let (private_pem, public_pem) = (
+        key.private_key_to_pem().unwrap(),
+        key.public_key_to_pem().unwrap(),
+    );
+    TenantIssuer {
+        tenant_id,
+        domain: domain.map(str::to_owned),
+        base_url: "https://sso.example.com".to_owned(),
+        login_ui_url: "https://login.example.com".to_owned(),
+        token_issuer: Arc::new(TokenIssuer::new_rsa_pem(&private_pem, "issuer")),
+        token_verifier: Arc::new(TokenVerifier::new_rsa_pem(&public_pem, "issuer")),
+        jwks: Arc::new(Value::Null),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_tenant_paths() {
+        let tenant_id = Uuid::new_v4();
+        let mut issuers = TenantIssuers::new(test_issuer(None, None));
+        issuers.insert(test_issuer(Some(tenant_id), None));
+
+        let (default, path) = issuers.resolve(None, "/oauth/token").unwrap();
+        assert_eq!(default.tenant_id, None);
+        assert_eq!(path, None);
+
+        let tenant_path = format!("/t/{tenant_id}/oauth/token");
+        let (tenant, path) = issuers.resolve(None, &tenant_path).unwrap();
+        assert_eq!(tenant.tenant_id, Some(tenant_id));
+        assert!(tenant.serves(tenant_id) && !tenant.serves(Uuid::new_v4()));
+        assert_eq!(path, Some("/oauth/token"));
+
+        assert!(
+            issuers
+                .resolve(None, &format!("/t/{}/oauth/token", Uuid::new_v4()))
+                .is_none()
+        );
+        assert!(issuers.resolve(None, "/t/acme/oauth/token").is_none());
+    }
+
+    #[test]
+    fn test_resolve_custom_domains() {
+        let tenant_id = Uuid::new_v4();
+        let mut issuers = TenantIssuers::new(test_issuer(None, None));
+        issuers.insert(test_issuer(Some(tenant_id), Some("login.acme.example")));
+
+        let (tenant, path) = issuers
+            .resolve(Some("Login.Acme.Example:443"), "/oauth/token")
+            .unwrap();
+        assert_eq!(tenant.tenant_id, Some(tenant_id));
+        assert_eq!(path, None);
+
+        let (default, _) = issuers
+            .resolve(Some("sso.example.com"), "/oauth/token")
+            .unwrap();
+        assert_eq!(default.tenant_id, None);
+    }
+
+    #[test]
+    fn test_login_url_resumes_at_issuer() {
+        let login_url =
+            test_issuer(Some(Uuid::new_v4()), None).login_url("/device?user_code=AB CD");
+        let (ui_url, query) = login_url.split_once('?').unwrap();
+        assert_eq!(ui_url, "https://login.example.com/login");
+        let query: HashMap<String, String> = serde_urlencoded::from_str(query).unwrap();
+        assert_eq!(
+            query["return_to"],
+            "https://sso.example.com/oauth/device?user_code=AB CD"
+        );
+    }
+}
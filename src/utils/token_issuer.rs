@@ -2,8 +2,14 @@ use std::{fs, io};
 
 use chrono::{Duration, Utc};
 use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde_json::{Map, Value};
 
-use crate::models::claims::{AccessTokenClaims, IdTokenClaims, RefreshTokenClaims};
+use crate::models::claims::{
+    AccessTokenClaims, AuthorizationResponseClaims, IdTokenClaims, RefreshTokenClaims,
+};
+
+/// JWT `typ` of access tokens (RFC 9068 2.1)
+pub const ACCESS_TOKEN_TYPE: &str = "at+jwt";
 
 pub struct TokenIssuer {
     pub issuer: String,
@@ -30,8 +36,8 @@ impl TokenIssuer {
         subject: &str,
         audience: &str,
         nonce: Option<String>,
-        email: Option<String>,
-        name: Option<String>,
+        auth_time: Option<i64>,
+        user_claims: Map<String, Value>,
         expiry_seconds: i64,
     ) -> Result<String, jsonwebtoken::errors::Error> {
         let now = Utc::now();
@@ -41,9 +47,9 @@ impl TokenIssuer {
             aud: audience.to_owned(),
             exp: (now + Duration::seconds(expiry_seconds)).timestamp() as usize,
             iat: now.timestamp() as usize,
+            auth_time: auth_time.map(|auth_time| auth_time as usize),
             nonce,
-            email,
-            name,
+            user_claims,
         };
 
         jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.encoding_key)
@@ -53,7 +59,9 @@ impl TokenIssuer {
         &self,
         subject: &str,
         audience: &str,
+        client_id: &str,
         scope: Option<String>,
+        user_claims: Map<String, Value>,
         expiry_seconds: i64,
     ) -> Result<String, jsonwebtoken::errors::Error> {
         let now = Utc::now();
@@ -61,28 +69,52 @@ impl TokenIssuer {
             iss: self.issuer.clone(),
             sub: subject.to_owned(),
             aud: audience.to_owned(),
+            client_id: client_id.to_owned(),
             exp: (now + Duration::seconds(expiry_seconds)).timestamp() as usize,
             iat: now.timestamp() as usize,
             scope,
+            user_claims,
         };
 
-        jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.encoding_key)
+        // The type keeps ID tokens, signed with the same key, from being used as access tokens
+        let mut header = Header::new(Algorithm::RS256);
+        header.typ = Some(ACCESS_TOKEN_TYPE.to_owned());
+        jsonwebtoken::encode(&header, &claims, &self.encoding_key)
     }
 
     pub fn create_refresh_token(
         &self,
         subject: &str,
+        jti: &str,
         expiry_seconds: i64,
     ) -> Result<String, jsonwebtoken::errors::Error> {
         let now = Utc::now();
         let claims = RefreshTokenClaims {
             sub: subject.to_owned(),
+            jti: jti.to_owned(),
             exp: (now + Duration::seconds(expiry_seconds)).timestamp() as usize,
             iat: now.timestamp() as usize,
         };
 
         jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.encoding_key)
     }
+
+    /// Sign the authorization response for the client `audience` (JARM)
+    pub fn create_authorization_response(
+        &self,
+        audience: &str,
+        params: Map<String, Value>,
+        expiry_seconds: i64,
+    ) -> Result<String, jsonwebtoken::errors::Error> {
+        let claims = AuthorizationResponseClaims {
+            iss: self.issuer.clone(),
+            aud: audience.to_owned(),
+            exp: (Utc::now() + Duration::seconds(expiry_seconds)).timestamp() as usize,
+            params,
+        };
+
+        jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.encoding_key)
+    }
 }
 
 #[cfg(test)]
@@ -119,6 +151,13 @@ mod tests {
         (private_pem.into_bytes(), public_pem.into_bytes())
     }
 
+    fn test_user_claims() -> Map<String, Value> {
+        let mut user_claims = Map::new();
+        user_claims.insert("email".to_string(), "user@example.com".into());
+        user_claims.insert("name".to_string(), "Test User".into());
+        user_claims
+    }
+
     #[tokio::test]
     async fn test_generate_keys() {
         let (ref private_pem, ref public_pem) = *TEST_KEYS;
@@ -130,14 +169,14 @@ mod tests {
     async fn test_create_id_token() {
         let (ref private_pem, _) = *TEST_KEYS;
         let issuer_url = "https://test-issuer.example";
-        let token_issuer = TokenIssuer::new_rsa_pem(&private_pem, issuer_url);
+        let token_issuer = TokenIssuer::new_rsa_pem(private_pem, issuer_url);
 
         let id_token_result = token_issuer.create_id_token(
             "user123",
             "client123",
             Some("nonce123".to_string()),
-            Some("user@example.com".to_string()),
-            Some("Test User".to_string()),
+            Some(1_700_000_000),
+            test_user_claims(),
             3600,
         );
 
@@ -148,81 +187,142 @@ mod tests {
     async fn test_verify_id_token() {
         let (ref private_pem, ref public_pem) = *TEST_KEYS;
         let issuer_url = "https://test-issuer.example";
-        let token_issuer = TokenIssuer::new_rsa_pem(&private_pem, issuer_url);
+        let token_issuer = TokenIssuer::new_rsa_pem(private_pem, issuer_url);
 
         let id_token = token_issuer
             .create_id_token(
                 "user123",
                 "client123",
                 Some("nonce123".to_string()),
-                Some("user@example.com".to_string()),
-                Some("Test User".to_string()),
+                Some(1_700_000_000),
+                test_user_claims(),
                 3600,
             )
             .expect("Failed to create ID token");
 
-        let verifier = TokenVerifier::new_rsa_pem(&public_pem, issuer_url, "client123");
+        let verifier = TokenVerifier::new_rsa_pem(public_pem, issuer_url);
         let id_claims = verifier
-            .verify_id_token(&id_token)
+            .verify_id_token(&id_token, "client123")
             .expect("Failed to verify ID token")
             .claims;
 
         assert_eq!(id_claims.sub, "user123");
         assert_eq!(id_claims.aud, "client123");
         assert_eq!(id_claims.nonce.unwrap(), "nonce123");
-        assert_eq!(id_claims.email.unwrap(), "user@example.com");
-        assert_eq!(id_claims.name.unwrap(), "Test User");
+        assert_eq!(id_claims.user_claims["email"], "user@example.com");
+        assert_eq!(id_claims.user_claims["name"], "Test User");
+        assert_eq!(id_claims.auth_time, Some(1_700_000_000));
     }
 
     #[tokio::test]
     async fn test_create_access_token() {
         let (ref private_pem, _) = *TEST_KEYS;
         let issuer_url = "https://test-issuer.example";
-        let token_issuer = TokenIssuer::new_rsa_pem(&private_pem, issuer_url);
+        let token_issuer = TokenIssuer::new_rsa_pem(private_pem, issuer_url);
 
         let access_token_result = token_issuer.create_access_token(
             "user123",
             "api123",
+            "api123",
             Some("openid profile email".to_string()),
+            Map::new(),
             900,
         );
 
         assert!(access_token_result.is_ok(), "Failed to create access token");
     }
 
+    #[tokio::test]
+    async fn test_create_resource_access_token() {
+        let (ref private_pem, ref public_pem) = *TEST_KEYS;
+        let issuer_url = "https://test-issuer.example";
+        let token_issuer = TokenIssuer::new_rsa_pem(private_pem, issuer_url);
+
+        // Tokens requested with `resource=` are for the API but still name the client
+        let access_token = token_issuer
+            .create_access_token(
+                "user123",
+                "https://orders.example/api",
+                "client123",
+                Some("orders:read".to_string()),
+                Map::new(),
+                900,
+            )
+            .expect("Failed to create access token");
+
+        let verifier = TokenVerifier::new_rsa_pem(public_pem, issuer_url);
+        let claims = verifier
+            .verify_access_token(&access_token, "https://orders.example/api")
+            .expect("Failed to verify access token")
+            .claims;
+        assert_eq!(claims.aud, "https://orders.example/api");
+        assert_eq!(claims.client_id, "client123");
+    }
+
     #[tokio::test]
     async fn test_verify_access_token() {
         let (ref private_pem, ref public_pem) = *TEST_KEYS;
         let issuer_url = "https://test-issuer.example";
-        let token_issuer = TokenIssuer::new_rsa_pem(&private_pem, issuer_url);
+        let token_issuer = TokenIssuer::new_rsa_pem(private_pem, issuer_url);
 
         let access_token = token_issuer
             .create_access_token(
                 "user123",
                 "api123",
+                "api123",
                 Some("openid profile email".to_string()),
+                test_user_claims(),
                 900,
             )
             .expect("Failed to create access token");
 
-        let verifier_access = TokenVerifier::new_rsa_pem(&public_pem, issuer_url, "api123");
+        let verifier_access = TokenVerifier::new_rsa_pem(public_pem, issuer_url);
         let access_claims = verifier_access
-            .verify_access_token(&access_token)
+            .verify_access_token(&access_token, "api123")
             .expect("Failed to verify access token")
             .claims;
 
         assert_eq!(access_claims.sub, "user123");
         assert_eq!(access_claims.aud, "api123");
         assert_eq!(access_claims.scope.unwrap(), "openid profile email");
+        assert_eq!(access_claims.user_claims["name"], "Test User");
+
+        // Tokens for one API are rejected by another
+        assert!(
+            verifier_access
+                .verify_access_token(&access_token, "https://orders.example/api")
+                .is_err()
+        );
+
+        // ID tokens have the claims of access tokens but not their type
+        let id_token = token_issuer
+            .create_id_token("user123", "api123", None, None, Map::new(), 900)
+            .expect("Failed to create ID token");
+        assert!(
+            verifier_access
+                .verify_access_token(&id_token, "api123")
+                .is_err()
+        );
+        assert!(
+            verifier_access
+                .verify_issued_access_token(&id_token)
+                .is_err()
+        );
+        assert!(
+            verifier_access
+                .verify_issued_access_token(&access_token)
+                .is_ok()
+        );
     }
 
     #[tokio::test]
     async fn test_create_refresh_token() {
         let (ref private_pem, _) = *TEST_KEYS;
         let issuer_url = "https://test-issuer.example";
-        let token_issuer = TokenIssuer::new_rsa_pem(&private_pem, issuer_url);
+        let token_issuer = TokenIssuer::new_rsa_pem(private_pem, issuer_url);
 
-        let refresh_token_result = token_issuer.create_refresh_token("user123", 86400);
+        let refresh_token_result =
+            token_issuer.create_refresh_token("user123", "refresh123", 86400);
 
         assert!(
             refresh_token_result.is_ok(),
@@ -234,18 +334,19 @@ mod tests {
     async fn test_verify_refresh_token() {
         let (ref private_pem, ref public_pem) = *TEST_KEYS;
         let issuer_url = "https://test-issuer.example";
-        let token_issuer = TokenIssuer::new_rsa_pem(&private_pem, issuer_url);
+        let token_issuer = TokenIssuer::new_rsa_pem(private_pem, issuer_url);
 
         let refresh_token = token_issuer
-            .create_refresh_token("user123", 86400)
+            .create_refresh_token("user123", "refresh123", 86400)
             .expect("Failed to create refresh token");
 
-        let verifier_refresh = TokenVerifier::new_rsa_pem(&public_pem, issuer_url, "");
+        let verifier_refresh = TokenVerifier::new_rsa_pem(public_pem, issuer_url);
         let refresh_claims = verifier_refresh
             .verify_refresh_token(&refresh_token)
             .expect("Failed to verify refresh token")
             .claims;
 
         assert_eq!(refresh_claims.sub, "user123");
+        assert_eq!(refresh_claims.jti, "refresh123");
     }
 }
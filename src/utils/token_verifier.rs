@@ -1,48 +1,91 @@
 use std::{fs, io};
 
-use jsonwebtoken::{Algorithm, DecodingKey, TokenData, Validation, decode};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, TokenData, Validation, decode, decode_header, errors::ErrorKind,
+};
 
-use crate::models::claims::{AccessTokenClaims, IdTokenClaims, RefreshTokenClaims};
+use crate::{
+    models::claims::{AccessTokenClaims, IdTokenClaims, RefreshTokenClaims},
+    utils::token_issuer::ACCESS_TOKEN_TYPE,
+};
 
 pub struct TokenVerifier {
     decoding_key: DecodingKey,
     issuer: String,
-    audience: String,
 }
 
 impl TokenVerifier {
-    pub fn new_rsa_pem(public_key_pem: &[u8], issuer: &str, audience: &str) -> Self {
+    pub fn new_rsa_pem(public_key_pem: &[u8], issuer: &str) -> Self {
         let decoding_key =
             DecodingKey::from_rsa_pem(public_key_pem).expect("Invalid RSA public key PEM");
         Self {
             decoding_key,
             issuer: issuer.to_string(),
-            audience: audience.to_string(),
         }
     }
 
-    pub fn from_pem_file(path: &str, issuer: &str, audience: &str) -> io::Result<Self> {
+    pub fn from_pem_file(path: &str, issuer: &str) -> io::Result<Self> {
         let pem_bytes = fs::read(path)?;
-        Ok(Self::new_rsa_pem(&pem_bytes, issuer, audience))
+        Ok(Self::new_rsa_pem(&pem_bytes, issuer))
     }
 
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// Verifies an ID token issued to `audience`, the server itself only verifies hints
+    #[cfg(test)]
     pub fn verify_id_token(
         &self,
         token: &str,
+        audience: &str,
+    ) -> Result<TokenData<IdTokenClaims>, jsonwebtoken::errors::Error> {
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[audience]);
+        validation.set_issuer(&[self.issuer.as_str()]);
+
+        decode::<IdTokenClaims>(token, &self.decoding_key, &validation)
+    }
+
+    /// Verifies an `id_token_hint` issued to `audience`. Expired tokens are accepted
+    /// since the hint only identifies the user of a previous authentication.
+    pub fn verify_id_token_hint(
+        &self,
+        token: &str,
+        audience: &str,
     ) -> Result<TokenData<IdTokenClaims>, jsonwebtoken::errors::Error> {
         let mut validation = Validation::new(Algorithm::RS256);
-        validation.set_audience(&[self.audience.as_str()]);
+        validation.set_audience(&[audience]);
         validation.set_issuer(&[self.issuer.as_str()]);
+        validation.validate_exp = false;
+        validation.required_spec_claims.remove("exp");
 
         decode::<IdTokenClaims>(token, &self.decoding_key, &validation)
     }
 
+    /// Verifies an access token for the client or API `audience` the way resource servers do
+    #[cfg(test)]
     pub fn verify_access_token(
         &self,
         token: &str,
+        audience: &str,
     ) -> Result<TokenData<AccessTokenClaims>, jsonwebtoken::errors::Error> {
+        check_access_token_type(token)?;
         let mut validation = Validation::new(Algorithm::RS256);
-        validation.set_audience(&[self.audience.as_str()]);
+        validation.set_audience(&[audience]);
+        validation.set_issuer(&[self.issuer.as_str()]);
+
+        decode::<AccessTokenClaims>(token, &self.decoding_key, &validation)
+    }
+
+    /// Verifies an access token this server issued to any client, e.g. for the UserInfo endpoint
+    pub fn verify_issued_access_token(
+        &self,
+        token: &str,
+    ) -> Result<TokenData<AccessTokenClaims>, jsonwebtoken::errors::Error> {
+        check_access_token_type(token)?;
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.validate_aud = false;
         validation.set_issuer(&[self.issuer.as_str()]);
 
         decode::<AccessTokenClaims>(token, &self.decoding_key, &validation)
@@ -60,3 +103,17 @@ impl TokenVerifier {
         decode::<RefreshTokenClaims>(token, &self.decoding_key, &validation)
     }
 }
+
+/// Only tokens typed as access tokens are accepted, ID tokens have the same claims and key
+fn check_access_token_type(token: &str) -> Result<(), jsonwebtoken::errors::Error> {
+    let typ = decode_header(token)?
+        .typ
+        .map(|typ| typ.to_ascii_lowercase());
+    match typ
+        .as_deref()
+        .map(|typ| typ.trim_start_matches("application/"))
+    {
+        Some(ACCESS_TOKEN_TYPE) => Ok(()),
+        _ => Err(ErrorKind::InvalidToken.into()),
+    }
+}